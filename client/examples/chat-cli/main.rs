@@ -47,11 +47,12 @@ fn main() {
     let addr = ConnectionArgs::Tcp {
         prefer_ipv6: false,
         hostname: server_addr,
+        identity: None,
     };
 
     // Create a client.
     let mut client = runtime
-        .block_on(Client::new(addr, runtime2, &mut None))
+        .block_on(Client::new(addr, runtime2, None, &mut None))
         .expect("Failed to create client instance");
 
     println!("Server info: {:?}", client.server_info());
@@ -1,3 +1,4 @@
+use network::IdentityKey;
 use std::net::SocketAddr;
 use tokio::net::lookup_host;
 use tracing::trace;
@@ -13,6 +14,9 @@ pub enum ConnectionArgs {
     Tcp {
         hostname: String,
         prefer_ipv6: bool,
+        /// The key the server has to prove its identity with, the server logs
+        /// it on start. Without it a man in the middle can't be ruled out.
+        identity: Option<IdentityKey>,
     },
     Mpsc(u64),
 }
@@ -27,6 +31,15 @@ impl ConnectionArgs {
 pub(crate) async fn resolve(
     address: &str,
     prefer_ipv6: bool,
+) -> Result<Vec<SocketAddr>, std::io::Error> {
+    resolve_with_port(address, prefer_ipv6, ConnectionArgs::DEFAULT_PORT).await
+}
+
+/// Like [`resolve`], but with another port for addresses without one
+pub(crate) async fn resolve_with_port(
+    address: &str,
+    prefer_ipv6: bool,
+    default_port: u16,
 ) -> Result<Vec<SocketAddr>, std::io::Error> {
     // `lookup_host` will internally try to parse it as a SocketAddr
     // 1. Assume it's a hostname + port
@@ -37,7 +50,7 @@ pub(crate) async fn resolve(
         },
         Err(e) => {
             // 2. Assume its a hostname without port
-            match lookup_host((address, ConnectionArgs::DEFAULT_PORT)).await {
+            match lookup_host((address, default_port)).await {
                 Ok(s) => {
                     trace!("Host lookup without ports succeeded");
                     Ok(sort_ipv6(s, prefer_ipv6))
@@ -99,6 +112,19 @@ mod tests {
         assert_eq!(args[0].port(), 666);
     }
 
+    #[tokio::test]
+    async fn resolve_other_default_port() {
+        let args = resolve_with_port("127.0.0.1", false, 14006)
+            .await
+            .expect("resolve failed");
+        assert_eq!(args[0].port(), 14006);
+
+        let args = resolve_with_port("127.0.0.1:666", false, 14006)
+            .await
+            .expect("resolve failed");
+        assert_eq!(args[0].port(), 666);
+    }
+
     #[tokio::test]
     async fn resolve_ipv6() {
         let args = resolve("localhost", true).await.expect("resolve failed");
@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 use std::{sync::Arc, time::Duration};
 use tokio::runtime::Runtime;
 use tracing::{info, trace, warn};
-use veloren_client::{addr::ConnectionArgs, Client};
+use veloren_client::{addr::ConnectionArgs, Client, LinkConditions};
 
 mod settings;
 mod tui;
@@ -51,21 +51,26 @@ pub struct BotClient {
     clock: Clock,
 }
 
-pub fn make_client(runtime: &Arc<Runtime>, server: &str) -> Client {
+pub fn make_client(
+    runtime: &Arc<Runtime>,
+    server: &str,
+    link_conditions: Option<LinkConditions>,
+) -> Client {
     let runtime_clone = Arc::clone(runtime);
     let addr = ConnectionArgs::Tcp {
         prefer_ipv6: false,
         hostname: server.to_owned(),
+        identity: None,
     };
     runtime
-        .block_on(Client::new(addr, runtime_clone, &mut None))
+        .block_on(Client::new(addr, runtime_clone, link_conditions, &mut None))
         .expect("Failed to connect to server")
 }
 
 impl BotClient {
     pub fn new(settings: Settings) -> BotClient {
         let runtime = Arc::new(Runtime::new().unwrap());
-        let menu_client: Client = make_client(&runtime, &settings.server);
+        let menu_client: Client = make_client(&runtime, &settings.server, settings.link_conditions);
         let clock = Clock::new(Duration::from_secs_f64(1.0 / 60.0));
         BotClient {
             settings,
@@ -157,10 +162,11 @@ impl BotClient {
             let runtime = Arc::clone(&self.runtime);
 
             let server = self.settings.server.clone();
+            let link_conditions = self.settings.link_conditions;
             let client = self
                 .bot_clients
                 .entry(cred.username.clone())
-                .or_insert_with(|| make_client(&runtime, &server));
+                .or_insert_with(|| make_client(&runtime, &server, link_conditions));
 
             // TODO: log the clients in in parallel instead of in series
             if let Err(e) = runtime.block_on(client.register(
@@ -1,6 +1,7 @@
 use super::BotCreds;
 use std::{fs, path::PathBuf};
 use tracing::warn;
+use veloren_client::LinkConditions;
 
 pub fn data_dir() -> PathBuf {
     let mut path = common_base::userdata_dir_workspace!();
@@ -12,6 +13,9 @@ pub fn data_dir() -> PathBuf {
 pub struct Settings {
     pub server: String,
     pub bot_logins: Vec<BotCreds>,
+    /// simulates a bad connection for all bots
+    #[serde(default)]
+    pub link_conditions: Option<LinkConditions>,
 }
 
 impl Default for Settings {
@@ -19,6 +23,7 @@ impl Default for Settings {
         Settings {
             server: "localhost".to_string(),
             bot_logins: Vec::new(),
+            link_conditions: None,
         }
     }
 }
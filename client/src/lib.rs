@@ -5,11 +5,13 @@
 pub mod addr;
 pub mod cmd;
 pub mod error;
+pub mod query;
 
 // Reexports
 pub use crate::error::Error;
 pub use authc::AuthClientError;
 pub use common_net::msg::ServerInfo;
+pub use network::{IdentityKey, LinkConditions};
 pub use specs::{
     join::Join,
     saveload::{Marker, MarkerAllocator},
@@ -19,6 +21,7 @@ pub use specs::{
 use crate::addr::ConnectionArgs;
 use byteorder::{ByteOrder, LittleEndian};
 use common::{
+    calendar::{Calendar, CalendarDate},
     character::{CharacterId, CharacterItem},
     comp::{
         self,
@@ -44,16 +47,17 @@ use common::{
     trade::{PendingTrade, SitePrices, TradeAction, TradeId, TradeResult},
     uid::{Uid, UidAllocator},
     vol::RectVolSize,
+    weather::{Weather, WeatherGrid},
 };
 use common_base::{prof_span, span};
 use common_net::{
     msg::{
         self, validate_chat_msg,
         world_msg::{EconomyInfo, PoiInfo, SiteId, SiteInfo},
-        ChatMsgValidationError, ClientGeneral, ClientMsg, ClientRegister, ClientType,
+        ChatMsgValidationError, ClientGeneral, ClientMsg, ClientRegister, ClientType, DeltaDecoder,
         DisconnectReason, InviteAnswer, Notification, PingMsg, PlayerInfo, PlayerListUpdate,
         PresenceKind, RegisterError, ServerGeneral, ServerInit, ServerRegisterAnswer,
-        MAX_BYTES_CHAT_MSG,
+        SpectateTarget, TerrainEncodingPreferences, MAX_BYTES_CHAT_MSG,
     },
     sync::WorldSyncExt,
 };
@@ -203,6 +207,7 @@ pub struct Client {
 
     pending_chunks: HashMap<Vec2<i32>, Instant>,
     target_time_of_day: Option<TimeOfDay>,
+    delta_decoder: DeltaDecoder,
 }
 
 /// Holds data related to the current players characters, as well as some
@@ -217,16 +222,23 @@ impl Client {
     pub async fn new(
         addr: ConnectionArgs,
         runtime: Arc<Runtime>,
+        // simulates a bad connection, for testing
+        link_conditions: Option<LinkConditions>,
         // TODO: refactor to avoid needing to use this out parameter
         mismatched_server_info: &mut Option<ServerInfo>,
     ) -> Result<Self, Error> {
         let network = Network::new(Pid::new(), &runtime);
+        network.condition_links(link_conditions);
 
         let participant = match addr {
             ConnectionArgs::Tcp {
                 hostname,
                 prefer_ipv6,
-            } => addr::try_connect(&network, &hostname, prefer_ipv6, ConnectAddr::Tcp).await?,
+                identity,
+            } => {
+                network.pin_identity(identity);
+                addr::try_connect(&network, &hostname, prefer_ipv6, ConnectAddr::Tcp).await?
+            },
             ConnectionArgs::Quic {
                 hostname,
                 prefer_ipv6,
@@ -292,6 +304,7 @@ impl Client {
             ServerInit::GameSync {
                 entity_package,
                 time_of_day,
+                calendar,
                 max_group_size,
                 client_timeout,
                 world_map,
@@ -306,6 +319,7 @@ impl Client {
 
                 let entity = state.ecs_mut().apply_entity_package(entity_package);
                 *state.ecs_mut().write_resource() = time_of_day;
+                state.ecs_mut().insert(calendar);
                 *state.ecs_mut().write_resource() = PlayerEntity(Some(entity));
                 state.ecs_mut().insert(material_stats);
                 state.ecs_mut().insert(ability_map);
@@ -650,6 +664,7 @@ impl Client {
 
             pending_chunks: HashMap::new(),
             target_time_of_day: None,
+            delta_decoder: DeltaDecoder::default(),
         })
     }
 
@@ -755,7 +770,9 @@ impl Client {
                     | ClientGeneral::RequestSiteInfo(_)
                     | ClientGeneral::UnlockSkillGroup(_)
                     | ClientGeneral::RequestPlayerPhysics { .. }
-                    | ClientGeneral::RequestLossyTerrainCompression { .. } => {
+                    | ClientGeneral::RequestTerrainEncoding(_)
+                    | ClientGeneral::AckDeltaSync(_)
+                    | ClientGeneral::SetSpectateTarget(_) => {
                         #[cfg(feature = "tracy")]
                         {
                             ingame = 1.0;
@@ -792,10 +809,12 @@ impl Client {
         })
     }
 
+    /// Lets the server pick lossy encodings for terrain chunks when the
+    /// bandwidth is low, all encodings this client can decode are advertised
     pub fn request_lossy_terrain_compression(&mut self, lossy_terrain_compression: bool) {
-        self.send_msg(ClientGeneral::RequestLossyTerrainCompression {
-            lossy_terrain_compression,
-        })
+        self.send_msg(ClientGeneral::RequestTerrainEncoding(
+            TerrainEncodingPreferences::all(lossy_terrain_compression),
+        ))
     }
 
     fn send_msg<S>(&mut self, msg: S)
@@ -819,6 +838,23 @@ impl Client {
         self.presence = Some(PresenceKind::Character(character_id));
     }
 
+    /// Watch the game without a character, the server only allows this if it
+    /// accepts spectators or we are an admin
+    pub fn request_spectate(&mut self) {
+        self.send_msg(ClientGeneral::Spectate);
+
+        //Assume we are in_game unless server tells us otherwise
+        self.presence = Some(PresenceKind::Spectator);
+    }
+
+    /// Moves the camera while spectating, the server syncs the world around
+    /// it
+    pub fn set_spectate_target(&mut self, target: SpectateTarget) {
+        if self.presence == Some(PresenceKind::Spectator) {
+            self.send_msg(ClientGeneral::SetSpectateTarget(target));
+        }
+    }
+
     /// Load the current players character list
     pub fn load_character_list(&mut self) {
         self.character_list.loading = true;
@@ -1314,6 +1350,26 @@ impl Client {
         self.state.terrain().get_key_arc(chunk_pos).cloned()
     }
 
+    /// Weather at the player's position, clear if it isn't known
+    pub fn current_weather(&self) -> Weather {
+        self.position()
+            .map(|pos| {
+                self.state
+                    .ecs()
+                    .read_resource::<WeatherGrid>()
+                    .get_interpolated(pos.xy())
+            })
+            .unwrap_or_default()
+    }
+
+    /// The in-game date, following the calendar of the server
+    pub fn current_date(&self) -> CalendarDate {
+        self.state
+            .ecs()
+            .read_resource::<Calendar>()
+            .date(*self.state.ecs().read_resource::<TimeOfDay>())
+    }
+
     pub fn current<C: Component>(&self) -> Option<C>
     where
         C: Clone,
@@ -1637,6 +1693,12 @@ impl Client {
                 self.in_game_stream
                     .send(ClientGeneral::PlayerPhysics { pos, vel, ori })?;
             }
+
+            // Acknowledge the delta synced components, so the server can encode
+            // against them
+            if let Some(seq) = self.delta_decoder.take_ack() {
+                self.in_game_stream.send(ClientGeneral::AckDeltaSync(seq))?;
+            }
         }
 
         /*
@@ -1796,6 +1858,12 @@ impl Client {
                     .ecs_mut()
                     .apply_comp_sync_package(comp_sync_package);
             },
+            ServerGeneral::DeltaSync(delta_sync_package) => {
+                let comp_sync_package = self.delta_decoder.decode(delta_sync_package);
+                self.state
+                    .ecs_mut()
+                    .apply_comp_sync_package(comp_sync_package);
+            },
             ServerGeneral::CreateEntity(entity_package) => {
                 self.state.ecs_mut().apply_entity_package(entity_package);
             },
@@ -1987,6 +2055,12 @@ impl Client {
                     rich.economy = Some(economy);
                 }
             },
+            ServerGeneral::WeatherUpdate(weather) => {
+                self.state
+                    .ecs_mut()
+                    .write_resource::<WeatherGrid>()
+                    .apply_patch(weather);
+            },
             _ => unreachable!("Not a in_game message"),
         }
         Ok(())
@@ -2509,8 +2583,10 @@ mod tests {
             ConnectionArgs::Tcp {
                 hostname: "127.0.0.1:9000".to_owned(),
                 prefer_ipv6: false,
+                identity: None,
             },
             runtime2,
+            None,
             &mut None,
         ));
 
@@ -0,0 +1,111 @@
+//! Queries servers for a server browser, without connecting to them, see
+//! [`common_net::msg::query`].
+
+use crate::{addr, error::Error};
+use common_net::msg::{
+    query::{DEFAULT_QUERY_PORT, QUERY_PROTOCOL_VERSION},
+    QueryRequest, QueryResponse, ServerQueryInfo,
+};
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::net::UdpSocket;
+use tracing::trace;
+
+/// A request is send this many times within the timeout, in case it or its
+/// response gets lost
+const QUERY_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+    pub info: ServerQueryInfo,
+    /// Round trip time of the query
+    pub ping: Duration,
+}
+
+/// Queries a single server. `address` is the host of the server, with the
+/// port of its query endpoint if it doesn't use the default one.
+pub async fn query_server(address: &str, timeout: Duration) -> Result<QueryResult, Error> {
+    let remotes = addr::resolve_with_port(address, false, DEFAULT_QUERY_PORT)
+        .await
+        .map_err(Error::HostnameLookupFailed)?;
+    let remote = *remotes
+        .first()
+        .ok_or_else(|| Error::Other("No Ip Addr provided".to_string()))?;
+    let bind = match remote {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let socket = UdpSocket::bind(bind)
+        .await
+        .map_err(|e| Error::Other(e.to_string()))?;
+
+    // every attempt has its own nonce, so the ping is measured against the
+    // request that was answered
+    let base_nonce = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    let mut sent = Vec::new();
+    let mut buffer = [0u8; 1024];
+    for attempt in 0..QUERY_ATTEMPTS {
+        let nonce = base_nonce.wrapping_add(attempt as u64);
+        socket
+            .send_to(&QueryRequest::new(nonce).to_bytes(), remote)
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?;
+        sent.push((nonce, Instant::now()));
+
+        let deadline = tokio::time::Instant::now() + timeout / QUERY_ATTEMPTS;
+        while let Ok(received) =
+            tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await
+        {
+            let (n, from) = received.map_err(|e| Error::Other(e.to_string()))?;
+            if from != remote {
+                continue;
+            }
+            let response = match QueryResponse::from_bytes(&buffer[..n]) {
+                Some(response) => response,
+                None => continue,
+            };
+            let sent_at = match sent.iter().find(|(nonce, _)| *nonce == response.nonce()) {
+                Some((_, sent_at)) => *sent_at,
+                None => continue,
+            };
+            return match response {
+                QueryResponse::Info { info, .. } => Ok(QueryResult {
+                    info,
+                    ping: sent_at.elapsed(),
+                }),
+                QueryResponse::UnsupportedVersion { version, .. } => Err(Error::Other(format!(
+                    "Server uses query protocol version {}, we use {}",
+                    version, QUERY_PROTOCOL_VERSION
+                ))),
+            };
+        }
+        trace!(?remote, ?attempt, "Query timed out");
+    }
+    Err(Error::ServerTimeout)
+}
+
+/// Queries all `addresses` at once, e.g. to fill a multiplayer server
+/// browser. The results are in the order of `addresses`.
+pub async fn query_servers(
+    addresses: &[String],
+    timeout: Duration,
+) -> Vec<Result<QueryResult, Error>> {
+    let queries = addresses
+        .iter()
+        .cloned()
+        .map(|address| tokio::spawn(async move { query_server(&address, timeout).await }))
+        .collect::<Vec<_>>();
+    let mut results = Vec::with_capacity(queries.len());
+    for query in queries {
+        results.push(
+            query
+                .await
+                .unwrap_or_else(|e| Err(Error::Other(e.to_string()))),
+        );
+    }
+    results
+}
@@ -1,4 +1,4 @@
-use super::{world_msg::SiteId, PingMsg};
+use super::{world_msg::SiteId, PingMsg, SpectateTarget, TerrainEncodingPreferences};
 use common::{
     character::CharacterId,
     comp,
@@ -75,6 +75,11 @@ pub enum ClientGeneral {
     RefundSkill(Skill),
     UnlockSkillGroup(SkillGroupKind),
     RequestSiteInfo(SiteId),
+    /// All `ServerGeneral::DeltaSync` packages up to this sequence number
+    /// were applied
+    AckDeltaSync(u64),
+    /// Moves the camera of a spectator
+    SetSpectateTarget(SpectateTarget),
     //Only in Game, via terrain stream
     TerrainChunkRequest {
         key: Vec2<i32>,
@@ -86,9 +91,8 @@ pub enum ClientGeneral {
     RequestPlayerPhysics {
         server_authoritative: bool,
     },
-    RequestLossyTerrainCompression {
-        lossy_terrain_compression: bool,
-    },
+    /// Which encodings the server may send terrain chunks with
+    RequestTerrainEncoding(TerrainEncodingPreferences),
 }
 
 impl ClientMsg {
@@ -127,7 +131,9 @@ impl ClientMsg {
                         | ClientGeneral::RequestSiteInfo(_)
                         | ClientGeneral::UnlockSkillGroup(_)
                         | ClientGeneral::RequestPlayerPhysics { .. }
-                        | ClientGeneral::RequestLossyTerrainCompression { .. } => {
+                        | ClientGeneral::RequestTerrainEncoding(_)
+                        | ClientGeneral::AckDeltaSync(_)
+                        | ClientGeneral::SetSpectateTarget(_) => {
                             c_type == ClientType::Game && presence.is_some()
                         },
                         //Always possible
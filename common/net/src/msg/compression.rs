@@ -1,4 +1,5 @@
 use common::{
+    assets,
     terrain::{chonk::Chonk, Block, BlockKind, SpriteKind},
     vol::{BaseVol, ReadVol, RectVolSize, WriteVol},
     volumes::vol_grid_2d::VolGrid2d,
@@ -67,6 +68,97 @@ impl<T: for<'a> Deserialize<'a>> CompressedData<T> {
     }
 }
 
+/// Like [`CompressedData`], but compressed with zstd and optionally a
+/// [`ZstdDictionary`], which needs to be known to both sides
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ZstdCompressedData<T> {
+    pub data: Vec<u8>,
+    /// Id of the dictionary that was used, see [`ZstdDictionary::id`]
+    pub dictionary: Option<u64>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Serialize> ZstdCompressedData<T> {
+    pub fn compress(t: &T, level: i32, dictionary: Option<&ZstdDictionary>) -> Self {
+        const EXPECT_MSG: &str =
+            "compression only fails for fallible Read/Write impls (which Vec<u8> is not)";
+        let uncompressed = bincode::serialize(t)
+            .expect("bincode serialization can only fail if a byte limit is set");
+
+        let dictionary = dictionary.filter(|dictionary| dictionary.id().is_some());
+        let buf = Vec::with_capacity(uncompressed.len() / 10);
+        let mut encoder = zstd::stream::write::Encoder::with_dictionary(
+            buf,
+            level,
+            dictionary.map_or(&[][..], |dictionary| dictionary.bytes()),
+        )
+        .expect(EXPECT_MSG);
+        encoder.write_all(&*uncompressed).expect(EXPECT_MSG);
+        ZstdCompressedData {
+            data: encoder.finish().expect(EXPECT_MSG),
+            dictionary: dictionary.and_then(ZstdDictionary::id),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: for<'a> Deserialize<'a>> ZstdCompressedData<T> {
+    /// Fails if the data was compressed with another dictionary than
+    /// `dictionary`
+    pub fn decompress(&self, dictionary: Option<&ZstdDictionary>) -> Option<T> {
+        let dictionary = match self.dictionary {
+            Some(id) => match dictionary {
+                Some(dictionary) if dictionary.id() == Some(id) => dictionary.bytes(),
+                _ => {
+                    warn!(?id, "Data was compressed with an unknown zstd dictionary");
+                    return None;
+                },
+            },
+            None => &[][..],
+        };
+        let mut uncompressed = Vec::with_capacity(self.data.len() * 4);
+        zstd::stream::read::Decoder::with_dictionary(&*self.data, dictionary)
+            .ok()?
+            .read_to_end(&mut uncompressed)
+            .ok()?;
+        bincode::deserialize(&*uncompressed).ok()
+    }
+}
+
+/// A zstd dictionary, trained on samples of the data it should compress. It's
+/// loaded as an asset, an empty file is the same as no dictionary.
+#[derive(Clone, Debug)]
+pub struct ZstdDictionary {
+    bytes: Vec<u8>,
+    id: u64,
+}
+
+impl ZstdDictionary {
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> std::io::Result<Self> {
+        zstd::dict::from_samples(samples, max_size).map(Self::from)
+    }
+
+    /// FNV-1a hash of the dictionary, `None` if it's empty
+    pub fn id(&self) -> Option<u64> { (!self.bytes.is_empty()).then(|| self.id) }
+
+    pub fn bytes(&self) -> &[u8] { &self.bytes }
+}
+
+impl From<Vec<u8>> for ZstdDictionary {
+    fn from(bytes: Vec<u8>) -> Self {
+        let id = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        Self { bytes, id }
+    }
+}
+
+impl assets::Asset for ZstdDictionary {
+    type Loader = assets::LoadFrom<Vec<u8>, assets::BytesLoader>;
+
+    const EXTENSION: &'static str = "dict";
+}
+
 /// Formula for packing voxel data into a 2d array
 pub trait PackingFormula: Copy {
     fn dimensions(&self, dims: Vec3<u32>) -> (u32, u32);
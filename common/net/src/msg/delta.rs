@@ -0,0 +1,414 @@
+//! Delta encoding of the components that change the most, so modifications of
+//! them only send the fields that actually changed.
+use super::EcsCompPacket;
+use crate::sync::{CompSyncPackage, ReceivedBaselines, SentBaselines};
+use common::{
+    comp::{self, HealthChange},
+    uid::Uid,
+};
+use serde::{Deserialize, Serialize};
+use sum_type::sum_type;
+use tracing::warn;
+use vek::Quaternion;
+
+sum_type! {
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub enum DeltaComp {
+        Pos(comp::Pos),
+        Vel(comp::Vel),
+        Ori(comp::Ori),
+        Health(comp::Health),
+        Energy(comp::Energy),
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DeltaCompKind {
+    Pos,
+    Vel,
+    Ori,
+    Health,
+    Energy,
+}
+
+/// The fields of a component that changed compared to a baseline, `None`
+/// for every field that didn't change
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum FieldDelta {
+    Pos([Option<f32>; 3]),
+    Vel([Option<f32>; 3]),
+    Ori([Option<f32>; 4]),
+    Health {
+        values: [Option<u32>; 3],
+        is_dead: Option<bool>,
+        last_change: Option<HealthChange>,
+    },
+    Energy {
+        values: [Option<u32>; 3],
+        regen_rate: Option<f32>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum DeltaUpdate {
+    Full(DeltaComp),
+    /// The baseline was sent `age` packages ago
+    Delta {
+        age: u8,
+        delta: FieldDelta,
+    },
+}
+
+/// Modifications of delta synced components, only send to a single client
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeltaSyncPackage {
+    /// Acknowledged by the client via `ClientGeneral::AckDeltaSync`
+    pub seq: u64,
+    pub updates: Vec<(u64, DeltaUpdate)>,
+}
+
+/// How many bytes delta encoding saved
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DeltaSyncStats {
+    pub full_updates: u64,
+    pub delta_updates: u64,
+    /// What the updates would have taken without delta encoding
+    pub full_bytes: u64,
+    pub sent_bytes: u64,
+}
+
+/// Kept by the server for every client, to encode the delta synced
+/// components against what the client acknowledged
+#[derive(Debug, Default)]
+pub struct DeltaEncoder {
+    baselines: SentBaselines<(u64, DeltaCompKind), DeltaComp>,
+    updates: Vec<(u64, DeltaUpdate)>,
+    stats: DeltaSyncStats,
+}
+
+/// Kept by the client, to resolve the deltas the server sends
+#[derive(Debug, Default)]
+pub struct DeltaDecoder {
+    baselines: ReceivedBaselines<(u64, DeltaCompKind), DeltaComp>,
+}
+
+fn diff<T: Copy, const N: usize>(
+    new: [T; N],
+    old: [T; N],
+    eq: impl Fn(T, T) -> bool,
+) -> [Option<T>; N] {
+    let mut diff = [None; N];
+    for i in 0..N {
+        if !eq(new[i], old[i]) {
+            diff[i] = Some(new[i]);
+        }
+    }
+    diff
+}
+
+fn patch<T: Copy, const N: usize>(mut old: [T; N], diff: [Option<T>; N]) -> [T; N] {
+    for (old, diff) in old.iter_mut().zip(diff) {
+        if let Some(new) = diff {
+            *old = new;
+        }
+    }
+    old
+}
+
+// Compare the bits, so even NaNs are send when they change
+fn f32_eq(a: f32, b: f32) -> bool { a.to_bits() == b.to_bits() }
+
+fn changed<T: PartialEq>(new: T, old: T) -> Option<T> { (new != old).then(|| new) }
+
+fn quat_array(quat: Quaternion<f32>) -> [f32; 4] { [quat.x, quat.y, quat.z, quat.w] }
+
+impl DeltaComp {
+    pub fn kind(&self) -> DeltaCompKind {
+        match self {
+            DeltaComp::Pos(_) => DeltaCompKind::Pos,
+            DeltaComp::Vel(_) => DeltaCompKind::Vel,
+            DeltaComp::Ori(_) => DeltaCompKind::Ori,
+            DeltaComp::Health(_) => DeltaCompKind::Health,
+            DeltaComp::Energy(_) => DeltaCompKind::Energy,
+        }
+    }
+
+    /// `None` if `baseline` is another kind of component
+    pub fn delta(&self, baseline: &DeltaComp) -> Option<FieldDelta> {
+        Some(match (self, baseline) {
+            (DeltaComp::Pos(new), DeltaComp::Pos(old)) => {
+                FieldDelta::Pos(diff(new.0.into_array(), old.0.into_array(), f32_eq))
+            },
+            (DeltaComp::Vel(new), DeltaComp::Vel(old)) => {
+                FieldDelta::Vel(diff(new.0.into_array(), old.0.into_array(), f32_eq))
+            },
+            (DeltaComp::Ori(new), DeltaComp::Ori(old)) => FieldDelta::Ori(diff(
+                quat_array(new.to_quat()),
+                quat_array(old.to_quat()),
+                f32_eq,
+            )),
+            (DeltaComp::Health(new), DeltaComp::Health(old)) => FieldDelta::Health {
+                values: diff(new.scaled_values(), old.scaled_values(), |a, b| a == b),
+                is_dead: changed(new.is_dead, old.is_dead),
+                last_change: changed(new.last_change, old.last_change),
+            },
+            (DeltaComp::Energy(new), DeltaComp::Energy(old)) => FieldDelta::Energy {
+                values: diff(new.scaled_values(), old.scaled_values(), |a, b| a == b),
+                regen_rate: (!f32_eq(new.regen_rate, old.regen_rate)).then(|| new.regen_rate),
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl FieldDelta {
+    pub fn kind(&self) -> DeltaCompKind {
+        match self {
+            FieldDelta::Pos(_) => DeltaCompKind::Pos,
+            FieldDelta::Vel(_) => DeltaCompKind::Vel,
+            FieldDelta::Ori(_) => DeltaCompKind::Ori,
+            FieldDelta::Health { .. } => DeltaCompKind::Health,
+            FieldDelta::Energy { .. } => DeltaCompKind::Energy,
+        }
+    }
+
+    /// `None` if `baseline` is another kind of component
+    pub fn apply(&self, baseline: &DeltaComp) -> Option<DeltaComp> {
+        Some(match (self, baseline) {
+            (FieldDelta::Pos(diff), DeltaComp::Pos(old)) => {
+                DeltaComp::Pos(comp::Pos(patch(old.0.into_array(), *diff).into()))
+            },
+            (FieldDelta::Vel(diff), DeltaComp::Vel(old)) => {
+                DeltaComp::Vel(comp::Vel(patch(old.0.into_array(), *diff).into()))
+            },
+            (FieldDelta::Ori(diff), DeltaComp::Ori(old)) => {
+                let [x, y, z, w] = patch(quat_array(old.to_quat()), *diff);
+                DeltaComp::Ori(comp::Ori::new(Quaternion { x, y, z, w }))
+            },
+            (
+                FieldDelta::Health {
+                    values,
+                    is_dead,
+                    last_change,
+                },
+                DeltaComp::Health(old),
+            ) => {
+                let mut health = old
+                    .clone()
+                    .with_scaled_values(patch(old.scaled_values(), *values));
+                health.is_dead = is_dead.unwrap_or(old.is_dead);
+                health.last_change = last_change.unwrap_or(old.last_change);
+                DeltaComp::Health(health)
+            },
+            (FieldDelta::Energy { values, regen_rate }, DeltaComp::Energy(old)) => {
+                let mut energy = old.with_scaled_values(patch(old.scaled_values(), *values));
+                energy.regen_rate = regen_rate.unwrap_or(old.regen_rate);
+                DeltaComp::Energy(energy)
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl From<DeltaComp> for EcsCompPacket {
+    fn from(comp: DeltaComp) -> Self {
+        match comp {
+            DeltaComp::Pos(pos) => pos.into(),
+            DeltaComp::Vel(vel) => vel.into(),
+            DeltaComp::Ori(ori) => ori.into(),
+            DeltaComp::Health(health) => health.into(),
+            DeltaComp::Energy(energy) => energy.into(),
+        }
+    }
+}
+
+fn serialized_size(update: &DeltaUpdate) -> u64 { bincode::serialized_size(update).unwrap_or(0) }
+
+impl DeltaEncoder {
+    /// The client applied all packages up to and including `seq`
+    pub fn ack(&mut self, seq: u64) { self.baselines.ack(seq); }
+
+    /// Adds a modification of `comp` to the next package, encoded against
+    /// the last value the client acknowledged if that is smaller
+    pub fn modified(&mut self, uid: Uid, comp: impl Into<DeltaComp>) {
+        let comp = comp.into();
+        if self.updates.is_empty() {
+            self.baselines.next_seq();
+        }
+        let key = (uid.0, comp.kind());
+        let full = DeltaUpdate::Full(comp.clone());
+        let full_size = serialized_size(&full);
+        let delta = self
+            .baselines
+            .baseline(&key)
+            .and_then(|(age, baseline)| Some((age, comp.delta(baseline)?)))
+            .map(|(age, delta)| DeltaUpdate::Delta { age, delta })
+            .map(|delta| (serialized_size(&delta), delta))
+            .filter(|(size, _)| *size < full_size);
+        self.stats.full_bytes += full_size;
+        let update = match delta {
+            Some((size, delta)) => {
+                self.stats.delta_updates += 1;
+                self.stats.sent_bytes += size;
+                delta
+            },
+            None => {
+                self.stats.full_updates += 1;
+                self.stats.sent_bytes += full_size;
+                full
+            },
+        };
+        self.baselines.sent(key, comp);
+        self.updates.push((uid.0, update));
+    }
+
+    /// The modifications added since the last package, if there are any
+    pub fn take_package(&mut self) -> Option<DeltaSyncPackage> {
+        (!self.updates.is_empty()).then(|| DeltaSyncPackage {
+            seq: self.baselines.seq(),
+            updates: std::mem::take(&mut self.updates),
+        })
+    }
+
+    pub fn take_stats(&mut self) -> DeltaSyncStats { std::mem::take(&mut self.stats) }
+}
+
+impl DeltaDecoder {
+    /// Resolves the deltas of `package` into modifications of whole
+    /// components
+    pub fn decode(&mut self, package: DeltaSyncPackage) -> CompSyncPackage<EcsCompPacket> {
+        self.baselines.begin(package.seq);
+        let mut comp_sync_package = CompSyncPackage::new();
+        for (uid, update) in package.updates {
+            let comp = match update {
+                DeltaUpdate::Full(comp) => comp,
+                DeltaUpdate::Delta { age, delta } => {
+                    match self
+                        .baselines
+                        .baseline(&(uid, delta.kind()), age)
+                        .and_then(|baseline| delta.apply(baseline))
+                    {
+                        Some(comp) => comp,
+                        None => {
+                            warn!(?uid, ?age, "Baseline of delta synced component is missing");
+                            continue;
+                        },
+                    }
+                },
+            };
+            self.baselines.received((uid, comp.kind()), comp.clone());
+            comp_sync_package.comp_modified(Uid(uid), EcsCompPacket::from(comp));
+        }
+        comp_sync_package
+    }
+
+    /// Sequence number to acknowledge, if packages were decoded since the
+    /// last acknowledgement
+    pub fn take_ack(&mut self) -> Option<u64> { self.baselines.take_ack() }
+}
+
+impl std::ops::AddAssign for DeltaSyncStats {
+    fn add_assign(&mut self, other: Self) {
+        self.full_updates += other.full_updates;
+        self.delta_updates += other.delta_updates;
+        self.full_bytes += other.full_bytes;
+        self.sent_bytes += other.sent_bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::CompUpdateKind;
+    use vek::Vec3;
+
+    fn decoded_pos(package: CompSyncPackage<EcsCompPacket>) -> Vec<Vec3<f32>> {
+        package
+            .comp_updates
+            .into_iter()
+            .filter_map(|(_, update)| match update {
+                CompUpdateKind::Modified(EcsCompPacket::Pos(pos)) => Some(pos.0),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deltas_after_ack() {
+        let mut encoder = DeltaEncoder::default();
+        let mut decoder = DeltaDecoder::default();
+        let uid = Uid(3);
+
+        encoder.modified(uid, comp::Pos(Vec3::new(1.0, 2.0, 3.0)));
+        let package = encoder.take_package().unwrap();
+        assert!(matches!(package.updates[0].1, DeltaUpdate::Full(_)));
+        assert_eq!(decoded_pos(decoder.decode(package)), vec![Vec3::new(
+            1.0, 2.0, 3.0
+        )]);
+        encoder.ack(decoder.take_ack().unwrap());
+
+        // only z changed, so only z is send
+        encoder.modified(uid, comp::Pos(Vec3::new(1.0, 2.0, 4.0)));
+        let package = encoder.take_package().unwrap();
+        assert!(matches!(package.updates[0].1, DeltaUpdate::Delta {
+            age: 1,
+            delta: FieldDelta::Pos([None, None, Some(_)])
+        }));
+        // without an ack the same baseline is used again
+        encoder.modified(uid, comp::Pos(Vec3::new(1.0, 2.0, 5.0)));
+        let package2 = encoder.take_package().unwrap();
+        assert!(matches!(package2.updates[0].1, DeltaUpdate::Delta {
+            age: 2,
+            delta: FieldDelta::Pos([None, None, Some(_)])
+        }));
+        assert_eq!(decoded_pos(decoder.decode(package)), vec![Vec3::new(
+            1.0, 2.0, 4.0
+        )]);
+        assert_eq!(decoded_pos(decoder.decode(package2)), vec![Vec3::new(
+            1.0, 2.0, 5.0
+        )]);
+
+        let stats = encoder.take_stats();
+        assert_eq!((stats.full_updates, stats.delta_updates), (1, 2));
+        assert!(stats.sent_bytes < stats.full_bytes);
+    }
+
+    #[test]
+    fn health_delta_roundtrip() {
+        let body = comp::Body::Object(comp::object::Body::Scarecrow);
+        let old = comp::Health::new(body, 0);
+        let mut new = old.clone();
+        new.kill();
+
+        let delta = DeltaComp::from(new.clone())
+            .delta(&DeltaComp::from(old.clone()))
+            .unwrap();
+        assert!(matches!(delta, FieldDelta::Health {
+            values: [Some(0), None, None],
+            is_dead: Some(true),
+            last_change: None,
+        }));
+        match delta.apply(&DeltaComp::from(old)) {
+            Some(DeltaComp::Health(health)) => {
+                assert!(health.is_dead);
+                assert_eq!(health.scaled_values(), new.scaled_values());
+            },
+            _ => panic!("Health delta applied to a health is not a health"),
+        }
+        assert!(
+            delta
+                .apply(&DeltaComp::from(comp::Pos(Vec3::zero())))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn other_kinds_are_no_baselines() {
+        let ori = DeltaComp::from(comp::Ori::default());
+        assert!(
+            ori.delta(&DeltaComp::from(comp::Vel(Vec3::zero())))
+                .is_none()
+        );
+        assert!(matches!(EcsCompPacket::from(ori), EcsCompPacket::Ori(_)));
+    }
+}
@@ -1,7 +1,10 @@
 pub mod client;
 pub mod compression;
+pub mod delta;
 pub mod ecs_packet;
+pub mod query;
 pub mod server;
+pub mod terrain_encoding;
 pub mod world_msg;
 
 // Reexports
@@ -9,18 +12,22 @@ pub use self::{
     client::{ClientGeneral, ClientMsg, ClientRegister, ClientType},
     compression::{
         CompressedData, GridLtrPacking, PackingFormula, QuadPngEncoding, TriPngEncoding,
-        VoxelImageEncoding, WidePacking, WireChonk,
+        VoxelImageEncoding, WidePacking, WireChonk, ZstdCompressedData, ZstdDictionary,
     },
+    delta::{DeltaDecoder, DeltaEncoder, DeltaSyncPackage, DeltaSyncStats},
     ecs_packet::EcsCompPacket,
+    query::{QueryBattleMode, QueryRequest, QueryResponse, ServerQueryInfo},
     server::{
         CharacterInfo, DisconnectReason, InviteAnswer, Notification, PlayerInfo, PlayerListUpdate,
         RegisterError, SerializedTerrainChunk, ServerGeneral, ServerInfo, ServerInit, ServerMsg,
         ServerRegisterAnswer,
     },
+    terrain_encoding::{TerrainEncoding, TerrainEncodingPreferences},
     world_msg::WorldMapMsg,
 };
-use common::character::CharacterId;
+use common::{character::CharacterId, uid::Uid};
 use serde::{Deserialize, Serialize};
+use vek::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum PresenceKind {
@@ -28,6 +35,15 @@ pub enum PresenceKind {
     Character(CharacterId),
 }
 
+/// Where a spectator looks, the world around it is synced to the spectator
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SpectateTarget {
+    /// Flies freely
+    Position(Vec3<f32>),
+    /// Follows the entity with this `Uid`
+    Entity(Uid),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum PingMsg {
     Ping,
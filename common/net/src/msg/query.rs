@@ -0,0 +1,187 @@
+//! Lightweight query protocol for server browsers.
+//!
+//! A client sends a single [`QueryRequest`] datagram to the query port of a
+//! server and gets a single [`QueryResponse`] back, without connecting or
+//! authenticating. Every datagram starts with [`QUERY_MAGIC`] followed by the
+//! bincode encoded message. Requests are padded to [`QUERY_REQUEST_SIZE`] and
+//! responses are kept smaller than that, so the endpoint can't be used to
+//! amplify traffic towards a spoofed address.
+use common::resources::BattleMode;
+use serde::{Deserialize, Serialize};
+
+/// Start of every query datagram
+pub const QUERY_MAGIC: [u8; 4] = *b"VQRY";
+/// Bumped on incompatible changes of [`QueryRequest`] and [`QueryResponse`]
+pub const QUERY_PROTOCOL_VERSION: u16 = 0;
+/// Port the query endpoint listens on by default
+pub const DEFAULT_QUERY_PORT: u16 = 14006;
+/// Requests shorter than this are ignored
+pub const QUERY_REQUEST_SIZE: usize = 512;
+/// Longer server names are cut off in responses
+pub const MAX_QUERY_NAME_LEN: usize = 64;
+/// Longer server descriptions are cut off in responses
+pub const MAX_QUERY_DESCRIPTION_LEN: usize = 320;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QueryRequest {
+    pub version: u16,
+    /// Echoed in the response, to match it with the request
+    pub nonce: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QueryResponse {
+    Info {
+        nonce: u64,
+        info: ServerQueryInfo,
+    },
+    /// The server speaks another version of the query protocol
+    UnsupportedVersion {
+        nonce: u64,
+        version: u16,
+    },
+}
+
+/// What a server browser needs to know about a server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerQueryInfo {
+    pub name: String,
+    pub description: String,
+    pub players: u32,
+    pub max_players: u32,
+    pub git_hash: String,
+    pub battle_mode: QueryBattleMode,
+    pub world_seed: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum QueryBattleMode {
+    Global(BattleMode),
+    /// Players choose, `default` is used until they do
+    PerPlayer {
+        default: BattleMode,
+    },
+}
+
+fn encode<T: Serialize>(msg: &T) -> Vec<u8> {
+    let mut bytes = QUERY_MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, msg).expect("query messages can always be serialized");
+    bytes
+}
+
+fn decode<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Option<T> {
+    bytes
+        .strip_prefix(&QUERY_MAGIC[..])
+        .and_then(|bytes| bincode::deserialize(bytes).ok())
+}
+
+impl QueryRequest {
+    pub fn new(nonce: u64) -> Self {
+        Self {
+            version: QUERY_PROTOCOL_VERSION,
+            nonce,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = encode(self);
+        bytes.resize(QUERY_REQUEST_SIZE.max(bytes.len()), 0);
+        bytes
+    }
+
+    /// `None` if `bytes` isn't a request or too short
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < QUERY_REQUEST_SIZE {
+            return None;
+        }
+        decode(bytes)
+    }
+}
+
+impl QueryResponse {
+    pub fn nonce(&self) -> u64 {
+        match self {
+            QueryResponse::Info { nonce, .. } | QueryResponse::UnsupportedVersion { nonce, .. } => {
+                *nonce
+            },
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> { encode(self) }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> { decode(bytes) }
+}
+
+impl ServerQueryInfo {
+    /// Cuts off the name and description, so the response stays smaller than
+    /// the request
+    pub fn truncated(mut self) -> Self {
+        fn truncate(s: &mut String, max_len: usize) {
+            if s.len() > max_len {
+                let mut len = max_len;
+                while !s.is_char_boundary(len) {
+                    len -= 1;
+                }
+                s.truncate(len);
+            }
+        }
+        truncate(&mut self.name, MAX_QUERY_NAME_LEN);
+        truncate(&mut self.description, MAX_QUERY_DESCRIPTION_LEN);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> ServerQueryInfo {
+        ServerQueryInfo {
+            name: "Ünïcödé".repeat(20),
+            description: "ä".repeat(1000),
+            players: 12,
+            max_players: 100,
+            git_hash: "0123456789abcdef0123456789abcdef01234567".to_owned(),
+            battle_mode: QueryBattleMode::PerPlayer {
+                default: BattleMode::PvE,
+            },
+            world_seed: 230,
+        }
+    }
+
+    #[test]
+    fn request_roundtrip() {
+        let request = QueryRequest::new(42);
+        let bytes = request.to_bytes();
+        assert_eq!(bytes.len(), QUERY_REQUEST_SIZE);
+        assert_eq!(QueryRequest::from_bytes(&bytes), Some(request));
+    }
+
+    #[test]
+    fn short_or_foreign_requests_are_ignored() {
+        let bytes = QueryRequest::new(42).to_bytes();
+        assert_eq!(QueryRequest::from_bytes(&bytes[..100]), None);
+        let mut foreign = bytes;
+        foreign[0] = b'X';
+        assert_eq!(QueryRequest::from_bytes(&foreign), None);
+    }
+
+    #[test]
+    fn responses_dont_amplify() {
+        let response = QueryResponse::Info {
+            nonce: u64::MAX,
+            info: info().truncated(),
+        };
+        let bytes = response.to_bytes();
+        assert!(bytes.len() < QUERY_REQUEST_SIZE);
+        assert_eq!(QueryResponse::from_bytes(&bytes), Some(response));
+    }
+
+    #[test]
+    fn truncate_on_char_boundary() {
+        let info = info().truncated();
+        assert!(info.name.len() <= MAX_QUERY_NAME_LEN);
+        assert!(info.description.len() <= MAX_QUERY_DESCRIPTION_LEN);
+        assert!(info.description.chars().all(|c| c == 'ä'));
+    }
+}
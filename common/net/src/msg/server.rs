@@ -1,9 +1,12 @@
 use super::{
-    world_msg::EconomyInfo, ClientType, CompressedData, EcsCompPacket, PingMsg, QuadPngEncoding,
-    TriPngEncoding, WidePacking, WireChonk,
+    terrain_encoding::{terrain_dictionary, TerrainEncoding, TERRAIN_ZSTD_LEVEL},
+    world_msg::EconomyInfo,
+    ClientType, CompressedData, DeltaSyncPackage, EcsCompPacket, PingMsg, QuadPngEncoding,
+    TriPngEncoding, WidePacking, WireChonk, ZstdCompressedData, ZstdDictionary,
 };
 use crate::sync;
 use common::{
+    calendar::Calendar,
     character::{self, CharacterItem},
     comp::{self, invite::InviteKind, item::MaterialStatManifest},
     outcome::Outcome,
@@ -12,6 +15,7 @@ use common::{
     terrain::{Block, TerrainChunk, TerrainChunkMeta, TerrainChunkSize},
     trade::{PendingTrade, SitePrices, TradeId, TradeResult},
     uid::Uid,
+    weather::WeatherPatch,
 };
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
@@ -55,6 +59,7 @@ pub enum ServerInit {
     GameSync {
         entity_package: sync::EntityPackage<EcsCompPacket>,
         time_of_day: TimeOfDay,
+        calendar: Calendar,
         max_group_size: u32,
         client_timeout: Duration,
         world_map: crate::msg::world_msg::WorldMapMsg,
@@ -71,6 +76,7 @@ pub enum SerializedTerrainChunk {
     DeflatedChonk(CompressedData<TerrainChunk>),
     QuadPng(WireChonk<QuadPngEncoding<4>, WidePacking<true>, TerrainChunkMeta, TerrainChunkSize>),
     TriPng(WireChonk<TriPngEncoding<false>, WidePacking<true>, TerrainChunkMeta, TerrainChunkSize>),
+    Zstd(ZstdCompressedData<TerrainChunk>),
 }
 
 impl SerializedTerrainChunk {
@@ -79,14 +85,21 @@ impl SerializedTerrainChunk {
             SerializedTerrainChunk::DeflatedChonk(data) => data.data.len(),
             SerializedTerrainChunk::QuadPng(data) => data.data.data.len(),
             SerializedTerrainChunk::TriPng(data) => data.data.data.len(),
+            SerializedTerrainChunk::Zstd(data) => data.data.len(),
         }
     }
 
-    pub fn via_heuristic(chunk: &TerrainChunk, lossy_compression: bool) -> Self {
-        if lossy_compression && (chunk.get_max_z() - chunk.get_min_z() <= 128) {
-            Self::quadpng(chunk)
-        } else {
-            Self::deflate(chunk)
+    /// `dictionary` is only used for [`TerrainEncoding::Zstd`]
+    pub fn encode(
+        chunk: &TerrainChunk,
+        encoding: TerrainEncoding,
+        dictionary: Option<&ZstdDictionary>,
+    ) -> Self {
+        match encoding {
+            TerrainEncoding::Deflate => Self::deflate(chunk),
+            TerrainEncoding::Zstd => Self::zstd(chunk, dictionary),
+            TerrainEncoding::QuadPng => Self::quadpng(chunk),
+            TerrainEncoding::TriPng => Self::tripng(chunk),
         }
     }
 
@@ -94,6 +107,14 @@ impl SerializedTerrainChunk {
         Self::DeflatedChonk(CompressedData::compress(chunk, 1))
     }
 
+    pub fn zstd(chunk: &TerrainChunk, dictionary: Option<&ZstdDictionary>) -> Self {
+        Self::Zstd(ZstdCompressedData::compress(
+            chunk,
+            TERRAIN_ZSTD_LEVEL,
+            dictionary,
+        ))
+    }
+
     pub fn quadpng(chunk: &TerrainChunk) -> Self {
         if let Some(wc) = WireChonk::from_chonk(QuadPngEncoding(), WidePacking(), chunk) {
             Self::QuadPng(wc)
@@ -117,6 +138,7 @@ impl SerializedTerrainChunk {
             Self::DeflatedChonk(chonk) => chonk.decompress(),
             Self::QuadPng(wc) => wc.to_chonk(),
             Self::TriPng(wc) => wc.to_chonk(),
+            Self::Zstd(data) => data.decompress(Some(&*terrain_dictionary().read())),
         }
     }
 }
@@ -178,6 +200,9 @@ pub enum ServerGeneral {
     TimeOfDay(TimeOfDay),
     EntitySync(sync::EntitySyncPackage),
     CompSync(sync::CompSyncPackage<EcsCompPacket>),
+    /// Modifications of the delta synced components, which have to be
+    /// acknowledged with `ClientGeneral::AckDeltaSync`
+    DeltaSync(DeltaSyncPackage),
     CreateEntity(sync::EntityPackage<EcsCompPacket>),
     DeleteEntity(Uid),
     Disconnect(DisconnectReason),
@@ -187,6 +212,8 @@ pub enum ServerGeneral {
     FinishedTrade(TradeResult),
     /// Economic information about sites
     SiteEconomy(EconomyInfo),
+    /// The weather around the client, sent periodically
+    WeatherUpdate(WeatherPatch),
 }
 
 impl ServerGeneral {
@@ -295,7 +322,8 @@ impl ServerMsg {
                         | ServerGeneral::Knockback(_)
                         | ServerGeneral::UpdatePendingTrade(_, _, _)
                         | ServerGeneral::FinishedTrade(_)
-                        | ServerGeneral::SiteEconomy(_) => {
+                        | ServerGeneral::SiteEconomy(_)
+                        | ServerGeneral::WeatherUpdate(_) => {
                             c_type == ClientType::Game && presence.is_some()
                         },
                         // Always possible
@@ -306,6 +334,7 @@ impl ServerMsg {
                         | ServerGeneral::TimeOfDay(_)
                         | ServerGeneral::EntitySync(_)
                         | ServerGeneral::CompSync(_)
+                        | ServerGeneral::DeltaSync(_)
                         | ServerGeneral::CreateEntity(_)
                         | ServerGeneral::DeleteEntity(_)
                         | ServerGeneral::Disconnect(_)
@@ -0,0 +1,203 @@
+//! Choice of the encoding terrain chunks are sent with. Clients advertise what
+//! they can decode in [`TerrainEncodingPreferences`], the server picks an
+//! encoding for every client from that and the bandwidth towards it.
+//!
+//! The thresholds are derived from
+//! `world/examples/chunk_compression_benchmarks`: image encodings are a
+//! fraction of the size of the lossless ones, but take longer to encode and
+//! only work for chunks that aren't too deep.
+use super::compression::ZstdDictionary;
+use common::assets::{AssetExt, AssetHandle};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// Asset of the dictionary used for [`TerrainEncoding::Zstd`]. Trained by
+/// `chunk_compression_benchmarks` with `TRAIN_ZSTD_DICTIONARY` and stored in
+/// git LFS. If it's missing zstd is used without a dictionary.
+pub const TERRAIN_DICTIONARY: &str = "common.terrain_zstd_dictionary";
+/// zstd level for chunks, higher levels cost too much time on the server
+pub const TERRAIN_ZSTD_LEVEL: i32 = 3;
+/// Deeper chunks are always sent lossless, images get too large for them
+pub const MAX_LOSSY_CHUNK_HEIGHT: i32 = 128;
+/// Above this many bytes/s chunks are sent lossless, even to clients that
+/// accept lossy encodings
+pub const LOSSLESS_BANDWIDTH: f32 = 1_000_000.0;
+/// Below this many bytes/s the smallest lossy encoding is preferred
+pub const TRIPNG_BANDWIDTH: f32 = 250_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TerrainEncoding {
+    Deflate,
+    Zstd,
+    QuadPng,
+    TriPng,
+}
+
+/// Sent by the client, to tell the server how it wants to receive chunks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TerrainEncodingPreferences {
+    /// Whether lossy encodings may be used to save bandwidth
+    pub lossy: bool,
+    /// Encodings the client can decode, [`TerrainEncoding::Deflate`] is
+    /// always supported
+    pub supported: Vec<TerrainEncoding>,
+    /// Id of the client's [`TERRAIN_DICTIONARY`], see [`ZstdDictionary::id`]
+    pub zstd_dictionary: Option<u64>,
+}
+
+impl TerrainEncoding {
+    pub const ALL: [Self; 4] = [Self::Deflate, Self::Zstd, Self::QuadPng, Self::TriPng];
+
+    pub fn is_lossy(self) -> bool { matches!(self, Self::QuadPng | Self::TriPng) }
+
+    /// Label for metrics
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Deflate => "deflate",
+            Self::Zstd => "zstd",
+            Self::QuadPng => "quadpng",
+            Self::TriPng => "tripng",
+        }
+    }
+}
+
+impl Default for TerrainEncodingPreferences {
+    /// What every client can decode, until it tells otherwise
+    fn default() -> Self {
+        Self {
+            lossy: false,
+            supported: vec![TerrainEncoding::Deflate],
+            zstd_dictionary: None,
+        }
+    }
+}
+
+impl TerrainEncodingPreferences {
+    /// Supports all encodings and the local [`TERRAIN_DICTIONARY`]
+    pub fn all(lossy: bool) -> Self {
+        Self {
+            lossy,
+            supported: TerrainEncoding::ALL.to_vec(),
+            zstd_dictionary: terrain_dictionary().read().id(),
+        }
+    }
+
+    pub fn supports(&self, encoding: TerrainEncoding) -> bool {
+        encoding == TerrainEncoding::Deflate || self.supported.contains(&encoding)
+    }
+
+    /// Encoding for a chunk of `height` (max z - min z) sent to a client
+    /// with `bandwidth` bytes/s, which is 0 if it's not known yet
+    pub fn select(&self, bandwidth: f32, height: i32) -> TerrainEncoding {
+        let lossless = if self.supports(TerrainEncoding::Zstd) {
+            TerrainEncoding::Zstd
+        } else {
+            TerrainEncoding::Deflate
+        };
+        if !self.lossy || height > MAX_LOSSY_CHUNK_HEIGHT || bandwidth >= LOSSLESS_BANDWIDTH {
+            return lossless;
+        }
+        let candidates = if bandwidth > 0.0 && bandwidth < TRIPNG_BANDWIDTH {
+            [TerrainEncoding::TriPng, TerrainEncoding::QuadPng]
+        } else {
+            [TerrainEncoding::QuadPng, TerrainEncoding::TriPng]
+        };
+        candidates
+            .iter()
+            .copied()
+            .find(|encoding| self.supports(*encoding))
+            .unwrap_or(lossless)
+    }
+
+    /// Whether the server's `dictionary` can be used for this client
+    pub fn shares_dictionary(&self, dictionary: &ZstdDictionary) -> bool {
+        dictionary.id().is_some() && dictionary.id() == self.zstd_dictionary
+    }
+}
+
+/// The local [`TERRAIN_DICTIONARY`], empty if the asset is missing
+pub fn terrain_dictionary() -> AssetHandle<ZstdDictionary> {
+    ZstdDictionary::load_or_insert_with(TERRAIN_DICTIONARY, |error| {
+        debug!(?error, "No terrain zstd dictionary, using zstd without one");
+        ZstdDictionary::from(Vec::new())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::compression::ZstdCompressedData;
+
+    fn preferences(lossy: bool, supported: &[TerrainEncoding]) -> TerrainEncodingPreferences {
+        TerrainEncodingPreferences {
+            lossy,
+            supported: supported.to_vec(),
+            zstd_dictionary: None,
+        }
+    }
+
+    #[test]
+    fn lossless_unless_requested() {
+        let all = preferences(false, &TerrainEncoding::ALL);
+        assert_eq!(all.select(0.0, 50), TerrainEncoding::Zstd);
+        assert_eq!(all.select(1000.0, 50), TerrainEncoding::Zstd);
+        let old = TerrainEncodingPreferences::default();
+        assert_eq!(old.select(1000.0, 50), TerrainEncoding::Deflate);
+    }
+
+    #[test]
+    fn lossy_depends_on_bandwidth() {
+        let all = preferences(true, &TerrainEncoding::ALL);
+        assert_eq!(all.select(0.0, 50), TerrainEncoding::QuadPng);
+        assert_eq!(all.select(100_000.0, 50), TerrainEncoding::TriPng);
+        assert_eq!(all.select(500_000.0, 50), TerrainEncoding::QuadPng);
+        assert_eq!(all.select(LOSSLESS_BANDWIDTH, 50), TerrainEncoding::Zstd);
+        // deep chunks don't fit into images
+        assert_eq!(all.select(100_000.0, 200), TerrainEncoding::Zstd);
+    }
+
+    #[test]
+    fn only_supported_encodings() {
+        let quad = preferences(true, &[TerrainEncoding::QuadPng]);
+        assert_eq!(quad.select(100_000.0, 50), TerrainEncoding::QuadPng);
+        assert_eq!(
+            quad.select(LOSSLESS_BANDWIDTH, 50),
+            TerrainEncoding::Deflate
+        );
+        let none = preferences(true, &[]);
+        assert_eq!(none.select(100_000.0, 50), TerrainEncoding::Deflate);
+    }
+
+    #[test]
+    fn zstd_roundtrip() {
+        let data = (0..1000u32).map(|i| i % 7).collect::<Vec<_>>();
+        let dictionary = ZstdDictionary::from(vec![7; 64]);
+        let plain = ZstdCompressedData::compress(&data, TERRAIN_ZSTD_LEVEL, None);
+        assert_eq!(plain.decompress(None), Some(data.clone()));
+        let with_dict = ZstdCompressedData::compress(&data, TERRAIN_ZSTD_LEVEL, Some(&dictionary));
+        assert_eq!(with_dict.dictionary, dictionary.id());
+        assert_eq!(with_dict.decompress(Some(&dictionary)), Some(data));
+        assert_eq!(with_dict.decompress(None), None);
+    }
+
+    #[test]
+    fn dictionary_must_match() {
+        let dictionary = ZstdDictionary::from(vec![1, 2, 3]);
+        let mut prefs = preferences(false, &TerrainEncoding::ALL);
+        assert!(!prefs.shares_dictionary(&dictionary));
+        prefs.zstd_dictionary = dictionary.id();
+        assert!(prefs.shares_dictionary(&dictionary));
+        let empty = ZstdDictionary::from(Vec::new());
+        prefs.zstd_dictionary = None;
+        assert!(!prefs.shares_dictionary(&empty));
+    }
+
+    #[test]
+    fn ships_terrain_dictionary() {
+        let dictionary = ZstdDictionary::load_expect(TERRAIN_DICTIONARY).read();
+        assert!(dictionary.id().is_some());
+        let data = (0..1000u32).map(|i| i % 7).collect::<Vec<_>>();
+        let compressed = ZstdCompressedData::compress(&data, TERRAIN_ZSTD_LEVEL, Some(&dictionary));
+        assert_eq!(compressed.decompress(Some(&dictionary)), Some(data));
+    }
+}
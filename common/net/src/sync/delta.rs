@@ -0,0 +1,258 @@
+//! Bookkeeping for syncing components as deltas.
+//!
+//! Every delta sync package gets a sequence number, which the client
+//! acknowledges once it applied the package. Values are only encoded against
+//! a baseline the client acknowledged, so both sides agree on it without the
+//! baseline being part of the package. A baseline is referenced by its age,
+//! i.e. how many packages ago it was sent.
+use hashbrown::HashMap;
+use std::{collections::VecDeque, hash::Hash};
+
+/// Baselines older than this many packages aren't referenced anymore, so
+/// both sides can forget them
+pub const MAX_BASELINE_AGE: u8 = 64;
+
+/// Kept by the server for every client. Remembers the values sent to the
+/// client, until they are acknowledged or too old to be used as a baseline.
+#[derive(Debug)]
+pub struct SentBaselines<K, V> {
+    /// Sequence number of the package currently assembled
+    seq: u64,
+    /// Newest sequence number the client acknowledged
+    acked: u64,
+    entries: HashMap<K, SentBaseline<V>>,
+}
+
+#[derive(Debug)]
+struct SentBaseline<V> {
+    acked: Option<(u64, V)>,
+    pending: VecDeque<(u64, V)>,
+}
+
+/// Kept by the client. Remembers the values received recently, as the server
+/// might encode new values against any of them.
+#[derive(Debug)]
+pub struct ReceivedBaselines<K, V> {
+    /// Sequence number of the package currently applied
+    seq: u64,
+    /// Newest sequence number that was acknowledged to the server
+    acked: u64,
+    entries: HashMap<K, VecDeque<(u64, V)>>,
+}
+
+fn too_old(seq: u64, now: u64) -> bool { now.saturating_sub(seq) > MAX_BASELINE_AGE as u64 }
+
+impl<K: Eq + Hash, V> SentBaselines<K, V> {
+    pub fn new() -> Self {
+        Self {
+            seq: 0,
+            acked: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Starts a new package and returns its sequence number
+    pub fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        // Forget about entities that haven't changed for a while, e.g. because
+        // they left the area of the client
+        if self.seq % MAX_BASELINE_AGE as u64 == 0 {
+            let seq = self.seq;
+            self.entries.retain(|_, entry| {
+                entry
+                    .pending
+                    .back()
+                    .or_else(|| entry.acked.as_ref())
+                    .map_or(false, |(sent, _)| !too_old(*sent, seq))
+            });
+        }
+        self.seq
+    }
+
+    /// Sequence number of the current package
+    pub fn seq(&self) -> u64 { self.seq }
+
+    /// The client applied all packages up to and including `seq`
+    pub fn ack(&mut self, seq: u64) {
+        // Acks for packages that weren't sent can happen after the client was
+        // moved to another entity, they are ignored
+        if seq > self.seq || seq <= self.acked {
+            return;
+        }
+        self.acked = seq;
+        for entry in self.entries.values_mut() {
+            while entry
+                .pending
+                .front()
+                .map_or(false, |(sent, _)| *sent <= seq)
+            {
+                entry.acked = entry.pending.pop_front();
+            }
+        }
+    }
+
+    /// Newest acknowledged value of `key` that is still usable as a baseline
+    /// for the current package, together with its age
+    pub fn baseline(&self, key: &K) -> Option<(u8, &V)> {
+        let (sent, value) = self.entries.get(key)?.acked.as_ref()?;
+        (!too_old(*sent, self.seq)).then(|| ((self.seq - sent) as u8, value))
+    }
+
+    /// Remembers that `value` was sent with the current package
+    pub fn sent(&mut self, key: K, value: V) {
+        let seq = self.seq;
+        let entry = self.entries.entry(key).or_insert_with(|| SentBaseline {
+            acked: None,
+            pending: VecDeque::new(),
+        });
+        // A client that stopped acknowledging shouldn't make us run out of memory
+        while entry
+            .pending
+            .front()
+            .map_or(false, |(sent, _)| too_old(*sent, seq))
+        {
+            entry.pending.pop_front();
+        }
+        entry.pending.push_back((seq, value));
+    }
+}
+
+impl<K: Eq + Hash, V> ReceivedBaselines<K, V> {
+    pub fn new() -> Self {
+        Self {
+            seq: 0,
+            acked: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Starts applying the package with sequence number `seq`
+    pub fn begin(&mut self, seq: u64) {
+        if seq <= self.seq {
+            // The server started over, e.g. because this client was moved to
+            // another entity, none of the old values is referenced anymore
+            self.entries.clear();
+            self.acked = 0;
+        }
+        self.seq = seq;
+        if seq % MAX_BASELINE_AGE as u64 == 0 {
+            self.entries.retain(|_, received| {
+                received
+                    .back()
+                    .map_or(false, |(received, _)| !too_old(*received, seq))
+            });
+        }
+    }
+
+    /// Value of `key` that was received `age` packages ago
+    pub fn baseline(&self, key: &K, age: u8) -> Option<&V> {
+        let seq = self.seq.checked_sub(age as u64)?;
+        self.entries
+            .get(key)?
+            .iter()
+            .rev()
+            .find(|(received, _)| *received == seq)
+            .map(|(_, value)| value)
+    }
+
+    /// Remembers that `value` was received with the current package
+    pub fn received(&mut self, key: K, value: V) {
+        let seq = self.seq;
+        let received = self.entries.entry(key).or_default();
+        while received
+            .front()
+            .map_or(false, |(received, _)| too_old(*received, seq))
+        {
+            received.pop_front();
+        }
+        received.push_back((seq, value));
+    }
+
+    /// Sequence number to acknowledge to the server, if packages were
+    /// applied since the last acknowledgement
+    pub fn take_ack(&mut self) -> Option<u64> {
+        (self.seq > self.acked).then(|| {
+            self.acked = self.seq;
+            self.seq
+        })
+    }
+}
+
+impl<K: Eq + Hash, V> Default for SentBaselines<K, V> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<K: Eq + Hash, V> Default for ReceivedBaselines<K, V> {
+    fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_acked_values_are_baselines() {
+        let mut sent = SentBaselines::new();
+        assert_eq!(sent.next_seq(), 1);
+        sent.sent(7, 1.0);
+        assert_eq!(sent.next_seq(), 2);
+        sent.sent(7, 2.0);
+        assert_eq!(sent.baseline(&7), None);
+        sent.ack(1);
+        assert_eq!(sent.baseline(&7), Some((1, &1.0)));
+        sent.next_seq();
+        assert_eq!(sent.baseline(&7), Some((2, &1.0)));
+        sent.ack(2);
+        assert_eq!(sent.baseline(&7), Some((1, &2.0)));
+        // acks for packages that were never sent are ignored
+        sent.ack(99);
+        assert_eq!(sent.baseline(&7), Some((1, &2.0)));
+    }
+
+    #[test]
+    fn baselines_expire() {
+        let mut sent = SentBaselines::new();
+        sent.next_seq();
+        sent.sent(7, 1.0);
+        sent.ack(1);
+        for _ in 0..MAX_BASELINE_AGE {
+            sent.next_seq();
+        }
+        assert_eq!(sent.baseline(&7), Some((MAX_BASELINE_AGE, &1.0)));
+        sent.next_seq();
+        assert_eq!(sent.baseline(&7), None);
+    }
+
+    #[test]
+    fn both_sides_agree_on_baselines() {
+        let mut sent = SentBaselines::new();
+        let mut received = ReceivedBaselines::new();
+        for i in 0..200u32 {
+            let seq = sent.next_seq();
+            received.begin(seq);
+            if let Some((age, value)) = sent.baseline(&1) {
+                assert_eq!(received.baseline(&1, age), Some(value));
+            }
+            sent.sent(1, i);
+            received.received(1, i);
+            // the client acknowledges with some delay, and not every package
+            if i % 3 == 0 {
+                if let Some(ack) = received.take_ack() {
+                    sent.ack(ack.saturating_sub(2));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn restarted_sequence_clears_received() {
+        let mut received = ReceivedBaselines::new();
+        received.begin(5);
+        received.received(1, 1.0);
+        assert_eq!(received.take_ack(), Some(5));
+        assert_eq!(received.take_ack(), None);
+        received.begin(1);
+        assert_eq!(received.baseline(&1, 0), None);
+        assert_eq!(received.take_ack(), Some(1));
+    }
+}
@@ -1,5 +1,6 @@
 // Note: Currently only one-way sync is supported until a usecase for two-way
 // sync arises
+mod delta;
 pub mod interpolation;
 mod packet;
 mod sync_ext;
@@ -7,9 +8,10 @@ mod track;
 
 // Reexports
 pub use common::uid::{Uid, UidAllocator};
+pub use delta::{ReceivedBaselines, SentBaselines, MAX_BASELINE_AGE};
 pub use packet::{
     handle_insert, handle_interp_insert, handle_interp_modify, handle_interp_remove, handle_modify,
-    handle_remove, CompPacket, CompSyncPackage, EntityPackage, EntitySyncPackage,
+    handle_remove, CompPacket, CompSyncPackage, CompUpdateKind, EntityPackage, EntitySyncPackage,
     InterpolatableComponent,
 };
 pub use sync_ext::WorldSyncExt;
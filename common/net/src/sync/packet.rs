@@ -184,4 +184,24 @@ impl<P: CompPacket> CompSyncPackage<P> {
         tracker.get_updates_for(uids, storage, filter, &mut self.comp_updates);
         self
     }
+
+    /// Like [`Self::with_component`], but without modifications, for
+    /// components whose modifications are delta synced to each client
+    pub fn with_component_unmodified<'a, C: Component + Clone + Send + Sync>(
+        mut self,
+        uids: &ReadStorage<'a, Uid>,
+        tracker: &UpdateTracker<C>,
+        storage: &ReadStorage<'a, C>,
+        filter: impl Join + Copy,
+    ) -> Self
+    where
+        P: From<C>,
+        C: TryFrom<P>,
+        P::Phantom: From<PhantomData<C>>,
+        P::Phantom: TryInto<PhantomData<C>>,
+        C::Storage: specs::storage::Tracked,
+    {
+        tracker.get_insertions_and_removals_for(uids, storage, filter, &mut self.comp_updates);
+        self
+    }
 }
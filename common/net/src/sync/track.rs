@@ -97,6 +97,42 @@ impl<C: Component + Clone + Send + Sync> UpdateTracker<C> {
         P::Phantom: From<PhantomData<C>>,
         P::Phantom: TryInto<PhantomData<C>>,
         C::Storage: specs::storage::Tracked,
+    {
+        self.push_updates(uids, storage, entity_filter, true, buf);
+    }
+
+    /// Like [`Self::get_updates_for`], but leaves out modifications
+    pub fn get_insertions_and_removals_for<'a, P>(
+        &self,
+        uids: &specs::ReadStorage<'a, Uid>,
+        storage: &specs::ReadStorage<'a, C>,
+        entity_filter: impl Join + Copy,
+        buf: &mut Vec<(u64, CompUpdateKind<P>)>,
+    ) where
+        P: CompPacket,
+        P: From<C>,
+        C: TryFrom<P>,
+        P::Phantom: From<PhantomData<C>>,
+        P::Phantom: TryInto<PhantomData<C>>,
+        C::Storage: specs::storage::Tracked,
+    {
+        self.push_updates(uids, storage, entity_filter, false, buf);
+    }
+
+    fn push_updates<'a, P>(
+        &self,
+        uids: &specs::ReadStorage<'a, Uid>,
+        storage: &specs::ReadStorage<'a, C>,
+        entity_filter: impl Join + Copy,
+        include_modified: bool,
+        buf: &mut Vec<(u64, CompUpdateKind<P>)>,
+    ) where
+        P: CompPacket,
+        P: From<C>,
+        C: TryFrom<P>,
+        P::Phantom: From<PhantomData<C>>,
+        P::Phantom: TryInto<PhantomData<C>>,
+        C::Storage: specs::storage::Tracked,
     {
         // Generate inserted updates
         for (uid, comp, _, _) in (uids, storage, &self.inserted, entity_filter).join() {
@@ -107,11 +143,13 @@ impl<C: Component + Clone + Send + Sync> UpdateTracker<C> {
         }
 
         // Generate modified updates
-        for (uid, comp, _, _) in (uids, storage, &self.modified, entity_filter).join() {
-            buf.push((
-                (*uid).into(),
-                CompUpdateKind::Modified(P::from(comp.clone())),
-            ));
+        if include_modified {
+            for (uid, comp, _, _) in (uids, storage, &self.modified, entity_filter).join() {
+                buf.push((
+                    (*uid).into(),
+                    CompUpdateKind::Modified(P::from(comp.clone())),
+                ));
+            }
         }
 
         // Generate removed updates
@@ -121,7 +121,12 @@ impl<S: Clone + Eq + Hash, H: BuildHasher + Clone> Astar<S, H> {
     {
         let iter_limit = self.max_iters.min(self.iter + iters);
         while self.iter < iter_limit {
-            if let Some(PathEntry { node, .. }) = self.potential_nodes.pop() {
+            if let Some(PathEntry { node, cost }) = self.potential_nodes.pop() {
+                // Nodes are pushed again when a cheaper way to them is found, the older
+                // entries are outdated
+                if self.final_scores.get(&node).map_or(false, |score| cost > *score) {
+                    continue;
+                }
                 if satisfied(&node) {
                     return PathResult::Path(self.reconstruct_path_to(node));
                 } else {
@@ -143,12 +148,11 @@ impl<S: Clone + Eq + Hash, H: BuildHasher + Clone> Astar<S, H> {
                                 self.cheapest_cost = Some(h);
                             };
 
-                            if self.visited.insert(neighbor.clone()) {
-                                self.potential_nodes.push(PathEntry {
-                                    node: neighbor,
-                                    cost: neighbor_cost,
-                                });
-                            }
+                            self.visited.insert(neighbor.clone());
+                            self.potential_nodes.push(PathEntry {
+                                node: neighbor,
+                                cost: neighbor_cost,
+                            });
                         }
                     }
                 }
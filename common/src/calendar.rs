@@ -0,0 +1,156 @@
+//! The in-game calendar. Dates aren't tracked on their own, they are derived
+//! from the [`TimeOfDay`] with the structure of the year described by the
+//! [`Calendar`] resource.
+use crate::resources::TimeOfDay;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Length of a day in [`TimeOfDay`] units
+pub const DAY_LENGTH: f64 = 60.0 * 60.0 * 24.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub const ALL: [Self; 4] = [Self::Spring, Self::Summer, Self::Autumn, Self::Winter];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Spring => "Spring",
+            Self::Summer => "Summer",
+            Self::Autumn => "Autumn",
+            Self::Winter => "Winter",
+        }
+    }
+}
+
+/// The structure of the year, the seasons split it into four equal parts
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Calendar {
+    pub days_per_month: u32,
+    pub months_per_year: u32,
+    /// Day of the year the world starts at, when the [`TimeOfDay`] is 0
+    pub start_day: u32,
+    /// Stay in this season all year round instead of cycling through them
+    pub fixed_season: Option<Season>,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Self {
+            days_per_month: 7,
+            months_per_year: 12,
+            // Early spring
+            start_day: 7,
+            fixed_season: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarDate {
+    /// Years since the world started, negative before
+    pub year: i64,
+    /// Month of the year, starting at 0
+    pub month: u32,
+    /// Day of the month, starting at 0
+    pub day: u32,
+    pub season: Season,
+}
+
+impl Calendar {
+    pub fn days_per_year(&self) -> u32 {
+        (self.days_per_month.max(1) * self.months_per_year.max(1)).max(1)
+    }
+
+    pub fn date(&self, time_of_day: TimeOfDay) -> CalendarDate {
+        let days_per_year = self.days_per_year() as i64;
+        let days = (time_of_day.0 / DAY_LENGTH).floor() as i64 + self.start_day as i64;
+        let day_of_year = days.rem_euclid(days_per_year) as u32;
+        let days_per_month = self.days_per_month.max(1);
+        CalendarDate {
+            year: days.div_euclid(days_per_year),
+            month: day_of_year / days_per_month,
+            day: day_of_year % days_per_month,
+            season: self.fixed_season.unwrap_or_else(|| {
+                Season::ALL[(day_of_year as usize * Season::ALL.len()) / days_per_year as usize]
+            }),
+        }
+    }
+
+    pub fn season(&self, time_of_day: TimeOfDay) -> Season { self.date(time_of_day).season }
+}
+
+impl fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Day {} of month {}, year {} ({})",
+            self.day + 1,
+            self.month + 1,
+            self.year + 1,
+            self.season.name()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar() -> Calendar {
+        Calendar {
+            days_per_month: 10,
+            months_per_year: 4,
+            start_day: 0,
+            fixed_season: None,
+        }
+    }
+
+    fn day(n: f64) -> TimeOfDay { TimeOfDay(n * DAY_LENGTH + 1.0) }
+
+    #[test]
+    fn dates() {
+        let calendar = calendar();
+        assert_eq!(calendar.date(day(0.0)), CalendarDate {
+            year: 0,
+            month: 0,
+            day: 0,
+            season: Season::Spring,
+        });
+        assert_eq!(calendar.date(day(25.0)), CalendarDate {
+            year: 0,
+            month: 2,
+            day: 5,
+            season: Season::Autumn,
+        });
+        assert_eq!(calendar.date(day(41.0)), CalendarDate {
+            year: 1,
+            month: 0,
+            day: 1,
+            season: Season::Spring,
+        });
+        // Setting the time back before the start of the world
+        assert_eq!(calendar.date(day(-1.0)), CalendarDate {
+            year: -1,
+            month: 3,
+            day: 9,
+            season: Season::Winter,
+        });
+    }
+
+    #[test]
+    fn fixed_season() {
+        let calendar = Calendar {
+            fixed_season: Some(Season::Summer),
+            ..calendar()
+        };
+        assert!((0..40).all(|n| calendar.season(day(n as f64)) == Season::Summer));
+    }
+}
@@ -46,6 +46,7 @@ pub enum ChatCommand {
     Airship,
     Alias,
     ApplyBuff,
+    Audit,
     Ban,
     BattleMode,
     BattleModeForce,
@@ -82,6 +83,7 @@ pub enum ChatCommand {
     MakeNpc,
     MakeSprite,
     Motd,
+    Mute,
     Object,
     PermitBuild,
     Players,
@@ -97,11 +99,13 @@ pub enum ChatCommand {
     SkillPoint,
     SkillPreset,
     Spawn,
+    Stash,
     Sudo,
     Tell,
     Time,
     Tp,
     Unban,
+    Unmute,
     Version,
     Waypoint,
     Whitelist,
@@ -315,6 +319,11 @@ impl ChatCommand {
                 "Cast a buff on player",
                 Some(Admin),
             ),
+            ChatCommand::Audit => cmd(
+                vec![Integer("count", 10, Optional), Any("username", Optional)],
+                "Show the last privileged commands, optionally only those of the given player",
+                Some(Admin),
+            ),
             ChatCommand::Ban => cmd(
                 vec![
                     Any("username", Required),
@@ -513,6 +522,17 @@ impl ChatCommand {
                 Some(Admin),
             ),
             ChatCommand::Motd => cmd(vec![Message(Optional)], "View the server description", None),
+            ChatCommand::Mute => cmd(
+                vec![
+                    Any("username", Required),
+                    Boolean("overwrite", "true".to_string(), Optional),
+                    Any("mute duration", Optional),
+                    Message(Optional),
+                ],
+                "Mute a player with a given username, for a given duration (if provided).  Pass \
+                 true for overwrite to alter an existing mute.",
+                Some(Moderator),
+            ),
             ChatCommand::Object => cmd(
                 vec![Enum("object", OBJECTS.clone(), Required)],
                 "Spawn an object",
@@ -597,6 +617,24 @@ impl ChatCommand {
                 "Spawn a test entity",
                 Some(Admin),
             ),
+            ChatCommand::Stash => cmd(
+                vec![
+                    Enum(
+                        "action",
+                        vec![
+                            "list".to_owned(),
+                            "deposit".to_owned(),
+                            "withdraw".to_owned(),
+                        ],
+                        Required,
+                    ),
+                    Integer("slot", 0, Optional),
+                ],
+                "Use the stash shared by the characters of your account:\n* list (show the items \
+                 in your stash and inventory)\n* deposit <inventory slot> (move an item to the \
+                 stash)\n* withdraw <stash slot> (move an item to the inventory)",
+                None,
+            ),
             ChatCommand::Sudo => cmd(
                 vec![PlayerName(Required), SubCommand],
                 "Run command as if you were another player",
@@ -622,6 +660,11 @@ impl ChatCommand {
                 "Remove the ban for the given username",
                 Some(Moderator),
             ),
+            ChatCommand::Unmute => cmd(
+                vec![Any("username", Required)],
+                "Remove the mute for the given username",
+                Some(Moderator),
+            ),
             ChatCommand::Version => cmd(vec![], "Prints server version", None),
             ChatCommand::Waypoint => cmd(
                 vec![],
@@ -650,6 +693,7 @@ impl ChatCommand {
             ChatCommand::Airship => "airship",
             ChatCommand::Alias => "alias",
             ChatCommand::ApplyBuff => "buff",
+            ChatCommand::Audit => "audit",
             ChatCommand::Ban => "ban",
             ChatCommand::BattleMode => "battlemode",
             ChatCommand::BattleModeForce => "battlemode_force",
@@ -686,6 +730,7 @@ impl ChatCommand {
             ChatCommand::MakeNpc => "make_npc",
             ChatCommand::MakeSprite => "make_sprite",
             ChatCommand::Motd => "motd",
+            ChatCommand::Mute => "mute",
             ChatCommand::Object => "object",
             ChatCommand::PermitBuild => "permit_build",
             ChatCommand::Players => "players",
@@ -701,11 +746,13 @@ impl ChatCommand {
             ChatCommand::SkillPoint => "skill_point",
             ChatCommand::SkillPreset => "skill_preset",
             ChatCommand::Spawn => "spawn",
+            ChatCommand::Stash => "stash",
             ChatCommand::Sudo => "sudo",
             ChatCommand::Tell => "tell",
             ChatCommand::Time => "time",
             ChatCommand::Tp => "tp",
             ChatCommand::Unban => "unban",
+            ChatCommand::Unmute => "unmute",
             ChatCommand::Version => "version",
             ChatCommand::Waypoint => "waypoint",
             ChatCommand::Wiring => "wiring",
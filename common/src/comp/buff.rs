@@ -381,6 +381,8 @@ pub enum BuffSource {
     World,
     /// Applied by command
     Command,
+    /// Applied by a plugin
+    Plugin,
     /// Applied by an item
     Item,
     /// Applied by another buff (like an after-effect)
@@ -53,6 +53,10 @@ pub enum InventoryManip {
         craft_sprite: Option<Vec3<i32>>,
     },
     SwapEquippedWeapons,
+    /// Move an item from the inventory to the stash of the account
+    StashDeposit(InvSlotId),
+    /// Move an item from the given slot of the stash to the inventory
+    StashWithdraw(usize),
 }
 
 impl From<InventoryAction> for InventoryManip {
@@ -65,6 +65,8 @@ pub enum Subject {
 pub enum MoodContext {
     /// The weather is good, sunny, appeasing, etc...
     GoodWeather,
+    /// It's raining, snowing or storming
+    BadWeather,
     /// Someone completed a quest and enlightened this NPC's day
     QuestSucceeded { hero: String, quest_desc: String },
 
@@ -109,6 +111,7 @@ impl MoodContext {
     pub fn describe(&self) -> String {
         match &self {
             MoodContext::GoodWeather => "The weather is great today!".to_string(),
+            MoodContext::BadWeather => "The weather is awful today!".to_string(),
             MoodContext::QuestSucceeded { hero, quest_desc } => {
                 format!("{} helped me on {}", hero, quest_desc)
             },
@@ -90,6 +90,20 @@ impl Health {
     /// Returns the fraction of health an entity has remaining
     pub fn fraction(&self) -> f32 { self.current() / self.maximum().max(1.0) }
 
+    /// Returns the scaled current, base maximum and maximum health, used to
+    /// sync health field by field
+    pub fn scaled_values(&self) -> [u32; 3] { [self.current, self.base_max, self.maximum] }
+
+    /// Replaces the values returned by [`Self::scaled_values`]
+    pub fn with_scaled_values(self, [current, base_max, maximum]: [u32; 3]) -> Self {
+        Self {
+            current,
+            base_max,
+            maximum,
+            ..self
+        }
+    }
+
     /// Updates the maximum value for health
     pub fn update_maximum(&mut self, modifiers: comp::stats::StatsModifier) {
         let maximum = modifiers
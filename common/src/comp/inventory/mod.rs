@@ -24,6 +24,7 @@ pub mod item;
 pub mod loadout;
 pub mod loadout_builder;
 pub mod slot;
+pub mod stash;
 #[cfg(test)] mod test;
 #[cfg(test)] mod test_helpers;
 pub mod trade_pricing;
@@ -779,6 +780,7 @@ pub enum InventoryUpdateEvent {
     Consumed(String),
     Gave,
     Given,
+    Removed,
     Swapped,
     Dropped,
     Collected(Item),
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use specs::Component;
+use specs_idvs::IdvStorage;
+
+use crate::comp::Item;
+
+/// Number of slots in the stash of an account
+pub const STASH_SLOTS: usize = 36;
+
+/// Storage shared by all the characters of a player's account. Unlike the
+/// inventory it isn't tied to a character, so it is kept when characters are
+/// deleted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Stash {
+    slots: Vec<Option<Item>>,
+}
+
+impl Default for Stash {
+    fn default() -> Self {
+        Self {
+            slots: (0..STASH_SLOTS).map(|_| None).collect(),
+        }
+    }
+}
+
+impl Stash {
+    pub fn slots(&self) -> impl Iterator<Item = &Option<Item>> { self.slots.iter() }
+
+    pub fn get(&self, slot: usize) -> Option<&Item> { self.slots.get(slot)?.as_ref() }
+
+    pub fn get_mut(&mut self, slot: usize) -> Option<&mut Item> {
+        self.slots.get_mut(slot)?.as_mut()
+    }
+
+    /// Put an item in the stash, stacking it onto an item of the same kind
+    /// when possible. The item is given back if the stash is full.
+    pub fn push(&mut self, item: Item) -> Result<(), Item> {
+        if item.is_stackable() {
+            if let Some(stack) = self
+                .slots
+                .iter_mut()
+                .filter_map(Option::as_mut)
+                .find(|stack| {
+                    **stack == item && stack.amount().checked_add(item.amount()).is_some()
+                })
+            {
+                stack
+                    .increase_amount(item.amount())
+                    .expect("Already checked that there is enough room.");
+                return Ok(());
+            }
+        }
+
+        match self.slots.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(item);
+                Ok(())
+            },
+            None => Err(item),
+        }
+    }
+
+    /// Put an item in the given slot, fails if the slot is out of range or
+    /// already occupied.
+    pub fn insert_at(&mut self, slot: usize, item: Item) -> Result<(), Item> {
+        match self.slots.get_mut(slot) {
+            Some(slot @ None) => {
+                *slot = Some(item);
+                Ok(())
+            },
+            _ => Err(item),
+        }
+    }
+
+    pub fn remove(&mut self, slot: usize) -> Option<Item> { self.slots.get_mut(slot)?.take() }
+}
+
+impl Component for Stash {
+    type Storage = IdvStorage<Self>;
+}
@@ -83,7 +83,9 @@ pub use self::{
             tool::{self, AbilityItem},
             Item, ItemConfig, ItemDrop,
         },
-        slot, Inventory, InventoryUpdate, InventoryUpdateEvent,
+        slot,
+        stash::Stash,
+        Inventory, InventoryUpdate, InventoryUpdateEvent,
     },
     last::Last,
     location::{Waypoint, WaypointArea},
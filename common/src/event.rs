@@ -116,8 +116,13 @@ pub enum ServerEvent {
             comp::Inventory,
             Option<comp::Waypoint>,
             Vec<(comp::Pet, comp::Body, comp::Stats)>,
+            comp::Stash,
         ),
     },
+    /// A client watches without a character
+    InitSpectator {
+        entity: EcsEntity,
+    },
     ExitIngame {
         entity: EcsEntity,
     },
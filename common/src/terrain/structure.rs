@@ -5,14 +5,15 @@ use crate::{
     vol::{BaseVol, ReadVol, SizedVol, WriteVol},
     volumes::dyna::{Dyna, DynaError},
 };
+use dot_vox::DotVoxData;
 use hashbrown::HashMap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{num::NonZeroU8, sync::Arc};
 use vek::*;
 
 make_case_elim!(
     structure_block,
-    #[derive(Copy, Clone, PartialEq, Debug, Deserialize)]
+    #[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
     #[repr(u8)]
     pub enum StructureBlock {
         None = 0,
@@ -37,6 +38,8 @@ make_case_elim!(
         Chestnut = 19,
         Baobab = 20,
         BirchWood = 21,
+        RotatedSprite(kind: SpriteKind, ori: u8) = 22,
+        WaterSprite(kind: SpriteKind, ori: u8) = 23,
     }
 );
 
@@ -104,6 +107,30 @@ impl Structure {
         StructuresGroup::load_expect(&["world.manifests.", specifier].concat())
     }
 
+    /// Build a structure from the models of a `.vox` file, merged by placing
+    /// the minimum corner of model `i` at `model_offsets[i]`. Models without an
+    /// offset are left out, and `center` is relative to the minimum corner of
+    /// the merged models. Unlike structures loaded from a manifest, only
+    /// `custom_indices` are applied on top of the palette, not the default
+    /// indices used by trees.
+    pub fn from_dot_vox(
+        dot_vox_data: &DotVoxData,
+        model_offsets: &[Vec3<i32>],
+        center: Vec3<i32>,
+        custom_indices: &HashMap<u8, StructureBlock>,
+    ) -> Self {
+        let mut indices = [None; 256];
+        for (&idx, &custom) in custom_indices {
+            indices[idx as usize] = Some(custom);
+        }
+
+        Structure {
+            center,
+            base: Arc::new(BaseStructure::from_models(dot_vox_data, model_offsets)),
+            custom_indices: indices,
+        }
+    }
+
     pub fn with_center(mut self, center: Vec3<i32>) -> Self {
         self.center = center;
         self
@@ -142,9 +169,28 @@ impl assets::Compound for BaseStructure {
         specifier: &str,
     ) -> Result<Self, Error> {
         let dot_vox_data = cache.load::<DotVoxAsset>(specifier)?.read();
-        let dot_vox_data = &dot_vox_data.0;
 
-        if let Some(model) = dot_vox_data.models.get(0) {
+        Ok(BaseStructure::from_dot_vox(&dot_vox_data.0))
+    }
+}
+
+impl BaseStructure {
+    fn from_dot_vox(dot_vox_data: &DotVoxData) -> Self {
+        Self::from_models(dot_vox_data, &[Vec3::zero()])
+    }
+
+    fn from_models(dot_vox_data: &DotVoxData, model_offsets: &[Vec3<i32>]) -> Self {
+        let models = dot_vox_data.models.iter().zip(model_offsets);
+        let bounds = models
+            .clone()
+            .map(|(model, offset)| Aabb {
+                min: *offset,
+                max: *offset
+                    + Vec3::new(model.size.x, model.size.y, model.size.z).map(|e| e as i32),
+            })
+            .reduce(|a, b| a.union(b));
+
+        if let Some(bounds) = bounds {
             let mut palette = [StructureBlock::None; 256];
 
             for (i, col) in dot_vox_data
@@ -156,25 +202,23 @@ impl assets::Compound for BaseStructure {
                 palette[(i + 1).min(255)] = StructureBlock::Filled(BlockKind::Misc, col);
             }
 
-            let mut vol = Dyna::filled(
-                Vec3::new(model.size.x, model.size.y, model.size.z),
-                None,
-                (),
-            );
-
-            for voxel in &model.voxels {
-                let _ = vol.set(
-                    Vec3::new(voxel.x, voxel.y, voxel.z).map(i32::from),
-                    Some(NonZeroU8::new(voxel.i + 1).unwrap()),
-                );
+            let mut vol = Dyna::filled(Vec3::from(bounds.size()).map(|e: i32| e as u32), None, ());
+
+            for (model, offset) in models {
+                for voxel in &model.voxels {
+                    let _ = vol.set(
+                        Vec3::new(voxel.x, voxel.y, voxel.z).map(i32::from) + *offset - bounds.min,
+                        Some(NonZeroU8::new(voxel.i + 1).unwrap()),
+                    );
+                }
             }
 
-            Ok(BaseStructure { vol, palette })
+            BaseStructure { vol, palette }
         } else {
-            Ok(BaseStructure {
+            BaseStructure {
                 vol: Dyna::filled(Vec3::zero(), None, ()),
                 palette: [StructureBlock::None; 256],
-            })
+            }
         }
     }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    calendar::Season,
     comp::inventory::{slot::InvSlotId, trade_pricing::TradePricing, Inventory},
     terrain::BiomeKind,
     uid::Uid,
@@ -334,6 +335,18 @@ impl Good {
             _ => 0.0,
         }
     }
+
+    /// How much dearer or cheaper the good is in `season`, food is plentiful
+    /// after the harvest and scarce in winter when firewood is in demand too
+    pub fn seasonal_price_factor(&self, season: Season) -> f32 {
+        match (self, season) {
+            (Good::Food | Good::Flour, Season::Autumn) => 0.75,
+            (Good::Food | Good::Flour, Season::Winter) => 1.5,
+            (Good::Food | Good::Flour, Season::Spring) => 1.2,
+            (Good::Wood, Season::Winter) => 1.3,
+            _ => 1.0,
+        }
+    }
 }
 
 // ideally this would be a real Id<Site> but that is from the world crate
@@ -351,6 +364,15 @@ pub struct SitePrices {
 }
 
 impl SitePrices {
+    /// Adjust the prices to the demand in `season`, see
+    /// [`Good::seasonal_price_factor`]
+    pub fn with_season(mut self, season: Season) -> Self {
+        for (good, value) in self.values.iter_mut() {
+            *value *= good.seasonal_price_factor(season);
+        }
+        self
+    }
+
     pub fn balance(
         &self,
         offers: &[HashMap<InvSlotId, u32>; 2],
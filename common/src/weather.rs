@@ -0,0 +1,264 @@
+//! Weather, simulated by the server on a coarse grid over the world. Clients
+//! are synced the cells around them as [`WeatherPatch`]es of their
+//! [`WeatherGrid`] resource.
+use crate::{
+    grid::Grid,
+    terrain::{Block, TerrainChunkSize, TerrainGrid},
+    vol::{ReadVol, RectVolSize},
+};
+use serde::{Deserialize, Serialize};
+use vek::*;
+
+/// Side length of a weather cell in chunks
+pub const CHUNKS_PER_CELL: u32 = 16;
+/// Side length of a weather cell in blocks
+pub const CELL_SIZE: u32 = CHUNKS_PER_CELL * TerrainChunkSize::RECT_SIZE.x;
+/// Cells in each direction around a client that are synced to it, enough to
+/// interpolate the weather anywhere in its view
+pub const SYNC_RADIUS: i32 = 2;
+
+/// Rain or snow above this makes for noticeably bad weather
+const PRECIPITATION_THRESHOLD: f32 = 0.2;
+/// Cloud cover above this hides the sun
+const CLOUD_THRESHOLD: f32 = 0.4;
+/// Wind speed in blocks/s above which heavy precipitation becomes a storm
+const STORM_WIND_SPEED: f32 = 15.0;
+/// Roofs up to this many blocks above keep rain and snow off
+const SHELTER_HEIGHT: f32 = 32.0;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Weather {
+    /// Cloud cover, 0.0 is a clear sky and 1.0 overcast
+    pub cloud: f32,
+    /// Rainfall, 0.0 is dry and 1.0 a downpour
+    pub rain: f32,
+    /// Snowfall, 0.0 is dry and 1.0 a blizzard
+    pub snow: f32,
+    /// Wind velocity in blocks/s
+    pub wind: Vec2<f32>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WeatherKind {
+    Clear,
+    Cloudy,
+    Rain,
+    Snow,
+    Storm,
+}
+
+impl Weather {
+    pub fn new(cloud: f32, rain: f32, snow: f32, wind: Vec2<f32>) -> Self {
+        Self {
+            cloud,
+            rain,
+            snow,
+            wind,
+        }
+    }
+
+    pub fn precipitation(&self) -> f32 { self.rain + self.snow }
+
+    pub fn kind(&self) -> WeatherKind {
+        if self.precipitation() > 0.5 && self.wind.magnitude_squared() > STORM_WIND_SPEED.powi(2) {
+            WeatherKind::Storm
+        } else if self.snow > PRECIPITATION_THRESHOLD && self.snow >= self.rain {
+            WeatherKind::Snow
+        } else if self.rain > PRECIPITATION_THRESHOLD {
+            WeatherKind::Rain
+        } else if self.cloud > CLOUD_THRESHOLD {
+            WeatherKind::Cloudy
+        } else {
+            WeatherKind::Clear
+        }
+    }
+
+    pub fn is_clear(&self) -> bool { self.kind() == WeatherKind::Clear }
+
+    /// Whether enough rain falls to soak entities and put out fires
+    pub fn is_raining(&self) -> bool { self.rain > PRECIPITATION_THRESHOLD }
+
+    pub fn lerp(from: Self, to: Self, t: f32) -> Self {
+        let t = t.clamped(0.0, 1.0);
+        Self {
+            cloud: Lerp::lerp_unclamped(from.cloud, to.cloud, t),
+            rain: Lerp::lerp_unclamped(from.rain, to.rain, t),
+            snow: Lerp::lerp_unclamped(from.snow, to.snow, t),
+            wind: Lerp::lerp_unclamped(from.wind, to.wind, t),
+        }
+    }
+}
+
+/// The weather of every cell of the world, see [`CELL_SIZE`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WeatherGrid {
+    weather: Grid<Weather>,
+}
+
+impl Default for WeatherGrid {
+    /// Clear weather everywhere, until the server tells otherwise
+    fn default() -> Self { Self::new(Vec2::zero()) }
+}
+
+impl WeatherGrid {
+    /// A grid of `size` cells with clear weather
+    pub fn new(size: Vec2<u32>) -> Self {
+        Self {
+            weather: Grid::new(size.map(|e| e as i32), Weather::default()),
+        }
+    }
+
+    /// Size of a grid covering a world of `world_size` chunks
+    pub fn size_for_world(world_size: Vec2<u32>) -> Vec2<u32> {
+        world_size.map(|e| (e + CHUNKS_PER_CELL - 1) / CHUNKS_PER_CELL)
+    }
+
+    pub fn size(&self) -> Vec2<u32> { self.weather.size().map(|e| e as u32) }
+
+    pub fn get(&self, cell: Vec2<i32>) -> Weather {
+        self.weather.get(cell).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, cell: Vec2<i32>, weather: Weather) { self.weather.set(cell, weather); }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Vec2<i32>, &Weather)> + '_ { self.weather.iter() }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Vec2<i32>, &mut Weather)> + '_ {
+        self.weather.iter_mut()
+    }
+
+    /// The cells within `radius` cells of the one containing the world
+    /// position `wpos`
+    pub fn patch_around(&self, wpos: Vec2<f32>, radius: i32) -> WeatherPatch {
+        let size = self.weather.size();
+        let cell = wpos.map(|e| (e / CELL_SIZE as f32).floor() as i32);
+        let min = (cell - radius).map2(size, |e, sz| e.clamped(0, sz));
+        let max = (cell + radius + 1).map2(size, |e, sz| e.clamped(0, sz));
+        WeatherPatch {
+            grid_size: self.size(),
+            min,
+            weather: Grid::populate_from(max - min, |pos| self.get(min + pos)),
+        }
+    }
+
+    /// Updates the cells of `patch`, the other cells keep the weather they
+    /// were last synced
+    pub fn apply_patch(&mut self, patch: WeatherPatch) {
+        if self.size() != patch.grid_size {
+            *self = Self::new(patch.grid_size);
+        }
+        for (pos, weather) in patch.weather.iter() {
+            self.set(patch.min + pos, *weather);
+        }
+    }
+
+    /// Weather at the world position `wpos`, interpolated between the centres
+    /// of the surrounding cells
+    pub fn get_interpolated(&self, wpos: Vec2<f32>) -> Weather {
+        let size = self.weather.size();
+        if size.x == 0 || size.y == 0 {
+            return Weather::default();
+        }
+        let cell_pos = wpos / CELL_SIZE as f32 - 0.5;
+        let base = cell_pos.map(|e| e.floor());
+        let t = cell_pos - base;
+        let get = |offs: Vec2<i32>| {
+            let cell = (base.map(|e| e as i32) + offs).map2(size, |e, sz| e.clamped(0, sz - 1));
+            self.get(cell)
+        };
+        Weather::lerp(
+            Weather::lerp(get(Vec2::new(0, 0)), get(Vec2::new(1, 0)), t.x),
+            Weather::lerp(get(Vec2::new(0, 1)), get(Vec2::new(1, 1)), t.x),
+            t.y,
+        )
+    }
+}
+
+/// Part of a [`WeatherGrid`], see [`WeatherGrid::patch_around`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WeatherPatch {
+    /// Size of the whole grid
+    grid_size: Vec2<u32>,
+    /// Cell of the grid at the origin of the patch
+    min: Vec2<i32>,
+    weather: Grid<Weather>,
+}
+
+/// Whether something above `pos` keeps rain and snow off it
+pub fn is_sheltered(terrain: &TerrainGrid, pos: Vec3<f32>) -> bool {
+    let from = pos + Vec3::unit_z() * 2.0;
+    matches!(
+        terrain
+            .ray(from, from + Vec3::unit_z() * SHELTER_HEIGHT)
+            .until(Block::is_opaque)
+            .cast()
+            .1,
+        Ok(Some(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_between_cells() {
+        let mut grid = WeatherGrid::new(Vec2::new(2, 1));
+        grid.set(Vec2::new(1, 0), Weather::new(1.0, 1.0, 0.0, Vec2::zero()));
+        let half = CELL_SIZE as f32 * 0.5;
+        assert_eq!(grid.get_interpolated(Vec2::new(half, half)).rain, 0.0);
+        assert_eq!(grid.get_interpolated(Vec2::new(half * 3.0, half)).rain, 1.0);
+        let between = grid.get_interpolated(Vec2::new(half * 2.0, half));
+        assert!((between.rain - 0.5).abs() < 1e-4);
+        // Outside the grid the closest cell is used
+        assert_eq!(grid.get_interpolated(Vec2::new(-1000.0, 0.0)).rain, 0.0);
+        assert_eq!(
+            WeatherGrid::default().get_interpolated(Vec2::zero()),
+            Weather::default()
+        );
+    }
+
+    #[test]
+    fn patches_around_positions() {
+        let mut grid = WeatherGrid::new(Vec2::new(8, 8));
+        grid.set(Vec2::new(0, 0), Weather::new(1.0, 0.0, 0.0, Vec2::zero()));
+        grid.set(Vec2::new(1, 1), Weather::new(0.0, 1.0, 0.0, Vec2::zero()));
+        grid.set(Vec2::new(7, 7), Weather::new(0.0, 0.0, 1.0, Vec2::zero()));
+
+        // Cut off at the edge of the grid
+        let patch = grid.patch_around(Vec2::new(1.5, 1.5) * CELL_SIZE as f32, 1);
+        assert_eq!(patch.weather.size(), Vec2::new(3, 3));
+        let mut synced = WeatherGrid::default();
+        synced.apply_patch(patch);
+        assert_eq!(synced.size(), grid.size());
+        assert_eq!(synced.get(Vec2::new(0, 0)), grid.get(Vec2::new(0, 0)));
+        assert_eq!(synced.get(Vec2::new(1, 1)), grid.get(Vec2::new(1, 1)));
+        assert_eq!(synced.get(Vec2::new(7, 7)), Weather::default());
+
+        let patch = grid.patch_around(Vec2::broadcast(-100.0), 1);
+        assert_eq!(patch.weather.size(), Vec2::new(1, 1));
+        let patch = grid.patch_around(Vec2::broadcast(1e6), 1);
+        assert_eq!(patch.weather.size(), Vec2::zero());
+    }
+
+    #[test]
+    fn weather_kinds() {
+        assert_eq!(Weather::default().kind(), WeatherKind::Clear);
+        assert_eq!(
+            Weather::new(0.8, 0.0, 0.0, Vec2::zero()).kind(),
+            WeatherKind::Cloudy
+        );
+        assert_eq!(
+            Weather::new(0.8, 0.6, 0.0, Vec2::zero()).kind(),
+            WeatherKind::Rain
+        );
+        assert_eq!(
+            Weather::new(0.8, 0.0, 0.6, Vec2::zero()).kind(),
+            WeatherKind::Snow
+        );
+        assert_eq!(
+            Weather::new(1.0, 0.8, 0.0, Vec2::new(20.0, 0.0)).kind(),
+            WeatherKind::Storm
+        );
+    }
+}
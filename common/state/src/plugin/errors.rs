@@ -1,5 +1,5 @@
 use bincode::ErrorKind;
-use wasmer::{ExportError, InstantiationError, RuntimeError};
+use wasmer::{CompileError, ExportError, InstantiationError, RuntimeError};
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
@@ -8,12 +8,14 @@ pub enum PluginError {
     Toml(toml::de::Error),
     NoConfig,
     NoSuchModule,
+    NoSuchPlugin(String),
     Encoding(Box<ErrorKind>),
     PluginModuleError(String, String, PluginModuleError),
 }
 
 #[derive(Debug)]
 pub enum PluginModuleError {
+    Compile(CompileError),
     InstantiationError(InstantiationError),
     MemoryAllocation(MemoryAllocationError),
     MemoryUninit(ExportError),
@@ -21,6 +23,21 @@ pub enum PluginModuleError {
     RunFunction(RuntimeError),
     InvalidArgumentType(),
     Encoding(Box<ErrorKind>),
+    /// The module ran more instructions than its fuel limit allows
+    OutOfFuel,
+    /// The linear memory of the module grew past its limit (size in bytes)
+    MemoryLimitExceeded(u64),
+}
+
+impl PluginModuleError {
+    /// Whether the error is caused by the module exceeding one of its
+    /// resource limits
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(
+            self,
+            PluginModuleError::OutOfFuel | PluginModuleError::MemoryLimitExceeded(_)
+        )
+    }
 }
 
 #[derive(Debug)]
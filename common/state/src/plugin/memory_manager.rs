@@ -7,7 +7,9 @@ use specs::{
 use wasmer::{Function, Memory, Value};
 
 use common::{
-    comp::{Health, Player},
+    calendar::CalendarDate,
+    comp::{Alignment, Body, Group, Health, Inventory, Player, Pos, SkillSet},
+    terrain::TerrainGrid,
     uid::{Uid, UidAllocator},
 };
 
@@ -18,7 +20,15 @@ pub struct EcsWorld<'a, 'b> {
     pub health: EcsComponentAccess<'a, 'b, Health>,
     pub uid: EcsComponentAccess<'a, 'b, Uid>,
     pub player: EcsComponentAccess<'a, 'b, Player>,
+    pub pos: EcsComponentAccess<'a, 'b, Pos>,
+    pub body: EcsComponentAccess<'a, 'b, Body>,
+    pub inventory: EcsComponentAccess<'a, 'b, Inventory>,
+    pub skill_set: EcsComponentAccess<'a, 'b, SkillSet>,
+    pub group: EcsComponentAccess<'a, 'b, Group>,
+    pub alignment: EcsComponentAccess<'a, 'b, Alignment>,
     pub uid_allocator: &'b Read<'a, UidAllocator>,
+    pub terrain: &'b TerrainGrid,
+    pub date: CalendarDate,
 }
 
 pub enum EcsComponentAccess<'a, 'b, T: Component> {
@@ -1,6 +1,8 @@
 pub mod errors;
 pub mod memory_manager;
 pub mod module;
+pub mod storage;
+pub mod tunables;
 pub mod wasm_env;
 
 use common::assets::ASSETS_PATH;
@@ -10,24 +12,52 @@ use std::{
     fs,
     io::Read,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use plugin_api::Event;
+use plugin_api::{Action, Event};
 
 use self::{
     errors::PluginError,
     memory_manager::EcsWorld,
     module::{PluginModule, PreparedEventQuery},
+    storage::{PluginStorage, StorageWrite},
 };
 
 use rayon::prelude::*;
 
+/// Resource limits applied to each module of a plugin, they can be set in the
+/// `[limits]` section of `plugin.toml`. A plugin exceeding them is disabled
+/// until it is reloaded.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginLimits {
+    /// Number of WASM instructions a module can execute per call
+    pub fuel: u64,
+    /// Size in bytes the linear memory of a module can grow to
+    pub memory: u64,
+}
+
+impl Default for PluginLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 10_000_000,
+            memory: 64 * 1024 * 1024,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PluginData {
     name: String,
     modules: HashSet<PathBuf>,
     dependencies: HashSet<String>,
+    #[serde(default)]
+    limits: PluginLimits,
 }
 
 #[derive(Clone)]
@@ -36,6 +66,10 @@ pub struct Plugin {
     modules: Vec<PluginModule>,
     #[allow(dead_code)]
     files: HashMap<PathBuf, Vec<u8>>,
+    /// The file the plugin was loaded from, used to reload it
+    path: Option<PathBuf>,
+    disabled: Arc<AtomicBool>,
+    storage: Arc<PluginStorage>,
 }
 
 impl Plugin {
@@ -64,12 +98,20 @@ impl Plugin {
         )
         .map_err(PluginError::Toml)?;
 
+        let storage = Arc::new(PluginStorage::default());
+
         let modules = data
             .modules
             .iter()
             .map(|path| {
                 let wasm_data = files.remove(path).ok_or(PluginError::NoSuchModule)?;
-                PluginModule::new(data.name.to_owned(), &wasm_data).map_err(|e| {
+                PluginModule::new(
+                    data.name.to_owned(),
+                    &wasm_data,
+                    data.limits,
+                    Arc::clone(&storage),
+                )
+                .map_err(|e| {
                     PluginError::PluginModuleError(data.name.to_owned(), "<init>".to_owned(), e)
                 })
             })
@@ -79,9 +121,27 @@ impl Plugin {
             data,
             modules,
             files,
+            path: None,
+            disabled: Arc::new(AtomicBool::new(false)),
+            storage,
         })
     }
 
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, PluginError> {
+        let mut plugin =
+            Self::from_reader(fs::File::open(path.as_ref()).map_err(PluginError::Io)?)?;
+        plugin.path = Some(path.as_ref().to_owned());
+        Ok(plugin)
+    }
+
+    pub fn name(&self) -> &str { &self.data.name }
+
+    /// Whether the plugin was disabled for exceeding its resource limits
+    pub fn is_disabled(&self) -> bool { self.disabled.load(Ordering::Relaxed) }
+
+    /// Execute an event on the modules of the plugin. If a module exceeds its
+    /// resource limits the plugin is disabled and the error is logged instead
+    /// of being returned.
     pub fn execute_prepared<T>(
         &self,
         ecs: &EcsWorld,
@@ -90,20 +150,48 @@ impl Plugin {
     where
         T: Event,
     {
+        let mut responses = Vec::new();
+        for module in &self.modules {
+            if self.is_disabled() {
+                break;
+            }
+            match module.try_execute(ecs, event) {
+                Some(Ok(response)) => responses.push(response),
+                Some(Err(e)) if e.is_limit_exceeded() => {
+                    error!(
+                        ?e,
+                        "Plugin '{}' exceeded its resource limits while executing {}, disabling it",
+                        self.data.name,
+                        event.get_function_name()
+                    );
+                    self.disabled.store(true, Ordering::Relaxed);
+                },
+                Some(Err(e)) => {
+                    return Err(PluginError::PluginModuleError(
+                        self.data.name.to_owned(),
+                        event.get_function_name().to_owned(),
+                        e,
+                    ));
+                },
+                None => {},
+            }
+        }
+        Ok(responses)
+    }
+
+    pub fn take_pending_actions(&self) -> Vec<Action> {
+        // A disabled plugin can't modify the world anymore, even with the actions it
+        // emitted before exceeding its limits
+        if self.is_disabled() {
+            for module in &self.modules {
+                module.take_pending_actions();
+            }
+            return Vec::new();
+        }
         self.modules
             .iter()
-            .flat_map(|module| {
-                module.try_execute(ecs, event).map(|x| {
-                    x.map_err(|e| {
-                        PluginError::PluginModuleError(
-                            self.data.name.to_owned(),
-                            event.get_function_name().to_owned(),
-                            e,
-                        )
-                    })
-                })
-            })
-            .collect::<Result<Vec<_>, _>>()
+            .flat_map(|module| module.take_pending_actions())
+            .collect()
     }
 }
 
@@ -138,6 +226,42 @@ impl PluginMgr {
             .collect())
     }
 
+    /// Collect the actions emitted by all plugins which modify the world, they
+    /// are applied by the server in the order they were sent
+    pub fn take_pending_actions(&self) -> Vec<Action> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| plugin.take_pending_actions())
+            .collect()
+    }
+
+    /// Insert a value that was loaded from the database into the storage of
+    /// the plugin named `plugin_name`
+    pub fn load_storage_value(&self, plugin_name: &str, key: String, value: Vec<u8>) {
+        if let Some(plugin) = self
+            .plugins
+            .iter()
+            .find(|plugin| plugin.data.name == plugin_name)
+        {
+            plugin.storage.load(key, value);
+        }
+    }
+
+    /// Collect the writes to the storage of every plugin that still have to be
+    /// persisted, along with the name of the plugin they belong to
+    pub fn take_storage_writes(&self) -> Vec<(String, StorageWrite)> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| {
+                plugin
+                    .storage
+                    .take_pending_writes()
+                    .into_iter()
+                    .map(move |write| (plugin.data.name.clone(), write))
+            })
+            .collect()
+    }
+
     pub fn execute_event<T>(
         &self,
         ecs: &EcsWorld,
@@ -149,6 +273,41 @@ impl PluginMgr {
         self.execute_prepared(ecs, &PreparedEventQuery::new(event)?)
     }
 
+    /// Unload the plugin named `name` and load it again from the file it was
+    /// originally loaded from. This also re-enables a plugin that was disabled
+    /// for exceeding its resource limits.
+    ///
+    /// The old plugin is kept if the new one fails to load.
+    pub fn reload_plugin(&mut self, name: &str) -> Result<&Plugin, PluginError> {
+        let index = self
+            .plugins
+            .iter()
+            .position(|plugin| plugin.data.name == name)
+            .ok_or_else(|| PluginError::NoSuchPlugin(name.to_owned()))?;
+        let path = self.plugins[index]
+            .path
+            .clone()
+            .ok_or_else(|| PluginError::NoSuchPlugin(name.to_owned()))?;
+
+        info!("Reloading plugin at {:?}", path);
+        let plugin = Plugin::from_path(&path)?;
+        plugin.storage.take_from(&self.plugins[index].storage);
+        if plugin.data.name != name {
+            warn!(
+                "Plugin '{}' was renamed to '{}' when reloading",
+                name, plugin.data.name
+            );
+        }
+        self.plugins[index] = plugin;
+        info!(
+            "Reloaded plugin '{}' with {} module(s)",
+            self.plugins[index].data.name,
+            self.plugins[index].modules.len()
+        );
+
+        Ok(&self.plugins[index])
+    }
+
     pub fn from_dir<P: AsRef<Path>>(path: P) -> Result<Self, PluginError> {
         let plugins = fs::read_dir(path)
             .map_err(PluginError::Io)?
@@ -163,8 +322,7 @@ impl PluginMgr {
                         .unwrap_or(false)
                 {
                     info!("Loading plugin at {:?}", entry.path());
-                    Plugin::from_reader(fs::File::open(entry.path()).map_err(PluginError::Io)?)
-                        .map(Some)
+                    Plugin::from_path(entry.path()).map(Some)
                 } else {
                     Ok(None)
                 }
@@ -5,16 +5,34 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use specs::saveload::MarkerAllocator;
-use wasmer::{imports, Cranelift, Function, Instance, Memory, Module, Store, Universal, Value};
+use common::{
+    comp::{group, Item},
+    uid::Uid,
+    vol::ReadVol,
+};
+use specs::{saveload::MarkerAllocator, Component, Entity, Join};
+use wasmer::{
+    imports, wasmparser::Operator, BaseTunables, CompilerConfig, Cranelift, Function, Instance,
+    Memory, Module, Pages, Store, Target, Universal, Value, WASM_PAGE_SIZE,
+};
+use wasmer_middlewares::{
+    metering::{get_remaining_points, set_remaining_points, MeteringPoints},
+    Metering,
+};
 
 use super::{
     errors::{PluginError, PluginModuleError},
-    memory_manager::{self, EcsAccessManager, EcsWorld, MemoryManager},
+    memory_manager::{self, EcsAccessManager, EcsComponentAccess, EcsWorld, MemoryManager},
+    storage::PluginStorage,
+    tunables::LimitingTunables,
     wasm_env::HostFunctionEnvironement,
+    PluginLimits,
 };
 
-use plugin_api::{Action, EcsAccessError, Event, Retrieve, RetrieveError, RetrieveResult};
+use plugin_api::{
+    Action, EcsAccessError, EntityBody, Event, ItemStack, Retrieve, RetrieveError, RetrieveResult,
+    SkillGroupInfo, SkillSetInfo,
+};
 
 #[derive(Clone)]
 /// This structure represent the WASM State of the plugin.
@@ -25,23 +43,42 @@ pub struct PluginModule {
     events: HashSet<String>,
     allocator: Function,
     memory: Memory,
+    pending_actions: Arc<Mutex<Vec<Action>>>,
+    limits: PluginLimits,
     #[allow(dead_code)]
     name: String,
 }
 
 impl PluginModule {
     /// This function takes bytes from a WASM File and compile them
-    pub fn new(name: String, wasm_data: &[u8]) -> Result<Self, PluginModuleError> {
+    pub fn new(
+        name: String,
+        wasm_data: &[u8],
+        limits: PluginLimits,
+        storage: Arc<PluginStorage>,
+    ) -> Result<Self, PluginModuleError> {
+        // Every executed instruction costs one point of fuel, the remaining fuel is
+        // reset before each call into the module
+        let metering = Arc::new(Metering::new(limits.fuel, |_: &Operator| -> u64 { 1 }));
+        let mut compiler = Cranelift::default();
+        compiler.push_middleware(metering);
         // This is creating the engine is this case a JIT based on Cranelift
-        let engine = Universal::new(Cranelift::default()).engine();
-        // We are creating an enironnement
-        let store = Store::new(&engine);
+        let engine = Universal::new(compiler).engine();
+        // We are creating an enironnement, its tunables cap the memory of the module
+        // to the number of pages the limit needs, rounded up so a limit below one page
+        // still leaves the module some memory
+        let pages = limits.memory.saturating_add(WASM_PAGE_SIZE as u64 - 1) / WASM_PAGE_SIZE as u64;
+        let tunables = LimitingTunables::new(
+            BaseTunables::for_target(&Target::default()),
+            Pages(pages.clamp(1, u32::MAX as u64) as u32),
+        );
+        let store = Store::new_with_tunables(&engine, tunables);
         // We are compiling the WASM file in the previously generated environement
-        let module = Module::new(&store, &wasm_data).expect("Can't compile");
+        let module = Module::new(&store, &wasm_data).map_err(PluginModuleError::Compile)?;
 
         // This is the function imported into the wasm environement
         fn raw_emit_actions(env: &HostFunctionEnvironement, ptr: i64, len: i64) {
-            handle_actions(match env.read_data(from_i64(ptr), from_i64(len)) {
+            handle_actions(env, match env.read_data(from_i64(ptr), from_i64(len)) {
                 Ok(e) => e,
                 Err(e) => {
                     tracing::error!(?e, "Can't decode action");
@@ -52,7 +89,7 @@ impl PluginModule {
 
         fn raw_retrieve_action(env: &HostFunctionEnvironement, ptr: i64, len: i64) -> i64 {
             let out = match env.read_data(from_i64(ptr), from_i64(len)) {
-                Ok(data) => retrieve_action(&env.ecs, data),
+                Ok(data) => retrieve_action(&env.ecs, &env.storage, data),
                 Err(e) => Err(RetrieveError::BincodeError(e.to_string())),
             };
 
@@ -67,12 +104,13 @@ impl PluginModule {
 
         let ecs = Arc::new(EcsAccessManager::default());
         let memory_manager = Arc::new(MemoryManager::default());
+        let pending_actions = Arc::new(Mutex::new(Vec::new()));
 
         // Create an import object.
         let import_object = imports! {
             "env" => {
-                "raw_emit_actions" => Function::new_native_with_env(&store, HostFunctionEnvironement::new(name.clone(), ecs.clone(),memory_manager.clone()), raw_emit_actions),
-                "raw_retrieve_action" => Function::new_native_with_env(&store, HostFunctionEnvironement::new(name.clone(), ecs.clone(),memory_manager.clone()), raw_retrieve_action),
+                "raw_emit_actions" => Function::new_native_with_env(&store, HostFunctionEnvironement::new(name.clone(), ecs.clone(),memory_manager.clone(), pending_actions.clone(), storage.clone()), raw_emit_actions),
+                "raw_retrieve_action" => Function::new_native_with_env(&store, HostFunctionEnvironement::new(name.clone(), ecs.clone(),memory_manager.clone(), pending_actions.clone(), storage), raw_retrieve_action),
                 "dbg" => Function::new_native(&store, dbg),
             }
         };
@@ -99,10 +137,18 @@ impl PluginModule {
                 .map(|(name, _)| name.to_string())
                 .collect(),
             wasm_state: Arc::new(Mutex::new(instance)),
+            pending_actions,
+            limits,
             name,
         })
     }
 
+    /// This function takes all the actions emitted by the module that still
+    /// have to be applied to the world
+    pub fn take_pending_actions(&self) -> Vec<Action> {
+        std::mem::take(&mut *self.pending_actions.lock().unwrap())
+    }
+
     /// This function tries to execute an event for the current module. Will
     /// return None if the event doesn't exists
     pub fn try_execute<T>(
@@ -184,13 +230,16 @@ fn execute_raw(
     event_name: &str,
     bytes: &[u8],
 ) -> Result<Vec<u8>, PluginModuleError> {
+    // Each call gets the full fuel budget of the module
+    set_remaining_points(instance, module.limits.fuel);
+
     // This write into memory `bytes` using allocation if necessary returning a
     // pointer and a length
 
-    let (mem_position, len) =
-        module
-            .memory_manager
-            .write_bytes(&module.memory, &module.allocator, bytes)?;
+    let (mem_position, len) = module
+        .memory_manager
+        .write_bytes(&module.memory, &module.allocator, bytes)
+        .map_err(|e| check_limits(module, instance, e))?;
 
     // This gets the event function from module exports
 
@@ -203,7 +252,7 @@ fn execute_raw(
 
     let function_result = func
         .call(&[Value::I64(to_i64(mem_position)), Value::I64(to_i64(len))])
-        .map_err(PluginModuleError::RunFunction)?;
+        .map_err(|e| check_limits(module, instance, PluginModuleError::RunFunction(e)))?;
 
     // Waiting for `multi-value` to be added to LLVM. So we encode a pointer to a
     // u128 that represent [u64; 2]
@@ -227,63 +276,173 @@ fn execute_raw(
     ))
 }
 
+/// Replace the error of a call into the module by `OutOfFuel` or
+/// `MemoryLimitExceeded` if the call failed because the module hit one of its
+/// limits
+fn check_limits(
+    module: &PluginModule,
+    instance: &Instance,
+    error: PluginModuleError,
+) -> PluginModuleError {
+    let memory_size = module.memory.data_size();
+    match get_remaining_points(instance) {
+        MeteringPoints::Exhausted => PluginModuleError::OutOfFuel,
+        // The tunables make growing the memory fail past the limit, which the
+        // module can only answer by trapping
+        MeteringPoints::Remaining(_)
+            if memory_size + WASM_PAGE_SIZE as u64 > module.limits.memory =>
+        {
+            PluginModuleError::MemoryLimitExceeded(memory_size)
+        },
+        MeteringPoints::Remaining(_) => error,
+    }
+}
+
 fn retrieve_action(
     ecs: &EcsAccessManager,
+    storage: &PluginStorage,
     action: Retrieve,
 ) -> Result<RetrieveResult, RetrieveError> {
+    // Safety: No reference is leaked out the function so it is safe.
+    let world = unsafe {
+        ecs.get().ok_or(RetrieveError::EcsAccessError(
+            EcsAccessError::EcsPointerNotAvailable,
+        ))?
+    };
     match action {
-        Retrieve::GetPlayerName(e) => {
-            // Safety: No reference is leaked out the function so it is safe.
-            let world = unsafe {
-                ecs.get().ok_or(RetrieveError::EcsAccessError(
-                    EcsAccessError::EcsPointerNotAvailable,
-                ))?
+        Retrieve::GetPlayerName(e) => Ok(RetrieveResult::GetPlayerName(
+            get_component(world, &world.player, e, "Player")?
+                .alias
+                .to_owned(),
+        )),
+        Retrieve::GetEntityHealth(e) => Ok(RetrieveResult::GetEntityHealth(
+            get_component(world, &world.health, e, "Health")?.clone(),
+        )),
+        Retrieve::GetEntityPosition(e) => Ok(RetrieveResult::GetEntityPosition(
+            get_component(world, &world.pos, e, "Pos")?.0,
+        )),
+        Retrieve::GetEntityBody(e) => {
+            let body = get_component(world, &world.body, e, "Body")?;
+            Ok(RetrieveResult::GetEntityBody(EntityBody {
+                kind: body.to_string(),
+                height: body.height(),
+                mass: body.mass().0,
+            }))
+        },
+        Retrieve::GetEntityInventory(e) => {
+            let inventory = get_component(world, &world.inventory, e, "Inventory")?;
+            let item_stack = |item: &Item, equipped| ItemStack {
+                item: item.item_definition_id().to_owned(),
+                amount: item.amount(),
+                equipped,
             };
-            let player = world.uid_allocator.retrieve_entity_internal(e.0).ok_or(
-                RetrieveError::EcsAccessError(EcsAccessError::EcsEntityNotFound(e)),
-            )?;
-
-            Ok(RetrieveResult::GetPlayerName(
-                world
-                    .player
-                    .get(player)
-                    .ok_or_else(|| {
-                        RetrieveError::EcsAccessError(EcsAccessError::EcsComponentNotFound(
-                            e,
-                            "Player".to_owned(),
-                        ))
-                    })?
-                    .alias
-                    .to_owned(),
+            Ok(RetrieveResult::GetEntityInventory(
+                inventory
+                    .equipped_items()
+                    .map(|item| item_stack(item, true))
+                    .chain(
+                        inventory
+                            .slots()
+                            .flatten()
+                            .map(|item| item_stack(item, false)),
+                    )
+                    .collect(),
             ))
         },
-        Retrieve::GetEntityHealth(e) => {
-            // Safety: No reference is leaked out the function so it is safe.
-            let world = unsafe {
-                ecs.get().ok_or(RetrieveError::EcsAccessError(
-                    EcsAccessError::EcsPointerNotAvailable,
-                ))?
-            };
-            let player = world.uid_allocator.retrieve_entity_internal(e.0).ok_or(
-                RetrieveError::EcsAccessError(EcsAccessError::EcsEntityNotFound(e)),
-            )?;
-            Ok(RetrieveResult::GetEntityHealth(
+        Retrieve::GetEntitySkillSet(e) => {
+            let skill_set = get_component(world, &world.skill_set, e, "SkillSet")?;
+            Ok(RetrieveResult::GetEntitySkillSet(SkillSetInfo {
+                skill_groups: skill_set
+                    .skill_groups
+                    .iter()
+                    .map(|group| SkillGroupInfo {
+                        kind: format!("{:?}", group.skill_group_kind),
+                        exp: group.exp,
+                        available_sp: group.available_sp,
+                        earned_sp: group.earned_sp,
+                    })
+                    .collect(),
+                skills: skill_set
+                    .skills
+                    .iter()
+                    .map(|(skill, level)| (format!("{:?}", skill), *level))
+                    .collect(),
+            }))
+        },
+        Retrieve::GetEntityGroup(e) => {
+            let entity = get_entity(world, e)?;
+            // Enemies and village NPCs share a group each, they are not reported as they
+            // are not actual groups
+            let members = world
+                .group
+                .get(entity)
+                .filter(|entity_group| {
+                    **entity_group != group::ENEMY && **entity_group != group::NPC
+                })
+                .map(|entity_group| {
+                    world
+                        .entities
+                        .join()
+                        .filter(|member| world.group.get(*member) == Some(entity_group))
+                        .filter_map(|member| world.uid.get(member).copied())
+                        .collect()
+                });
+            Ok(RetrieveResult::GetEntityGroup(members))
+        },
+        Retrieve::GetEntityAlignment(e) => {
+            let entity = get_entity(world, e)?;
+            Ok(RetrieveResult::GetEntityAlignment(
                 world
-                    .health
-                    .get(player)
-                    .ok_or_else(|| {
-                        RetrieveError::EcsAccessError(EcsAccessError::EcsComponentNotFound(
-                            e,
-                            "Health".to_owned(),
-                        ))
-                    })?
-                    .clone(),
+                    .alignment
+                    .get(entity)
+                    .map(|alignment| (*alignment).into()),
             ))
         },
+        Retrieve::GetNearbyEntities { pos, radius } => Ok(RetrieveResult::GetNearbyEntities(
+            world
+                .entities
+                .join()
+                .filter(|entity| {
+                    world
+                        .pos
+                        .get(*entity)
+                        .map_or(false, |p| p.0.distance_squared(pos) <= radius.powi(2))
+                })
+                .filter_map(|entity| world.uid.get(entity).copied())
+                .collect(),
+        )),
+        Retrieve::GetBlock(pos) => {
+            let block = world.terrain.get(pos).map_err(|_| {
+                RetrieveError::EcsAccessError(EcsAccessError::EcsBlockNotLoaded(pos))
+            })?;
+            Ok(RetrieveResult::GetBlock((*block).into()))
+        },
+        Retrieve::GetStorageValue(key) => Ok(RetrieveResult::GetStorageValue(storage.get(&key))),
+        Retrieve::GetDate => Ok(RetrieveResult::GetDate(world.date)),
     }
 }
 
-fn handle_actions(actions: Vec<Action>) {
+fn get_entity(world: &EcsWorld, uid: Uid) -> Result<Entity, RetrieveError> {
+    world
+        .uid_allocator
+        .retrieve_entity_internal(uid.0)
+        .ok_or(RetrieveError::EcsAccessError(
+            EcsAccessError::EcsEntityNotFound(uid),
+        ))
+}
+
+fn get_component<'c, T: Component>(
+    world: &EcsWorld,
+    storage: &'c EcsComponentAccess<T>,
+    uid: Uid,
+    name: &str,
+) -> Result<&'c T, RetrieveError> {
+    storage.get(get_entity(world, uid)?).ok_or_else(|| {
+        RetrieveError::EcsAccessError(EcsAccessError::EcsComponentNotFound(uid, name.to_owned()))
+    })
+}
+
+fn handle_actions(env: &HostFunctionEnvironement, actions: Vec<Action>) {
     for action in actions {
         match action {
             Action::ServerClose => {
@@ -299,6 +458,21 @@ fn handle_actions(actions: Vec<Action>) {
             Action::KillEntity(e) => {
                 tracing::info!("Kill Entity {}", e);
             },
+            // The storage is updated right away so the plugin reads its own writes, the
+            // server persists the writes later
+            Action::SetStorageValue { key, value } => env.storage.set(key, Some(value)),
+            Action::RemoveStorageValue(key) => env.storage.set(key, None),
+            // These actions modify the world, they are queued to be applied by the server
+            // during its next tick
+            action @ (Action::SpawnEntity { .. }
+            | Action::TeleportEntity(..)
+            | Action::GiveItem { .. }
+            | Action::RemoveItem { .. }
+            | Action::ApplyBuff { .. }
+            | Action::SetBlock { .. }
+            | Action::SetSprite { .. }) => {
+                env.pending_actions.lock().unwrap().push(action);
+            },
         }
     }
 }
@@ -0,0 +1,50 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, RwLock},
+};
+
+/// A change of a value in the storage of a plugin, `None` means that the value
+/// was removed
+pub type StorageWrite = (String, Option<Vec<u8>>);
+
+/// The key/value storage of a plugin. The values are kept in memory so plugins
+/// can read them without blocking on the database, writes are queued until
+/// the server persists them.
+#[derive(Default)]
+pub struct PluginStorage {
+    values: RwLock<HashMap<String, Vec<u8>>>,
+    pending_writes: Mutex<Vec<StorageWrite>>,
+}
+
+impl PluginStorage {
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.values.read().unwrap().get(key).cloned()
+    }
+
+    pub fn set(&self, key: String, value: Option<Vec<u8>>) {
+        let mut values = self.values.write().unwrap();
+        match &value {
+            Some(value) => values.insert(key.clone(), value.clone()),
+            None => values.remove(&key),
+        };
+        self.pending_writes.lock().unwrap().push((key, value));
+    }
+
+    /// Insert a value that is already persisted, it isn't queued as a write
+    pub fn load(&self, key: String, value: Vec<u8>) {
+        self.values.write().unwrap().insert(key, value);
+    }
+
+    /// Take the writes that haven't been persisted yet, in the order they
+    /// happened
+    pub fn take_pending_writes(&self) -> Vec<StorageWrite> {
+        std::mem::take(&mut *self.pending_writes.lock().unwrap())
+    }
+
+    /// Move the values and pending writes of another storage into this one,
+    /// used to keep the data of a plugin when it is reloaded
+    pub fn take_from(&self, other: &PluginStorage) {
+        *self.values.write().unwrap() = std::mem::take(&mut *other.values.write().unwrap());
+        *self.pending_writes.lock().unwrap() = other.take_pending_writes();
+    }
+}
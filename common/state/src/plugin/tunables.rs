@@ -0,0 +1,86 @@
+use loupe::MemoryUsage;
+use std::{ptr::NonNull, sync::Arc};
+use wasmer::{
+    vm::{self, MemoryError, MemoryStyle, TableStyle, VMMemoryDefinition, VMTableDefinition},
+    MemoryType, Pages, TableType, Tunables,
+};
+
+/// Tunables capping the linear memory of a module, growing the memory past the
+/// limit fails inside the module instead of being noticed after the call.
+#[derive(MemoryUsage)]
+pub struct LimitingTunables<T: Tunables> {
+    limit: Pages,
+    base: T,
+}
+
+impl<T: Tunables> LimitingTunables<T> {
+    pub fn new(base: T, limit: Pages) -> Self { Self { limit, base } }
+
+    /// Lower the maximum size requested by the module to the limit
+    fn adjust_memory(&self, requested: &MemoryType) -> MemoryType {
+        let mut adjusted = *requested;
+        adjusted.maximum = Some(
+            requested
+                .maximum
+                .map_or(self.limit, |maximum| maximum.min(self.limit)),
+        );
+        adjusted
+    }
+
+    fn validate_memory(&self, ty: &MemoryType) -> Result<(), MemoryError> {
+        if ty.minimum > self.limit {
+            Err(MemoryError::Generic(
+                "Minimum exceeds the allowed memory limit".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T: Tunables> Tunables for LimitingTunables<T> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(&self.adjust_memory(memory))
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle { self.base.table_style(table) }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        let adjusted = self.adjust_memory(ty);
+        self.validate_memory(&adjusted)?;
+        self.base.create_host_memory(&adjusted, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        let adjusted = self.adjust_memory(ty);
+        self.validate_memory(&adjusted)?;
+        self.base
+            .create_vm_memory(&adjusted, style, vm_definition_location)
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base.create_vm_table(ty, style, vm_definition_location)
+    }
+}
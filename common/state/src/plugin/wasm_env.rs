@@ -1,11 +1,14 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use serde::{de::DeserializeOwned, Serialize};
 use wasmer::{Function, HostEnvInitError, Instance, LazyInit, Memory, WasmerEnv};
 
+use plugin_api::Action;
+
 use super::{
     errors::PluginModuleError,
     memory_manager::{self, EcsAccessManager, MemoryManager},
+    storage::PluginStorage,
 };
 
 #[derive(Clone)]
@@ -17,7 +20,9 @@ pub struct HostFunctionEnvironement {
     pub allocator: LazyInit<Function>, // Linked to: wasm_prepare_buffer
     pub memory_manager: Arc<MemoryManager>, /* This object represent the current buffer size and
                                    * pointer */
-    pub name: String, // This represent the plugin name
+    pub name: String,                             // This represent the plugin name
+    pub pending_actions: Arc<Mutex<Vec<Action>>>, // Actions applied by the server next tick
+    pub storage: Arc<PluginStorage>,              // Key/value storage shared by the plugin modules
 }
 
 impl HostFunctionEnvironement {
@@ -25,6 +30,8 @@ impl HostFunctionEnvironement {
         name: String,
         ecs: Arc<EcsAccessManager>,
         memory_manager: Arc<MemoryManager>,
+        pending_actions: Arc<Mutex<Vec<Action>>>,
+        storage: Arc<PluginStorage>,
     ) -> Self {
         Self {
             memory_manager,
@@ -32,6 +39,8 @@ impl HostFunctionEnvironement {
             allocator: LazyInit::new(),
             memory: LazyInit::new(),
             name,
+            pending_actions,
+            storage,
         }
     }
 
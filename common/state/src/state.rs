@@ -1,10 +1,11 @@
 #[cfg(feature = "plugins")]
 use crate::plugin::memory_manager::EcsWorld;
 #[cfg(feature = "plugins")]
-use crate::plugin::PluginMgr;
+use crate::plugin::{errors::PluginError, module::PreparedEventQuery, PluginMgr};
 #[cfg(feature = "plugins")]
 use common::uid::UidAllocator;
 use common::{
+    calendar::{Calendar, CalendarDate},
     comp,
     event::{EventBus, LocalEvent, ServerEvent},
     outcome::Outcome,
@@ -17,7 +18,9 @@ use common::{
     terrain::{Block, TerrainChunk, TerrainGrid},
     time::DayPeriod,
     trade::Trades,
+    uid::Uid,
     vol::{ReadVol, WriteVol},
+    weather::WeatherGrid,
 };
 use common_base::span;
 use common_ecs::{PhysicsMetrics, SysMetrics};
@@ -48,10 +51,21 @@ const MAX_DELTA_TIME: f32 = 1.0;
 #[derive(Default)]
 pub struct BlockChange {
     blocks: HashMap<Vec3<i32>, Block>,
+    /// The entities responsible for the changes, when known
+    causes: HashMap<Vec3<i32>, Uid>,
 }
 
 impl BlockChange {
-    pub fn set(&mut self, pos: Vec3<i32>, block: Block) { self.blocks.insert(pos, block); }
+    pub fn set(&mut self, pos: Vec3<i32>, block: Block) {
+        self.blocks.insert(pos, block);
+        self.causes.remove(&pos);
+    }
+
+    /// Like [`BlockChange::set`], remembering which entity changed the block
+    pub fn set_by(&mut self, pos: Vec3<i32>, block: Block, cause: Uid) {
+        self.blocks.insert(pos, block);
+        self.causes.insert(pos, cause);
+    }
 
     pub fn try_set(&mut self, pos: Vec3<i32>, block: Block) -> Option<()> {
         if !self.blocks.contains_key(&pos) {
@@ -62,7 +76,20 @@ impl BlockChange {
         }
     }
 
-    pub fn clear(&mut self) { self.blocks.clear(); }
+    /// Like [`BlockChange::try_set`], remembering which entity changed the
+    /// block
+    pub fn try_set_by(&mut self, pos: Vec3<i32>, block: Block, cause: Uid) -> Option<()> {
+        let res = self.try_set(pos, block);
+        if res.is_some() {
+            self.causes.insert(pos, cause);
+        }
+        res
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.causes.clear();
+    }
 }
 
 #[derive(Default)]
@@ -71,6 +98,9 @@ pub struct TerrainChanges {
     pub modified_chunks: HashSet<Vec2<i32>>,
     pub removed_chunks: HashSet<Vec2<i32>>,
     pub modified_blocks: HashMap<Vec3<i32>, Block>,
+    /// The blocks replaced by the last block changes, along with the entity
+    /// that changed them when known
+    pub replaced_blocks: HashMap<Vec3<i32>, (Block, Option<Uid>)>,
 }
 
 impl TerrainChanges {
@@ -92,9 +122,16 @@ pub struct State {
 
 impl State {
     /// Create a new `State` in client mode.
-    pub fn client() -> Self { Self::new(GameMode::Client) }
+    pub fn client() -> Self {
+        #[allow(unused_mut)]
+        let mut state = Self::new(GameMode::Client);
+        #[cfg(feature = "plugins")]
+        state.init_plugins();
+        state
+    }
 
-    /// Create a new `State` in server mode.
+    /// Create a new `State` in server mode. The plugins aren't initialized
+    /// yet, see [`State::init_plugins`].
     pub fn server() -> Self { Self::new(GameMode::Server) }
 
     pub fn new(game_mode: GameMode) -> Self {
@@ -188,6 +225,7 @@ impl State {
         ecs.register::<comp::InventoryUpdate>();
         ecs.register::<comp::Admin>();
         ecs.register::<comp::Waypoint>();
+        ecs.register::<comp::Stash>();
         ecs.register::<comp::Projectile>();
         ecs.register::<comp::Melee>();
         ecs.register::<comp::ItemDrop>();
@@ -199,6 +237,8 @@ impl State {
 
         // Register synced resources used by the ECS.
         ecs.insert(TimeOfDay(0.0));
+        ecs.insert(Calendar::default());
+        ecs.insert(WeatherGrid::default());
 
         // Register unsynced resources used by the ECS.
         ecs.insert(Time(0.0));
@@ -232,29 +272,11 @@ impl State {
         ecs.insert(Trades::default());
         ecs.insert(PlayerPhysicsSettings::default());
 
-        // Load plugins from asset directory
+        // Load plugins from asset directory, their `on_load` event is run by
+        // `init_plugins`
         #[cfg(feature = "plugins")]
         ecs.insert(match PluginMgr::from_assets() {
-            Ok(plugin_mgr) => {
-                let ecs_world = EcsWorld {
-                    entities: &ecs.entities(),
-                    health: ecs.read_component().into(),
-                    uid: ecs.read_component().into(),
-                    uid_allocator: &ecs.read_resource::<UidAllocator>().into(),
-                    player: ecs.read_component().into(),
-                };
-                if let Err(e) = plugin_mgr
-                    .execute_event(&ecs_world, &plugin_api::event::PluginLoadEvent {
-                        game_mode,
-                    })
-                {
-                    tracing::debug!(?e, "Failed to run plugin init");
-                    tracing::info!("Plugins disabled, enable debug logging for more information.");
-                    PluginMgr::default()
-                } else {
-                    plugin_mgr
-                }
-            },
+            Ok(plugin_mgr) => plugin_mgr,
             Err(e) => {
                 tracing::debug!(?e, "Failed to read plugins from assets");
                 tracing::info!("Plugins disabled, enable debug logging for more information.");
@@ -331,6 +353,79 @@ impl State {
     /// Get a mutable reference to the internal ECS world.
     pub fn ecs_mut(&mut self) -> &mut specs::World { &mut self.ecs }
 
+    /// Execute an event on every loaded plugin, giving them read access to the
+    /// ECS.
+    ///
+    /// This must not be called while a component storage used by plugins is
+    /// borrowed mutably.
+    #[cfg(feature = "plugins")]
+    pub fn execute_plugin_event<T: plugin_api::Event>(
+        &self,
+        event: &T,
+    ) -> Result<Vec<T::Response>, PluginError> {
+        let ecs_world = EcsWorld {
+            entities: &self.ecs.entities(),
+            health: self.ecs.read_component().into(),
+            uid: self.ecs.read_component().into(),
+            uid_allocator: &self.ecs.read_resource::<UidAllocator>().into(),
+            player: self.ecs.read_component().into(),
+            pos: self.ecs.read_component().into(),
+            body: self.ecs.read_component().into(),
+            inventory: self.ecs.read_component().into(),
+            skill_set: self.ecs.read_component().into(),
+            group: self.ecs.read_component().into(),
+            alignment: self.ecs.read_component().into(),
+            terrain: &self.ecs.read_resource::<TerrainGrid>(),
+            date: self.get_date(),
+        };
+        self.ecs
+            .read_resource::<PluginMgr>()
+            .execute_event(&ecs_world, event)
+    }
+
+    /// Run the `on_load` event of the plugins, they are disabled if it fails.
+    /// The server runs it once the storage of the plugins is loaded, so that
+    /// what plugins write to it in `on_load` isn't overwritten.
+    #[cfg(feature = "plugins")]
+    pub fn init_plugins(&mut self) {
+        let game_mode = *self.ecs.read_resource::<GameMode>();
+        if let Err(e) = self.execute_plugin_event(&plugin_api::event::PluginLoadEvent { game_mode })
+        {
+            tracing::debug!(?e, "Failed to run plugin init");
+            tracing::info!("Plugins disabled, enable debug logging for more information.");
+            self.ecs.insert(PluginMgr::default());
+        }
+    }
+
+    /// Reload a plugin from disk and run its `on_load` event again.
+    #[cfg(feature = "plugins")]
+    pub fn reload_plugin(&mut self, name: &str) -> Result<(), PluginError> {
+        let mut plugin_mgr = self.ecs.write_resource::<PluginMgr>();
+        let plugin = plugin_mgr.reload_plugin(name)?;
+        let ecs_world = EcsWorld {
+            entities: &self.ecs.entities(),
+            health: self.ecs.read_component().into(),
+            uid: self.ecs.read_component().into(),
+            uid_allocator: &self.ecs.read_resource::<UidAllocator>().into(),
+            player: self.ecs.read_component().into(),
+            pos: self.ecs.read_component().into(),
+            body: self.ecs.read_component().into(),
+            inventory: self.ecs.read_component().into(),
+            skill_set: self.ecs.read_component().into(),
+            group: self.ecs.read_component().into(),
+            alignment: self.ecs.read_component().into(),
+            terrain: &self.ecs.read_resource::<TerrainGrid>(),
+            date: self.get_date(),
+        };
+        plugin.execute_prepared(
+            &ecs_world,
+            &PreparedEventQuery::new(&plugin_api::event::PluginLoadEvent {
+                game_mode: *self.ecs.read_resource::<GameMode>(),
+            })?,
+        )?;
+        Ok(())
+    }
+
     pub fn thread_pool(&self) -> &Arc<ThreadPool> { &self.thread_pool }
 
     /// Get a reference to the `TerrainChanges` structure of the state. This
@@ -344,6 +439,13 @@ impl State {
     /// localised timings.
     pub fn get_time_of_day(&self) -> f64 { self.ecs.read_resource::<TimeOfDay>().0 }
 
+    /// Get the current in-game date, see [`Calendar`].
+    pub fn get_date(&self) -> CalendarDate {
+        self.ecs
+            .read_resource::<Calendar>()
+            .date(*self.ecs.read_resource::<TimeOfDay>())
+    }
+
     /// Get the current in-game day period (period of the day/night cycle)
     /// Get the current in-game day period (period of the day/night cycle)
     pub fn get_day_period(&self) -> DayPeriod { self.get_time_of_day().into() }
@@ -375,6 +477,13 @@ impl State {
         self.ecs.write_resource::<BlockChange>().set(pos, block);
     }
 
+    /// Set a block in this state's terrain on behalf of an entity.
+    pub fn set_block_by(&self, pos: Vec3<i32>, block: Block, cause: Uid) {
+        self.ecs
+            .write_resource::<BlockChange>()
+            .set_by(pos, block, cause);
+    }
+
     /// Check if the block at given position `pos` has already been modified
     /// this tick.
     pub fn can_set_block(&self, pos: Vec3<i32>) -> bool {
@@ -458,12 +567,21 @@ impl State {
             "State::apply_terrain_changes"
         );
         let mut terrain = self.ecs.write_resource::<TerrainGrid>();
-        let mut modified_blocks =
-            std::mem::take(&mut self.ecs.write_resource::<BlockChange>().blocks);
+        let (mut modified_blocks, mut causes) = {
+            let mut block_change = self.ecs.write_resource::<BlockChange>();
+            (
+                std::mem::take(&mut block_change.blocks),
+                std::mem::take(&mut block_change.causes),
+            )
+        };
+        let mut replaced_blocks = HashMap::new();
         // Apply block modifications
         // Only include in `TerrainChanges` if successful
         modified_blocks.retain(|pos, block| {
             let res = terrain.set(*pos, *block);
+            if let Ok(old_block) = &res {
+                replaced_blocks.insert(*pos, (*old_block, causes.remove(pos)));
+            }
             if let (&Ok(old_block), true) = (&res, during_tick) {
                 // NOTE: If the changes are applied during the tick, we push the *old* value as
                 // the modified block (since it otherwise can't be recovered after the tick).
@@ -473,7 +591,9 @@ impl State {
             }
             res.is_ok()
         });
-        self.ecs.write_resource::<TerrainChanges>().modified_blocks = modified_blocks;
+        let mut terrain_changes = self.ecs.write_resource::<TerrainChanges>();
+        terrain_changes.modified_blocks = modified_blocks;
+        terrain_changes.replaced_blocks = replaced_blocks;
     }
 
     /// Execute a single tick, simulating the game state by the given duration.
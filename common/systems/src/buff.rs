@@ -7,19 +7,21 @@ use common::{
             Buffs,
         },
         fluid_dynamics::{Fluid, LiquidKind},
-        Group, Health, HealthChange, Inventory, LightEmitter, ModifierKind, PhysicsState, Stats,
+        Group, Health, HealthChange, Inventory, LightEmitter, ModifierKind, PhysicsState, Pos,
+        Stats,
     },
     event::{EventBus, ServerEvent},
     resources::{DeltaTime, Time},
-    terrain::SpriteKind,
+    terrain::{SpriteKind, TerrainGrid},
     uid::UidAllocator,
+    weather::{self, WeatherGrid},
     Damage, DamageSource,
 };
 use common_ecs::{Job, Origin, Phase, System};
 use hashbrown::HashMap;
 use specs::{
-    saveload::MarkerAllocator, shred::ResourceId, Entities, Join, Read, ReadStorage, SystemData,
-    World, WriteStorage,
+    saveload::MarkerAllocator, shred::ResourceId, Entities, Join, Read, ReadExpect, ReadStorage,
+    SystemData, World, WriteStorage,
 };
 use std::time::Duration;
 
@@ -34,6 +36,9 @@ pub struct ReadData<'a> {
     groups: ReadStorage<'a, Group>,
     uid_allocator: Read<'a, UidAllocator>,
     time: Read<'a, Time>,
+    positions: ReadStorage<'a, Pos>,
+    terrain: ReadExpect<'a, TerrainGrid>,
+    weather: Read<'a, WeatherGrid>,
 }
 
 #[derive(Default)]
@@ -60,31 +65,44 @@ impl<'a> System<'a> for Sys {
         // Set to false to avoid spamming server
         buffs.set_event_emission(false);
         stats.set_event_emission(false);
-        for (entity, mut body, physics_state) in
-            (&read_data.entities, &mut bodies, &read_data.physics_states).join()
+        // How much rain falls on something at `pos`, if any
+        let rain_at = |pos: Option<&Pos>| {
+            let pos = pos?;
+            let weather = read_data.weather.get_interpolated(pos.0.xy());
+            (weather.is_raining() && !weather::is_sheltered(&read_data.terrain, pos.0))
+                .then(|| weather.rain)
+        };
+        for (entity, mut body, physics_state, pos) in (
+            &read_data.entities,
+            &mut bodies,
+            &read_data.physics_states,
+            read_data.positions.maybe(),
+        )
+            .join()
         {
-            // Put out underwater campfires. Logically belongs here since this system also
-            // removes burning, but campfires don't have healths/stats/energies/buffs, so
-            // this needs a separate loop.
+            // Put out underwater or rained on campfires. Logically belongs here since this
+            // system also removes burning, but campfires don't have
+            // healths/stats/energies/buffs, so this needs a separate loop.
             if matches!(*body, Body::Object(object::Body::CampfireLit))
-                && matches!(
+                && (matches!(
                     physics_state.in_fluid,
                     Some(Fluid::Liquid {
                         kind: LiquidKind::Water,
                         ..
                     })
-                )
+                ) || rain_at(pos).is_some())
             {
                 *body = Body::Object(object::Body::Campfire);
                 light_emitters.remove(entity);
             }
         }
-        for (entity, mut buff_comp, mut stat, health, physics_state) in (
+        for (entity, mut buff_comp, mut stat, health, physics_state, pos) in (
             &read_data.entities,
             &mut buffs,
             &mut stats,
             &read_data.healths,
             read_data.physics_states.maybe(),
+            read_data.positions.maybe(),
         )
             .join()
         {
@@ -138,6 +156,28 @@ impl<'a> System<'a> for Sys {
                 }
             }
 
+            if let Some(rain) = rain_at(pos) {
+                if buff_comp.kinds.contains_key(&BuffKind::Burning) {
+                    // Rain puts out burning entities too
+                    server_emitter.emit(ServerEvent::Buff {
+                        entity,
+                        buff_change: BuffChange::RemoveByKind(BuffKind::Burning),
+                    });
+                }
+                if !buff_comp.kinds.contains_key(&BuffKind::Wet) {
+                    // Soak entities standing in the rain, renewed once it wears off
+                    server_emitter.emit(ServerEvent::Buff {
+                        entity,
+                        buff_change: BuffChange::Add(Buff::new(
+                            BuffKind::Wet,
+                            BuffData::new(rain * 0.5, Some(Duration::from_secs_f32(10.0))),
+                            vec![BuffCategory::Natural],
+                            BuffSource::World,
+                        )),
+                    });
+                }
+            }
+
             let (buff_comp_kinds, buff_comp_buffs): (
                 &HashMap<BuffKind, Vec<BuffId>>,
                 &mut HashMap<BuffId, Buff>,
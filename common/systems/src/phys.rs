@@ -14,6 +14,7 @@ use common::{
     uid::Uid,
     util::{Projection, SpatialGrid},
     vol::{BaseVol, ReadVol},
+    weather::WeatherGrid,
 };
 use common_base::{prof_span, span};
 use common_ecs::{Job, Origin, ParMode, Phase, PhysicsMetrics, System};
@@ -118,6 +119,7 @@ pub struct PhysicsRead<'a> {
     character_states: ReadStorage<'a, CharacterState>,
     densities: ReadStorage<'a, Density>,
     stats: ReadStorage<'a, Stats>,
+    weather: Read<'a, WeatherGrid>,
 }
 
 #[derive(SystemData)]
@@ -1181,6 +1183,15 @@ impl<'a> PhysicsData<'a> {
                             },
                         );
 
+                    // The air moves with the wind, which only carries gliders. Everyone else
+                    // would be pushed around by it, even on the ground.
+                    if let (Some(CharacterState::Glide(_)), Some(Fluid::Air { vel: air_vel, .. })) =
+                        (character_state, &mut physics_state.in_fluid)
+                    {
+                        *air_vel =
+                            Vel(read.weather.get_interpolated(tgt_pos.xy()).wind.with_z(0.0));
+                    }
+
                     if tgt_pos != pos.0 {
                         pos_vel_ori_defer.pos = Some(Pos(tgt_pos));
                     } else {
@@ -0,0 +1,261 @@
+///run with
+/// ```bash
+/// (cd network/examples/capture-replay && RUST_BACKTRACE=1 cargo run -- --protocol=tcp --decode=server /tmp/server.vcap)
+/// ```
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use clap::{App, Arg};
+use common_net::msg::{ClientGeneral, ServerGeneral};
+use network_protocol::{
+    CaptureDirection, CapturedFrame, Cid, Mid, ProtocolError, ProtocolEvent, ProtocolMetricCache,
+    ProtocolMetrics, QuicDataFormat, QuicDataFormatStream, QuicRecvProtocol, RecvProtocol, Sid,
+    TcpRecvProtocol, UnreliableSink,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use veloren_network::{Message, Promises};
+
+/// Feeds captured frames into a protocol, one by one
+struct ReplaySink {
+    frames: VecDeque<CapturedFrame>,
+    /// time of the last frame handed to the protocol
+    time: Arc<Mutex<Duration>>,
+    /// QUIC only, which stream a message was send on
+    message_sids: HashMap<Mid, Sid>,
+}
+
+impl ReplaySink {
+    fn next(&mut self) -> Result<CapturedFrame, ProtocolError> {
+        let frame = self.frames.pop_front().ok_or(ProtocolError::Closed)?;
+        *self.time.lock().unwrap() = frame.time;
+        Ok(frame)
+    }
+}
+
+struct TcpReplaySink(ReplaySink);
+struct QuicReplaySink(ReplaySink);
+
+#[async_trait]
+impl UnreliableSink for TcpReplaySink {
+    type DataFormat = BytesMut;
+
+    async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError> {
+        Ok(BytesMut::from(&self.0.next()?.frame[..]))
+    }
+}
+
+#[async_trait]
+impl UnreliableSink for QuicReplaySink {
+    type DataFormat = QuicDataFormat;
+
+    async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError> {
+        let frame = self.0.next()?;
+        // the capture doesn't know the QUIC streams, so every message is
+        // replayed as reliable on its own stream
+        let stream = match (frame.sid, frame.mid) {
+            (Some(sid), Some(mid)) => {
+                self.0.message_sids.insert(mid, sid);
+                QuicDataFormatStream::Reliable(sid)
+            },
+            (None, Some(mid)) => match self.0.message_sids.get(&mid) {
+                Some(sid) => QuicDataFormatStream::Reliable(*sid),
+                None => QuicDataFormatStream::Unreliable,
+            },
+            _ => QuicDataFormatStream::Main,
+        };
+        Ok(QuicDataFormat {
+            stream,
+            data: BytesMut::from(&frame.frame[..]),
+        })
+    }
+}
+
+fn read_capture(path: &str) -> Result<Vec<CapturedFrame>, String> {
+    let data = fs::read(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+    let magic = CapturedFrame::FILE_MAGIC;
+    if !data.starts_with(&magic) {
+        return Err(format!("{} is not a capture", path));
+    }
+    let mut bytes = BytesMut::from(&data[magic.len()..]);
+    let mut frames = vec![];
+    loop {
+        match CapturedFrame::read_bytes(&mut bytes) {
+            Ok(Some(frame)) => frames.push(frame),
+            // the last frame might be cut off, if the capture didn't end cleanly
+            Ok(None) => break,
+            Err(()) => return Err(format!("{} is corrupted", path)),
+        }
+    }
+    Ok(frames)
+}
+
+/// `side` is where the capture was taken, the server sends `ServerGeneral`
+/// and receives `ClientGeneral`
+fn decode(data: Bytes, promises: Promises, side: &str, direction: CaptureDirection) -> String {
+    let message = Message::from_raw(data, promises);
+    let decoded = match (side, direction) {
+        ("server", CaptureDirection::Out) | ("client", CaptureDirection::In) => message
+            .deserialize::<ServerGeneral>()
+            .map(|m| format!("{:?}", m)),
+        _ => message
+            .deserialize::<ClientGeneral>()
+            .map(|m| format!("{:?}", m)),
+    };
+    decoded.unwrap_or_else(|e| format!("undecodable: {:?}", e))
+}
+
+async fn replay(
+    cid: Cid,
+    direction: CaptureDirection,
+    frames: VecDeque<CapturedFrame>,
+    protocol: &str,
+    side: Option<&str>,
+) {
+    let time = Arc::new(Mutex::new(Duration::ZERO));
+    let sink = ReplaySink {
+        frames,
+        time: Arc::clone(&time),
+        message_sids: HashMap::new(),
+    };
+    let metrics =
+        ProtocolMetricCache::new(&cid.to_string(), Arc::new(ProtocolMetrics::new().unwrap()));
+    let mut protocol: Box<dyn RecvProtocol + Send> = match protocol {
+        "tcp" => Box::new(TcpRecvProtocol::new(TcpReplaySink(sink), metrics)),
+        "quic" => Box::new(QuicRecvProtocol::new(QuicReplaySink(sink), metrics)),
+        _ => unreachable!(),
+    };
+
+    println!("channel {} {:?}", cid, direction);
+    let mut promises = HashMap::new();
+    loop {
+        let event = match protocol.recv().await {
+            Ok(event) => event,
+            Err(ProtocolError::Closed) => break,
+            Err(ProtocolError::Violated) => {
+                println!("  protocol violated, stop replaying this channel");
+                break;
+            },
+        };
+        let time = time.lock().unwrap().as_secs_f64();
+        match event {
+            ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises: p,
+                guaranteed_bandwidth,
+            } => {
+                promises.insert(sid, p);
+                println!(
+                    "  {:>10.4}s open stream {} prio: {} promises: {:?} bandwidth: {}",
+                    time, sid, prio, p, guaranteed_bandwidth
+                );
+            },
+            ProtocolEvent::CloseStream { sid } => {
+                println!("  {:>10.4}s close stream {}", time, sid)
+            },
+            ProtocolEvent::Shutdown => println!("  {:>10.4}s shutdown", time),
+            ProtocolEvent::Message { data, sid } => {
+                println!("  {:>10.4}s message on {} {} bytes", time, sid, data.len());
+                if let Some(side) = side {
+                    let p = promises.get(&sid).copied().unwrap_or_else(Promises::empty);
+                    println!("    {}", decode(data, p, side, direction));
+                }
+            },
+        }
+    }
+}
+
+/// Replays a capture created with `Network::capture` through the receiving
+/// side of a protocol, and optionally decodes the messages into the ones of
+/// the game.
+fn main() {
+    let matches = App::new("Veloren Capture Replay Utility")
+        .version("0.1.0")
+        .author("Marcel Märtens <marcel.cochem@googlemail.com>")
+        .about("Replays a capture of veloren-network for offline analysis")
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .help("capture created with `Network::capture`"),
+        )
+        .arg(
+            Arg::with_name("protocol")
+                .long("protocol")
+                .takes_value(true)
+                .default_value("tcp")
+                .possible_values(&["tcp", "quic"])
+                .help("protocol that is used to replay the frames"),
+        )
+        .arg(
+            Arg::with_name("cid")
+                .long("cid")
+                .takes_value(true)
+                .help("only replay this channel"),
+        )
+        .arg(
+            Arg::with_name("direction")
+                .long("direction")
+                .takes_value(true)
+                .default_value("both")
+                .possible_values(&["in", "out", "both"])
+                .help("replay frames that were received, send or both"),
+        )
+        .arg(
+            Arg::with_name("decode")
+                .long("decode")
+                .takes_value(true)
+                .possible_values(&["server", "client"])
+                .help(
+                    "decode messages into `ServerGeneral` and `ClientGeneral`, tell whether the \
+                     capture was taken on the server or client. Messages of the register streams \
+                     can't be decoded",
+                ),
+        )
+        .get_matches();
+
+    let frames = match read_capture(matches.value_of("file").unwrap()) {
+        Ok(frames) => frames,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+    };
+    let cid = matches
+        .value_of("cid")
+        .map(|cid| cid.parse::<Cid>().expect("cid must be a number"));
+    let directions = match matches.value_of("direction").unwrap() {
+        "in" => vec![CaptureDirection::In],
+        "out" => vec![CaptureDirection::Out],
+        _ => vec![CaptureDirection::In, CaptureDirection::Out],
+    };
+    let protocol = matches.value_of("protocol").unwrap();
+    let side = matches.value_of("decode");
+
+    // every direction of a channel is replayed on its own
+    let mut channels = Vec::<(Cid, CaptureDirection, VecDeque<CapturedFrame>)>::new();
+    for frame in frames {
+        if cid.map_or(false, |cid| cid != frame.cid) || !directions.contains(&frame.direction) {
+            continue;
+        }
+        match channels
+            .iter_mut()
+            .find(|(c, d, _)| *c == frame.cid && *d == frame.direction)
+        {
+            Some((_, _, frames)) => frames.push_back(frame),
+            None => channels.push((frame.cid, frame.direction, VecDeque::from(vec![frame]))),
+        }
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        for (cid, direction, frames) in channels {
+            replay(cid, direction, frames, protocol, side).await;
+        }
+    });
+}
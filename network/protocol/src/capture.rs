@@ -0,0 +1,298 @@
+//! Capture of the frames a channel sends and receives, for debugging.
+//!
+//! A protocol with a [`FrameCapture`] hands every frame to a
+//! [`CaptureSink`], before encryption and after decryption. Frames are stored
+//! in their wire format, so a capture can be replayed through a
+//! [`RecvProtocol`] later. [`CapturedFrame::write_bytes`] and
+//! [`CapturedFrame::read_bytes`] define the format of a capture file.
+//!
+//! [`RecvProtocol`]: crate::RecvProtocol
+use crate::{
+    frame::{ITFrame, OTFrame},
+    types::{Cid, Mid, Sid},
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Whether a frame was send or received by the capturing side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureDirection {
+    Out,
+    In,
+}
+
+/// A single frame of a capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedFrame {
+    /// since the capture started
+    pub time: Duration,
+    pub cid: Cid,
+    pub direction: CaptureDirection,
+    pub sid: Option<Sid>,
+    pub mid: Option<Mid>,
+    /// the frame as it is written to the wire, without encryption
+    pub frame: Bytes,
+}
+
+/// Receives the frames of all channels that are captured, e.g. to write them
+/// to a file. Called from within the protocols, so it shouldn't block.
+pub trait CaptureSink: Send + Sync + std::fmt::Debug {
+    fn capture(&self, frame: CapturedFrame);
+}
+
+/// Attached to a protocol via `with_capture` to capture its frames
+#[derive(Debug, Clone)]
+pub struct FrameCapture {
+    cid: Cid,
+    start: Instant,
+    sink: Arc<dyn CaptureSink>,
+}
+
+impl CaptureDirection {
+    fn to_u8(self) -> u8 {
+        match self {
+            CaptureDirection::Out => 0,
+            CaptureDirection::In => 1,
+        }
+    }
+
+    fn from_u8(direction: u8) -> Option<Self> {
+        match direction {
+            0 => Some(CaptureDirection::Out),
+            1 => Some(CaptureDirection::In),
+            _ => None,
+        }
+    }
+}
+
+impl CapturedFrame {
+    // Size WITHOUT the frame itself
+    const CNS: usize = 8 + 8 + 1 + 1 + 8 + 8 + 4;
+    /// Start of every capture file, followed by the frames
+    pub const FILE_MAGIC: [u8; 8] = *b"VELOCAP1";
+    const HAS_MID: u8 = 0b10;
+    const HAS_SID: u8 = 0b01;
+
+    pub fn write_bytes(&self, bytes: &mut BytesMut) {
+        bytes.reserve(Self::CNS + self.frame.len());
+        bytes.put_u64_le(self.time.as_nanos() as u64);
+        bytes.put_u64_le(self.cid);
+        bytes.put_u8(self.direction.to_u8());
+        let mut flags = 0;
+        if self.sid.is_some() {
+            flags |= Self::HAS_SID;
+        }
+        if self.mid.is_some() {
+            flags |= Self::HAS_MID;
+        }
+        bytes.put_u8(flags);
+        bytes.put_u64_le(self.sid.map_or(0, |sid| sid.get_u64()));
+        bytes.put_u64_le(self.mid.unwrap_or(0));
+        bytes.put_u32_le(self.frame.len() as u32);
+        bytes.put_slice(&self.frame);
+    }
+
+    /// Err => cannot recover
+    /// Ok(None) => waiting for more data
+    #[allow(clippy::result_unit_err)]
+    pub fn read_bytes(bytes: &mut BytesMut) -> Result<Option<Self>, ()> {
+        if bytes.len() < Self::CNS {
+            return Ok(None);
+        }
+        let length = u32::from_le_bytes([bytes[34], bytes[35], bytes[36], bytes[37]]) as usize;
+        if bytes.len() < Self::CNS + length {
+            return Ok(None);
+        }
+        let mut bytes = bytes.split_to(Self::CNS + length);
+        let time = Duration::from_nanos(bytes.get_u64_le());
+        let cid = bytes.get_u64_le();
+        let direction = CaptureDirection::from_u8(bytes.get_u8()).ok_or(())?;
+        let flags = bytes.get_u8();
+        let sid = Sid::new(bytes.get_u64_le());
+        let mid = bytes.get_u64_le();
+        bytes.advance(4);
+        Ok(Some(Self {
+            time,
+            cid,
+            direction,
+            sid: (flags & Self::HAS_SID != 0).then(|| sid),
+            mid: (flags & Self::HAS_MID != 0).then(|| mid),
+            frame: bytes.freeze(),
+        }))
+    }
+}
+
+impl FrameCapture {
+    /// `start` should be shared by all channels of a capture, so their times
+    /// can be compared
+    pub fn new(cid: Cid, start: Instant, sink: Arc<dyn CaptureSink>) -> Self {
+        Self { cid, start, sink }
+    }
+
+    pub(crate) fn out_frame(&self, frame: &OTFrame) {
+        self.capture(CaptureDirection::Out, frame.clone());
+    }
+
+    pub(crate) fn in_frame(&self, frame: &ITFrame) {
+        // received frames are captured in the same format as send ones
+        let frame = match frame {
+            ITFrame::Shutdown => OTFrame::Shutdown,
+            ITFrame::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+            } => OTFrame::OpenStream {
+                sid: *sid,
+                prio: *prio,
+                promises: *promises,
+                guaranteed_bandwidth: *guaranteed_bandwidth,
+            },
+            ITFrame::CloseStream { sid } => OTFrame::CloseStream { sid: *sid },
+            ITFrame::DataHeader { mid, sid, length } => OTFrame::DataHeader {
+                mid: *mid,
+                sid: *sid,
+                length: *length,
+            },
+            ITFrame::Data { mid, data } => OTFrame::Data {
+                mid: *mid,
+                data: Bytes::copy_from_slice(data),
+            },
+            ITFrame::Ack { sid, count } => OTFrame::Ack {
+                sid: *sid,
+                count: *count,
+            },
+            ITFrame::Ping { id } => OTFrame::Ping { id: *id },
+            ITFrame::Pong { id } => OTFrame::Pong { id: *id },
+        };
+        self.capture(CaptureDirection::In, frame);
+    }
+
+    fn capture(&self, direction: CaptureDirection, frame: OTFrame) {
+        let (sid, mid) = match &frame {
+            OTFrame::Shutdown | OTFrame::Ping { .. } | OTFrame::Pong { .. } => (None, None),
+            OTFrame::OpenStream { sid, .. }
+            | OTFrame::CloseStream { sid }
+            | OTFrame::Ack { sid, .. } => (Some(*sid), None),
+            OTFrame::DataHeader { mid, sid, .. } => (Some(*sid), Some(*mid)),
+            OTFrame::Data { mid, .. } => (None, Some(*mid)),
+        };
+        let mut bytes = BytesMut::new();
+        frame.write_bytes(&mut bytes);
+        self.sink.capture(CapturedFrame {
+            time: self.start.elapsed(),
+            cid: self.cid,
+            direction,
+            sid,
+            mid,
+            frame: bytes.freeze(),
+        });
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// keeps every captured frame in memory
+    #[derive(Debug, Default)]
+    pub struct VecSink(pub Mutex<Vec<CapturedFrame>>);
+
+    impl CaptureSink for VecSink {
+        fn capture(&self, frame: CapturedFrame) { self.0.lock().unwrap().push(frame); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_utils::VecSink, *};
+    use crate::types::Promises;
+
+    fn frame(sid: Option<Sid>, mid: Option<Mid>, len: usize) -> CapturedFrame {
+        CapturedFrame {
+            time: Duration::from_millis(1337),
+            cid: 42,
+            direction: CaptureDirection::In,
+            sid,
+            mid,
+            frame: Bytes::from(vec![7u8; len]),
+        }
+    }
+
+    #[test]
+    fn captured_frame_roundtrip() {
+        let frames = vec![
+            frame(None, None, 1),
+            frame(Some(Sid::new(3)), None, 18),
+            frame(Some(Sid::new(3)), Some(99), 25),
+            frame(None, Some(99), 1400),
+        ];
+        let mut bytes = BytesMut::new();
+        for f in &frames {
+            f.write_bytes(&mut bytes);
+        }
+        for f in &frames {
+            assert_eq!(CapturedFrame::read_bytes(&mut bytes), Ok(Some(f.clone())));
+        }
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn captured_frame_too_short_buffer() {
+        let mut bytes = BytesMut::new();
+        frame(None, Some(1), 100).write_bytes(&mut bytes);
+        let mut partial = bytes.split_to(bytes.len() - 1);
+        assert_eq!(CapturedFrame::read_bytes(&mut partial), Ok(None));
+        let mut partial = BytesMut::from(&partial[..10]);
+        assert_eq!(CapturedFrame::read_bytes(&mut partial), Ok(None));
+    }
+
+    #[test]
+    fn captured_frame_rubish() {
+        let mut bytes = BytesMut::new();
+        frame(None, None, 1).write_bytes(&mut bytes);
+        bytes[16] = 99;
+        assert_eq!(CapturedFrame::read_bytes(&mut bytes), Err(()));
+    }
+
+    #[test]
+    fn capture_frames_in_wire_format() {
+        let sink = Arc::new(VecSink::default());
+        let capture =
+            FrameCapture::new(3, Instant::now(), Arc::clone(&sink) as Arc<dyn CaptureSink>);
+        let sid = Sid::new(12);
+        let open = OTFrame::OpenStream {
+            sid,
+            prio: 5,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1000,
+        };
+        capture.out_frame(&open);
+        capture.in_frame(&ITFrame::Data {
+            mid: 4,
+            data: BytesMut::from(&b"hello"[..]),
+        });
+
+        let frames = sink.0.lock().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].direction, CaptureDirection::Out);
+        assert_eq!(
+            (frames[0].cid, frames[0].sid, frames[0].mid),
+            (3, Some(sid), None)
+        );
+        let mut bytes = BytesMut::from(&frames[0].frame[..]);
+        let read = ITFrame::read_frame(&mut bytes).unwrap().unwrap();
+        assert_eq!(open, read);
+        assert_eq!(frames[1].direction, CaptureDirection::In);
+        assert_eq!((frames[1].sid, frames[1].mid), (None, Some(4)));
+        let mut bytes = BytesMut::from(&frames[1].frame[..]);
+        assert!(matches!(
+            ITFrame::read_frame(&mut bytes),
+            Ok(Some(ITFrame::Data { mid: 4, .. }))
+        ));
+    }
+}
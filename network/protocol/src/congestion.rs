@@ -0,0 +1,203 @@
+//! Congestion control, estimates how much bandwidth a channel can use.
+//!
+//! The estimate follows AIMD: it grows while the link takes everything that
+//! is send, and is cut as soon as the link shows congestion. A link is
+//! considered congested when datagrams get lost, the round trip time grows
+//! because queues build up, or the flush blocks because the OS buffers are
+//! full. TCP retransmits on its own, so a TCP channel only measures the round
+//! trip time with pings.
+use crate::types::Bandwidth;
+use std::time::{Duration, Instant};
+
+/// What a protocol learned about its link since the last report
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LinkReport {
+    /// smoothed round trip time, if the protocol measures it
+    pub rtt: Option<Duration>,
+    /// datagrams that needed to be retransmitted
+    pub lost: u64,
+}
+
+/// Keeps the bandwidth estimate of a single channel
+#[derive(Debug)]
+pub struct CongestionControl {
+    /// bytes/s
+    bandwidth: f64,
+    slow_start: bool,
+    min_rtt: Option<Duration>,
+    rtt: Option<Duration>,
+    last_decrease: Instant,
+}
+
+impl CongestionControl {
+    /// Per rtt, once slow start is over
+    const ADDITIVE_INCREASE: f64 = 64_000.0;
+    /// Used while the protocol doesn't tell the rtt
+    const DEFAULT_RTT: Duration = Duration::from_millis(100);
+    const INITIAL_BANDWIDTH: Bandwidth = 1_000_000;
+    /// Matches what was used before the bandwidth was measured
+    pub const MAX_BANDWIDTH: Bandwidth = 1_000_000_000;
+    /// Never throttle below this, so a channel can recover
+    pub const MIN_BANDWIDTH: Bandwidth = 16_000;
+    const MULTIPLICATIVE_DECREASE: f64 = 0.7;
+    /// Queueing delay that is tolerated before the link counts as congested
+    const RTT_TOLERANCE: Duration = Duration::from_millis(10);
+
+    pub fn new(now: Instant) -> Self {
+        Self {
+            bandwidth: Self::INITIAL_BANDWIDTH as f64,
+            slow_start: true,
+            min_rtt: None,
+            rtt: None,
+            last_decrease: now,
+        }
+    }
+
+    /// The bandwidth in bytes/s the channel should use for its next flush
+    pub fn bandwidth(&self) -> Bandwidth { self.bandwidth as Bandwidth }
+
+    /// Update the estimate after a flush.
+    /// `dt` is the time since the last flush, `sent` the bytes that were
+    /// flushed and `flush_time` the time the flush took.
+    pub fn on_flush(
+        &mut self,
+        now: Instant,
+        dt: Duration,
+        sent: Bandwidth,
+        flush_time: Duration,
+        report: LinkReport,
+    ) {
+        if let Some(rtt) = report.rtt {
+            self.rtt = Some(rtt);
+            self.min_rtt = Some(self.min_rtt.map_or(rtt, |min| min.min(rtt)));
+        }
+        let rtt = self.rtt.unwrap_or(Self::DEFAULT_RTT);
+        let queueing = match (self.rtt, self.min_rtt) {
+            (Some(rtt), Some(min_rtt)) => rtt > min_rtt * 3 / 2 + Self::RTT_TOLERANCE,
+            _ => false,
+        };
+        let blocked = flush_time > dt.max(Self::RTT_TOLERANCE);
+
+        if report.lost > 0 || queueing || blocked {
+            // only react once per rtt, the signals of a single congestion
+            // event usually arrive over several flushes
+            if now.saturating_duration_since(self.last_decrease) >= rtt {
+                self.bandwidth *= Self::MULTIPLICATIVE_DECREASE;
+                self.slow_start = false;
+                self.last_decrease = now;
+            }
+        } else if sent as f64 >= self.bandwidth * dt.as_secs_f64() / 2.0 {
+            // only grow if the bandwidth is actually used
+            let rtts = dt.as_secs_f64() / rtt.as_secs_f64();
+            if self.slow_start {
+                self.bandwidth *= 1.0 + rtts.min(1.0);
+            } else {
+                self.bandwidth += Self::ADDITIVE_INCREASE * rtts;
+            }
+        }
+        self.bandwidth = self
+            .bandwidth
+            .clamp(Self::MIN_BANDWIDTH as f64, Self::MAX_BANDWIDTH as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(5);
+
+    /// flush `n` times using all of the available bandwidth
+    fn busy(cc: &mut CongestionControl, now: &mut Instant, n: usize, report: LinkReport) {
+        for _ in 0..n {
+            *now += TICK;
+            let sent = (cc.bandwidth() as f64 * TICK.as_secs_f64()) as Bandwidth;
+            cc.on_flush(*now, TICK, sent, Duration::from_micros(100), report);
+        }
+    }
+
+    #[test]
+    fn grows_while_link_is_used() {
+        let mut now = Instant::now();
+        let mut cc = CongestionControl::new(now);
+        let report = LinkReport {
+            rtt: Some(Duration::from_millis(20)),
+            lost: 0,
+        };
+        busy(&mut cc, &mut now, 100, report);
+        assert!(cc.bandwidth() > CongestionControl::INITIAL_BANDWIDTH * 10);
+    }
+
+    #[test]
+    fn idle_link_doesnt_grow() {
+        let mut now = Instant::now();
+        let mut cc = CongestionControl::new(now);
+        for _ in 0..100 {
+            now += TICK;
+            cc.on_flush(
+                now,
+                TICK,
+                0,
+                Duration::from_micros(100),
+                LinkReport::default(),
+            );
+        }
+        assert_eq!(cc.bandwidth(), CongestionControl::INITIAL_BANDWIDTH);
+    }
+
+    #[test]
+    fn loss_decreases_once_per_rtt() {
+        let mut now = Instant::now();
+        let mut cc = CongestionControl::new(now);
+        let rtt = Duration::from_millis(20);
+        busy(&mut cc, &mut now, 1, LinkReport {
+            rtt: Some(rtt),
+            lost: 0,
+        });
+        let before = cc.bandwidth();
+        now += rtt;
+        // 3 flushes within a single rtt
+        busy(&mut cc, &mut now, 3, LinkReport {
+            rtt: Some(rtt),
+            lost: 1,
+        });
+        let expected = (before as f64 * CongestionControl::MULTIPLICATIVE_DECREASE) as Bandwidth;
+        assert!(cc.bandwidth() <= expected + 1);
+        assert!(cc.bandwidth() + 1 >= expected);
+    }
+
+    #[test]
+    fn growing_rtt_is_congestion() {
+        let mut now = Instant::now();
+        let mut cc = CongestionControl::new(now);
+        let fast = LinkReport {
+            rtt: Some(Duration::from_millis(20)),
+            lost: 0,
+        };
+        busy(&mut cc, &mut now, 20, fast);
+        let before = cc.bandwidth();
+        let slow = LinkReport {
+            rtt: Some(Duration::from_millis(200)),
+            lost: 0,
+        };
+        busy(&mut cc, &mut now, 200, slow);
+        assert!(cc.bandwidth() < before);
+    }
+
+    #[test]
+    fn blocking_flush_is_congestion() {
+        let mut now = Instant::now();
+        let mut cc = CongestionControl::new(now);
+        for _ in 0..100 {
+            now += Duration::from_millis(50);
+            cc.on_flush(
+                now,
+                TICK,
+                cc.bandwidth(),
+                Duration::from_millis(50),
+                LinkReport::default(),
+            );
+        }
+        assert_eq!(cc.bandwidth(), CongestionControl::MIN_BANDWIDTH);
+    }
+}
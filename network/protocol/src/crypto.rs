@@ -0,0 +1,384 @@
+//! Encryption of the frames of protocols without one of their own (e.g. TCP).
+//!
+//! During the handshake both sides send an ephemeral X25519 public key in
+//! [`InitFrame::KeyExchange`], the shared secret is then used to derive one
+//! ChaCha20-Poly1305 key per direction. Everything sent after the key exchange
+//! is split into records: the length of the sealed data as `u32` followed by
+//! the sealed data and its tag.
+//!
+//! The listening side signs both public keys with its long-term [`Identity`].
+//! A connecting side which knows the [`IdentityKey`] of the listener verifies
+//! that signature, without it a man in the middle can't be detected and
+//! [`Promises::ENCRYPTED`] isn't supported.
+//!
+//! [`InitFrame::KeyExchange`]: crate::frame::InitFrame::KeyExchange
+//! [`Promises::ENCRYPTED`]: crate::Promises::ENCRYPTED
+use bytes::{Buf, BufMut, BytesMut};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN},
+    agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, X25519},
+    hkdf::{Salt, HKDF_SHA256},
+    rand::SystemRandom,
+    signature::{self, Ed25519KeyPair, KeyPair, ED25519},
+};
+use std::sync::Arc;
+
+pub(crate) const PUBLIC_KEY_LEN: usize = 32;
+pub(crate) const SIGNATURE_LEN: usize = 64;
+const SIGNATURE_CONTEXT: &[u8] = b"veloren key exchange";
+const RECORD_HEADER_LEN: usize = 4;
+/// Records are never bigger than what is flushed at once, anything bigger is
+/// considered a protocol violation
+const MAX_RECORD_LEN: usize = 64 * 1024 * 1024;
+const INITIATOR_INFO: &[u8] = b"veloren initiator to responder";
+const RESPONDER_INFO: &[u8] = b"veloren responder to initiator";
+
+/// Whether a protocol encrypts its frames, see [`Promises::ENCRYPTED`]
+///
+/// [`Promises::ENCRYPTED`]: crate::Promises::ENCRYPTED
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encryption {
+    /// The protocol can't encrypt its frames, or already is encrypted by
+    /// other means
+    Unsupported,
+    /// Frames are encrypted if the remote side supports it
+    Supported,
+    /// The handshake fails if the remote side doesn't support encryption
+    Required,
+}
+
+/// How the key exchange is authenticated
+#[derive(Debug, Clone)]
+pub enum Authentication {
+    /// Nobody proves their identity, [`Promises::ENCRYPTED`] is never
+    /// supported
+    ///
+    /// [`Promises::ENCRYPTED`]: crate::Promises::ENCRYPTED
+    None,
+    /// Listening side: sign the key exchange with this identity
+    Sign(Arc<Identity>),
+    /// Connecting side: the handshake fails unless the remote side signs the
+    /// key exchange with the identity of this key
+    Verify(IdentityKey),
+}
+
+/// Long-term key pair of a listening side, persist it to keep the
+/// [`IdentityKey`] that connecting sides pin
+pub struct Identity {
+    key_pair: Ed25519KeyPair,
+    pkcs8: Vec<u8>,
+}
+
+impl Identity {
+    #[allow(clippy::result_unit_err)]
+    pub fn generate() -> Result<Self, ()> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).map_err(|_| ())?;
+        Self::from_pkcs8(pkcs8.as_ref())
+    }
+
+    /// Load an identity stored with [`Identity::to_pkcs8`]
+    #[allow(clippy::result_unit_err)]
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, ()> {
+        Ok(Self {
+            key_pair: Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|_| ())?,
+            pkcs8: pkcs8.to_vec(),
+        })
+    }
+
+    pub fn to_pkcs8(&self) -> &[u8] { &self.pkcs8 }
+
+    pub fn public_key(&self) -> IdentityKey {
+        let mut key = [0u8; PUBLIC_KEY_LEN];
+        key.copy_from_slice(self.key_pair.public_key().as_ref());
+        IdentityKey(key)
+    }
+
+    pub(crate) fn sign(
+        &self,
+        initiator_key: &[u8; PUBLIC_KEY_LEN],
+        responder_key: &[u8; PUBLIC_KEY_LEN],
+    ) -> [u8; SIGNATURE_LEN] {
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(
+            self.key_pair
+                .sign(&signed_message(initiator_key, responder_key))
+                .as_ref(),
+        );
+        signature
+    }
+}
+
+/// Public key of an [`Identity`], written as 64 hex digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IdentityKey(pub [u8; PUBLIC_KEY_LEN]);
+
+impl IdentityKey {
+    pub(crate) fn verify(
+        &self,
+        initiator_key: &[u8; PUBLIC_KEY_LEN],
+        responder_key: &[u8; PUBLIC_KEY_LEN],
+        signature: &[u8; SIGNATURE_LEN],
+    ) -> bool {
+        signature::UnparsedPublicKey::new(&ED25519, &self.0)
+            .verify(&signed_message(initiator_key, responder_key), signature)
+            .is_ok()
+    }
+}
+
+impl std::fmt::Display for IdentityKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl std::str::FromStr for IdentityKey {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 2 * PUBLIC_KEY_LEN || !s.is_ascii() {
+            return Err("Identity keys are 64 hex digits");
+        }
+        let mut key = [0u8; PUBLIC_KEY_LEN];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16)
+                .map_err(|_| "Identity keys are 64 hex digits")?;
+        }
+        Ok(Self(key))
+    }
+}
+
+impl std::fmt::Debug for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Identity {{ public_key: {} }}", self.public_key())
+    }
+}
+
+/// The signature covers both ephemeral keys, so it can't be replayed in
+/// another key exchange
+fn signed_message(
+    initiator_key: &[u8; PUBLIC_KEY_LEN],
+    responder_key: &[u8; PUBLIC_KEY_LEN],
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(SIGNATURE_CONTEXT.len() + 2 * PUBLIC_KEY_LEN);
+    message.extend_from_slice(SIGNATURE_CONTEXT);
+    message.extend_from_slice(initiator_key);
+    message.extend_from_slice(responder_key);
+    message
+}
+
+/// Our half of the key exchange
+pub(crate) struct KeyExchange {
+    private_key: EphemeralPrivateKey,
+    public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+impl KeyExchange {
+    pub(crate) fn new() -> Result<Self, ()> {
+        let rng = SystemRandom::new();
+        let private_key = EphemeralPrivateKey::generate(&X25519, &rng).map_err(|_| ())?;
+        let mut public_key = [0u8; PUBLIC_KEY_LEN];
+        public_key.copy_from_slice(private_key.compute_public_key().map_err(|_| ())?.as_ref());
+        Ok(Self {
+            private_key,
+            public_key,
+        })
+    }
+
+    pub(crate) fn public_key(&self) -> [u8; PUBLIC_KEY_LEN] { self.public_key }
+
+    /// Derive the ciphers for both directions from the public key of the
+    /// remote side. Both public keys are mixed into the keys, so that both
+    /// sides need to have seen the same exchange.
+    pub(crate) fn agree(
+        self,
+        initializer: bool,
+        remote_public_key: [u8; PUBLIC_KEY_LEN],
+    ) -> Result<(SealingCipher, OpeningCipher), ()> {
+        let (initiator_key, responder_key) = if initializer {
+            (self.public_key, remote_public_key)
+        } else {
+            (remote_public_key, self.public_key)
+        };
+        let mut salt = [0u8; 2 * PUBLIC_KEY_LEN];
+        salt[..PUBLIC_KEY_LEN].copy_from_slice(&initiator_key);
+        salt[PUBLIC_KEY_LEN..].copy_from_slice(&responder_key);
+
+        agreement::agree_ephemeral(
+            self.private_key,
+            &UnparsedPublicKey::new(&X25519, remote_public_key),
+            (),
+            |shared_secret| {
+                let prk = Salt::new(HKDF_SHA256, &salt).extract(shared_secret);
+                let key = |info: &[u8]| -> Result<LessSafeKey, ()> {
+                    let info = [info];
+                    let okm = prk.expand(&info, &CHACHA20_POLY1305).map_err(|_| ())?;
+                    Ok(LessSafeKey::new(UnboundKey::from(okm)))
+                };
+                let (send_info, recv_info) = if initializer {
+                    (INITIATOR_INFO, RESPONDER_INFO)
+                } else {
+                    (RESPONDER_INFO, INITIATOR_INFO)
+                };
+                Ok((
+                    SealingCipher {
+                        key: key(send_info)?,
+                        nonce: 0,
+                    },
+                    OpeningCipher {
+                        key: key(recv_info)?,
+                        nonce: 0,
+                    },
+                ))
+            },
+        )
+    }
+}
+
+/// Every record uses the next nonce, as both sides count the records the
+/// nonces never need to be transmitted
+fn nonce(counter: &mut u64) -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[..8].copy_from_slice(&counter.to_le_bytes());
+    *counter += 1;
+    Nonce::assume_unique_for_key(nonce)
+}
+
+/// Encrypts the data we send
+pub struct SealingCipher {
+    key: LessSafeKey,
+    nonce: u64,
+}
+
+impl SealingCipher {
+    /// Turn `data` into a record
+    pub(crate) fn seal(&mut self, data: BytesMut) -> BytesMut {
+        let mut record =
+            BytesMut::with_capacity(RECORD_HEADER_LEN + data.len() + CHACHA20_POLY1305.tag_len());
+        record.put_u32_le((data.len() + CHACHA20_POLY1305.tag_len()) as u32);
+        let mut sealed = record.split_off(RECORD_HEADER_LEN);
+        sealed.extend_from_slice(&data);
+        self.key
+            .seal_in_place_append_tag(nonce(&mut self.nonce), Aad::empty(), &mut sealed)
+            .expect("Sealing only fails for data bigger than what can be allocated");
+        record.unsplit(sealed);
+        record
+    }
+}
+
+/// Decrypts the data we receive
+pub struct OpeningCipher {
+    key: LessSafeKey,
+    nonce: u64,
+}
+
+impl OpeningCipher {
+    /// Take the next record out of `buffer`.
+    /// Err => the record was altered or isn't a record, cannot recover
+    /// Ok(None) => waiting for more data
+    pub(crate) fn open(&mut self, buffer: &mut BytesMut) -> Result<Option<BytesMut>, ()> {
+        if buffer.len() < RECORD_HEADER_LEN {
+            return Ok(None);
+        }
+        let length = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+        if length < CHACHA20_POLY1305.tag_len() || length > MAX_RECORD_LEN {
+            return Err(());
+        }
+        if buffer.len() < RECORD_HEADER_LEN + length {
+            return Ok(None);
+        }
+        buffer.advance(RECORD_HEADER_LEN);
+        let mut record = buffer.split_to(length);
+        let data_len = self
+            .key
+            .open_in_place(nonce(&mut self.nonce), Aad::empty(), &mut record)
+            .map_err(|_| ())?
+            .len();
+        record.truncate(data_len);
+        Ok(Some(record))
+    }
+}
+
+impl std::fmt::Debug for SealingCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SealingCipher {{ nonce: {} }}", self.nonce)
+    }
+}
+
+impl std::fmt::Debug for OpeningCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OpeningCipher {{ nonce: {} }}", self.nonce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ciphers() -> (
+        (SealingCipher, OpeningCipher),
+        (SealingCipher, OpeningCipher),
+    ) {
+        let a = KeyExchange::new().unwrap();
+        let b = KeyExchange::new().unwrap();
+        let (a_key, b_key) = (a.public_key(), b.public_key());
+        (
+            a.agree(true, b_key).unwrap(),
+            b.agree(false, a_key).unwrap(),
+        )
+    }
+
+    #[test]
+    fn seal_and_open() {
+        let ((mut a_seal, _), (_, mut b_open)) = ciphers();
+        let mut buffer = BytesMut::new();
+        buffer.unsplit(a_seal.seal(BytesMut::from(&b"hello"[..])));
+        buffer.unsplit(a_seal.seal(BytesMut::from(&b"world"[..])));
+        // Records can arrive in arbitrary chunks
+        let mut incoming = buffer.split_to(7);
+        assert_eq!(b_open.open(&mut incoming), Ok(None));
+        incoming.unsplit(buffer);
+        assert_eq!(
+            b_open.open(&mut incoming),
+            Ok(Some(BytesMut::from(&b"hello"[..])))
+        );
+        assert_eq!(
+            b_open.open(&mut incoming),
+            Ok(Some(BytesMut::from(&b"world"[..])))
+        );
+        assert_eq!(b_open.open(&mut incoming), Ok(None));
+    }
+
+    #[test]
+    fn altered_record() {
+        let ((mut a_seal, _), (_, mut b_open)) = ciphers();
+        let mut record = a_seal.seal(BytesMut::from(&b"hello"[..]));
+        record[RECORD_HEADER_LEN] ^= 1;
+        assert_eq!(b_open.open(&mut record), Err(()));
+    }
+
+    #[test]
+    fn signed_key_exchange() {
+        let identity = Identity::generate().unwrap();
+        let (a, b) = ([1u8; PUBLIC_KEY_LEN], [2u8; PUBLIC_KEY_LEN]);
+        let signature = identity.sign(&a, &b);
+        assert!(identity.public_key().verify(&a, &b, &signature));
+        // a signature of another key exchange is useless
+        assert!(!identity.public_key().verify(&b, &a, &signature));
+        let other = Identity::generate().unwrap();
+        assert!(!other.public_key().verify(&a, &b, &signature));
+        // keys survive being stored
+        let loaded = Identity::from_pkcs8(identity.to_pkcs8()).unwrap();
+        assert_eq!(loaded.public_key(), identity.public_key());
+        assert_eq!(
+            identity.public_key().to_string().parse(),
+            Ok(identity.public_key())
+        );
+    }
+
+    #[test]
+    fn directions_use_different_keys() {
+        let ((mut a_seal, mut a_open), _) = ciphers();
+        let mut record = a_seal.seal(BytesMut::from(&b"hello"[..]));
+        assert_eq!(a_open.open(&mut record), Err(()));
+    }
+}
@@ -6,6 +6,10 @@ pub enum InitProtocolError {
     Closed,
     WrongMagicNumber([u8; 7]),
     WrongVersion([u32; 3]),
+    /// Local side requires encryption, but the remote side can't provide it
+    EncryptionUnsupported,
+    /// Remote side couldn't prove the identity we expected
+    AuthenticationFailed,
 }
 
 /// When you return closed you must stay closed!
@@ -46,6 +50,13 @@ impl core::fmt::Display for InitProtocolError {
                 &r,
                 &crate::types::VELOREN_NETWORK_VERSION
             ),
+            InitProtocolError::EncryptionUnsupported => write!(
+                f,
+                "Encryption is required, but the remote side doesn't support it"
+            ),
+            InitProtocolError::AuthenticationFailed => {
+                write!(f, "Remote side couldn't prove the identity we expected")
+            },
         }
     }
 }
@@ -25,6 +25,11 @@ pub enum ProtocolEvent {
         data: Bytes,
         sid: Sid,
     },
+    /// The sender received `count` messages of this stream so far
+    Ack {
+        sid: Sid,
+        count: u64,
+    },
 }
 
 impl ProtocolEvent {
@@ -43,6 +48,10 @@ impl ProtocolEvent {
                 guaranteed_bandwidth: *guaranteed_bandwidth,
             },
             ProtocolEvent::CloseStream { sid } => OTFrame::CloseStream { sid: *sid },
+            ProtocolEvent::Ack { sid, count } => OTFrame::Ack {
+                sid: *sid,
+                count: *count,
+            },
             ProtocolEvent::Message { .. } => {
                 unimplemented!("Event::Message to OTFrame IS NOT supported")
             },
@@ -10,7 +10,10 @@ const FRAME_CLOSE_STREAM: u8 = 5;
 const FRAME_DATA_HEADER: u8 = 6;
 const FRAME_DATA: u8 = 7;
 const FRAME_RAW: u8 = 8;
-//const FRAME_RESERVED_2: u8 = 10;
+const FRAME_KEY_EXCHANGE: u8 = 9;
+const FRAME_PONG: u8 = 10;
+const FRAME_ACK: u8 = 11;
+const FRAME_PING: u8 = 12;
 //const FRAME_RESERVED_3: u8 = 13;
 
 /// Used for Communication between Channel <----(TCP/UDP)----> Channel
@@ -20,9 +23,18 @@ pub enum InitFrame {
         magic_number: [u8; 7],
         version: [u32; 3],
     },
+    /// Ephemeral public key of the sender, `None` if it can't encrypt. The
+    /// listening side signs both public keys if it has an identity.
+    KeyExchange {
+        public_key: Option<[u8; 32]>,
+        signature: Option<[u8; 64]>,
+    },
+    /// `resume_token` can be presented by the remote to resume the
+    /// participant on a new channel later, only the listening side offers one
     Init {
         pid: Pid,
         secret: u128,
+        resume_token: Option<u128>,
     },
     /// WARNING: sending RAW is only for debug purposes and will drop the
     /// connection
@@ -52,6 +64,19 @@ pub enum OTFrame {
         mid: Mid,
         data: Bytes,
     },
+    /// The sender received `count` messages of this stream so far
+    Ack {
+        sid: Sid,
+        count: u64,
+    },
+    /// Asks for a [`OTFrame::Pong`] with the same `id`, to measure the round
+    /// trip time
+    Ping {
+        id: u64,
+    },
+    Pong {
+        id: u64,
+    },
 }
 
 /// Used for IN TCP Communication between Channel <--(TCP)-- Channel
@@ -77,12 +102,23 @@ pub enum ITFrame {
         mid: Mid,
         data: BytesMut,
     },
+    Ack {
+        sid: Sid,
+        count: u64,
+    },
+    Ping {
+        id: u64,
+    },
+    Pong {
+        id: u64,
+    },
 }
 
 impl InitFrame {
     // Size WITHOUT the 1rst indicating byte
     pub(crate) const HANDSHAKE_CNS: usize = 19;
-    pub(crate) const INIT_CNS: usize = 32;
+    pub(crate) const INIT_CNS: usize = 49;
+    pub(crate) const KEY_EXCHANGE_CNS: usize = 98;
     /// const part of the RAW frame, actual size is variable
     pub(crate) const RAW_CNS: usize = 2;
 
@@ -99,10 +135,42 @@ impl InitFrame {
                 bytes.put_u32_le(version[1]);
                 bytes.put_u32_le(version[2]);
             },
-            InitFrame::Init { pid, secret } => {
+            InitFrame::KeyExchange {
+                public_key,
+                signature,
+            } => {
+                bytes.put_u8(FRAME_KEY_EXCHANGE);
+                match public_key {
+                    Some(public_key) => {
+                        bytes.put_u8(1);
+                        bytes.put_slice(&public_key);
+                    },
+                    None => {
+                        bytes.put_u8(0);
+                        bytes.put_slice(&[0u8; 32]);
+                    },
+                }
+                match signature {
+                    Some(signature) => {
+                        bytes.put_u8(1);
+                        bytes.put_slice(&signature);
+                    },
+                    None => {
+                        bytes.put_u8(0);
+                        bytes.put_slice(&[0u8; 64]);
+                    },
+                }
+            },
+            InitFrame::Init {
+                pid,
+                secret,
+                resume_token,
+            } => {
                 bytes.put_u8(FRAME_INIT);
                 pid.to_bytes(bytes);
                 bytes.put_u128_le(secret);
+                bytes.put_u8(resume_token.is_some() as u8);
+                bytes.put_u128_le(resume_token.unwrap_or_default());
             },
             InitFrame::Raw(data) => {
                 bytes.put_u8(FRAME_RAW);
@@ -131,14 +199,35 @@ impl InitFrame {
                     version: [bytes.get_u32_le(), bytes.get_u32_le(), bytes.get_u32_le()],
                 }
             },
+            FRAME_KEY_EXCHANGE => {
+                if bytes.len() < Self::KEY_EXCHANGE_CNS + 1 {
+                    return None;
+                }
+                bytes.advance(1);
+                let has_key = bytes.get_u8() != 0;
+                let mut public_key = [0u8; 32];
+                bytes.copy_to_slice(&mut public_key);
+                let has_signature = bytes.get_u8() != 0;
+                let mut signature = [0u8; 64];
+                bytes.copy_to_slice(&mut signature);
+                InitFrame::KeyExchange {
+                    public_key: has_key.then(|| public_key),
+                    signature: has_signature.then(|| signature),
+                }
+            },
             FRAME_INIT => {
                 if bytes.len() < Self::INIT_CNS + 1 {
                     return None;
                 }
                 bytes.advance(1);
+                let pid = Pid::from_bytes(bytes);
+                let secret = bytes.get_u128_le();
+                let has_resume_token = bytes.get_u8() != 0;
+                let resume_token = bytes.get_u128_le();
                 InitFrame::Init {
-                    pid: Pid::from_bytes(bytes),
-                    secret: bytes.get_u128_le(),
+                    pid,
+                    secret,
+                    resume_token: has_resume_token.then(|| resume_token),
                 }
             },
             FRAME_RAW => {
@@ -159,11 +248,14 @@ impl InitFrame {
     }
 }
 
+pub(crate) const TCP_ACK_CNS: usize = 16;
 pub(crate) const TCP_CLOSE_STREAM_CNS: usize = 8;
 /// const part of the DATA frame, actual size is variable
 pub(crate) const TCP_DATA_CNS: usize = 10;
 pub(crate) const TCP_DATA_HEADER_CNS: usize = 24;
 pub(crate) const TCP_OPEN_STREAM_CNS: usize = 18;
+pub(crate) const TCP_PING_CNS: usize = 8;
+pub(crate) const TCP_PONG_CNS: usize = 8;
 // Size WITHOUT the 1rst indicating byte
 pub(crate) const TCP_SHUTDOWN_CNS: usize = 0;
 
@@ -201,6 +293,19 @@ impl OTFrame {
                 bytes.put_u16_le(data.len() as u16);
                 bytes.put_slice(&data);
             },
+            Self::Ack { sid, count } => {
+                bytes.put_u8(FRAME_ACK);
+                sid.to_bytes(bytes);
+                bytes.put_u64_le(count);
+            },
+            Self::Ping { id } => {
+                bytes.put_u8(FRAME_PING);
+                bytes.put_u64_le(id);
+            },
+            Self::Pong { id } => {
+                bytes.put_u8(FRAME_PONG);
+                bytes.put_u64_le(id);
+            },
         }
     }
 }
@@ -224,6 +329,9 @@ impl ITFrame {
                 }
                 u16::from_le_bytes([bytes[8 + 1], bytes[9 + 1]]) as usize + TCP_DATA_CNS
             },
+            FRAME_ACK => TCP_ACK_CNS,
+            FRAME_PING => TCP_PING_CNS,
+            FRAME_PONG => TCP_PONG_CNS,
             _ => return Err(()),
         };
 
@@ -270,6 +378,28 @@ impl ITFrame {
                 let data = bytes.split_to(length as usize);
                 Self::Data { mid, data }
             },
+            FRAME_ACK => {
+                let mut bytes = bytes.split_to(size + 1);
+                bytes.advance(1);
+                Self::Ack {
+                    sid: Sid::from_bytes(&mut bytes),
+                    count: bytes.get_u64_le(),
+                }
+            },
+            FRAME_PING => {
+                let mut bytes = bytes.split_to(size + 1);
+                bytes.advance(1);
+                Self::Ping {
+                    id: bytes.get_u64_le(),
+                }
+            },
+            FRAME_PONG => {
+                let mut bytes = bytes.split_to(size + 1);
+                bytes.advance(1);
+                Self::Pong {
+                    id: bytes.get_u64_le(),
+                }
+            },
             _ => unreachable!("Frame::to_frame should be handled before!"),
         };
         Ok(Some(frame))
@@ -297,6 +427,9 @@ impl PartialEq<ITFrame> for OTFrame {
                 matches!(other, ITFrame::DataHeader { mid, sid, length })
             },
             Self::Data { mid, data } => matches!(other, ITFrame::Data { mid, data }),
+            Self::Ack { sid, count } => matches!(other, ITFrame::Ack { sid, count }),
+            Self::Ping { id } => matches!(other, ITFrame::Ping { id }),
+            Self::Pong { id } => matches!(other, ITFrame::Pong { id }),
         }
     }
 }
@@ -312,9 +445,27 @@ mod tests {
                 magic_number: VELOREN_MAGIC_NUMBER,
                 version: VELOREN_NETWORK_VERSION,
             },
+            InitFrame::KeyExchange {
+                public_key: Some([7u8; 32]),
+                signature: Some([9u8; 64]),
+            },
+            InitFrame::KeyExchange {
+                public_key: Some([7u8; 32]),
+                signature: None,
+            },
+            InitFrame::KeyExchange {
+                public_key: None,
+                signature: None,
+            },
             InitFrame::Init {
                 pid: Pid::fake(0),
                 secret: 0u128,
+                resume_token: None,
+            },
+            InitFrame::Init {
+                pid: Pid::fake(1),
+                secret: 42u128,
+                resume_token: Some(1337u128),
             },
             InitFrame::Raw(vec![1, 2, 3]),
         ]
@@ -341,6 +492,12 @@ mod tests {
                 mid: 0,
                 data: Bytes::from(&[42u8; 16][..]),
             },
+            OTFrame::Ack {
+                sid: Sid::new(1337),
+                count: 2,
+            },
+            OTFrame::Ping { id: 7 },
+            OTFrame::Pong { id: 7 },
             OTFrame::CloseStream {
                 sid: Sid::new(1337),
             },
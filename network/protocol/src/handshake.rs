@@ -1,4 +1,5 @@
 use crate::{
+    crypto::{Authentication, Encryption, KeyExchange, OpeningCipher, SealingCipher},
     error::{InitProtocolError, ProtocolError},
     frame::InitFrame,
     types::{
@@ -22,6 +23,17 @@ use tracing::{debug, error, info, trace};
 #[async_trait]
 pub trait ReliableDrain {
     async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError>;
+
+    /// Whether this side offers to encrypt the channel during the Handshake
+    fn encryption(&self) -> Encryption { Encryption::Unsupported }
+
+    /// How the key exchange is authenticated, only used with encryption
+    fn authentication(&self) -> Authentication { Authentication::None }
+
+    /// Called once both sides agreed on a key, every frame send afterwards
+    /// MUST be sealed with `cipher`. `authenticated` is true if the remote
+    /// side can't be a man in the middle.
+    fn enable_encryption(&mut self, _cipher: SealingCipher, _authenticated: bool) {}
 }
 
 /// Implement this for auto Handshake with [`ReliableDrain`]. See
@@ -31,6 +43,10 @@ pub trait ReliableDrain {
 #[async_trait]
 pub trait ReliableSink {
     async fn recv(&mut self) -> Result<InitFrame, ProtocolError>;
+
+    /// Called once both sides agreed on a key, every frame received afterwards
+    /// MUST be opened with `cipher`
+    fn enable_encryption(&mut self, _cipher: OpeningCipher) {}
 }
 
 #[async_trait]
@@ -39,12 +55,13 @@ where
     D: ReliableDrain + Send,
     S: ReliableSink + Send,
 {
-    async fn initialize(
+    async fn initialize_with_token(
         &mut self,
         initializer: bool,
         local_pid: Pid,
         local_secret: u128,
-    ) -> Result<(Pid, Sid, u128), InitProtocolError> {
+        local_resume_token: Option<u128>,
+    ) -> Result<(Pid, Sid, u128, Option<u128>), InitProtocolError> {
         #[cfg(debug_assertions)]
         const WRONG_NUMBER: &str = "Handshake does not contain the magic number required by \
                                     veloren server.\nWe are not sure if you are a valid veloren \
@@ -53,6 +70,12 @@ where
         const WRONG_VERSION: &str = "Handshake does contain a correct magic number, but invalid \
                                      version.\nWe don't know how to communicate with \
                                      you.\nClosing the connection";
+        #[cfg(debug_assertions)]
+        const NO_ENCRYPTION: &str = "Handshake does not contain a key, but we require an \
+                                     encrypted connection.\nClosing the connection";
+        #[cfg(debug_assertions)]
+        const NOT_AUTHENTICATED: &str =
+            "Handshake does not prove the identity we expected.\nClosing the connection";
         const ERR_S: &str = "Got A Raw Message, these are usually Debug Messages indicating that \
                              something went wrong on network layer and connection will be closed";
 
@@ -99,14 +122,7 @@ where
                     Err(InitProtocolError::WrongVersion(version))
                 } else {
                     trace!("Handshake Frame completed");
-                    if initializer {
-                        drain
-                            .send(InitFrame::Init {
-                                pid: local_pid,
-                                secret: local_secret,
-                            })
-                            .await?;
-                    } else {
+                    if !initializer {
                         drain
                             .send(InitFrame::Handshake {
                                 magic_number: VELOREN_MAGIC_NUMBER,
@@ -130,8 +146,122 @@ where
             },
         }?;
 
+        let encryption = drain.encryption();
+        let authentication = drain.authentication();
+        let key_exchange = match encryption {
+            Encryption::Unsupported => None,
+            Encryption::Supported | Encryption::Required => {
+                Some(KeyExchange::new().map_err(|()| {
+                    error!("Couldn't generate a key for the key exchange");
+                    InitProtocolError::Closed
+                })?)
+            },
+        };
+        let local_key = key_exchange.as_ref().map(KeyExchange::public_key);
+        // the listening side sends the handshake, but answers here, so it can sign
+        // both keys
+        if !initializer {
+            drain
+                .send(InitFrame::KeyExchange {
+                    public_key: local_key,
+                    signature: None,
+                })
+                .await?;
+        }
+
         match sink.recv().await? {
-            InitFrame::Init { pid, secret } => {
+            InitFrame::KeyExchange {
+                public_key,
+                signature,
+            } => {
+                if initializer {
+                    let signature = match (&authentication, public_key, local_key) {
+                        (Authentication::Sign(identity), Some(remote_key), Some(local_key)) => {
+                            Some(identity.sign(&local_key, &remote_key))
+                        },
+                        _ => None,
+                    };
+                    drain
+                        .send(InitFrame::KeyExchange {
+                            public_key: local_key,
+                            signature,
+                        })
+                        .await?;
+                }
+                let authenticated = match &authentication {
+                    Authentication::None => false,
+                    // only the listening side signs
+                    Authentication::Sign(_) => initializer,
+                    Authentication::Verify(identity_key) => {
+                        let verified = match (local_key, public_key, signature) {
+                            (Some(local_key), Some(remote_key), Some(signature)) => {
+                                identity_key.verify(&remote_key, &local_key, &signature)
+                            },
+                            _ => false,
+                        };
+                        if !verified {
+                            error!("Connection without the expected identity");
+                            #[cfg(debug_assertions)]
+                            drain
+                                .send(InitFrame::Raw(NOT_AUTHENTICATED.as_bytes().to_vec()))
+                                .await?;
+                            return Err(InitProtocolError::AuthenticationFailed);
+                        }
+                        true
+                    },
+                };
+                match (key_exchange, public_key) {
+                    (Some(key_exchange), Some(public_key)) => {
+                        let (sealing, opening) =
+                            key_exchange.agree(initializer, public_key).map_err(|()| {
+                                info!("Key exchange failed");
+                                InitProtocolError::Closed
+                            })?;
+                        drain.enable_encryption(sealing, authenticated);
+                        sink.enable_encryption(opening);
+                        debug!(?authenticated, "Channel is now encrypted");
+                        Ok(())
+                    },
+                    (_, None) if encryption == Encryption::Required => {
+                        error!("Connection without the required encryption");
+                        #[cfg(debug_assertions)]
+                        drain
+                            .send(InitFrame::Raw(NO_ENCRYPTION.as_bytes().to_vec()))
+                            .await?;
+                        Err(InitProtocolError::EncryptionUnsupported)
+                    },
+                    _ => Ok(()),
+                }
+            },
+            InitFrame::Raw(bytes) => {
+                match std::str::from_utf8(bytes.as_slice()) {
+                    Ok(string) => error!(?string, ERR_S),
+                    _ => error!(?bytes, ERR_S),
+                }
+                Err(InitProtocolError::Closed)
+            },
+            _ => {
+                info!("Handshake failed");
+                Err(InitProtocolError::Closed)
+            },
+        }?;
+
+        if initializer {
+            drain
+                .send(InitFrame::Init {
+                    pid: local_pid,
+                    secret: local_secret,
+                    resume_token: local_resume_token,
+                })
+                .await?;
+        }
+
+        match sink.recv().await? {
+            InitFrame::Init {
+                pid,
+                secret,
+                resume_token,
+            } => {
                 debug!(?pid, "Participant send their ID");
                 let stream_id_offset = if initializer {
                     STREAM_ID_OFFSET1
@@ -140,12 +270,13 @@ where
                         .send(InitFrame::Init {
                             pid: local_pid,
                             secret: local_secret,
+                            resume_token: local_resume_token,
                         })
                         .await?;
                     STREAM_ID_OFFSET2
                 };
                 info!(?pid, "This Handshake is now configured!");
-                Ok((pid, stream_id_offset, secret))
+                Ok((pid, stream_id_offset, secret, resume_token))
             },
             InitFrame::Raw(bytes) => {
                 match std::str::from_utf8(bytes.as_slice()) {
@@ -231,7 +362,7 @@ mod tests {
                 version: VELOREN_NETWORK_VERSION,
             })
             .await?;
-            let _ = p2.1.recv().await?;
+            // the connecting side starts the key exchange
             p2.0.send(InitFrame::Raw(b"Hello World".to_vec())).await?;
             Result::<(), InitProtocolError>::Ok(())
         });
@@ -15,8 +15,7 @@
 //!  - TCP
 //!  - MPSC
 //!  - QUIC
-//!
-//! eventually a pure UDP implementation will follow
+//!  - UDP
 //!
 //! warning: don't mix protocol, using the TCP variant for actual UDP socket
 //! will result in dropped data  using UDP with a TCP socket will be a waste of
@@ -50,6 +49,9 @@
 //! [`RecvProtocol`]: crate::RecvProtocol
 //! [`InitProtocol`]: crate::InitProtocol
 
+mod capture;
+mod congestion;
+mod crypto;
 mod error;
 mod event;
 mod frame;
@@ -61,8 +63,12 @@ mod prio;
 mod quic;
 mod tcp;
 mod types;
+mod udp;
 mod util;
 
+pub use capture::{CaptureDirection, CaptureSink, CapturedFrame, FrameCapture};
+pub use congestion::{CongestionControl, LinkReport};
+pub use crypto::{Authentication, Encryption, Identity, IdentityKey};
 pub use error::{InitProtocolError, ProtocolError};
 pub use event::ProtocolEvent;
 pub use metrics::ProtocolMetricCache;
@@ -70,8 +76,13 @@ pub use metrics::ProtocolMetricCache;
 pub use metrics::ProtocolMetrics;
 pub use mpsc::{MpscMsg, MpscRecvProtocol, MpscSendProtocol};
 pub use quic::{QuicDataFormat, QuicDataFormatStream, QuicRecvProtocol, QuicSendProtocol};
-pub use tcp::{TcpRecvProtocol, TcpSendProtocol};
-pub use types::{Bandwidth, Cid, Pid, Prio, Promises, Sid, HIGHEST_PRIO, VELOREN_NETWORK_VERSION};
+pub use tcp::{tcp_protocols, TcpRecvProtocol, TcpSendProtocol};
+pub use types::{
+    Bandwidth, Cid, Mid, Pid, Prio, Promises, Sid, HIGHEST_PRIO, VELOREN_NETWORK_VERSION,
+};
+pub use udp::{
+    is_handshake_datagram, udp_protocols, UdpRecvProtocol, UdpSendProtocol, UDP_MAX_DATAGRAM_SIZE,
+};
 
 ///use at own risk, might change any time, for internal benchmarks
 pub mod _internal {
@@ -91,7 +102,21 @@ pub trait InitProtocol {
         initializer: bool,
         local_pid: Pid,
         secret: u128,
-    ) -> Result<(Pid, Sid, u128), InitProtocolError>;
+    ) -> Result<(Pid, Sid, u128), InitProtocolError> {
+        self.initialize_with_token(initializer, local_pid, secret, None)
+            .await
+            .map(|(pid, sid, secret, _)| (pid, sid, secret))
+    }
+
+    /// Like `initialize`, but both sides also exchange a token which lets the
+    /// remote resume the participant on a new channel later
+    async fn initialize_with_token(
+        &mut self,
+        initializer: bool,
+        local_pid: Pid,
+        secret: u128,
+        resume_token: Option<u128>,
+    ) -> Result<(Pid, Sid, u128, Option<u128>), InitProtocolError>;
 }
 
 /// Generic Network Send Protocol.
@@ -125,6 +150,16 @@ pub trait SendProtocol {
         bandwidth: Bandwidth,
         dt: std::time::Duration,
     ) -> Result<Bandwidth, ProtocolError>;
+    /// What the protocol learned about its link since the last call, used
+    /// for [`CongestionControl`]. Protocols that don't measure the link
+    /// report nothing.
+    ///
+    /// [`CongestionControl`]: crate::CongestionControl
+    fn link_report(&mut self) -> LinkReport { LinkReport::default() }
+    /// Take back all messages that weren't flushed completely, e.g. to send
+    /// them via another channel after this one failed. Partially flushed
+    /// messages are returned whole, as the remote side can't finish them.
+    fn take_unflushed(&mut self) -> Vec<(Sid, bytes::Bytes)> { vec![] }
 }
 
 /// Generic Network Recv Protocol. See: [`SendProtocol`]
@@ -17,6 +17,8 @@ pub(crate) const ALLOC_BLOCK: usize = 16_777_216;
 #[derive(Debug)]
 pub(crate) struct OTMessage {
     data: Bytes,
+    /// the whole message, in case it needs to be send via another channel
+    original: Bytes,
     original_length: u64,
     send_header: bool,
     mid: Mid,
@@ -37,6 +39,7 @@ impl OTMessage {
     pub(crate) fn new(data: Bytes, mid: Mid, sid: Sid) -> Self {
         let original_length = data.len() as u64;
         Self {
+            original: data.clone(),
             data,
             original_length,
             send_header: false,
@@ -78,6 +81,9 @@ impl OTMessage {
     }
 
     pub(crate) fn get_sid_len(&self) -> (Sid, u64) { (self.sid, self.original_length) }
+
+    /// the whole message, no matter how much of it was already send
+    pub(crate) fn into_original(self) -> (Mid, Sid, Bytes) { (self.mid, self.sid, self.original) }
 }
 
 impl ITMessage {
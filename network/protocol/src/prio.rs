@@ -23,9 +23,14 @@ struct StreamInfo {
 /// every stream has a guaranteed bandwidth and a prio 0-7.
 /// when `n` Bytes are available in the buffer, first the guaranteed bandwidth
 /// is used. Then remaining bandwidth is used to fill up the prios.
+/// When the bandwidth doesn't even cover all guaranteed bandwidths, the streams
+/// with the lowest prio are throttled first.
 #[derive(Debug)]
 pub(crate) struct PrioManager {
     streams: HashMap<Sid, StreamInfo>,
+    /// frames are never split, bytes send above the budget are subtracted
+    /// from the next one
+    overdraft: u64,
     metrics: ProtocolMetricCache,
 }
 
@@ -35,6 +40,7 @@ impl PrioManager {
     pub fn new(metrics: ProtocolMetricCache) -> Self {
         Self {
             streams: HashMap::new(),
+            overdraft: 0,
             metrics,
         }
     }
@@ -74,10 +80,32 @@ impl PrioManager {
             .push_back(OTMessage::new(buffer, mid, sid));
     }
 
+    /// Removes all messages that weren't grabbed completely, ordered by their
+    /// [`Mid`]. The streams stay open.
+    pub fn take_unflushed(&mut self) -> Vec<(Sid, Bytes)> {
+        let metrics = &mut self.metrics;
+        let mut messages = self
+            .streams
+            .values_mut()
+            .flat_map(|stream| stream.messages.drain(..))
+            .map(|msg| {
+                let (sid, bytes) = msg.get_sid_len();
+                metrics.smsg_ob(sid, RemoveReason::Dropped, bytes);
+                msg.into_original()
+            })
+            .collect::<Vec<_>>();
+        messages.sort_unstable_by_key(|(mid, _, _)| *mid);
+        messages
+            .into_iter()
+            .map(|(_, sid, data)| (sid, data))
+            .collect()
+    }
+
     /// bandwidth might be extended, as for technical reasons
     /// guaranteed_bandwidth is used and frames are always 1400 bytes.
     pub fn grab(&mut self, bandwidth: Bandwidth, dt: Duration) -> (Vec<(Sid, OTFrame)>, Bandwidth) {
-        let total_bytes = (bandwidth as f64 * dt.as_secs_f64()) as u64;
+        let budget = (bandwidth as f64 * dt.as_secs_f64()) as u64;
+        let total_bytes = budget.saturating_sub(self.overdraft);
         let mut cur_bytes = 0u64;
         let mut frames = vec![];
 
@@ -111,10 +139,16 @@ impl PrioManager {
                 }
             };
 
-        // Add guaranteed bandwidth
-        for (sid, stream) in self.streams.iter_mut() {
+        // Add guaranteed bandwidth, highest prio first
+        let mut streams = self.streams.iter_mut().collect::<Vec<_>>();
+        streams.sort_unstable_by_key(|(_, stream)| stream.prio);
+        for (sid, stream) in streams {
             prios[stream.prio as usize] += 1;
+            if cur_bytes >= total_bytes {
+                continue;
+            }
             let stream_byte_cnt = (stream.guaranteed_bandwidth as f64 * dt.as_secs_f64()) as u64;
+            let stream_byte_cnt = stream_byte_cnt.min(total_bytes - cur_bytes);
             process_stream(sid, stream, stream_byte_cnt as i64, &mut cur_bytes);
         }
 
@@ -124,7 +158,12 @@ impl PrioManager {
                 if prios[prio as usize] == 0 {
                     continue;
                 }
-                let per_stream_bytes = ((total_bytes - cur_bytes) / prios[prio as usize]) as i64;
+                // frames are never split, so the budget may already be exceeded
+                let remaining_bytes = total_bytes.saturating_sub(cur_bytes);
+                if remaining_bytes == 0 {
+                    break;
+                }
+                let per_stream_bytes = (remaining_bytes / prios[prio as usize]) as i64;
                 for (sid, stream) in self.streams.iter_mut() {
                     if stream.prio != prio {
                         continue;
@@ -133,6 +172,36 @@ impl PrioManager {
                 }
             }
         }
+        self.overdraft = (self.overdraft + cur_bytes).saturating_sub(budget);
         (frames, cur_bytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::ProtocolMetrics;
+    use std::sync::Arc;
+
+    #[test]
+    fn low_prio_throttled_first() {
+        let metrics = ProtocolMetricCache::new("prio", Arc::new(ProtocolMetrics::new().unwrap()));
+        let mut prio = PrioManager::new(metrics);
+        let (high, low) = (Sid::new(1), Sid::new(2));
+        prio.open_stream(high, 0, Promises::empty(), 2_000);
+        prio.open_stream(low, 5, Promises::empty(), 1_000_000);
+        prio.add(Bytes::from(&[1u8; 10_000][..]), 0, high);
+        prio.add(Bytes::from(&[2u8; 10_000][..]), 1, low);
+
+        // the guarantees of both streams exceed the bandwidth, the overdraft
+        // of the high prio stream is carried over
+        let mut bytes = 0;
+        for _ in 0..3 {
+            let (frames, b) = prio.grab(2_000, Duration::from_secs(1));
+            assert!(!frames.is_empty());
+            assert!(frames.iter().all(|(sid, _)| *sid == high));
+            bytes += b;
+        }
+        assert!(bytes < 3 * 2_000 + 1_500);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    capture::FrameCapture,
     error::ProtocolError,
     event::ProtocolEvent,
     frame::{ITFrame, InitFrame, OTFrame},
@@ -11,7 +12,7 @@ use crate::{
     RecvProtocol, SendProtocol, UnreliableDrain, UnreliableSink,
 };
 use async_trait::async_trait;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use hashbrown::HashMap;
 use std::time::{Duration, Instant};
 use tracing::info;
@@ -74,6 +75,7 @@ where
     #[allow(dead_code)]
     last: Instant,
     metrics: ProtocolMetricCache,
+    capture: Option<FrameCapture>,
 }
 
 /// QUIC implementation of [`RecvProtocol`]
@@ -92,6 +94,7 @@ where
     incoming: HashMap<Mid, ITMessage>,
     sink: S,
     metrics: ProtocolMetricCache,
+    capture: Option<FrameCapture>,
 }
 
 fn is_reliable(p: &Promises) -> bool {
@@ -116,6 +119,19 @@ where
             drain,
             last: Instant::now(),
             metrics,
+            capture: None,
+        }
+    }
+
+    /// Capture every send frame, see [`FrameCapture`]
+    pub fn with_capture(mut self, capture: FrameCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    fn capture(&self, frame: &OTFrame) {
+        if let Some(capture) = &self.capture {
+            capture.out_frame(frame);
         }
     }
 
@@ -144,9 +160,16 @@ where
             incoming: HashMap::new(),
             sink,
             metrics,
+            capture: None,
         }
     }
 
+    /// Capture every received frame, see [`FrameCapture`]
+    pub fn with_capture(mut self, capture: FrameCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
     async fn recv_into_stream(&mut self) -> Result<QuicDataFormatStream, ProtocolError> {
         let chunk = self.sink.recv().await?;
         let buffer = match chunk.stream {
@@ -225,7 +248,9 @@ where
                         .send(QuicDataFormat::with_reliable(&mut BytesMut::new(), sid))
                         .await?;
                 }
-                event.to_frame().write_bytes(&mut self.main_buffer);
+                let frame = event.to_frame();
+                self.capture(&frame);
+                frame.write_bytes(&mut self.main_buffer);
                 self.drain
                     .send(QuicDataFormat::with_main(&mut self.main_buffer))
                     .await?;
@@ -233,7 +258,9 @@ where
             ProtocolEvent::CloseStream { sid } => {
                 if self.store.try_close_stream(sid) {
                     let _ = self.reliable_buffers.delete(&sid); //delete if it was reliable
-                    event.to_frame().write_bytes(&mut self.main_buffer);
+                    let frame = event.to_frame();
+                    self.capture(&frame);
+                    frame.write_bytes(&mut self.main_buffer);
                     self.drain
                         .send(QuicDataFormat::with_main(&mut self.main_buffer))
                         .await?;
@@ -245,7 +272,9 @@ where
             },
             ProtocolEvent::Shutdown => {
                 if self.store.is_empty() {
-                    event.to_frame().write_bytes(&mut self.main_buffer);
+                    let frame = event.to_frame();
+                    self.capture(&frame);
+                    frame.write_bytes(&mut self.main_buffer);
                     self.drain
                         .send(QuicDataFormat::with_main(&mut self.main_buffer))
                         .await?;
@@ -260,6 +289,14 @@ where
                 self.store.add(data, self.next_mid, sid);
                 self.next_mid += 1;
             },
+            ProtocolEvent::Ack { .. } => {
+                let frame = event.to_frame();
+                self.capture(&frame);
+                frame.write_bytes(&mut self.main_buffer);
+                self.drain
+                    .send(QuicDataFormat::with_main(&mut self.main_buffer))
+                    .await?;
+            },
         }
         Ok(())
     }
@@ -278,6 +315,7 @@ where
                 data_bandwidth += data.len();
                 data_frames += 1;
             }
+            self.capture(&frame);
             match self.reliable_buffers.get_mut(&sid) {
                 Some(buffer) => frame.write_bytes(buffer),
                 None => {
@@ -302,7 +340,9 @@ where
             if self.store.try_close_stream(sid) {
                 #[cfg(feature = "trace_pedantic")]
                 trace!(?sid, "close stream, as it's now empty");
-                OTFrame::CloseStream { sid }.write_bytes(&mut self.main_buffer);
+                let frame = OTFrame::CloseStream { sid };
+                self.capture(&frame);
+                frame.write_bytes(&mut self.main_buffer);
                 self.drain
                     .send(QuicDataFormat::with_main(&mut self.main_buffer))
                     .await?;
@@ -328,7 +368,9 @@ where
         if self.pending_shutdown && self.store.is_empty() {
             #[cfg(feature = "trace_pedantic")]
             trace!("shutdown, as it's now empty");
-            OTFrame::Shutdown {}.write_bytes(&mut self.main_buffer);
+            let frame = OTFrame::Shutdown {};
+            self.capture(&frame);
+            frame.write_bytes(&mut self.main_buffer);
             self.drain
                 .send(QuicDataFormat::with_main(&mut self.main_buffer))
                 .await?;
@@ -336,6 +378,8 @@ where
         }
         Ok(data_bandwidth as u64)
     }
+
+    fn take_unflushed(&mut self) -> Vec<(Sid, Bytes)> { self.store.take_unflushed() }
 }
 
 #[async_trait]
@@ -349,6 +393,9 @@ where
                 Ok(Some(frame)) => {
                     #[cfg(feature = "trace_pedantic")]
                     trace!(?frame, "recv");
+                    if let Some(capture) = &self.capture {
+                        capture.in_frame(&frame);
+                    }
                     match frame {
                         ITFrame::Shutdown => break 'outer Ok(ProtocolEvent::Shutdown),
                         ITFrame::OpenStream {
@@ -372,6 +419,9 @@ where
                             //let _ = self.reliable_buffers.delete(sid); // if it was reliable
                             break 'outer Ok(ProtocolEvent::CloseStream { sid });
                         },
+                        ITFrame::Ack { sid, count } => {
+                            break 'outer Ok(ProtocolEvent::Ack { sid, count });
+                        },
                         _ => break 'outer Err(ProtocolError::Violated),
                     };
                 },
@@ -423,6 +473,9 @@ where
                         Ok(Some(frame)) => {
                             #[cfg(feature = "trace_pedantic")]
                             trace!(?frame, "recv");
+                            if let Some(capture) = &self.capture {
+                                capture.in_frame(&frame);
+                            }
                             match frame {
                                 ITFrame::DataHeader { sid, mid, length } => {
                                     let m = ITMessage::new(sid, length, &mut self.itmsg_allocator);
@@ -499,14 +552,16 @@ where
     S: UnreliableSink<DataFormat = QuicDataFormat>,
 {
     async fn recv(&mut self) -> Result<InitFrame, ProtocolError> {
-        while self.main_buffer.len() < 100 {
-            if self.recv_into_stream().await? == QuicDataFormatStream::Main {
-                if let Some(frame) = InitFrame::read_frame(&mut self.main_buffer) {
-                    return Ok(frame);
-                }
+        // multiple frames might have arrived in a single chunk
+        loop {
+            if let Some(frame) = InitFrame::read_frame(&mut self.main_buffer) {
+                return Ok(frame);
             }
+            if self.main_buffer.len() >= 100 {
+                return Err(ProtocolError::Violated);
+            }
+            self.recv_into_stream().await?;
         }
-        Err(ProtocolError::Violated)
     }
 }
 
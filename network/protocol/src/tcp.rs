@@ -1,4 +1,7 @@
 use crate::{
+    capture::FrameCapture,
+    congestion::LinkReport,
+    crypto::{Authentication, Encryption, OpeningCipher, SealingCipher},
     error::ProtocolError,
     event::ProtocolEvent,
     frame::{ITFrame, InitFrame, OTFrame},
@@ -10,13 +13,64 @@ use crate::{
     RecvProtocol, SendProtocol, UnreliableDrain, UnreliableSink,
 };
 use async_trait::async_trait;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use hashbrown::HashMap;
-use std::time::{Duration, Instant};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tracing::info;
 #[cfg(feature = "trace_pedantic")]
 use tracing::trace;
 
+/// Time between two measurements of the round trip time
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Measures the round trip time with pings, shared by the send and receive
+/// half of a channel: the receiving half learns about pings to answer and
+/// pongs that answer ours.
+#[derive(Debug, Default)]
+struct RttProbe {
+    /// ids of pings of the remote side that still need a pong
+    pongs_due: Vec<u64>,
+    /// our ping that wasn't answered yet
+    ping: Option<(u64, Instant)>,
+    next_ping: u64,
+    srtt: Option<Duration>,
+}
+
+impl RttProbe {
+    fn pong(&mut self, id: u64, now: Instant) {
+        if let Some((ping, sent)) = self.ping {
+            if ping == id {
+                self.ping = None;
+                let rtt = now.saturating_duration_since(sent);
+                self.srtt = Some(match self.srtt {
+                    Some(srtt) => srtt * 7 / 8 + rtt / 8,
+                    None => rtt,
+                });
+            }
+        }
+    }
+}
+
+/// Create both halves of a TCP channel, sharing the round trip time
+/// measurement. Halves created on their own don't measure it.
+pub fn tcp_protocols<D, S>(
+    drain: D,
+    sink: S,
+    metrics: ProtocolMetricCache,
+) -> (TcpSendProtocol<D>, TcpRecvProtocol<S>)
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    let send = TcpSendProtocol::new(drain, metrics.clone());
+    let mut recv = TcpRecvProtocol::new(sink, metrics);
+    recv.rtt_probe = Arc::clone(&send.rtt_probe);
+    (send, recv)
+}
+
 /// TCP implementation of [`SendProtocol`]
 ///
 /// [`SendProtocol`]: crate::SendProtocol
@@ -32,9 +86,15 @@ where
     notify_closing_streams: Vec<Sid>,
     pending_shutdown: bool,
     drain: D,
-    #[allow(dead_code)]
-    last: Instant,
+    encryption: Encryption,
+    authentication: Authentication,
+    cipher: Option<SealingCipher>,
+    /// whether the key exchange was authenticated, see [`Authentication`]
+    authenticated: bool,
+    rtt_probe: Arc<Mutex<RttProbe>>,
+    last_ping: Instant,
     metrics: ProtocolMetricCache,
+    capture: Option<FrameCapture>,
 }
 
 /// TCP implementation of [`RecvProtocol`]
@@ -49,7 +109,12 @@ where
     itmsg_allocator: BytesMut,
     incoming: HashMap<Mid, ITMessage>,
     sink: S,
+    /// received data that still needs to be opened by the `cipher`
+    sealed: BytesMut,
+    cipher: Option<OpeningCipher>,
+    rtt_probe: Arc<Mutex<RttProbe>>,
     metrics: ProtocolMetricCache,
+    capture: Option<FrameCapture>,
 }
 
 impl<D> TcpSendProtocol<D>
@@ -65,18 +130,67 @@ where
             notify_closing_streams: vec![],
             pending_shutdown: false,
             drain,
-            last: Instant::now(),
+            encryption: Encryption::Supported,
+            authentication: Authentication::None,
+            cipher: None,
+            authenticated: false,
+            rtt_probe: Arc::default(),
+            last_ping: Instant::now(),
             metrics,
+            capture: None,
         }
     }
 
+    /// Set whether the channel is encrypted, defaults to
+    /// [`Encryption::Supported`]. Takes effect on the next Handshake.
+    pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
+    /// Set how the key exchange is authenticated, defaults to
+    /// [`Authentication::None`]. Takes effect on the next Handshake.
+    pub fn with_authentication(mut self, authentication: Authentication) -> Self {
+        self.authentication = authentication;
+        self
+    }
+
+    /// Capture every send frame, see [`FrameCapture`]
+    pub fn with_capture(mut self, capture: FrameCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
     /// returns all promises that this Protocol can take care of
     /// If you open a Stream anyway, unsupported promises are ignored.
-    pub fn supported_promises() -> Promises {
-        Promises::ORDERED
+    /// [`Promises::ENCRYPTED`] is only supported once the Handshake agreed on
+    /// a key and authenticated the key exchange.
+    pub fn supported_promises(&self) -> Promises {
+        let promises = Promises::ORDERED
             | Promises::CONSISTENCY
             | Promises::GUARANTEED_DELIVERY
-            | Promises::COMPRESSED
+            | Promises::COMPRESSED;
+        if self.cipher.is_some() && self.authenticated {
+            promises | Promises::ENCRYPTED
+        } else {
+            promises
+        }
+    }
+
+    fn write_frame(&mut self, frame: OTFrame) {
+        if let Some(capture) = &self.capture {
+            capture.out_frame(&frame);
+        }
+        frame.write_bytes(&mut self.buffer);
+    }
+
+    /// send everything buffered, sealed if the channel is encrypted
+    async fn send_buffer(&mut self) -> Result<(), ProtocolError> {
+        let data = self.buffer.split();
+        match &mut self.cipher {
+            Some(cipher) if !data.is_empty() => self.drain.send(cipher.seal(data)).await,
+            _ => self.drain.send(data).await,
+        }
     }
 }
 
@@ -90,7 +204,49 @@ where
             itmsg_allocator: BytesMut::with_capacity(ALLOC_BLOCK),
             incoming: HashMap::new(),
             sink,
+            sealed: BytesMut::new(),
+            cipher: None,
+            rtt_probe: Arc::default(),
             metrics,
+            capture: None,
+        }
+    }
+
+    /// Capture every received frame, see [`FrameCapture`]
+    pub fn with_capture(mut self, capture: FrameCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    /// receive more data into `buffer`, opened if the channel is encrypted
+    async fn recv_chunk(&mut self) -> Result<(), ProtocolError> {
+        let cipher = match &mut self.cipher {
+            Some(cipher) => cipher,
+            None => {
+                let chunk = self.sink.recv().await?;
+                if self.buffer.is_empty() {
+                    self.buffer = chunk;
+                } else {
+                    self.buffer.extend_from_slice(&chunk);
+                }
+                return Ok(());
+            },
+        };
+        loop {
+            match cipher.open(&mut self.sealed) {
+                Ok(Some(data)) => {
+                    self.buffer.extend_from_slice(&data);
+                    return Ok(());
+                },
+                Ok(None) => {
+                    let chunk = self.sink.recv().await?;
+                    self.sealed.extend_from_slice(&chunk);
+                },
+                Err(()) => {
+                    info!("protocol violation by remote side: record couldn't be opened");
+                    return Err(ProtocolError::Violated);
+                },
+            }
         }
     }
 }
@@ -134,13 +290,13 @@ where
             } => {
                 self.store
                     .open_stream(sid, prio, promises, guaranteed_bandwidth);
-                event.to_frame().write_bytes(&mut self.buffer);
-                self.drain.send(self.buffer.split()).await?;
+                self.write_frame(event.to_frame());
+                self.send_buffer().await?;
             },
             ProtocolEvent::CloseStream { sid } => {
                 if self.store.try_close_stream(sid) {
-                    event.to_frame().write_bytes(&mut self.buffer);
-                    self.drain.send(self.buffer.split()).await?;
+                    self.write_frame(event.to_frame());
+                    self.send_buffer().await?;
                 } else {
                     #[cfg(feature = "trace_pedantic")]
                     trace!(?sid, "hold back close stream");
@@ -149,8 +305,8 @@ where
             },
             ProtocolEvent::Shutdown => {
                 if self.store.is_empty() {
-                    event.to_frame().write_bytes(&mut self.buffer);
-                    self.drain.send(self.buffer.split()).await?;
+                    self.write_frame(event.to_frame());
+                    self.send_buffer().await?;
                 } else {
                     #[cfg(feature = "trace_pedantic")]
                     trace!("hold back shutdown");
@@ -162,6 +318,10 @@ where
                 self.store.add(data, self.next_mid, sid);
                 self.next_mid += 1;
             },
+            ProtocolEvent::Ack { .. } => {
+                self.write_frame(event.to_frame());
+                self.send_buffer().await?;
+            },
         }
         Ok(())
     }
@@ -171,6 +331,30 @@ where
         bandwidth: Bandwidth,
         dt: Duration,
     ) -> Result</* actual */ Bandwidth, ProtocolError> {
+        // pings and pongs go first, so that only the queues of the link delay them
+        let now = Instant::now();
+        let (pongs, ping) = {
+            let mut probe = self.rtt_probe.lock().unwrap();
+            let ping = if probe.ping.is_none()
+                && now.saturating_duration_since(self.last_ping) >= PING_INTERVAL
+            {
+                let id = probe.next_ping;
+                probe.next_ping += 1;
+                probe.ping = Some((id, now));
+                self.last_ping = now;
+                Some(id)
+            } else {
+                None
+            };
+            (std::mem::take(&mut probe.pongs_due), ping)
+        };
+        for id in pongs {
+            self.write_frame(OTFrame::Pong { id });
+        }
+        if let Some(id) = ping {
+            self.write_frame(OTFrame::Ping { id });
+        }
+
         let (frames, total_bytes) = self.store.grab(bandwidth, dt);
         self.buffer.reserve(total_bytes as usize);
         let mut data_frames = 0;
@@ -180,19 +364,20 @@ where
                 data_bandwidth += data.len();
                 data_frames += 1;
             }
-            frame.write_bytes(&mut self.buffer);
+            self.write_frame(frame);
         }
-        self.drain.send(self.buffer.split()).await?;
+        self.send_buffer().await?;
         self.metrics
             .sdata_frames_b(data_frames, data_bandwidth as u64);
 
         let mut finished_streams = vec![];
-        for (i, &sid) in self.closing_streams.iter().enumerate() {
+        // copied, as writing the frame needs `self` mutably
+        for (i, sid) in self.closing_streams.clone().into_iter().enumerate() {
             if self.store.try_close_stream(sid) {
                 #[cfg(feature = "trace_pedantic")]
                 trace!(?sid, "close stream, as it's now empty");
-                OTFrame::CloseStream { sid }.write_bytes(&mut self.buffer);
-                self.drain.send(self.buffer.split()).await?;
+                self.write_frame(OTFrame::CloseStream { sid });
+                self.send_buffer().await?;
                 finished_streams.push(i);
             }
         }
@@ -215,12 +400,21 @@ where
         if self.pending_shutdown && self.store.is_empty() {
             #[cfg(feature = "trace_pedantic")]
             trace!("shutdown, as it's now empty");
-            OTFrame::Shutdown {}.write_bytes(&mut self.buffer);
-            self.drain.send(self.buffer.split()).await?;
+            self.write_frame(OTFrame::Shutdown {});
+            self.send_buffer().await?;
             self.pending_shutdown = false;
         }
         Ok(data_bandwidth as u64)
     }
+
+    fn link_report(&mut self) -> LinkReport {
+        LinkReport {
+            rtt: self.rtt_probe.lock().unwrap().srtt,
+            lost: 0,
+        }
+    }
+
+    fn take_unflushed(&mut self) -> Vec<(Sid, Bytes)> { self.store.take_unflushed() }
 }
 
 #[async_trait]
@@ -235,6 +429,9 @@ where
                     Ok(Some(frame)) => {
                         #[cfg(feature = "trace_pedantic")]
                         trace!(?frame, "recv");
+                        if let Some(capture) = &self.capture {
+                            capture.in_frame(&frame);
+                        }
                         match frame {
                             ITFrame::Shutdown => break 'outer Ok(ProtocolEvent::Shutdown),
                             ITFrame::OpenStream {
@@ -253,6 +450,15 @@ where
                             ITFrame::CloseStream { sid } => {
                                 break 'outer Ok(ProtocolEvent::CloseStream { sid });
                             },
+                            ITFrame::Ack { sid, count } => {
+                                break 'outer Ok(ProtocolEvent::Ack { sid, count });
+                            },
+                            ITFrame::Ping { id } => {
+                                self.rtt_probe.lock().unwrap().pongs_due.push(id);
+                            },
+                            ITFrame::Pong { id } => {
+                                self.rtt_probe.lock().unwrap().pong(id, Instant::now());
+                            },
                             ITFrame::DataHeader { sid, mid, length } => {
                                 let m = ITMessage::new(sid, length, &mut self.itmsg_allocator);
                                 self.metrics.rmsg_ib(sid, length);
@@ -292,12 +498,7 @@ where
                     Err(()) => return Err(ProtocolError::Violated),
                 }
             }
-            let chunk = self.sink.recv().await?;
-            if self.buffer.is_empty() {
-                self.buffer = chunk;
-            } else {
-                self.buffer.extend_from_slice(&chunk);
-            }
+            self.recv_chunk().await?;
         }
     }
 }
@@ -308,9 +509,18 @@ where
     D: UnreliableDrain<DataFormat = BytesMut>,
 {
     async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError> {
-        let mut buffer = BytesMut::with_capacity(500);
-        frame.write_bytes(&mut buffer);
-        self.drain.send(buffer).await
+        self.buffer.reserve(500);
+        frame.write_bytes(&mut self.buffer);
+        self.send_buffer().await
+    }
+
+    fn encryption(&self) -> Encryption { self.encryption }
+
+    fn authentication(&self) -> Authentication { self.authentication.clone() }
+
+    fn enable_encryption(&mut self, cipher: SealingCipher, authenticated: bool) {
+        self.cipher = Some(cipher);
+        self.authenticated = authenticated;
     }
 }
 
@@ -320,14 +530,22 @@ where
     S: UnreliableSink<DataFormat = BytesMut>,
 {
     async fn recv(&mut self) -> Result<InitFrame, ProtocolError> {
-        while self.buffer.len() < 100 {
-            let chunk = self.sink.recv().await?;
-            self.buffer.extend_from_slice(&chunk);
+        // multiple frames might have arrived in a single chunk
+        loop {
             if let Some(frame) = InitFrame::read_frame(&mut self.buffer) {
                 return Ok(frame);
             }
+            if self.buffer.len() >= 100 {
+                return Err(ProtocolError::Violated);
+            }
+            self.recv_chunk().await?;
         }
-        Err(ProtocolError::Violated)
+    }
+
+    fn enable_encryption(&mut self, cipher: OpeningCipher) {
+        // everything after the key exchange is sealed already
+        self.sealed = self.buffer.split();
+        self.cipher = Some(cipher);
     }
 }
 
@@ -358,14 +576,8 @@ mod test_utils {
             ProtocolMetricCache::new("tcp", Arc::new(ProtocolMetrics::new().unwrap()))
         });
         [
-            (
-                TcpSendProtocol::new(TcpDrain { sender: s1 }, m.clone()),
-                TcpRecvProtocol::new(TcpSink { receiver: r2 }, m.clone()),
-            ),
-            (
-                TcpSendProtocol::new(TcpDrain { sender: s2 }, m.clone()),
-                TcpRecvProtocol::new(TcpSink { receiver: r1 }, m),
-            ),
+            tcp_protocols(TcpDrain { sender: s1 }, TcpSink { receiver: r2 }, m.clone()),
+            tcp_protocols(TcpDrain { sender: s2 }, TcpSink { receiver: r1 }, m),
         ]
     }
 
@@ -397,15 +609,20 @@ mod test_utils {
 #[cfg(test)]
 mod tests {
     use crate::{
+        capture::{test_utils::VecSink, CaptureDirection, CaptureSink, FrameCapture},
         error::ProtocolError,
         frame::OTFrame,
         metrics::{ProtocolMetricCache, ProtocolMetrics, RemoveReason},
-        tcp::test_utils::*,
+        tcp::{test_utils::*, TcpRecvProtocol, TcpSendProtocol},
         types::{Pid, Promises, Sid, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2},
-        InitProtocol, ProtocolEvent, RecvProtocol, SendProtocol,
+        Authentication, Encryption, Identity, IdentityKey, InitProtocol, InitProtocolError,
+        ProtocolEvent, RecvProtocol, SendProtocol,
     };
     use bytes::{Bytes, BytesMut};
-    use std::{sync::Arc, time::Duration};
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
 
     #[tokio::test]
     async fn handshake_all_good() {
@@ -417,6 +634,125 @@ mod tests {
         assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
     }
 
+    fn authenticated_bound(
+        identity: Identity,
+        pinned: IdentityKey,
+    ) -> [(TcpSendProtocol<TcpDrain>, TcpRecvProtocol<TcpSink>); 2] {
+        let [p1, p2] = tcp_bound(10, None);
+        [
+            (
+                p1.0.with_authentication(Authentication::Sign(Arc::new(identity))),
+                p1.1,
+            ),
+            (
+                p2.0.with_authentication(Authentication::Verify(pinned)),
+                p2.1,
+            ),
+        ]
+    }
+
+    #[tokio::test]
+    async fn handshake_encrypted() {
+        let identity = Identity::generate().unwrap();
+        let pinned = identity.public_key();
+        let [mut p1, mut p2] = authenticated_bound(identity, pinned);
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337).await.unwrap();
+            p1
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(false, Pid::fake(3), 42).await.unwrap();
+            p2
+        });
+        let (p1, p2) = tokio::join!(r1, r2);
+        let (p1, p2) = (p1.unwrap(), p2.unwrap());
+        assert!(p2.0.supported_promises().contains(Promises::ENCRYPTED));
+        let (mut s, mut r) = (p1.0, p2.1);
+        assert!(s.supported_promises().contains(Promises::ENCRYPTED));
+        let event = ProtocolEvent::OpenStream {
+            sid: Sid::new(10),
+            prio: 3u8,
+            promises: Promises::ENCRYPTED,
+            guaranteed_bandwidth: 1_000_000,
+        };
+        s.send(event.clone()).await.unwrap();
+        assert_eq!(event, r.recv().await.unwrap());
+        let event = ProtocolEvent::Message {
+            sid: Sid::new(10),
+            data: Bytes::from(&[188u8; 600][..]),
+        };
+        s.send(event.clone()).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(event, r.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn handshake_unauthenticated() {
+        let [mut p1, mut p2] = tcp_bound(10, None);
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337).await.unwrap();
+            p1
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(false, Pid::fake(3), 42).await.unwrap();
+            p2
+        });
+        let (p1, p2) = tokio::join!(r1, r2);
+        let (mut s, mut r) = (p1.unwrap().0, p2.unwrap().1);
+        // encrypted, but a man in the middle can't be ruled out
+        assert!(!s.supported_promises().contains(Promises::ENCRYPTED));
+        let event = ProtocolEvent::OpenStream {
+            sid: Sid::new(10),
+            prio: 3u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+        };
+        s.send(event.clone()).await.unwrap();
+        assert_eq!(event, r.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn handshake_wrong_identity() {
+        let pinned = Identity::generate().unwrap().public_key();
+        let [mut p1, mut p2] = authenticated_bound(Identity::generate().unwrap(), pinned);
+        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(r1.unwrap(), Err(InitProtocolError::Closed));
+        assert_eq!(r2.unwrap(), Err(InitProtocolError::AuthenticationFailed));
+    }
+
+    #[tokio::test]
+    async fn handshake_unencrypted() {
+        let [mut p1, p2] = tcp_bound(10, None);
+        let mut p2 = (p2.0.with_encryption(Encryption::Unsupported), p2.1);
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337).await.unwrap();
+            p1
+        });
+        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let (p1, r2) = tokio::join!(r1, r2);
+        assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
+        assert!(
+            !p1.unwrap()
+                .0
+                .supported_promises()
+                .contains(Promises::ENCRYPTED)
+        );
+    }
+
+    #[tokio::test]
+    async fn handshake_encryption_required() {
+        let [p1, p2] = tcp_bound(10, None);
+        let mut p1 = (p1.0.with_encryption(Encryption::Required), p1.1);
+        let mut p2 = (p2.0.with_encryption(Encryption::Unsupported), p2.1);
+        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(r1.unwrap(), Err(InitProtocolError::EncryptionUnsupported));
+        assert_eq!(r2.unwrap(), Err(InitProtocolError::Closed));
+    }
+
     #[tokio::test]
     async fn open_stream() {
         let [p1, p2] = tcp_bound(10, None);
@@ -552,6 +888,29 @@ mod tests {
         assert!(matches!(e, ProtocolEvent::Shutdown { .. }));
     }
 
+    #[tokio::test]
+    async fn ack_doesnt_wait_for_flush() {
+        let sid = Sid::new(1);
+        let [p1, p2] = tcp_bound(10000, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let event = ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::GUARANTEED_DELIVERY,
+            guaranteed_bandwidth: 0,
+        };
+        s.send(event).await.unwrap();
+        let _ = r.recv().await.unwrap();
+        let event = ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&[99u8; 500][..]),
+        };
+        s.send(event).await.unwrap();
+        let event = ProtocolEvent::Ack { sid, count: 7 };
+        s.send(event.clone()).await.unwrap();
+        assert_eq!(event, r.recv().await.unwrap());
+    }
+
     #[tokio::test]
     async fn msg_finishes_after_drop() {
         let sid = Sid::new(1);
@@ -585,6 +944,135 @@ mod tests {
         assert!(matches!(e, ProtocolEvent::Message { .. }));
     }
 
+    #[tokio::test]
+    async fn take_unflushed_msgs() {
+        let sid = Sid::new(1);
+        let [p1, _p2] = tcp_bound(10_000, None);
+        let mut s = p1.0;
+        let event = ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 0,
+        };
+        s.send(event).await.unwrap();
+        let big = Bytes::from(&[99u8; 500_000][..]);
+        let small = Bytes::from(&[7u8; 30][..]);
+        s.send(ProtocolEvent::Message {
+            sid,
+            data: big.clone(),
+        })
+        .await
+        .unwrap();
+        s.send(ProtocolEvent::Message {
+            sid,
+            data: small.clone(),
+        })
+        .await
+        .unwrap();
+        // only a part of the first message gets flushed
+        s.flush(100_000, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(s.take_unflushed(), vec![(sid, big), (sid, small)]);
+        assert!(s.take_unflushed().is_empty());
+    }
+
+    #[tokio::test]
+    async fn capture_and_replay() {
+        let sink = Arc::new(VecSink::default());
+        let start = Instant::now();
+        let [p1, p2] = tcp_bound(10, None);
+        let mut s = p1.0.with_capture(FrameCapture::new(
+            1,
+            start,
+            Arc::clone(&sink) as Arc<dyn CaptureSink>,
+        ));
+        let mut r = p2.1.with_capture(FrameCapture::new(
+            2,
+            start,
+            Arc::clone(&sink) as Arc<dyn CaptureSink>,
+        ));
+        let sid = Sid::new(1);
+        let open = ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 0,
+        };
+        let msg = ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&[3u8; 5000][..]),
+        };
+        s.send(open.clone()).await.unwrap();
+        s.send(msg.clone()).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(r.recv().await.unwrap(), open);
+        assert_eq!(r.recv().await.unwrap(), msg);
+
+        let frames = sink.0.lock().unwrap().clone();
+        let out = frames
+            .iter()
+            .filter(|f| f.direction == CaptureDirection::Out)
+            .collect::<Vec<_>>();
+        let inc = frames
+            .iter()
+            .filter(|f| f.direction == CaptureDirection::In)
+            .collect::<Vec<_>>();
+        // OpenStream, DataHeader and multiple Data frames
+        assert!(out.len() > 3);
+        assert!(out.iter().all(|f| f.cid == 1));
+        assert!(inc.iter().all(|f| f.cid == 2));
+        assert_eq!((out[0].sid, out[0].mid), (Some(sid), None));
+        assert_eq!(out[1].sid, Some(sid));
+        assert_eq!(out[2].mid, out[1].mid);
+        assert_eq!(
+            out.iter().map(|f| &f.frame).collect::<Vec<_>>(),
+            inc.iter().map(|f| &f.frame).collect::<Vec<_>>()
+        );
+
+        // replay the received frames
+        let (sender, receiver) = async_channel::bounded(1);
+        let metrics = ProtocolMetricCache::new("replay", Arc::new(ProtocolMetrics::new().unwrap()));
+        let mut replay = TcpRecvProtocol::new(TcpSink { receiver }, metrics);
+        let mut bytes = BytesMut::new();
+        for f in inc {
+            bytes.extend_from_slice(&f.frame);
+        }
+        sender.send(bytes).await.unwrap();
+        assert_eq!(replay.recv().await.unwrap(), open);
+        assert_eq!(replay.recv().await.unwrap(), msg);
+    }
+
+    #[tokio::test]
+    async fn measures_rtt() {
+        let [mut p1, mut p2] = tcp_bound(10, None);
+        assert_eq!(p1.0.link_report().rtt, None);
+        // don't wait for the first ping
+        p1.0.last_ping = Instant::now() - super::PING_INTERVAL;
+        p1.0.flush(1_000_000, Duration::from_millis(5)).await.unwrap();
+        // pings and pongs are handled by the protocol, wait for the next event
+        let event = ProtocolEvent::OpenStream {
+            sid: Sid::new(10),
+            prio: 3u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+        };
+        p1.0.send(event.clone()).await.unwrap();
+        assert_eq!(event, p2.1.recv().await.unwrap());
+
+        p2.0.flush(1_000_000, Duration::from_millis(5)).await.unwrap();
+        let event = ProtocolEvent::OpenStream {
+            sid: Sid::new(11),
+            prio: 3u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+        };
+        p2.0.send(event.clone()).await.unwrap();
+        assert_eq!(event, p1.1.recv().await.unwrap());
+        assert!(p1.0.link_report().rtt.is_some());
+        // the remote side doesn't ping before the interval passed
+        assert_eq!(p2.0.link_report().rtt, None);
+    }
+
     #[tokio::test]
     async fn header_and_data_in_seperate_msg() {
         let sid = Sid::new(1);
@@ -1,37 +1,1203 @@
-// TODO: quick and dirty which activly waits for an ack!
-/*
-UDP protocol
-
-All Good Case:
-S --HEADER--> R
-S --DATA--> R
-S --DATA--> R
-S <--FINISHED-- R
-
-
-Delayed HEADER:
-S --HEADER-->
-S --DATA--> R // STORE IT
-  --HEADER--> R // apply left data and continue
-S --DATA--> R
-S <--FINISHED-- R
-
-
-NO HEADER:
-S --HEADER--> !
-S --DATA--> R // STORE IT
-S --DATA--> R // STORE IT
-S <--MISSING_HEADER-- R // SEND AFTER 10 ms after DATA1
-S --HEADER--> R
-S <--FINISHED-- R
-
-
-NO DATA:
-S --HEADER--> R
-S --DATA--> R
-S --DATA--> !
-S --STATUS--> R
-S <--MISSING_DATA -- R
-S --DATA--> R
-S <--FINISHED-- R
-*/
+//! UDP implementation of the protocol
+//!
+//! UDP neither guarantees delivery nor order, so this protocol takes care of
+//! both for the streams that need it. Every datagram starts with its kind:
+//! ```ignore
+//! MAIN:       [seq][lane_seq][bytes]      handshake and OpenStream, CloseStream, Shutdown
+//! STREAM:     [seq][sid][lane_seq][bytes] frames of a reliable stream
+//! UNRELIABLE: [frames]                    frames of all other streams
+//! ACK:        [ack][ack_bits]             every seq < ack, and seq ack+1+i for every bit i
+//! ```
+//! `MAIN` and `STREAM` datagrams are reliable: they are kept until the remote
+//! side acknowledges their `seq` and are retransmitted otherwise. Every lane
+//! (main or a single stream) is an ordered stream of bytes, just like TCP, but
+//! a lost datagram only blocks its own lane. Streams with
+//! [`Promises::ORDERED`], [`Promises::CONSISTENCY`] or
+//! [`Promises::GUARANTEED_DELIVERY`] get their own lane, the frames of all
+//! other streams are send in `UNRELIABLE` datagrams that are never
+//! retransmitted. An unreliable message is dropped once a newer message of the
+//! same stream arrives, so e.g. position updates never wait on lost ones.
+//!
+//! Retransmission needs a clock, the [`UnreliableSink`] therefore MUST return
+//! an empty datagram when nothing arrived for a while, e.g. after 10 ms.
+//!
+//! [`Promises::ORDERED`]: crate::Promises::ORDERED
+//! [`Promises::CONSISTENCY`]: crate::Promises::CONSISTENCY
+//! [`Promises::GUARANTEED_DELIVERY`]: crate::Promises::GUARANTEED_DELIVERY
+//! [`UnreliableSink`]: crate::UnreliableSink
+use crate::{
+    congestion::LinkReport,
+    error::ProtocolError,
+    event::ProtocolEvent,
+    frame::{ITFrame, InitFrame, OTFrame},
+    handshake::{ReliableDrain, ReliableSink},
+    message::{ITMessage, ALLOC_BLOCK},
+    metrics::{ProtocolMetricCache, RemoveReason},
+    prio::PrioManager,
+    types::{Bandwidth, Mid, Promises, Sid, VELOREN_MAGIC_NUMBER},
+    util::SortedVec,
+    RecvProtocol, SendProtocol, UnreliableDrain, UnreliableSink,
+};
+use async_trait::async_trait;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use hashbrown::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::info;
+#[cfg(feature = "trace_pedantic")]
+use tracing::trace;
+
+const DATAGRAM_MAIN: u8 = 1;
+const DATAGRAM_STREAM: u8 = 2;
+const DATAGRAM_UNRELIABLE: u8 = 3;
+const DATAGRAM_ACK: u8 = 4;
+
+/// Keeps every datagram below a MTU of 1500 bytes, even with IPv6 and UDP
+/// headers
+pub const UDP_MAX_DATAGRAM_SIZE: usize = 1452;
+const MAIN_HEADER_SIZE: usize = 1 + 8 + 8;
+const STREAM_HEADER_SIZE: usize = 1 + 8 + 8 + 8;
+const ACK_SIZE: usize = 1 + 8 + 8;
+
+const INITIAL_RTO: Duration = Duration::from_millis(200);
+const MIN_RTO: Duration = Duration::from_millis(20);
+const MAX_RTO: Duration = Duration::from_secs(3);
+/// The remote side sends at least one datagram this often, even when idle
+const KEEPALIVE: Duration = Duration::from_secs(1);
+/// Without any datagram from the remote side for this long, the channel is
+/// considered closed
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Reliable datagrams that far ahead of the last one received in order are
+/// dropped, so the remote side can't make us buffer without bounds
+const MAX_RECEIVE_WINDOW: u64 = 16_384;
+
+/// Whether `datagram` is the first one a connecting side sends, i.e. the
+/// reliable datagram that starts its handshake. Listeners use it to ignore
+/// stray datagrams before keeping any state for their sender.
+pub fn is_handshake_datagram(datagram: &[u8]) -> bool {
+    if datagram.len() < MAIN_HEADER_SIZE || datagram[0] != DATAGRAM_MAIN {
+        return false;
+    }
+    // seq and lane_seq of the very first datagram are 0
+    if datagram[1..MAIN_HEADER_SIZE].iter().any(|&b| b != 0) {
+        return false;
+    }
+    matches!(
+        InitFrame::read_frame(&mut BytesMut::from(&datagram[MAIN_HEADER_SIZE..])),
+        Some(InitFrame::Handshake { magic_number, .. }) if magic_number == VELOREN_MAGIC_NUMBER
+    )
+}
+
+/// A reliable datagram that was send but not acknowledged yet
+#[derive(Debug)]
+struct Unacked {
+    lane: Option<Sid>,
+    datagram: Bytes,
+    sent: Instant,
+    retransmitted: bool,
+}
+
+/// State that the send and receive half of a channel share: the receiving
+/// half learns about acknowledgements and streams opened by the remote side.
+#[derive(Debug)]
+struct Shared {
+    next_seq: u64,
+    unacked: BTreeMap<u64, Unacked>,
+    srtt: Option<Duration>,
+    rto: Duration,
+    /// streams that either side opened, data of other streams is held back
+    opened: HashSet<Sid>,
+    /// datagrams retransmitted since the last [`LinkReport`]
+    retransmitted: u64,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Self {
+            next_seq: 0,
+            unacked: BTreeMap::new(),
+            srtt: None,
+            rto: INITIAL_RTO,
+            opened: HashSet::new(),
+            retransmitted: 0,
+        }
+    }
+
+    fn acknowledge(&mut self, ack: u64, ack_bits: u64, now: Instant) {
+        let mut acked = self
+            .unacked
+            .range(..ack)
+            .map(|(&seq, _)| seq)
+            .collect::<Vec<_>>();
+        acked.extend(
+            (0..64u64)
+                .filter(|i| ack_bits & (1 << i) != 0)
+                .filter_map(|i| ack.checked_add(i + 1)),
+        );
+        for seq in acked {
+            if let Some(unacked) = self.unacked.remove(&seq) {
+                // Karn's algorithm: retransmitted datagrams don't tell the rtt
+                if !unacked.retransmitted {
+                    self.update_rtt(now.saturating_duration_since(unacked.sent));
+                }
+            }
+        }
+    }
+
+    fn update_rtt(&mut self, rtt: Duration) {
+        let srtt = match self.srtt {
+            Some(srtt) => srtt * 7 / 8 + rtt / 8,
+            None => rtt,
+        };
+        self.srtt = Some(srtt);
+        self.rto = (srtt * 2).clamp(MIN_RTO, MAX_RTO);
+    }
+
+    /// returns all datagrams that need to be retransmitted
+    fn due(&mut self, now: Instant) -> Vec<Bytes> {
+        let rto = self.rto;
+        let due = self
+            .unacked
+            .values_mut()
+            .filter(|u| now.saturating_duration_since(u.sent) >= rto)
+            .map(|u| {
+                u.sent = now;
+                u.retransmitted = true;
+                u.datagram.clone()
+            })
+            .collect::<Vec<_>>();
+        if !due.is_empty() {
+            self.rto = (self.rto * 2).min(MAX_RTO);
+            self.retransmitted += due.len() as u64;
+        }
+        due
+    }
+
+    fn lane_acked(&self, sid: Sid) -> bool { !self.unacked.values().any(|u| u.lane == Some(sid)) }
+
+    fn streams_acked(&self) -> bool { self.unacked.values().all(|u| u.lane.is_none()) }
+}
+
+fn is_reliable(p: &Promises) -> bool {
+    p.contains(Promises::ORDERED)
+        || p.contains(Promises::CONSISTENCY)
+        || p.contains(Promises::GUARANTEED_DELIVERY)
+}
+
+#[derive(Debug, Default)]
+struct SendLane {
+    next_seq: u64,
+    buffer: BytesMut,
+}
+
+/// Send everything buffered in a lane, the datagrams are kept until they are
+/// acknowledged
+async fn send_lane<D>(
+    drain: &mut D,
+    shared: &Mutex<Shared>,
+    lane: &mut SendLane,
+    sid: Option<Sid>,
+) -> Result<(), ProtocolError>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    let header_size = match sid {
+        Some(_) => STREAM_HEADER_SIZE,
+        None => MAIN_HEADER_SIZE,
+    };
+    while !lane.buffer.is_empty() {
+        let len = lane.buffer.len().min(UDP_MAX_DATAGRAM_SIZE - header_size);
+        let datagram = {
+            let mut datagram = BytesMut::with_capacity(header_size + len);
+            let mut shared = shared.lock().unwrap();
+            let seq = shared.next_seq;
+            shared.next_seq += 1;
+            match sid {
+                Some(sid) => {
+                    datagram.put_u8(DATAGRAM_STREAM);
+                    datagram.put_u64_le(seq);
+                    sid.to_bytes(&mut datagram);
+                },
+                None => {
+                    datagram.put_u8(DATAGRAM_MAIN);
+                    datagram.put_u64_le(seq);
+                },
+            }
+            datagram.put_u64_le(lane.next_seq);
+            lane.next_seq += 1;
+            datagram.extend_from_slice(&lane.buffer.split_to(len));
+            shared.unacked.insert(seq, Unacked {
+                lane: sid,
+                datagram: Bytes::copy_from_slice(&datagram),
+                sent: Instant::now(),
+                retransmitted: false,
+            });
+            datagram
+        };
+        drain.send(datagram).await?;
+    }
+    Ok(())
+}
+
+/// UDP implementation of [`SendProtocol`]
+///
+/// [`SendProtocol`]: crate::SendProtocol
+#[derive(Debug)]
+pub struct UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    main: SendLane,
+    lanes: SortedVec<Sid, SendLane>,
+    unreliable_buffer: BytesMut,
+    store: PrioManager,
+    next_mid: Mid,
+    closing_streams: Vec<Sid>,
+    notify_closing_streams: Vec<Sid>,
+    pending_shutdown: bool,
+    drain: D,
+    shared: Arc<Mutex<Shared>>,
+    last_keepalive: Instant,
+    metrics: ProtocolMetricCache,
+}
+
+#[derive(Debug, Default)]
+struct RecvLane {
+    next_seq: u64,
+    pending: BTreeMap<u64, BytesMut>,
+    buffer: BytesMut,
+}
+
+impl RecvLane {
+    fn insert(&mut self, lane_seq: u64, data: BytesMut) {
+        if lane_seq == self.next_seq {
+            self.buffer.extend_from_slice(&data);
+            self.next_seq += 1;
+            while let Some(data) = self.pending.remove(&self.next_seq) {
+                self.buffer.extend_from_slice(&data);
+                self.next_seq += 1;
+            }
+        } else if lane_seq > self.next_seq {
+            self.pending.insert(lane_seq, data);
+        }
+    }
+}
+
+/// Reliable datagrams received so far
+#[derive(Debug, Default)]
+struct ReceivedSeqs {
+    /// all seqs below were received
+    next: u64,
+    above: BTreeSet<u64>,
+}
+
+impl ReceivedSeqs {
+    /// returns false if `seq` was received before or is outside the window
+    fn insert(&mut self, seq: u64) -> bool {
+        if seq < self.next || seq >= self.next + MAX_RECEIVE_WINDOW {
+            false
+        } else if seq == self.next {
+            self.next += 1;
+            while self.above.remove(&self.next) {
+                self.next += 1;
+            }
+            true
+        } else {
+            self.above.insert(seq)
+        }
+    }
+
+    fn ack(&self) -> (u64, u64) {
+        let ack_bits = self
+            .above
+            .range(self.next + 1..self.next + 65)
+            .fold(0u64, |bits, seq| bits | 1 << (seq - self.next - 1));
+        (self.next, ack_bits)
+    }
+}
+
+/// UDP implementation of [`RecvProtocol`], it also needs a [`UnreliableDrain`]
+/// to acknowledge datagrams.
+///
+/// [`RecvProtocol`]: crate::RecvProtocol
+/// [`UnreliableDrain`]: crate::UnreliableDrain
+#[derive(Debug)]
+pub struct UdpRecvProtocol<S, D>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    main: RecvLane,
+    lanes: HashMap<Sid, RecvLane>,
+    received: ReceivedSeqs,
+    unreliable_buffer: BytesMut,
+    itmsg_allocator: BytesMut,
+    incoming: HashMap<Mid, ITMessage>,
+    unreliable_incoming: HashMap<Mid, ITMessage>,
+    sink: S,
+    drain: D,
+    shared: Arc<Mutex<Shared>>,
+    last_recv: Instant,
+    metrics: ProtocolMetricCache,
+}
+
+/// Create both halves of a UDP channel, the `drain` is used by both of them.
+pub fn udp_protocols<D, S>(
+    drain: D,
+    sink: S,
+    metrics: ProtocolMetricCache,
+) -> (UdpSendProtocol<D>, UdpRecvProtocol<S, D>)
+where
+    D: UnreliableDrain<DataFormat = BytesMut> + Clone,
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    let shared = Arc::new(Mutex::new(Shared::new()));
+    let send = UdpSendProtocol {
+        main: SendLane::default(),
+        lanes: SortedVec::default(),
+        unreliable_buffer: BytesMut::new(),
+        store: PrioManager::new(metrics.clone()),
+        next_mid: 0u64,
+        closing_streams: vec![],
+        notify_closing_streams: vec![],
+        pending_shutdown: false,
+        drain: drain.clone(),
+        shared: Arc::clone(&shared),
+        last_keepalive: Instant::now(),
+        metrics: metrics.clone(),
+    };
+    let recv = UdpRecvProtocol {
+        main: RecvLane::default(),
+        lanes: HashMap::new(),
+        received: ReceivedSeqs::default(),
+        unreliable_buffer: BytesMut::new(),
+        itmsg_allocator: BytesMut::with_capacity(ALLOC_BLOCK),
+        incoming: HashMap::new(),
+        unreliable_incoming: HashMap::new(),
+        sink,
+        drain,
+        shared,
+        last_recv: Instant::now(),
+        metrics,
+    };
+    (send, recv)
+}
+
+impl<D> UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    /// returns all promises that this Protocol can take care of
+    /// If you open a Stream anyway, unsupported promises are ignored.
+    pub fn supported_promises() -> Promises {
+        Promises::ORDERED
+            | Promises::CONSISTENCY
+            | Promises::GUARANTEED_DELIVERY
+            | Promises::COMPRESSED
+    }
+
+    async fn send_main(&mut self) -> Result<(), ProtocolError> {
+        send_lane(&mut self.drain, &self.shared, &mut self.main, None).await
+    }
+
+    /// whole frames only, as every unreliable datagram is read on its own
+    async fn send_unreliable(&mut self, frame: OTFrame) -> Result<(), ProtocolError> {
+        let mut bytes = BytesMut::new();
+        frame.write_bytes(&mut bytes);
+        if self.unreliable_buffer.len() + bytes.len() > UDP_MAX_DATAGRAM_SIZE {
+            self.flush_unreliable().await?;
+        }
+        if self.unreliable_buffer.is_empty() {
+            self.unreliable_buffer.put_u8(DATAGRAM_UNRELIABLE);
+        }
+        self.unreliable_buffer.extend_from_slice(&bytes);
+        Ok(())
+    }
+
+    async fn flush_unreliable(&mut self) -> Result<(), ProtocolError> {
+        if self.unreliable_buffer.is_empty() {
+            return Ok(());
+        }
+        self.drain.send(self.unreliable_buffer.split()).await
+    }
+
+    fn lane_acked(&self, sid: Sid) -> bool { self.shared.lock().unwrap().lane_acked(sid) }
+}
+
+impl<S, D> UdpRecvProtocol<S, D>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    async fn retransmit(&mut self) -> Result<(), ProtocolError> {
+        let due = self.shared.lock().unwrap().due(Instant::now());
+        for datagram in due {
+            self.drain.send(BytesMut::from(&datagram[..])).await?;
+        }
+        Ok(())
+    }
+
+    async fn acknowledge(&mut self) -> Result<(), ProtocolError> {
+        let (ack, ack_bits) = self.received.ack();
+        let mut datagram = BytesMut::with_capacity(ACK_SIZE);
+        datagram.put_u8(DATAGRAM_ACK);
+        datagram.put_u64_le(ack);
+        datagram.put_u64_le(ack_bits);
+        self.drain.send(datagram).await
+    }
+
+    /// receive the next datagram and sort it into its lane
+    async fn recv_datagram(&mut self) -> Result<(), ProtocolError> {
+        let mut datagram = self.sink.recv().await?;
+        let now = Instant::now();
+        if datagram.is_empty() {
+            // idle, nothing arrived for a while
+            if now.saturating_duration_since(self.last_recv) >= TIMEOUT {
+                info!("remote side timed out");
+                return Err(ProtocolError::Closed);
+            }
+            return self.retransmit().await;
+        }
+        self.last_recv = now;
+        match datagram.get_u8() {
+            DATAGRAM_MAIN if datagram.len() >= MAIN_HEADER_SIZE - 1 => {
+                let seq = datagram.get_u64_le();
+                let lane_seq = datagram.get_u64_le();
+                if self.received.insert(seq) {
+                    self.main.insert(lane_seq, datagram);
+                }
+                self.acknowledge().await
+            },
+            DATAGRAM_STREAM if datagram.len() >= STREAM_HEADER_SIZE - 1 => {
+                let seq = datagram.get_u64_le();
+                let sid = Sid::from_bytes(&mut datagram);
+                let lane_seq = datagram.get_u64_le();
+                if self.received.insert(seq) {
+                    self.lanes
+                        .entry(sid)
+                        .or_default()
+                        .insert(lane_seq, datagram);
+                }
+                self.acknowledge().await
+            },
+            DATAGRAM_UNRELIABLE => {
+                self.unreliable_buffer.extend_from_slice(&datagram);
+                Ok(())
+            },
+            DATAGRAM_ACK if datagram.len() >= ACK_SIZE - 1 => {
+                let ack = datagram.get_u64_le();
+                let ack_bits = datagram.get_u64_le();
+                self.shared.lock().unwrap().acknowledge(ack, ack_bits, now);
+                self.retransmit().await
+            },
+            kind => {
+                info!(?kind, "protocol violation by remote side: invalid datagram");
+                Err(ProtocolError::Violated)
+            },
+        }
+    }
+
+    fn add_data(&mut self, frame: ITFrame) -> Result<Option<ProtocolEvent>, ProtocolError> {
+        match frame {
+            ITFrame::DataHeader { sid, mid, length } => {
+                let m = ITMessage::new(sid, length, &mut self.itmsg_allocator);
+                self.metrics.rmsg_ib(sid, length);
+                self.incoming.insert(mid, m);
+                Ok(None)
+            },
+            ITFrame::Data { mid, data } => {
+                self.metrics.rdata_frames_b(data.len() as u64);
+                let m = match self.incoming.get_mut(&mid) {
+                    Some(m) => m,
+                    None => {
+                        info!(
+                            ?mid,
+                            "protocol violation by remote side: send Data before Header"
+                        );
+                        return Err(ProtocolError::Violated);
+                    },
+                };
+                m.data.extend_from_slice(&data);
+                if m.data.len() == m.length as usize {
+                    // finished, yay
+                    let m = self.incoming.remove(&mid).ok_or(ProtocolError::Violated)?;
+                    self.metrics
+                        .rmsg_ob(m.sid, RemoveReason::Finished, m.data.len() as u64);
+                    Ok(Some(ProtocolEvent::Message {
+                        sid: m.sid,
+                        data: m.data.freeze(),
+                    }))
+                } else {
+                    Ok(None)
+                }
+            },
+            _ => Err(ProtocolError::Violated),
+        }
+    }
+
+    /// like `add_data`, but frames of lost datagrams are tolerated
+    fn add_unreliable_data(&mut self, frame: ITFrame) -> Result<Option<ProtocolEvent>, ()> {
+        match frame {
+            ITFrame::DataHeader { sid, mid, length } => {
+                if !self.shared.lock().unwrap().opened.contains(&sid) {
+                    return Ok(None);
+                }
+                // messages of a stream are send one after another, so older
+                // unfinished ones lost a datagram and will never finish
+                let metrics = &mut self.metrics;
+                self.unreliable_incoming.retain(|&old_mid, m| {
+                    let lost = m.sid == sid && old_mid < mid;
+                    if lost {
+                        metrics.rmsg_ob(m.sid, RemoveReason::Dropped, m.data.len() as u64);
+                    }
+                    !lost
+                });
+                let m = ITMessage::new(sid, length, &mut self.itmsg_allocator);
+                self.metrics.rmsg_ib(sid, length);
+                self.unreliable_incoming.insert(mid, m);
+                Ok(None)
+            },
+            ITFrame::Data { mid, data } => {
+                self.metrics.rdata_frames_b(data.len() as u64);
+                let m = match self.unreliable_incoming.get_mut(&mid) {
+                    Some(m) => m,
+                    // the header got lost
+                    None => return Ok(None),
+                };
+                m.data.extend_from_slice(&data);
+                if m.data.len() >= m.length as usize {
+                    let m = self.unreliable_incoming.remove(&mid).ok_or(())?;
+                    if m.data.len() > m.length as usize {
+                        return Err(());
+                    }
+                    self.metrics
+                        .rmsg_ob(m.sid, RemoveReason::Finished, m.data.len() as u64);
+                    Ok(Some(ProtocolEvent::Message {
+                        sid: m.sid,
+                        data: m.data.freeze(),
+                    }))
+                } else {
+                    Ok(None)
+                }
+            },
+            _ => Err(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<D> SendProtocol for UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    fn notify_from_recv(&mut self, event: ProtocolEvent) {
+        match event {
+            ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+            } => {
+                self.store
+                    .open_stream(sid, prio, promises, guaranteed_bandwidth);
+                if is_reliable(&promises) {
+                    self.lanes.insert(sid, SendLane::default());
+                }
+            },
+            ProtocolEvent::CloseStream { sid } => {
+                if self.store.try_close_stream(sid) {
+                    let _ = self.lanes.delete(&sid);
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?sid, "hold back notify close stream");
+                    self.notify_closing_streams.push(sid);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    async fn send(&mut self, event: ProtocolEvent) -> Result<(), ProtocolError> {
+        #[cfg(feature = "trace_pedantic")]
+        trace!(?event, "send");
+        match event {
+            ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+            } => {
+                self.store
+                    .open_stream(sid, prio, promises, guaranteed_bandwidth);
+                if is_reliable(&promises) {
+                    self.lanes.insert(sid, SendLane::default());
+                }
+                self.shared.lock().unwrap().opened.insert(sid);
+                event.to_frame().write_bytes(&mut self.main.buffer);
+                self.send_main().await?;
+            },
+            ProtocolEvent::CloseStream { sid } => {
+                // the remote side must have received all data of the stream before
+                if self.lane_acked(sid) && self.store.try_close_stream(sid) {
+                    let _ = self.lanes.delete(&sid);
+                    event.to_frame().write_bytes(&mut self.main.buffer);
+                    self.send_main().await?;
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?sid, "hold back close stream");
+                    self.closing_streams.push(sid);
+                }
+            },
+            ProtocolEvent::Shutdown => {
+                if self.store.is_empty() && self.shared.lock().unwrap().streams_acked() {
+                    event.to_frame().write_bytes(&mut self.main.buffer);
+                    self.send_main().await?;
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!("hold back shutdown");
+                    self.pending_shutdown = true;
+                }
+            },
+            ProtocolEvent::Message { data, sid } => {
+                self.metrics.smsg_ib(sid, data.len() as u64);
+                self.store.add(data, self.next_mid, sid);
+                self.next_mid += 1;
+            },
+            ProtocolEvent::Ack { .. } => {
+                event.to_frame().write_bytes(&mut self.main.buffer);
+                self.send_main().await?;
+            },
+        }
+        Ok(())
+    }
+
+    async fn flush(
+        &mut self,
+        bandwidth: Bandwidth,
+        dt: Duration,
+    ) -> Result</* actual */ Bandwidth, ProtocolError> {
+        let now = Instant::now();
+        let due = self.shared.lock().unwrap().due(now);
+        for datagram in due {
+            self.drain.send(BytesMut::from(&datagram[..])).await?;
+        }
+
+        let (frames, _) = self.store.grab(bandwidth, dt);
+        let mut data_frames = 0;
+        let mut data_bandwidth = 0;
+        for (sid, frame) in frames {
+            if let OTFrame::Data { mid: _, data } = &frame {
+                data_bandwidth += data.len();
+                data_frames += 1;
+            }
+            match self.lanes.get_mut(&sid) {
+                Some(lane) => frame.write_bytes(&mut lane.buffer),
+                None => self.send_unreliable(frame).await?,
+            }
+        }
+        for (sid, lane) in self.lanes.data.iter_mut() {
+            send_lane(&mut self.drain, &self.shared, lane, Some(*sid)).await?;
+        }
+        self.flush_unreliable().await?;
+        self.metrics
+            .sdata_frames_b(data_frames, data_bandwidth as u64);
+
+        let mut finished_streams = vec![];
+        for (i, &sid) in self.closing_streams.iter().enumerate() {
+            if self.shared.lock().unwrap().lane_acked(sid) && self.store.try_close_stream(sid) {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(?sid, "close stream, as it's now empty and acknowledged");
+                let _ = self.lanes.delete(&sid);
+                OTFrame::CloseStream { sid }.write_bytes(&mut self.main.buffer);
+                send_lane(&mut self.drain, &self.shared, &mut self.main, None).await?;
+                finished_streams.push(i);
+            }
+        }
+        for i in finished_streams.iter().rev() {
+            self.closing_streams.remove(*i);
+        }
+
+        let mut finished_streams = vec![];
+        for (i, &sid) in self.notify_closing_streams.iter().enumerate() {
+            if self.store.try_close_stream(sid) {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(?sid, "close stream, as it's now empty");
+                let _ = self.lanes.delete(&sid);
+                finished_streams.push(i);
+            }
+        }
+        for i in finished_streams.iter().rev() {
+            self.notify_closing_streams.remove(*i);
+        }
+
+        if self.pending_shutdown
+            && self.store.is_empty()
+            && self.shared.lock().unwrap().streams_acked()
+        {
+            #[cfg(feature = "trace_pedantic")]
+            trace!("shutdown, as it's now empty");
+            OTFrame::Shutdown {}.write_bytes(&mut self.main.buffer);
+            self.send_main().await?;
+            self.pending_shutdown = false;
+        }
+
+        if now.saturating_duration_since(self.last_keepalive) >= KEEPALIVE {
+            self.last_keepalive = now;
+            let mut datagram = BytesMut::with_capacity(1);
+            datagram.put_u8(DATAGRAM_UNRELIABLE);
+            self.drain.send(datagram).await?;
+        }
+        Ok(data_bandwidth as u64)
+    }
+
+    fn link_report(&mut self) -> LinkReport {
+        let mut shared = self.shared.lock().unwrap();
+        LinkReport {
+            rtt: shared.srtt,
+            lost: std::mem::take(&mut shared.retransmitted),
+        }
+    }
+
+    fn take_unflushed(&mut self) -> Vec<(Sid, Bytes)> { self.store.take_unflushed() }
+}
+
+#[async_trait]
+impl<S, D> RecvProtocol for UdpRecvProtocol<S, D>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    async fn recv(&mut self) -> Result<ProtocolEvent, ProtocolError> {
+        loop {
+            // streams first, a CloseStream on the main lane must not overtake
+            // their remaining messages
+            let sids = {
+                let shared = self.shared.lock().unwrap();
+                self.lanes
+                    .keys()
+                    .filter(|sid| shared.opened.contains(sid))
+                    .copied()
+                    .collect::<Vec<_>>()
+            };
+            for sid in sids {
+                while let Some(lane) = self.lanes.get_mut(&sid) {
+                    match ITFrame::read_frame(&mut lane.buffer) {
+                        Ok(Some(frame)) => {
+                            #[cfg(feature = "trace_pedantic")]
+                            trace!(?frame, "recv");
+                            if let Some(event) = self.add_data(frame)? {
+                                return Ok(event);
+                            }
+                        },
+                        Ok(None) => break, //inner => read more data
+                        Err(()) => return Err(ProtocolError::Violated),
+                    }
+                }
+            }
+
+            match ITFrame::read_frame(&mut self.main.buffer) {
+                Ok(Some(frame)) => {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?frame, "recv");
+                    match frame {
+                        ITFrame::Shutdown => return Ok(ProtocolEvent::Shutdown),
+                        ITFrame::OpenStream {
+                            sid,
+                            prio,
+                            promises,
+                            guaranteed_bandwidth,
+                        } => {
+                            self.shared.lock().unwrap().opened.insert(sid);
+                            return Ok(ProtocolEvent::OpenStream {
+                                sid,
+                                prio: prio.min(crate::types::HIGHEST_PRIO),
+                                promises,
+                                guaranteed_bandwidth,
+                            });
+                        },
+                        ITFrame::CloseStream { sid } => {
+                            self.lanes.remove(&sid);
+                            self.shared.lock().unwrap().opened.remove(&sid);
+                            return Ok(ProtocolEvent::CloseStream { sid });
+                        },
+                        ITFrame::Ack { sid, count } => {
+                            return Ok(ProtocolEvent::Ack { sid, count });
+                        },
+                        _ => return Err(ProtocolError::Violated),
+                    };
+                },
+                Ok(None) => {},
+                Err(()) => return Err(ProtocolError::Violated),
+            }
+
+            while !self.unreliable_buffer.is_empty() {
+                match ITFrame::read_frame(&mut self.unreliable_buffer) {
+                    Ok(Some(frame)) => {
+                        #[cfg(feature = "trace_pedantic")]
+                        trace!(?frame, "recv");
+                        match self.add_unreliable_data(frame) {
+                            Ok(Some(event)) => return Ok(event),
+                            Ok(None) => {},
+                            Err(()) => return Err(ProtocolError::Violated),
+                        }
+                    },
+                    // unreliable datagrams only contain whole frames
+                    Ok(None) | Err(()) => return Err(ProtocolError::Violated),
+                }
+            }
+
+            self.recv_datagram().await?;
+        }
+    }
+}
+
+#[async_trait]
+impl<D> ReliableDrain for UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError> {
+        frame.write_bytes(&mut self.main.buffer);
+        self.send_main().await
+    }
+}
+
+#[async_trait]
+impl<S, D> ReliableSink for UdpRecvProtocol<S, D>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    async fn recv(&mut self) -> Result<InitFrame, ProtocolError> {
+        loop {
+            if let Some(frame) = InitFrame::read_frame(&mut self.main.buffer) {
+                return Ok(frame);
+            }
+            if self.main.buffer.len() >= 100 {
+                return Err(ProtocolError::Violated);
+            }
+            self.recv_datagram().await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_utils {
+    //UDP protocol based on Channel, dropping some datagrams
+    use super::*;
+    use crate::metrics::{ProtocolMetricCache, ProtocolMetrics};
+    use async_channel::{Receiver, Sender};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// decides by the index and content of a datagram whether it gets lost
+    pub type Lossy = Arc<dyn Fn(usize, &BytesMut) -> bool + Send + Sync>;
+
+    #[derive(Clone)]
+    pub struct UdpDrain {
+        pub sender: Sender<BytesMut>,
+        pub count: Arc<AtomicUsize>,
+        pub drop: Lossy,
+    }
+
+    pub struct UdpSink {
+        pub receiver: Receiver<BytesMut>,
+    }
+
+    pub type UdpProtocols = (
+        UdpSendProtocol<UdpDrain>,
+        UdpRecvProtocol<UdpSink, UdpDrain>,
+    );
+
+    /// emulate Udp on Channels
+    pub fn udp_bound(cap: usize, drop: Lossy) -> [UdpProtocols; 2] {
+        let (s1, r1) = async_channel::bounded(cap);
+        let (s2, r2) = async_channel::bounded(cap);
+        let m = ProtocolMetricCache::new("udp", Arc::new(ProtocolMetrics::new().unwrap()));
+        let drain = |sender| UdpDrain {
+            sender,
+            count: Arc::new(AtomicUsize::new(0)),
+            drop: Arc::clone(&drop),
+        };
+        [
+            udp_protocols(drain(s1), UdpSink { receiver: r2 }, m.clone()),
+            udp_protocols(drain(s2), UdpSink { receiver: r1 }, m),
+        ]
+    }
+
+    #[async_trait]
+    impl UnreliableDrain for UdpDrain {
+        type DataFormat = BytesMut;
+
+        async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError> {
+            if (self.drop)(self.count.fetch_add(1, Ordering::Relaxed), &data) {
+                return Ok(());
+            }
+            self.sender
+                .send(data)
+                .await
+                .map_err(|_| ProtocolError::Closed)
+        }
+    }
+
+    #[async_trait]
+    impl UnreliableSink for UdpSink {
+        type DataFormat = BytesMut;
+
+        async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError> {
+            match tokio::time::timeout(Duration::from_millis(10), self.receiver.recv()).await {
+                Ok(data) => data.map_err(|_| ProtocolError::Closed),
+                Err(_) => Ok(BytesMut::new()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_handshake_datagram, test_utils::*, ReceivedSeqs, DATAGRAM_MAIN, DATAGRAM_UNRELIABLE,
+        MAIN_HEADER_SIZE,
+    };
+    use crate::{
+        types::{Pid, Promises, Sid, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2},
+        InitProtocol, InitProtocolError, ProtocolEvent, RecvProtocol, SendProtocol,
+    };
+    use bytes::{Bytes, BytesMut};
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn handshake_datagram() {
+        let datagrams = Arc::new(Mutex::new(Vec::new()));
+        let datagrams_clone = Arc::clone(&datagrams);
+        let [p1, p2] = udp_bound(
+            1000,
+            Arc::new(move |_, datagram| {
+                datagrams_clone.lock().unwrap().push(datagram.clone());
+                false
+            }),
+        );
+        let (r1, r2) = handshake(p1, p2).await;
+        assert!(r1.is_ok());
+        assert!(r2.is_ok());
+
+        let datagrams = datagrams.lock().unwrap();
+        // the connecting side sends first
+        let first = datagrams[0].to_vec();
+        assert!(is_handshake_datagram(&first));
+        assert!(
+            datagrams
+                .iter()
+                .filter(|datagram| datagram.first() != Some(&DATAGRAM_MAIN))
+                .all(|datagram| !is_handshake_datagram(datagram))
+        );
+        let mut later = first.clone();
+        later[1] = 1;
+        assert!(!is_handshake_datagram(&later));
+        let mut wrong_magic_number = first;
+        wrong_magic_number[MAIN_HEADER_SIZE + 1] ^= 1;
+        assert!(!is_handshake_datagram(&wrong_magic_number));
+        assert!(!is_handshake_datagram(&[]));
+    }
+
+    /// Both sides are kept till the handshake finished on both of them, the
+    /// one finishing first still acknowledges the last datagram of the other
+    async fn handshake(
+        mut p1: UdpProtocols,
+        mut p2: UdpProtocols,
+    ) -> (
+        Result<(Pid, Sid, u128), InitProtocolError>,
+        Result<(Pid, Sid, u128), InitProtocolError>,
+    ) {
+        let r1 = tokio::spawn(async move { (p1.initialize(true, Pid::fake(2), 1337).await, p1) });
+        let r2 = tokio::spawn(async move { (p2.initialize(false, Pid::fake(3), 42).await, p2) });
+        let (r1, r2) = tokio::join!(r1, r2);
+        (r1.unwrap().0, r2.unwrap().0)
+    }
+
+    /// handshake without loss, afterwards every `nth` datagram is lost
+    async fn connect(nth: usize) -> [UdpProtocols; 2] {
+        let lossy = Arc::new(AtomicBool::new(false));
+        let lossy_clone = Arc::clone(&lossy);
+        let [mut p1, mut p2] = udp_bound(
+            1000,
+            Arc::new(move |i, _| lossy_clone.load(Ordering::Relaxed) && i % nth == 0),
+        );
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337).await.unwrap();
+            p1
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(false, Pid::fake(3), 42).await.unwrap();
+            p2
+        });
+        let (p1, p2) = tokio::join!(r1, r2);
+        lossy.store(true, Ordering::Relaxed);
+        [p1.unwrap(), p2.unwrap()]
+    }
+
+    /// drives `p1` until `p2` received `n` events
+    async fn transfer(p1: UdpProtocols, p2: UdpProtocols, n: usize) -> Vec<ProtocolEvent> {
+        let (mut s, mut acks) = p1;
+        let (_s2, mut r) = p2;
+        let flush = tokio::spawn(async move {
+            loop {
+                s.flush(1_000_000_000, Duration::from_millis(5))
+                    .await
+                    .unwrap();
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        });
+        let acks = tokio::spawn(async move { acks.recv().await });
+        let mut events = vec![];
+        for _ in 0..n {
+            events.push(r.recv().await.unwrap());
+        }
+        flush.abort();
+        acks.abort();
+        events
+    }
+
+    #[tokio::test]
+    async fn handshake_all_good() {
+        let [p1, p2] = udp_bound(10, Arc::new(|_, _| false));
+        let (r1, r2) = handshake(p1, p2).await;
+        assert_eq!(r1, Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
+        assert_eq!(r2, Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
+    }
+
+    #[tokio::test]
+    async fn handshake_with_loss() {
+        // both sides lose their first handshake datagram
+        let [p1, p2] = udp_bound(10, Arc::new(|i, _| i == 0));
+        let (r1, r2) = handshake(p1, p2).await;
+        assert_eq!(r1, Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
+        assert_eq!(r2, Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
+    }
+
+    #[tokio::test]
+    async fn reliable_msgs_with_loss() {
+        let [mut p1, p2] = connect(3).await;
+        let sid = Sid::new(1);
+        p1.0.send(ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::ORDERED | Promises::GUARANTEED_DELIVERY,
+            guaranteed_bandwidth: 1_000_000,
+        })
+        .await
+        .unwrap();
+        let msgs = [
+            Bytes::from(&[99u8; 20_000][..]),
+            Bytes::from(&[7u8; 30][..]),
+            Bytes::from(&[42u8; 5_000][..]),
+        ];
+        for data in msgs.iter() {
+            p1.0.send(ProtocolEvent::Message {
+                sid,
+                data: data.clone(),
+            })
+            .await
+            .unwrap();
+        }
+        let events = transfer(p1, p2, 4).await;
+        assert!(matches!(events[0], ProtocolEvent::OpenStream { .. }));
+        for (event, data) in events[1..].iter().zip(msgs.iter()) {
+            assert_eq!(event, &ProtocolEvent::Message {
+                sid,
+                data: data.clone()
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn unreliable_msgs_skip_lost_ones() {
+        // loses the datagrams that start with a Data frame (7) of the first message
+        let is_lost = |_: usize, d: &BytesMut| {
+            d.len() > 2 && d[0] == DATAGRAM_UNRELIABLE && d[1] == 7 && d[d.len() - 1] == 1
+        };
+        let [mut p1, mut p2] = udp_bound(1000, Arc::new(is_lost));
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337).await.unwrap();
+            p1
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(false, Pid::fake(3), 42).await.unwrap();
+            p2
+        });
+        let (p1, p2) = tokio::join!(r1, r2);
+        let (mut p1, mut p2) = (p1.unwrap(), p2.unwrap());
+        let sid = Sid::new(1);
+        p1.0.send(ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::COMPRESSED,
+            guaranteed_bandwidth: 1_000_000,
+        })
+        .await
+        .unwrap();
+        let e = p2.1.recv().await.unwrap();
+        assert!(matches!(e, ProtocolEvent::OpenStream { .. }));
+        // spans 3 datagrams, only the first one arrives
+        p1.0.send(ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&[1u8; 3_000][..]),
+        })
+        .await
+        .unwrap();
+        let event = ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&[2u8; 30][..]),
+        };
+        p1.0.send(event.clone()).await.unwrap();
+        p1.0.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(p2.1.recv().await.unwrap(), event);
+        assert!(p2.1.unreliable_incoming.is_empty());
+    }
+
+    #[tokio::test]
+    async fn close_stream_after_its_data() {
+        let [mut p1, p2] = connect(4).await;
+        let sid = Sid::new(1);
+        p1.0.send(ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+        })
+        .await
+        .unwrap();
+        p1.0.send(ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&[99u8; 50_000][..]),
+        })
+        .await
+        .unwrap();
+        p1.0.send(ProtocolEvent::CloseStream { sid }).await.unwrap();
+        let events = transfer(p1, p2, 3).await;
+        assert!(matches!(events[0], ProtocolEvent::OpenStream { .. }));
+        assert!(matches!(events[1], ProtocolEvent::Message { .. }));
+        assert!(matches!(events[2], ProtocolEvent::CloseStream { .. }));
+    }
+
+    #[test]
+    fn selective_ack() {
+        let mut received = ReceivedSeqs::default();
+        assert!(received.insert(0));
+        assert!(received.insert(2));
+        assert!(received.insert(4));
+        assert!(!received.insert(2));
+        assert_eq!(received.ack(), (1, 0b101));
+        assert!(received.insert(1));
+        assert_eq!(received.ack(), (3, 0b1));
+        assert!(!received.insert(0));
+    }
+}
@@ -1,4 +1,7 @@
 use crate::{
+    capture::{CaptureSlot, FileCapture},
+    channel::IdentitySlot,
+    link::{LinkConditions, LinkSlot},
     message::{partial_eq_bincode, Message},
     participant::{A2bStreamOpen, S2bShutdownBparticipant},
     scheduler::{A2sConnect, Scheduler},
@@ -7,17 +10,20 @@ use bytes::Bytes;
 use hashbrown::HashMap;
 #[cfg(feature = "compression")]
 use lz_fear::raw::DecodeError;
-use network_protocol::{Bandwidth, InitProtocolError, Pid, Prio, Promises, Sid};
+use network_protocol::{
+    Bandwidth, Identity, IdentityKey, InitProtocolError, Pid, Prio, Promises, Sid,
+};
 #[cfg(feature = "metrics")]
 use prometheus::Registry;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     net::SocketAddr,
+    path::Path,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::{
     io,
@@ -120,6 +126,10 @@ pub enum ParticipantError {
     /// that failed. This is also returned when local side tries to do
     /// something while remote site gracefully disconnects
     ProtocolFailedUnrecoverable,
+    /// None of the channels of the participant can keep these [`Promises`],
+    /// e.g. [`Promises::ENCRYPTED`] on a TCP channel whose key exchange
+    /// wasn't authenticated
+    UnsupportedPromises(Promises),
 }
 
 /// Error type thrown by [`Streams`](Stream) methods
@@ -189,6 +199,9 @@ pub struct Network {
     connect_sender: Mutex<mpsc::UnboundedSender<A2sConnect>>,
     connected_receiver: Mutex<mpsc::UnboundedReceiver<Participant>>,
     shutdown_network_s: Option<oneshot::Sender<oneshot::Sender<()>>>,
+    capture: CaptureSlot,
+    link: LinkSlot,
+    identities: IdentitySlot,
 }
 
 impl Network {
@@ -264,9 +277,15 @@ impl Network {
         let p = participant_id;
         let span = tracing::info_span!("network", ?p);
         span.in_scope(|| trace!("Starting Network"));
+        let capture = CaptureSlot::default();
+        let link = LinkSlot::default();
+        let identities = IdentitySlot::default();
         let (scheduler, listen_sender, connect_sender, connected_receiver, shutdown_sender) =
             Scheduler::new(
                 participant_id,
+                Arc::clone(&capture),
+                Arc::clone(&link),
+                Arc::clone(&identities),
                 #[cfg(feature = "metrics")]
                 registry,
             );
@@ -294,9 +313,88 @@ impl Network {
             connect_sender: Mutex::new(connect_sender),
             connected_receiver: Mutex::new(connected_receiver),
             shutdown_network_s: Some(shutdown_network_s),
+            capture,
+            link,
+            identities,
         }
     }
 
+    /// Captures every frame that is send or received on TCP and QUIC channels
+    /// created from now on to a file, with a timestamp, [`Sid`] and [`Mid`].
+    /// Frames are captured before they are encrypted. Use the
+    /// `capture-replay` example to analyze a capture offline.
+    /// The capture ends when the `Network` and all channels are dropped.
+    /// Capturing again switches the file for new channels.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use tokio::runtime::Runtime;
+    /// use veloren_network::{Network, Pid};
+    ///
+    /// let runtime = Runtime::new().unwrap();
+    /// let network = Network::new(Pid::new(), &runtime);
+    /// let path = std::env::temp_dir().join("veloren-network-doctest.vcap");
+    /// network.capture(&path).unwrap();
+    /// # drop(network);
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    ///
+    /// [`Sid`]: network_protocol::Sid
+    /// [`Mid`]: network_protocol::Mid
+    pub fn capture(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let sink = FileCapture::create(path.as_ref())?;
+        debug!(path = ?path.as_ref(), "capturing frames");
+        *self.capture.lock().unwrap() = Some((Instant::now(), Arc::new(sink)));
+        Ok(())
+    }
+
+    /// Simulates a bad connection on TCP, UDP and MPSC channels created from
+    /// now on, by delaying, dropping and reordering what they send. Only
+    /// affects the data this side sends, condition both sides for a
+    /// symmetric link. `None` stops conditioning new channels.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::time::Duration;
+    /// use tokio::runtime::Runtime;
+    /// use veloren_network::{LinkConditions, Network, Pid};
+    ///
+    /// let runtime = Runtime::new().unwrap();
+    /// let network = Network::new(Pid::new(), &runtime);
+    /// network.condition_links(Some(LinkConditions {
+    ///     latency: Duration::from_millis(100),
+    ///     jitter: Duration::from_millis(20),
+    ///     loss: 0.02,
+    ///     ..Default::default()
+    /// }));
+    /// ```
+    pub fn condition_links(&self, conditions: Option<LinkConditions>) {
+        debug!(?conditions, "conditioning links");
+        *self.link.lock().unwrap() = conditions;
+    }
+
+    /// Proves `identity` to everyone connecting to the TCP listeners started
+    /// from now on, by signing the key exchange. Store the [`Identity`] to
+    /// keep its [`IdentityKey`], which connecting sides pin with
+    /// [`pin_identity`].
+    ///
+    /// [`pin_identity`]: Network::pin_identity
+    pub fn set_identity(&self, identity: Option<Identity>) {
+        debug!(key = ?identity.as_ref().map(Identity::public_key), "set identity");
+        self.identities.lock().unwrap().own = identity.map(Arc::new);
+    }
+
+    /// TCP connections created from now on fail unless the listening side
+    /// proves the identity of `key`, see [`set_identity`]. Without a pinned
+    /// key TCP channels are still encrypted, but a man in the middle can't be
+    /// ruled out, so they don't support [`Promises::ENCRYPTED`].
+    ///
+    /// [`set_identity`]: Network::set_identity
+    pub fn pin_identity(&self, key: Option<IdentityKey>) {
+        debug!(?key, "pin identity");
+        self.identities.lock().unwrap().pinned = key;
+    }
+
     /// starts listening on an [`ListenAddr`].
     /// When the method returns the `Network` is ready to listen for incoming
     /// connections OR has returned a [`NetworkError`] (e.g. port already used).
@@ -363,9 +461,6 @@ impl Network {
     ///     let p1 = network
     ///         .connect(ConnectAddr::Tcp("127.0.0.1:2010".parse().unwrap()))
     ///         .await?;
-    ///     # //this doesn't work yet, so skip the test
-    ///     # //TODO fixme!
-    ///     # return Ok(());
     ///     let p2 = network
     ///         .connect(ConnectAddr::Udp("127.0.0.1:2011".parse().unwrap()))
     ///         .await?;
@@ -394,7 +489,7 @@ impl Network {
         self.connect_sender
             .lock()
             .await
-            .send((address, pid_sender))?;
+            .send((address, None, pid_sender))?;
         let participant = match pid_receiver.await? {
             Ok(p) => p,
             Err(e) => return Err(NetworkError::ConnectFailed(e)),
@@ -547,8 +642,10 @@ impl Participant {
     ///   [`Bandwidth`] for details.
     ///
     /// A [`ParticipantError`] might be thrown if the `Participant` is already
-    /// closed. [`Streams`] can be created without a answer from the remote
-    /// side, resulting in very fast creation and closing latency.
+    /// closed, or if none of its channels can keep `promises`, see
+    /// [`ParticipantError::UnsupportedPromises`]. [`Streams`] can be created
+    /// without a answer from the remote side, resulting in very fast
+    /// creation and closing latency.
     ///
     /// # Examples
     /// ```rust
@@ -587,7 +684,8 @@ impl Participant {
         bandwidth: Bandwidth,
     ) -> Result<Stream, ParticipantError> {
         debug_assert!(prio <= network_protocol::HIGHEST_PRIO, "invalid prio");
-        let (p2a_return_stream_s, p2a_return_stream_r) = oneshot::channel::<Stream>();
+        let (p2a_return_stream_s, p2a_return_stream_r) =
+            oneshot::channel::<Result<Stream, ParticipantError>>();
         if let Err(e) = self.a2b_open_stream_s.lock().await.send((
             prio,
             promises,
@@ -598,11 +696,15 @@ impl Participant {
             return Err(ParticipantError::ParticipantDisconnected);
         }
         match p2a_return_stream_r.await {
-            Ok(stream) => {
+            Ok(Ok(stream)) => {
                 let sid = stream.sid;
                 trace!(?sid, "opened stream");
                 Ok(stream)
             },
+            Ok(Err(e)) => {
+                debug!(?e, "couldn't open stream");
+                Err(e)
+            },
             Err(_) => {
                 debug!("p2a_return_stream_r failed, closing participant");
                 Err(ParticipantError::ParticipantDisconnected)
@@ -751,8 +853,12 @@ impl Participant {
         }
     }
 
-    /// Returns the current approximation on the maximum bandwidth available.
-    /// This WILL fluctuate based on the amount/size of send messages.
+    /// Returns the bandwidth in bytes/s that congestion control currently
+    /// allows to send to this `Participant`, summed over all its channels.
+    /// It shrinks when the link shows loss or growing latency, then streams
+    /// with a lower [`Prio`] are throttled first.
+    ///
+    /// [`Prio`]: network_protocol::Prio
     pub fn bandwidth(&self) -> f32 { *self.b2a_bandwidth_stats_r.borrow() }
 
     /// Returns the remote [`Pid`](network_protocol::Pid)
@@ -1238,6 +1344,9 @@ impl core::fmt::Display for ParticipantError {
             ParticipantError::ProtocolFailedUnrecoverable => {
                 write!(f, "underlying protocol failed unrecoverable")
             },
+            ParticipantError::UnsupportedPromises(promises) => {
+                write!(f, "no channel can keep the promises {:?}", promises)
+            },
         }
     }
 }
@@ -0,0 +1,53 @@
+use bytes::BytesMut;
+use network_protocol::{CaptureSink, CapturedFrame};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Arc,
+    time::Instant,
+};
+use tracing::*;
+
+/// Shared between `Network` and `Scheduler`, channels created while it's set
+/// are captured
+pub(crate) type CaptureSlot = Arc<std::sync::Mutex<Option<(Instant, Arc<dyn CaptureSink>)>>>;
+
+/// Writes captured frames to a file, see [`Network::capture`].
+/// Writing happens in its own thread, so protocols never wait for the disk.
+///
+/// [`Network::capture`]: crate::api::Network::capture
+#[derive(Debug)]
+pub(crate) struct FileCapture {
+    sender: crossbeam_channel::Sender<CapturedFrame>,
+}
+
+impl FileCapture {
+    pub(crate) fn create(path: &Path) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&CapturedFrame::FILE_MAGIC)?;
+        let (sender, receiver) = crossbeam_channel::unbounded::<CapturedFrame>();
+        std::thread::Builder::new()
+            .name("network-capture".to_string())
+            .spawn(move || {
+                let mut bytes = BytesMut::new();
+                // ends once all channels and the `Network` are dropped
+                for frame in receiver.iter() {
+                    frame.write_bytes(&mut bytes);
+                    if let Err(e) = file.write_all(&bytes.split()) {
+                        warn!(?e, "writing capture failed, stop capturing");
+                        return;
+                    }
+                    if receiver.is_empty() {
+                        let _ = file.flush();
+                    }
+                }
+                let _ = file.flush();
+            })?;
+        Ok(Self { sender })
+    }
+}
+
+impl CaptureSink for FileCapture {
+    fn capture(&self, frame: CapturedFrame) { let _ = self.sender.send(frame); }
+}
@@ -1,4 +1,7 @@
-use crate::api::NetworkConnectError;
+use crate::{
+    api::NetworkConnectError,
+    link::{LinkConditions, LinkDrain},
+};
 use async_trait::async_trait;
 use bytes::BytesMut;
 use futures_util::FutureExt;
@@ -6,9 +9,11 @@ use futures_util::FutureExt;
 use futures_util::StreamExt;
 use hashbrown::HashMap;
 use network_protocol::{
-    Bandwidth, Cid, InitProtocolError, MpscMsg, MpscRecvProtocol, MpscSendProtocol, Pid,
-    ProtocolError, ProtocolEvent, ProtocolMetricCache, ProtocolMetrics, Sid, TcpRecvProtocol,
-    TcpSendProtocol, UnreliableDrain, UnreliableSink,
+    is_handshake_datagram, tcp_protocols, Authentication, Bandwidth, Cid, Encryption, FrameCapture,
+    Identity, IdentityKey, InitProtocolError, LinkReport, MpscMsg, MpscRecvProtocol,
+    MpscSendProtocol, Pid, Promises, ProtocolError, ProtocolEvent, ProtocolMetricCache,
+    ProtocolMetrics, Sid, TcpRecvProtocol, TcpSendProtocol, UdpRecvProtocol, UdpSendProtocol,
+    UnreliableDrain, UnreliableSink, UDP_MAX_DATAGRAM_SIZE,
 };
 #[cfg(feature = "quic")]
 use network_protocol::{QuicDataFormat, QuicDataFormatStream, QuicRecvProtocol, QuicSendProtocol};
@@ -19,7 +24,7 @@ use std::{
         atomic::{AtomicU64, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
@@ -28,21 +33,63 @@ use tokio::{
     select,
     sync::{mpsc, oneshot, Mutex},
 };
-use tracing::{error, info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
+
+/// UDP remote sides whose handshake might still be running, handshakes of
+/// further remote sides are ignored until some finished. Keeps spoofed
+/// handshakes from making a listener hold unbounded state.
+const UDP_MAX_PENDING_REMOTES: usize = 64;
+/// A UDP channel which didn't hear from its remote side for this long is
+/// closed, so this bounds how long a handshake can be pending
+const UDP_PENDING_TIME: Duration = Duration::from_secs(30);
+
+/// Identities used by the key exchange of TCP channels, see
+/// [`Network::set_identity`] and [`Network::pin_identity`]
+///
+/// [`Network::set_identity`]: crate::api::Network::set_identity
+/// [`Network::pin_identity`]: crate::api::Network::pin_identity
+#[derive(Debug, Default)]
+pub(crate) struct Identities {
+    /// proven to everyone connecting to our listeners
+    pub own: Option<Arc<Identity>>,
+    /// required from everyone we connect to
+    pub pinned: Option<IdentityKey>,
+}
+
+/// Shared between `Network` and `Scheduler`, channels created afterwards use
+/// the identities
+pub(crate) type IdentitySlot = Arc<std::sync::Mutex<Identities>>;
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub(crate) enum Protocols {
-    Tcp((TcpSendProtocol<TcpDrain>, TcpRecvProtocol<TcpSink>)),
-    Mpsc((MpscSendProtocol<MpscDrain>, MpscRecvProtocol<MpscSink>)),
+    Tcp(
+        (
+            TcpSendProtocol<LinkDrain<TcpDrain>>,
+            TcpRecvProtocol<TcpSink>,
+        ),
+    ),
+    Mpsc(
+        (
+            MpscSendProtocol<LinkDrain<MpscDrain>>,
+            MpscRecvProtocol<MpscSink>,
+        ),
+    ),
+    Udp(
+        (
+            UdpSendProtocol<LinkDrain<UdpDrain>>,
+            UdpRecvProtocol<UdpSink, LinkDrain<UdpDrain>>,
+        ),
+    ),
     #[cfg(feature = "quic")]
     Quic((QuicSendProtocol<QuicDrain>, QuicRecvProtocol<QuicSink>)),
 }
 
 #[derive(Debug)]
 pub(crate) enum SendProtocols {
-    Tcp(TcpSendProtocol<TcpDrain>),
-    Mpsc(MpscSendProtocol<MpscDrain>),
+    Tcp(TcpSendProtocol<LinkDrain<TcpDrain>>),
+    Mpsc(MpscSendProtocol<LinkDrain<MpscDrain>>),
+    Udp(UdpSendProtocol<LinkDrain<UdpDrain>>),
     #[cfg(feature = "quic")]
     Quic(QuicSendProtocol<QuicDrain>),
 }
@@ -51,6 +98,7 @@ pub(crate) enum SendProtocols {
 pub(crate) enum RecvProtocols {
     Tcp(TcpRecvProtocol<TcpSink>),
     Mpsc(MpscRecvProtocol<MpscSink>),
+    Udp(UdpRecvProtocol<UdpSink, LinkDrain<UdpDrain>>),
     #[cfg(feature = "quic")]
     Quic(QuicRecvProtocol<QuicSink>),
 }
@@ -72,6 +120,8 @@ impl Protocols {
     pub(crate) async fn with_tcp_connect(
         addr: SocketAddr,
         metrics: ProtocolMetricCache,
+        link: Option<LinkConditions>,
+        pinned: Option<IdentityKey>,
     ) -> Result<Self, NetworkConnectError> {
         let stream = net::TcpStream::connect(addr)
             .await
@@ -84,13 +134,16 @@ impl Protocols {
             "Connecting Tcp to: {}",
             stream.peer_addr().map_err(NetworkConnectError::Io)?
         );
-        Ok(Self::new_tcp(stream, metrics))
+        let authentication = pinned.map_or(Authentication::None, Authentication::Verify);
+        Ok(Self::new_tcp(stream, metrics, link, authentication))
     }
 
     pub(crate) async fn with_tcp_listen(
         addr: SocketAddr,
         cids: Arc<AtomicU64>,
         metrics: Arc<ProtocolMetrics>,
+        link: Option<LinkConditions>,
+        identity: Option<Arc<Identity>>,
         s2s_stop_listening_r: oneshot::Receiver<()>,
         c2s_protocol_s: mpsc::UnboundedSender<(Self, Cid)>,
     ) -> std::io::Result<()> {
@@ -118,28 +171,41 @@ impl Protocols {
                 let cid = cids.fetch_add(1, Ordering::Relaxed);
                 info!(?remote_addr, ?cid, "Accepting Tcp from");
                 let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
-                let _ = c2s_protocol_s.send((Self::new_tcp(stream, metrics.clone()), cid));
+                let authentication = identity
+                    .clone()
+                    .map_or(Authentication::None, Authentication::Sign);
+                let _ = c2s_protocol_s.send((
+                    Self::new_tcp(stream, metrics.clone(), link, authentication),
+                    cid,
+                ));
             }
         });
         Ok(())
     }
 
-    pub(crate) fn new_tcp(stream: tokio::net::TcpStream, metrics: ProtocolMetricCache) -> Self {
+    pub(crate) fn new_tcp(
+        stream: tokio::net::TcpStream,
+        metrics: ProtocolMetricCache,
+        link: Option<LinkConditions>,
+        authentication: Authentication,
+    ) -> Self {
         let (r, w) = stream.into_split();
-        let sp = TcpSendProtocol::new(TcpDrain { half: w }, metrics.clone());
-        let rp = TcpRecvProtocol::new(
-            TcpSink {
-                half: r,
-                buffer: BytesMut::new(),
-            },
-            metrics,
-        );
+        let drain = LinkDrain::new(TcpDrain { half: w }, link, true);
+        let sink = TcpSink {
+            half: r,
+            buffer: BytesMut::new(),
+        };
+        let (sp, rp) = tcp_protocols(drain, sink, metrics);
+        let sp = sp
+            .with_encryption(Encryption::Required)
+            .with_authentication(authentication);
         Protocols::Tcp((sp, rp))
     }
 
     pub(crate) async fn with_mpsc_connect(
         addr: u64,
         metrics: ProtocolMetricCache,
+        link: Option<LinkConditions>,
     ) -> Result<Self, NetworkConnectError> {
         let mpsc_s = MPSC_POOL
             .lock()
@@ -170,6 +236,7 @@ impl Protocols {
             local_to_remote_s,
             remote_to_local_r,
             metrics,
+            link,
         ))
     }
 
@@ -177,6 +244,7 @@ impl Protocols {
         addr: u64,
         cids: Arc<AtomicU64>,
         metrics: Arc<ProtocolMetrics>,
+        link: Option<LinkConditions>,
         s2s_stop_listening_r: oneshot::Receiver<()>,
         c2s_protocol_s: mpsc::UnboundedSender<(Self, Cid)>,
     ) -> std::io::Result<()> {
@@ -199,7 +267,7 @@ impl Protocols {
                 info!(?addr, ?cid, "Accepting Mpsc from");
                 let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
                 let _ = c2s_protocol_s.send((
-                    Self::new_mpsc(local_to_remote_s, remote_to_local_r, metrics.clone()),
+                    Self::new_mpsc(local_to_remote_s, remote_to_local_r, metrics.clone(), link),
                     cid,
                 ));
             }
@@ -212,12 +280,140 @@ impl Protocols {
         sender: mpsc::Sender<MpscMsg>,
         receiver: mpsc::Receiver<MpscMsg>,
         metrics: ProtocolMetricCache,
+        link: Option<LinkConditions>,
     ) -> Self {
-        let sp = MpscSendProtocol::new(MpscDrain { sender }, metrics.clone());
+        let drain = LinkDrain::new(MpscDrain { sender }, link, true);
+        let sp = MpscSendProtocol::new(drain, metrics.clone());
         let rp = MpscRecvProtocol::new(MpscSink { receiver }, metrics);
         Protocols::Mpsc((sp, rp))
     }
 
+    pub(crate) async fn with_udp_connect(
+        addr: SocketAddr,
+        metrics: ProtocolMetricCache,
+        link: Option<LinkConditions>,
+    ) -> Result<Self, NetworkConnectError> {
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+        let bindsock = match addr {
+            SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+        };
+        let socket = Arc::new(
+            net::UdpSocket::bind(bindsock)
+                .await
+                .map_err(NetworkConnectError::Io)?,
+        );
+        info!("Connecting Udp to: {}", &addr);
+        let (datagram_s, datagram_r) = mpsc::unbounded_channel();
+        let reader = Arc::clone(&socket);
+        tokio::spawn(async move {
+            let mut buffer = [0u8; UDP_MAX_DATAGRAM_SIZE];
+            loop {
+                let (n, remote_addr) = select! {
+                    next = reader.recv_from(&mut buffer) => match next {
+                        Ok(next) => next,
+                        Err(e) => {
+                            trace!(?e, "UdpSocket Error, ignoring datagram");
+                            continue;
+                        },
+                    },
+                    _ = datagram_s.closed() => break,
+                };
+                // everyone can send to our socket
+                if remote_addr == addr {
+                    let _ = datagram_s.send(BytesMut::from(&buffer[..n]));
+                }
+            }
+        });
+        Ok(Self::new_udp(socket, addr, datagram_r, metrics, link))
+    }
+
+    pub(crate) async fn with_udp_listen(
+        addr: SocketAddr,
+        cids: Arc<AtomicU64>,
+        metrics: Arc<ProtocolMetrics>,
+        link: Option<LinkConditions>,
+        s2s_stop_listening_r: oneshot::Receiver<()>,
+        c2s_protocol_s: mpsc::UnboundedSender<(Self, Cid)>,
+    ) -> std::io::Result<()> {
+        let socket = Arc::new(net::UdpSocket::bind(addr).await?);
+        trace!(?addr, "Udp Listener bound");
+        let mut end_receiver = s2s_stop_listening_r.fuse();
+        tokio::spawn(async move {
+            // all channels share one socket, datagrams are sorted by their sender
+            let mut remotes: HashMap<SocketAddr, (mpsc::UnboundedSender<BytesMut>, Instant)> =
+                HashMap::new();
+            let mut accepting = true;
+            let mut buffer = [0u8; UDP_MAX_DATAGRAM_SIZE];
+            loop {
+                let (n, remote_addr) = select! {
+                    next = socket.recv_from(&mut buffer) => match next {
+                        Ok(next) => next,
+                        Err(e) => {
+                            trace!(?e, "UdpSocket Error, ignoring datagram");
+                            continue;
+                        },
+                    },
+                    _ = &mut end_receiver, if accepting => {
+                        // existing channels keep using the socket
+                        accepting = false;
+                        continue;
+                    },
+                };
+                remotes.retain(|_, (datagram_s, _)| !datagram_s.is_closed());
+                let datagram = BytesMut::from(&buffer[..n]);
+                if let Some((datagram_s, _)) = remotes.get(&remote_addr) {
+                    let _ = datagram_s.send(datagram);
+                    continue;
+                }
+                if !accepting {
+                    if remotes.is_empty() {
+                        break;
+                    }
+                    continue;
+                }
+                // no state is kept for anything but the start of a handshake
+                if !is_handshake_datagram(&datagram) {
+                    trace!(?remote_addr, "Ignoring Udp datagram outside of a channel");
+                    continue;
+                }
+                let pending = remotes
+                    .values()
+                    .filter(|(_, accepted)| accepted.elapsed() < UDP_PENDING_TIME)
+                    .count();
+                if pending >= UDP_MAX_PENDING_REMOTES {
+                    debug!(?remote_addr, "Too many pending Udp handshakes, ignoring");
+                    continue;
+                }
+                let cid = cids.fetch_add(1, Ordering::Relaxed);
+                info!(?remote_addr, ?cid, "Accepting Udp from");
+                let (datagram_s, datagram_r) = mpsc::unbounded_channel();
+                let _ = datagram_s.send(datagram);
+                remotes.insert(remote_addr, (datagram_s, Instant::now()));
+                let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
+                let udp =
+                    Self::new_udp(Arc::clone(&socket), remote_addr, datagram_r, metrics, link);
+                let _ = c2s_protocol_s.send((udp, cid));
+            }
+        });
+        Ok(())
+    }
+
+    pub(crate) fn new_udp(
+        socket: Arc<net::UdpSocket>,
+        remote: SocketAddr,
+        receiver: mpsc::UnboundedReceiver<BytesMut>,
+        metrics: ProtocolMetricCache,
+        link: Option<LinkConditions>,
+    ) -> Self {
+        Protocols::Udp(network_protocol::udp_protocols(
+            LinkDrain::new(UdpDrain { socket, remote }, link, false),
+            UdpSink { receiver },
+            metrics,
+        ))
+    }
+
     #[cfg(feature = "quic")]
     pub(crate) async fn with_quic_connect(
         addr: SocketAddr,
@@ -352,10 +548,26 @@ impl Protocols {
         Ok(Protocols::Quic((sp, rp)))
     }
 
+    /// Capture the frames of this channel, MPSC and UDP channels aren't
+    /// captured
+    pub(crate) fn with_capture(self, capture: FrameCapture) -> Self {
+        match self {
+            Protocols::Tcp((s, r)) => {
+                Protocols::Tcp((s.with_capture(capture.clone()), r.with_capture(capture)))
+            },
+            #[cfg(feature = "quic")]
+            Protocols::Quic((s, r)) => {
+                Protocols::Quic((s.with_capture(capture.clone()), r.with_capture(capture)))
+            },
+            p => p,
+        }
+    }
+
     pub(crate) fn split(self) -> (SendProtocols, RecvProtocols) {
         match self {
             Protocols::Tcp((s, r)) => (SendProtocols::Tcp(s), RecvProtocols::Tcp(r)),
             Protocols::Mpsc((s, r)) => (SendProtocols::Mpsc(s), RecvProtocols::Mpsc(r)),
+            Protocols::Udp((s, r)) => (SendProtocols::Udp(s), RecvProtocols::Udp(r)),
             #[cfg(feature = "quic")]
             Protocols::Quic((s, r)) => (SendProtocols::Quic(s), RecvProtocols::Quic(r)),
         }
@@ -364,17 +576,48 @@ impl Protocols {
 
 #[async_trait]
 impl network_protocol::InitProtocol for Protocols {
-    async fn initialize(
+    async fn initialize_with_token(
         &mut self,
         initializer: bool,
         local_pid: Pid,
         secret: u128,
-    ) -> Result<(Pid, Sid, u128), InitProtocolError> {
+        resume_token: Option<u128>,
+    ) -> Result<(Pid, Sid, u128, Option<u128>), InitProtocolError> {
         match self {
-            Protocols::Tcp(p) => p.initialize(initializer, local_pid, secret).await,
-            Protocols::Mpsc(p) => p.initialize(initializer, local_pid, secret).await,
+            Protocols::Tcp(p) => {
+                p.initialize_with_token(initializer, local_pid, secret, resume_token)
+                    .await
+            },
+            Protocols::Mpsc(p) => {
+                p.initialize_with_token(initializer, local_pid, secret, resume_token)
+                    .await
+            },
+            // a Udp listener only learns about a remote by its first datagram, so
+            // the connecting side has to start the handshake
+            Protocols::Udp(p) => {
+                p.initialize_with_token(!initializer, local_pid, secret, resume_token)
+                    .await
+            },
             #[cfg(feature = "quic")]
-            Protocols::Quic(p) => p.initialize(initializer, local_pid, secret).await,
+            Protocols::Quic(p) => {
+                p.initialize_with_token(initializer, local_pid, secret, resume_token)
+                    .await
+            },
+        }
+    }
+}
+
+impl SendProtocols {
+    /// Promises that streams on this channel can rely on
+    pub(crate) fn supported_promises(&self) -> Promises {
+        match self {
+            SendProtocols::Tcp(s) => s.supported_promises(),
+            SendProtocols::Mpsc(_) => {
+                MpscSendProtocol::<LinkDrain<MpscDrain>>::supported_promises()
+            },
+            SendProtocols::Udp(_) => UdpSendProtocol::<LinkDrain<UdpDrain>>::supported_promises(),
+            #[cfg(feature = "quic")]
+            SendProtocols::Quic(_) => QuicSendProtocol::<QuicDrain>::supported_promises(),
         }
     }
 }
@@ -385,6 +628,7 @@ impl network_protocol::SendProtocol for SendProtocols {
         match self {
             SendProtocols::Tcp(s) => s.notify_from_recv(event),
             SendProtocols::Mpsc(s) => s.notify_from_recv(event),
+            SendProtocols::Udp(s) => s.notify_from_recv(event),
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.notify_from_recv(event),
         }
@@ -394,6 +638,7 @@ impl network_protocol::SendProtocol for SendProtocols {
         match self {
             SendProtocols::Tcp(s) => s.send(event).await,
             SendProtocols::Mpsc(s) => s.send(event).await,
+            SendProtocols::Udp(s) => s.send(event).await,
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.send(event).await,
         }
@@ -407,10 +652,31 @@ impl network_protocol::SendProtocol for SendProtocols {
         match self {
             SendProtocols::Tcp(s) => s.flush(bandwidth, dt).await,
             SendProtocols::Mpsc(s) => s.flush(bandwidth, dt).await,
+            SendProtocols::Udp(s) => s.flush(bandwidth, dt).await,
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.flush(bandwidth, dt).await,
         }
     }
+
+    fn link_report(&mut self) -> LinkReport {
+        match self {
+            SendProtocols::Tcp(s) => s.link_report(),
+            SendProtocols::Mpsc(s) => s.link_report(),
+            SendProtocols::Udp(s) => s.link_report(),
+            #[cfg(feature = "quic")]
+            SendProtocols::Quic(s) => s.link_report(),
+        }
+    }
+
+    fn take_unflushed(&mut self) -> Vec<(Sid, bytes::Bytes)> {
+        match self {
+            SendProtocols::Tcp(s) => s.take_unflushed(),
+            SendProtocols::Mpsc(s) => s.take_unflushed(),
+            SendProtocols::Udp(s) => s.take_unflushed(),
+            #[cfg(feature = "quic")]
+            SendProtocols::Quic(s) => s.take_unflushed(),
+        }
+    }
 }
 
 #[async_trait]
@@ -419,6 +685,7 @@ impl network_protocol::RecvProtocol for RecvProtocols {
         match self {
             RecvProtocols::Tcp(r) => r.recv().await,
             RecvProtocols::Mpsc(r) => r.recv().await,
+            RecvProtocols::Udp(r) => r.recv().await,
             #[cfg(feature = "quic")]
             RecvProtocols::Quic(r) => r.recv().await,
         }
@@ -499,6 +766,45 @@ impl UnreliableSink for MpscSink {
 
 ///////////////////////////////////////
 //// QUIC
+///////////////////////////////////////
+//// UDP
+#[derive(Debug, Clone)]
+pub struct UdpDrain {
+    socket: Arc<net::UdpSocket>,
+    remote: SocketAddr,
+}
+
+#[derive(Debug)]
+pub struct UdpSink {
+    receiver: mpsc::UnboundedReceiver<BytesMut>,
+}
+
+#[async_trait]
+impl UnreliableDrain for UdpDrain {
+    type DataFormat = BytesMut;
+
+    async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError> {
+        match self.socket.send_to(&data, self.remote).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err(ProtocolError::Closed),
+        }
+    }
+}
+
+#[async_trait]
+impl UnreliableSink for UdpSink {
+    type DataFormat = BytesMut;
+
+    async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError> {
+        // the protocol needs to be woken up regularly to retransmit datagrams
+        match tokio::time::timeout(Duration::from_millis(10), self.receiver.recv()).await {
+            Ok(Some(data)) => Ok(data),
+            Ok(None) => Err(ProtocolError::Closed),
+            Err(_) => Ok(BytesMut::new()),
+        }
+    }
+}
+
 #[cfg(feature = "quic")]
 type QuicStream = (
     BytesMut,
@@ -668,8 +974,8 @@ mod tests {
         let client = TcpStream::connect("127.0.0.1:5000").await.unwrap();
         let (_listener, server) = r1.await.unwrap();
         let metrics = ProtocolMetricCache::new("0", Arc::new(ProtocolMetrics::new().unwrap()));
-        let client = Protocols::new_tcp(client, metrics.clone());
-        let server = Protocols::new_tcp(server, metrics);
+        let client = Protocols::new_tcp(client, metrics.clone(), None, Authentication::None);
+        let server = Protocols::new_tcp(server, metrics, None, Authentication::None);
         let (mut s, _) = client.split();
         let (_, mut r) = server.split();
         let event = ProtocolEvent::OpenStream {
@@ -717,8 +1023,8 @@ mod tests {
         let client = TcpStream::connect("127.0.0.1:5001").await.unwrap();
         let (_listener, server) = r1.await.unwrap();
         let metrics = ProtocolMetricCache::new("0", Arc::new(ProtocolMetrics::new().unwrap()));
-        let client = Protocols::new_tcp(client, metrics.clone());
-        let server = Protocols::new_tcp(server, metrics);
+        let client = Protocols::new_tcp(client, metrics.clone(), None, Authentication::None);
+        let server = Protocols::new_tcp(server, metrics, None, Authentication::None);
         let (s, _) = client.split();
         let (_, mut r) = server.split();
         let e = tokio::spawn(async move { r.recv().await });
@@ -99,7 +99,9 @@
 //! [`Promises`]: network_protocol::Promises
 
 mod api;
+mod capture;
 mod channel;
+mod link;
 mod message;
 mod metrics;
 mod participant;
@@ -110,5 +112,6 @@ pub use api::{
     ConnectAddr, ListenAddr, Network, NetworkConnectError, NetworkError, Participant,
     ParticipantError, Stream, StreamError, StreamParams,
 };
+pub use link::LinkConditions;
 pub use message::Message;
-pub use network_protocol::{InitProtocolError, Pid, Promises};
+pub use network_protocol::{Identity, IdentityKey, InitProtocolError, Pid, Promises};
@@ -0,0 +1,386 @@
+//! Degrades channels on purpose, to reproduce bad connections locally, see
+//! [`Network::condition_links`].
+//!
+//! [`Network::condition_links`]: crate::api::Network::condition_links
+use async_trait::async_trait;
+use bytes::BytesMut;
+use network_protocol::{MpscMsg, ProtocolError, ProtocolEvent, UnreliableDrain};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{select, sync::mpsc};
+use tracing::trace;
+
+/// How a link between two participants should behave. Applies to the
+/// direction data is send in, so both sides need it for a symmetric link.
+///
+/// TCP and MPSC deliver in order, so a lost packet is delayed by a
+/// retransmission instead and nothing is reordered.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkConditions {
+    /// Delay added to everything that is send
+    pub latency: Duration,
+    /// Random extra delay, up to this much
+    pub jitter: Duration,
+    /// Chance between 0 and 1 that a packet is lost
+    pub loss: f32,
+    /// Chance between 0 and 1 that a packet is held back, so the following
+    /// ones overtake it
+    pub reorder: f32,
+    /// Bytes per second, unlimited if `None`
+    pub bandwidth: Option<u64>,
+}
+
+/// Shared between `Network` and `Scheduler`, channels created while it's set
+/// are conditioned
+pub(crate) type LinkSlot = Arc<Mutex<Option<LinkConditions>>>;
+
+/// Drain between a protocol and its socket, which optionally conditions the
+/// link
+#[derive(Debug)]
+pub(crate) enum LinkDrain<D: UnreliableDrain> {
+    Direct(D),
+    Conditioned(ConditionedDrain<D::DataFormat>),
+}
+
+#[derive(Debug)]
+pub(crate) struct ConditionedDrain<T> {
+    conditions: LinkConditions,
+    ordered: bool,
+    state: Arc<Mutex<LinkState>>,
+    sender: mpsc::UnboundedSender<(Instant, T)>,
+}
+
+#[derive(Debug)]
+struct LinkState {
+    /// When everything queued for the bandwidth cap is on the wire
+    busy_until: Instant,
+    /// Ordered links never deliver earlier than this
+    last_delivery: Instant,
+}
+
+/// Size of the data for the bandwidth cap
+pub(crate) trait LinkData {
+    fn link_len(&self) -> usize;
+}
+
+impl LinkData for BytesMut {
+    fn link_len(&self) -> usize { self.len() }
+}
+
+impl LinkData for MpscMsg {
+    fn link_len(&self) -> usize {
+        match self {
+            MpscMsg::Event(ProtocolEvent::Message { data, .. }) => data.len(),
+            _ => 16,
+        }
+    }
+}
+
+impl Default for LinkConditions {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            loss: 0.0,
+            reorder: 0.0,
+            bandwidth: None,
+        }
+    }
+}
+
+impl<T> ConditionedDrain<T> {
+    /// Data that would queue longer than this for the bandwidth cap is
+    /// dropped on unordered links, ordered ones wait instead
+    const MAX_QUEUE_DELAY: Duration = Duration::from_millis(500);
+    /// Minimal time a lost packet is delayed on ordered links, like the
+    /// minimal retransmission timeout of TCP
+    const MIN_RETRANSMISSION_DELAY: Duration = Duration::from_millis(200);
+}
+
+impl<D> LinkDrain<D>
+where
+    D: UnreliableDrain + 'static,
+    D::DataFormat: LinkData + Send + 'static,
+{
+    /// `ordered` links, like TCP, deliver everything in the order it was send
+    pub(crate) fn new(drain: D, conditions: Option<LinkConditions>, ordered: bool) -> Self {
+        match conditions {
+            None => LinkDrain::Direct(drain),
+            Some(conditions) => {
+                trace!(?conditions, "conditioning link");
+                let (sender, receiver) = mpsc::unbounded_channel();
+                tokio::spawn(deliver(drain, receiver));
+                let now = Instant::now();
+                LinkDrain::Conditioned(ConditionedDrain {
+                    conditions,
+                    ordered,
+                    state: Arc::new(Mutex::new(LinkState {
+                        busy_until: now,
+                        last_delivery: now,
+                    })),
+                    sender,
+                })
+            },
+        }
+    }
+}
+
+impl<T: LinkData> ConditionedDrain<T> {
+    /// When `data` arrives at the other side, `None` if it's lost. Also
+    /// returns how long to wait before the link accepts more data.
+    fn schedule(&self, data: &T) -> (Option<Instant>, Duration) {
+        let LinkConditions {
+            latency,
+            jitter,
+            loss,
+            reorder,
+            bandwidth,
+        } = self.conditions;
+        let mut rng = rand::thread_rng();
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        let start = state.busy_until.max(now);
+        let queued = start - now;
+        if !self.ordered && queued > Self::MAX_QUEUE_DELAY {
+            return (None, Duration::ZERO);
+        }
+        let on_wire = match bandwidth {
+            Some(bandwidth) if bandwidth > 0 => {
+                start + Duration::from_secs_f64(data.link_len() as f64 / bandwidth as f64)
+            },
+            _ => start,
+        };
+        state.busy_until = on_wire;
+        let backpressure = queued.saturating_sub(Self::MAX_QUEUE_DELAY);
+
+        let mut delay = latency + jitter.mul_f64(rng.gen());
+        if rng.gen::<f32>() < loss {
+            if !self.ordered {
+                return (None, backpressure);
+            }
+            delay += (latency * 2).max(Self::MIN_RETRANSMISSION_DELAY);
+        }
+        if !self.ordered && rng.gen::<f32>() < reorder {
+            delay += (latency + jitter).max(Duration::from_millis(10));
+        }
+        let mut delivery = on_wire + delay;
+        if self.ordered {
+            delivery = delivery.max(state.last_delivery);
+            state.last_delivery = delivery;
+        }
+        (Some(delivery), backpressure)
+    }
+}
+
+/// Hands the data to `drain` once it's due
+async fn deliver<D: UnreliableDrain>(
+    mut drain: D,
+    mut receiver: mpsc::UnboundedReceiver<(Instant, D::DataFormat)>,
+) {
+    let mut queue = BTreeMap::<(Instant, u64), D::DataFormat>::new();
+    let mut id = 0u64;
+    loop {
+        let next = queue.keys().next().map(|(time, _)| *time);
+        // the branch is disabled without `next`, but the future is still created
+        let wake = next.unwrap_or_else(Instant::now);
+        select! {
+            data = receiver.recv() => match data {
+                Some((time, data)) => {
+                    queue.insert((time, id), data);
+                    id += 1;
+                },
+                None => break,
+            },
+            _ = tokio::time::sleep_until(wake.into()), if next.is_some() => {
+                let key = *queue.keys().next().unwrap();
+                let data = queue.remove(&key).unwrap();
+                if drain.send(data).await.is_err() {
+                    return;
+                }
+            },
+        }
+    }
+    // The protocol is gone, but what it send is still in flight, e.g. a shutdown
+    for ((time, _), data) in queue {
+        tokio::time::sleep_until(time.into()).await;
+        if drain.send(data).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[async_trait]
+impl<D> UnreliableDrain for LinkDrain<D>
+where
+    D: UnreliableDrain,
+    D::DataFormat: LinkData + Send,
+{
+    type DataFormat = D::DataFormat;
+
+    async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError> {
+        match self {
+            LinkDrain::Direct(drain) => drain.send(data).await,
+            LinkDrain::Conditioned(drain) => {
+                let (delivery, backpressure) = drain.schedule(&data);
+                if !backpressure.is_zero() {
+                    tokio::time::sleep(backpressure).await;
+                }
+                match delivery {
+                    Some(delivery) => drain
+                        .sender
+                        .send((delivery, data))
+                        .map_err(|_| ProtocolError::Closed),
+                    None => Ok(()),
+                }
+            },
+        }
+    }
+}
+
+impl<D: UnreliableDrain + Clone> Clone for LinkDrain<D> {
+    fn clone(&self) -> Self {
+        match self {
+            LinkDrain::Direct(drain) => LinkDrain::Direct(drain.clone()),
+            LinkDrain::Conditioned(drain) => LinkDrain::Conditioned(ConditionedDrain {
+                conditions: drain.conditions,
+                ordered: drain.ordered,
+                state: Arc::clone(&drain.state),
+                sender: drain.sender.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct VecDrain(mpsc::UnboundedSender<(Instant, BytesMut)>);
+
+    #[async_trait]
+    impl UnreliableDrain for VecDrain {
+        type DataFormat = BytesMut;
+
+        async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError> {
+            self.0
+                .send((Instant::now(), data))
+                .map_err(|_| ProtocolError::Closed)
+        }
+    }
+
+    fn conditioned(
+        conditions: LinkConditions,
+        ordered: bool,
+    ) -> (
+        LinkDrain<VecDrain>,
+        mpsc::UnboundedReceiver<(Instant, BytesMut)>,
+    ) {
+        let (s, r) = mpsc::unbounded_channel();
+        (LinkDrain::new(VecDrain(s), Some(conditions), ordered), r)
+    }
+
+    fn data(i: u8) -> BytesMut { BytesMut::from(&[i; 100][..]) }
+
+    #[tokio::test]
+    async fn latency_is_added() {
+        let latency = Duration::from_millis(50);
+        let (mut drain, mut r) = conditioned(
+            LinkConditions {
+                latency,
+                ..Default::default()
+            },
+            true,
+        );
+        let start = Instant::now();
+        drain.send(data(1)).await.unwrap();
+        let (arrived, d) = r.recv().await.unwrap();
+        assert!(arrived - start >= latency);
+        assert_eq!(d, data(1));
+    }
+
+    #[tokio::test]
+    async fn ordered_link_keeps_order_despite_jitter_and_loss() {
+        let (mut drain, mut r) = conditioned(
+            LinkConditions {
+                jitter: Duration::from_millis(20),
+                loss: 0.3,
+                reorder: 1.0,
+                ..Default::default()
+            },
+            true,
+        );
+        for i in 0..50 {
+            drain.send(data(i)).await.unwrap();
+        }
+        for i in 0..50 {
+            assert_eq!(r.recv().await.unwrap().1, data(i));
+        }
+    }
+
+    #[tokio::test]
+    async fn unordered_link_loses_everything() {
+        let (mut drain, mut r) = conditioned(
+            LinkConditions {
+                loss: 1.0,
+                ..Default::default()
+            },
+            false,
+        );
+        for i in 0..10 {
+            drain.send(data(i)).await.unwrap();
+        }
+        drop(drain);
+        assert!(r.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn unordered_link_reorders() {
+        let (mut drain, mut r) = conditioned(
+            LinkConditions {
+                latency: Duration::from_millis(5),
+                reorder: 0.5,
+                ..Default::default()
+            },
+            false,
+        );
+        for i in 0..50 {
+            drain.send(data(i)).await.unwrap();
+        }
+        drop(drain);
+        let mut received = vec![];
+        while let Some((_, d)) = r.recv().await {
+            received.push(d[0]);
+        }
+        assert_eq!(received.len(), 50);
+        assert!(received.windows(2).any(|w| w[0] > w[1]));
+    }
+
+    #[tokio::test]
+    async fn bandwidth_is_capped() {
+        // 100 bytes take 10ms
+        let (mut drain, mut r) = conditioned(
+            LinkConditions {
+                bandwidth: Some(10_000),
+                ..Default::default()
+            },
+            true,
+        );
+        let start = Instant::now();
+        for i in 0..10 {
+            drain.send(data(i)).await.unwrap();
+        }
+        let mut last = start;
+        for _ in 0..10 {
+            last = r.recv().await.unwrap().0;
+        }
+        assert!(last - start >= Duration::from_millis(100));
+    }
+}
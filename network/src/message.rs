@@ -1,6 +1,5 @@
 use crate::api::{StreamError, StreamParams};
 use bytes::Bytes;
-#[cfg(feature = "compression")]
 use network_protocol::Promises;
 use serde::{de::DeserializeOwned, Serialize};
 use std::io;
@@ -63,6 +62,20 @@ impl Message {
         }
     }
 
+    /// Wraps data that was received without a [`Stream`], e.g. when replaying
+    /// a capture. `promises` are the ones of the [`Stream`] it was send on.
+    ///
+    /// [`Stream`]: crate::api::Stream
+    pub fn from_raw(data: Bytes, promises: Promises) -> Self {
+        #[cfg(not(feature = "compression"))]
+        let _promises = promises;
+        Self {
+            data,
+            #[cfg(feature = "compression")]
+            compressed: promises.contains(Promises::COMPRESSED),
+        }
+    }
+
     /// deserialize this `Message`. This consumes the struct, as deserialization
     /// is only expected once. Use this when deserialize a [`recv_raw`]
     /// `Message`. If you are resending this message, deserialization might need
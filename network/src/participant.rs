@@ -1,17 +1,19 @@
 use crate::{
-    api::{ParticipantError, Stream},
+    api::{ConnectAddr, NetworkConnectError, ParticipantError, Stream},
     channel::{Protocols, RecvProtocols, SendProtocols},
     metrics::NetworkMetrics,
+    scheduler::A2sConnect,
     util::DeferredTracer,
 };
 use bytes::Bytes;
 use futures_util::{FutureExt, StreamExt};
 use hashbrown::HashMap;
 use network_protocol::{
-    Bandwidth, Cid, Pid, Prio, Promises, ProtocolEvent, RecvProtocol, SendProtocol, Sid,
-    _internal::SortedVec,
+    Bandwidth, Cid, CongestionControl, Pid, Prio, Promises, ProtocolEvent, RecvProtocol,
+    SendProtocol, Sid, _internal::SortedVec,
 };
 use std::{
+    collections::VecDeque,
     sync::{
         atomic::{AtomicBool, AtomicI32, Ordering},
         Arc,
@@ -26,7 +28,12 @@ use tokio::{
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::*;
 
-pub(crate) type A2bStreamOpen = (Prio, Promises, Bandwidth, oneshot::Sender<Stream>);
+pub(crate) type A2bStreamOpen = (
+    Prio,
+    Promises,
+    Bandwidth,
+    oneshot::Sender<Result<Stream, ParticipantError>>,
+);
 pub(crate) type S2bCreateChannel = (Cid, Sid, Protocols, oneshot::Sender<()>);
 pub(crate) type S2bShutdownBparticipant = (Duration, oneshot::Sender<Result<(), ParticipantError>>);
 pub(crate) type B2sPrioStatistic = (Pid, u64, u64);
@@ -42,12 +49,69 @@ struct ChannelInfo {
 struct StreamInfo {
     #[allow(dead_code)]
     prio: Prio,
-    #[allow(dead_code)]
     promises: Promises,
     send_closed: Arc<AtomicBool>,
     b2a_msg_recv_s: Mutex<async_channel::Sender<Bytes>>,
 }
 
+/// A stream as the send_mgr knows it, it's opened again when its channel is
+/// lost, see [`ResumeConfig`]
+#[derive(Debug)]
+struct SendStreamInfo {
+    prio: Prio,
+    promises: Promises,
+    guaranteed_bandwidth: Bandwidth,
+    /// promises its channel kept, a new channel needs to keep them too
+    kept: Promises,
+    /// only for streams with [`Promises::GUARANTEED_DELIVERY`]
+    unacked: Option<Unacked>,
+}
+
+/// Messages the remote didn't acknowledge yet, they are send again when the
+/// stream resumes on another channel
+impl SendStreamInfo {
+    fn new(
+        prio: Prio,
+        promises: Promises,
+        guaranteed_bandwidth: Bandwidth,
+        supported: Promises,
+    ) -> Self {
+        Self {
+            prio,
+            promises,
+            guaranteed_bandwidth,
+            kept: promises & supported,
+            unacked: promises
+                .contains(Promises::GUARANTEED_DELIVERY)
+                .then(Unacked::default),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Unacked {
+    /// messages the remote acknowledged so far
+    acked: u64,
+    messages: VecDeque<Bytes>,
+    /// opened again on another channel, nothing is send till the remote tells
+    /// which messages it already received
+    resuming: bool,
+}
+
+impl Unacked {
+    /// Drop all messages the remote received, false if it claims to have
+    /// received more than was send
+    fn ack(&mut self, count: u64) -> bool {
+        if count > self.acked + self.messages.len() as u64 {
+            return false;
+        }
+        let newly = count.saturating_sub(self.acked) as usize;
+        self.messages.drain(..newly);
+        self.acked = self.acked.max(count);
+        true
+    }
+}
+
 #[derive(Debug)]
 struct ControlChannels {
     a2b_open_stream_r: mpsc::UnboundedReceiver<A2bStreamOpen>,
@@ -57,6 +121,30 @@ struct ControlChannels {
     s2b_shutdown_bparticipant_r: oneshot::Receiver<S2bShutdownBparticipant>, /* own */
 }
 
+/// How a `BParticipant` survives losing all of its channels, e.g. when the IP
+/// of a client changes. Its streams stay open for `grace` and the side that
+/// connected keeps connecting to the same address meanwhile. It presents the
+/// resume token the listening side offered in the first handshake, only then
+/// the scheduler attaches the new channel to the existing participant.
+///
+/// Streams are opened again on a channel that keeps their promises, the
+/// others are closed. Both sides acknowledge received messages of streams
+/// with [`Promises::GUARANTEED_DELIVERY`], so after a resume the unacknowledged
+/// ones are send again, exactly once. Messages of other streams that the lost
+/// channel didn't flush completely are send again, the rest is lost.
+#[derive(Debug, Clone)]
+pub(crate) struct ResumeConfig {
+    pub(crate) grace: Duration,
+    pub(crate) reconnect: Option<(ConnectAddr, u128, mpsc::UnboundedSender<A2sConnect>)>,
+}
+
+impl ResumeConfig {
+    /// Shorter than the default `client_timeout` of the server, so that the
+    /// game doesn't notice a resumed participant
+    pub(crate) const GRACE: Duration = Duration::from_secs(20);
+    const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+}
+
 #[derive(Debug)]
 struct OpenStreamInfo {
     a2b_msg_s: crossbeam_channel::Sender<(Sid, Bytes)>,
@@ -75,9 +163,11 @@ pub struct BParticipant {
     shutdown_barrier: AtomicI32,
     metrics: Arc<NetworkMetrics>,
     open_stream_channels: Arc<Mutex<Option<OpenStreamInfo>>>,
+    resume: ResumeConfig,
 }
 
 impl BParticipant {
+    const ACK_INTERVAL: Duration = Duration::from_millis(100);
     // We use integer instead of Barrier to not block mgr from freeing at the end
     const BARR_CHANNEL: i32 = 1;
     const BARR_RECV: i32 = 4;
@@ -91,6 +181,7 @@ impl BParticipant {
         remote_pid: Pid,
         offset_sid: Sid,
         metrics: Arc<NetworkMetrics>,
+        resume: ResumeConfig,
     ) -> (
         Self,
         mpsc::UnboundedSender<A2bStreamOpen>,
@@ -127,6 +218,7 @@ impl BParticipant {
                 run_channels,
                 metrics,
                 open_stream_channels: Arc::new(Mutex::new(None)),
+                resume,
             },
             a2b_open_stream_s,
             b2a_stream_opened_r,
@@ -145,10 +237,15 @@ impl BParticipant {
             async_channel::unbounded::<Cid>();
         let (b2b_force_close_recv_protocol_s, b2b_force_close_recv_protocol_r) =
             async_channel::unbounded::<Cid>();
+        let (b2b_lost_protocol_s, b2b_lost_protocol_r) = async_channel::unbounded::<Cid>();
         let (b2b_notify_send_of_recv_open_s, b2b_notify_send_of_recv_open_r) =
             crossbeam_channel::unbounded::<(Cid, Sid, Prio, Promises, u64)>();
         let (b2b_notify_send_of_recv_close_s, b2b_notify_send_of_recv_close_r) =
             crossbeam_channel::unbounded::<(Cid, Sid)>();
+        let (b2b_notify_send_of_recv_msg_s, b2b_notify_send_of_recv_msg_r) =
+            crossbeam_channel::unbounded::<(Sid, u64)>();
+        let (b2b_notify_send_of_recv_ack_s, b2b_notify_send_of_recv_ack_r) =
+            crossbeam_channel::unbounded::<(Cid, Sid, u64)>();
 
         let (a2b_close_stream_s, a2b_close_stream_r) = mpsc::unbounded_channel::<Sid>();
         let (a2b_msg_s, a2b_msg_r) = crossbeam_channel::unbounded::<(Sid, Bytes)>();
@@ -166,8 +263,11 @@ impl BParticipant {
                 a2b_msg_r,
                 b2b_add_send_protocol_r,
                 b2b_close_send_protocol_r,
+                b2b_lost_protocol_r,
                 b2b_notify_send_of_recv_open_r,
                 b2b_notify_send_of_recv_close_r,
+                b2b_notify_send_of_recv_msg_r,
+                b2b_notify_send_of_recv_ack_r,
                 b2s_prio_statistic_s,
                 run_channels.b2a_bandwidth_stats_s,
             )
@@ -177,8 +277,11 @@ impl BParticipant {
                 b2b_add_recv_protocol_r,
                 b2b_force_close_recv_protocol_r,
                 b2b_close_send_protocol_s.clone(),
+                b2b_lost_protocol_s,
                 b2b_notify_send_of_recv_open_s,
                 b2b_notify_send_of_recv_close_s,
+                b2b_notify_send_of_recv_msg_s,
+                b2b_notify_send_of_recv_ack_s,
             )
             .instrument(tracing::info_span!("recv")),
             self.create_channel_mgr(
@@ -194,17 +297,13 @@ impl BParticipant {
         );
     }
 
+    /// The best channel that keeps all `promises`, `None` if no channel can
     fn best_protocol(all: &SortedVec<Cid, SendProtocols>, promises: Promises) -> Option<Cid> {
         // check for mpsc
         all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Mpsc(_))).map(|(c, _)| *c).or_else(
-            || if network_protocol::TcpSendProtocol::<crate::channel::TcpDrain>::supported_promises()
-                .contains(promises)
-            {
-                // check for tcp
-                all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Tcp(_))).map(|(c, _)| *c)
-            } else {
-                None
-            }
+            // check for tcp, promises depend on the negotiated encryption
+            || all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Tcp(tcp) if tcp.supported_promises().contains(promises)))
+                .map(|(c, _)| *c)
         ).or_else(
             // check for quic, TODO: evaluate to order quic BEFORE tcp once its stable
             || if network_protocol::QuicSendProtocol::<crate::channel::QuicDrain>::supported_promises()
@@ -215,9 +314,13 @@ impl BParticipant {
                 None
             }
         ).or_else(
-            || {
-                warn!("couldn't satisfy promises");
-                all.data.first().map(|(c, _)| *c)
+            // check for udp
+            || if network_protocol::UdpSendProtocol::<crate::channel::UdpDrain>::supported_promises()
+                .contains(promises)
+            {
+                all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Udp(_))).map(|(c, _)| *c)
+            } else {
+                None
             }
         )
     }
@@ -231,6 +334,7 @@ impl BParticipant {
         a2b_msg_r: crossbeam_channel::Receiver<(Sid, Bytes)>,
         mut b2b_add_protocol_r: mpsc::UnboundedReceiver<(Cid, SendProtocols)>,
         b2b_close_send_protocol_r: async_channel::Receiver<Cid>,
+        b2b_lost_protocol_r: async_channel::Receiver<Cid>,
         b2b_notify_send_of_recv_open_r: crossbeam_channel::Receiver<(
             Cid,
             Sid,
@@ -239,37 +343,104 @@ impl BParticipant {
             Bandwidth,
         )>,
         b2b_notify_send_of_recv_close_r: crossbeam_channel::Receiver<(Cid, Sid)>,
+        b2b_notify_send_of_recv_msg_r: crossbeam_channel::Receiver<(Sid, u64)>,
+        b2b_notify_send_of_recv_ack_r: crossbeam_channel::Receiver<(Cid, Sid, u64)>,
         _b2s_prio_statistic_s: mpsc::UnboundedSender<B2sPrioStatistic>,
         b2a_bandwidth_stats_s: watch::Sender<f32>,
     ) {
         let mut sorted_send_protocols = SortedVec::<Cid, SendProtocols>::default();
         let mut sorted_stream_protocols = SortedVec::<Sid, Cid>::default();
+        // every channel is its own link, so each has its own bandwidth estimate
+        let mut congestion = SortedVec::<Cid, CongestionControl>::default();
+        // all open streams, they are opened again when their channel is lost
+        let mut stream_infos = HashMap::<Sid, SendStreamInfo>::new();
+        // messages that couldn't be send yet, as their channel was lost
+        let mut held = Vec::<(Sid, Bytes)>::new();
+        // closed by the api, but their messages still need to be send again
+        let mut closing = Vec::<Sid>::new();
+        // messages the recv_mgr got per stream, the remote learns about them every
+        // `ACK_INTERVAL` and when a stream resumes
+        let mut received = HashMap::<Sid, u64>::new();
+        let mut acks_due = HashMap::<Sid, u64>::new();
+        let mut last_ack = Instant::now();
+        let mut lost_since: Option<Instant> = None;
+        let mut reconnect: Option<JoinHandle<()>> = None;
         let mut interval = tokio::time::interval(Self::TICK_TIME);
         let mut last_instant = Instant::now();
         let mut stream_ids = self.offset_sid;
         let mut part_bandwidth = 0.0f32;
         trace!("workaround, actively wait for first protocol");
         if let Some((c, p)) = b2b_add_protocol_r.recv().await {
-            sorted_send_protocols.insert(c, p)
+            sorted_send_protocols.insert(c, p);
+            congestion.insert(c, CongestionControl::new(Instant::now()));
         }
         loop {
-            let (open, close, _, addp, remp) = select!(
-                Some(n) = a2b_open_stream_r.recv().fuse() => (Some(n), None, None, None, None),
-                Some(n) = a2b_close_stream_r.recv().fuse() => (None, Some(n), None, None, None),
-                _ = interval.tick() => (None, None, Some(()), None, None),
-                Some(n) = b2b_add_protocol_r.recv().fuse() => (None, None, None, Some(n), None),
-                Ok(n) = b2b_close_send_protocol_r.recv().fuse() => (None, None, None, None, Some(n)),
+            // streams can't be opened or closed without a channel
+            let has_channel = !sorted_send_protocols.data.is_empty();
+            let (open, close, _, addp, remp, lost) = select!(
+                Some(n) = a2b_open_stream_r.recv().fuse(), if has_channel => (Some(n), None, None, None, None, None),
+                Some(n) = a2b_close_stream_r.recv().fuse(), if has_channel => (None, Some(n), None, None, None, None),
+                _ = interval.tick() => (None, None, Some(()), None, None, None),
+                Some(n) = b2b_add_protocol_r.recv().fuse() => (None, None, None, Some(n), None, None),
+                Ok(n) = b2b_close_send_protocol_r.recv().fuse() => (None, None, None, None, Some(n), None),
+                Ok(n) = b2b_lost_protocol_r.recv().fuse() => (None, None, None, None, None, Some(n)),
             );
 
             if let Some((cid, p)) = addp {
                 debug!(?cid, "add protocol");
                 sorted_send_protocols.insert(cid, p);
+                congestion.insert(cid, CongestionControl::new(Instant::now()));
+                if let Some(since) = lost_since.take() {
+                    info!(?cid, lost_for = ?since.elapsed(), "resumed with a new channel");
+                }
+                if let Some(reconnect) = reconnect.take() {
+                    reconnect.abort();
+                }
+            }
+
+            if let Some(cid) = lost {
+                debug!(?cid, "lost protocol");
+                self.lose_protocol(
+                    cid,
+                    &mut sorted_send_protocols,
+                    &mut sorted_stream_protocols,
+                    &mut congestion,
+                    &stream_infos,
+                    &mut held,
+                );
+                if sorted_send_protocols.data.is_empty() {
+                    lost_since = Some(Instant::now());
+                }
             }
 
             //verify that we have at LEAST 1 channel before continuing
             if sorted_send_protocols.data.is_empty() {
-                warn!("no channel");
-                tokio::time::sleep(Self::TICK_TIME * 1000).await; //TODO: failover
+                match lost_since {
+                    Some(since) if since.elapsed() >= self.resume.grace => {
+                        info!("no channel came back in time");
+                        break;
+                    },
+                    Some(_) => {
+                        if let (None, Some((addr, resume_token, a2s_connect_s))) =
+                            (&reconnect, &self.resume.reconnect)
+                        {
+                            info!(grace = ?self.resume.grace, "lost all channels, reconnecting");
+                            reconnect = Some(tokio::spawn(
+                                Self::reconnect(
+                                    addr.clone(),
+                                    self.remote_pid,
+                                    *resume_token,
+                                    a2s_connect_s.clone(),
+                                )
+                                .instrument(tracing::info_span!("reconnect")),
+                            ));
+                        }
+                    },
+                    None => {
+                        warn!("no channel");
+                        tokio::time::sleep(Self::TICK_TIME * 1000).await; //TODO: failover
+                    },
+                }
                 continue;
             }
 
@@ -278,37 +449,101 @@ impl BParticipant {
             let mut cid = u64::MAX;
 
             let active_err = async {
-                if let Some((prio, promises, guaranteed_bandwidth, return_s)) = open {
-                    let sid = stream_ids;
-                    stream_ids += Sid::from(1);
-                    cid = Self::best_protocol(&sorted_send_protocols, promises).unwrap();
-                    trace!(?sid, ?cid, "open stream");
-
-                    let stream = self
-                        .create_stream(sid, prio, promises, guaranteed_bandwidth)
-                        .await;
+                for (sid, count) in b2b_notify_send_of_recv_msg_r.try_iter() {
+                    received.insert(sid, count);
+                    acks_due.insert(sid, count);
+                }
 
-                    let event = ProtocolEvent::OpenStream {
-                        sid,
-                        prio,
-                        promises,
-                        guaranteed_bandwidth,
+                // streams of a lost channel continue on the remaining ones
+                let mut unresumable = vec![];
+                for (&sid, si) in stream_infos.iter_mut() {
+                    if sorted_stream_protocols.get(&sid).is_some() {
+                        continue;
+                    }
+                    cid = match Self::best_protocol(&sorted_send_protocols, si.kept) {
+                        Some(c) => c,
+                        None => {
+                            unresumable.push(sid);
+                            continue;
+                        },
                     };
-
+                    trace!(?sid, ?cid, "open stream again");
                     sorted_stream_protocols.insert(sid, cid);
-                    return_s.send(stream).unwrap();
-                    sorted_send_protocols
-                        .get_mut(&cid)
-                        .unwrap()
-                        .send(event)
-                        .await?;
+                    let p = sorted_send_protocols.get_mut(&cid).unwrap();
+                    p.send(ProtocolEvent::OpenStream {
+                        sid,
+                        prio: si.prio,
+                        promises: si.promises,
+                        guaranteed_bandwidth: si.guaranteed_bandwidth,
+                    })
+                    .await?;
+                    if let Some(unacked) = &mut si.unacked {
+                        unacked.resuming = true;
+                        let count = received.get(&sid).copied().unwrap_or_default();
+                        p.send(ProtocolEvent::Ack { sid, count }).await?;
+                    }
+                }
+                for sid in unresumable {
+                    // the remote has the same channels left, so it closes it as well
+                    info!(
+                        ?sid,
+                        "no channel keeps the promises of the stream, closing it"
+                    );
+                    stream_infos.remove(&sid);
+                    received.remove(&sid);
+                    acks_due.remove(&sid);
+                    self.delete_stream(sid).await;
+                }
+
+                if let Some((prio, promises, guaranteed_bandwidth, return_s)) = open {
+                    match Self::best_protocol(&sorted_send_protocols, promises) {
+                        Some(c) => {
+                            cid = c;
+                            let sid = stream_ids;
+                            stream_ids += Sid::from(1);
+                            trace!(?sid, ?cid, "open stream");
+
+                            let stream = self
+                                .create_stream(sid, prio, promises, guaranteed_bandwidth)
+                                .await;
+
+                            let event = ProtocolEvent::OpenStream {
+                                sid,
+                                prio,
+                                promises,
+                                guaranteed_bandwidth,
+                            };
+
+                            let p = sorted_send_protocols.get_mut(&cid).unwrap();
+                            sorted_stream_protocols.insert(sid, cid);
+                            stream_infos.insert(
+                                sid,
+                                SendStreamInfo::new(
+                                    prio,
+                                    promises,
+                                    guaranteed_bandwidth,
+                                    p.supported_promises(),
+                                ),
+                            );
+                            return_s.send(Ok(stream)).unwrap();
+                            p.send(event).await?;
+                        },
+                        None => {
+                            warn!(?promises, "no channel keeps the promises, rejecting stream");
+                            let _ =
+                                return_s.send(Err(ParticipantError::UnsupportedPromises(promises)));
+                        },
+                    }
                 }
 
                 // process recv content first
-                for (cid, sid, prio, promises, guaranteed_bandwidth) in
+                for (c, sid, prio, promises, guaranteed_bandwidth) in
                     b2b_notify_send_of_recv_open_r.try_iter()
                 {
+                    cid = c;
                     match sorted_send_protocols.get_mut(&cid) {
+                        // we already opened it again on this channel
+                        Some(_) if sorted_stream_protocols.get(&sid) == Some(&cid) => {},
                         Some(p) => {
                             sorted_stream_protocols.insert(sid, cid);
                             p.notify_from_recv(ProtocolEvent::OpenStream {
@@ -317,27 +552,124 @@ impl BParticipant {
                                 promises,
                                 guaranteed_bandwidth,
                             });
+                            match stream_infos.get_mut(&sid) {
+                                // the remote resumed it on this channel
+                                Some(si) => {
+                                    if let Some(unacked) = &mut si.unacked {
+                                        unacked.resuming = true;
+                                        let count = received.get(&sid).copied().unwrap_or_default();
+                                        p.send(ProtocolEvent::Ack { sid, count }).await?;
+                                    }
+                                },
+                                None => {
+                                    let si = SendStreamInfo::new(
+                                        prio,
+                                        promises,
+                                        guaranteed_bandwidth,
+                                        p.supported_promises(),
+                                    );
+                                    stream_infos.insert(sid, si);
+                                },
+                            }
                         },
                         None => warn!(?cid, "couldn't notify create protocol, doesn't exist"),
                     };
                 }
 
-                // get all messages and assign it to a channel
-                for (sid, buffer) in a2b_msg_r.try_iter() {
-                    cid = *sorted_stream_protocols.get(&sid).unwrap();
-                    let event = ProtocolEvent::Message { data: buffer, sid };
-                    sorted_send_protocols
-                        .get_mut(&cid)
-                        .unwrap()
-                        .send(event)
-                        .await?;
+                // the remote tells which messages it received, resumed streams continue
+                // with the rest
+                for (c, sid, count) in b2b_notify_send_of_recv_ack_r.try_iter() {
+                    let unacked = match stream_infos
+                        .get_mut(&sid)
+                        .and_then(|si| si.unacked.as_mut())
+                    {
+                        Some(unacked) => unacked,
+                        None => continue,
+                    };
+                    if !unacked.ack(count) {
+                        warn!(
+                            ?sid,
+                            ?count,
+                            "remote acknowledged messages that were never send"
+                        );
+                        continue;
+                    }
+                    if unacked.resuming && sorted_stream_protocols.get(&sid) == Some(&c) {
+                        trace!(?sid, resend = unacked.messages.len(), "resume stream");
+                        unacked.resuming = false;
+                        cid = c;
+                        let p = sorted_send_protocols.get_mut(&cid).unwrap();
+                        for data in unacked.messages.iter() {
+                            let event = ProtocolEvent::Message {
+                                data: data.clone(),
+                                sid,
+                            };
+                            p.send(event).await?;
+                        }
+                    }
+                }
+
+                // get all messages and assign it to a channel, held ones first
+                let mut messages = std::mem::take(&mut held);
+                messages.extend(a2b_msg_r.try_iter());
+                for (sid, buffer) in messages {
+                    let si = match stream_infos.get_mut(&sid) {
+                        Some(si) => si,
+                        None => {
+                            trace!(?sid, "drop message of a closed stream");
+                            continue;
+                        },
+                    };
+                    match (sorted_stream_protocols.get(&sid), &mut si.unacked) {
+                        (Some(&c), Some(unacked)) if !unacked.resuming => {
+                            unacked.messages.push_back(buffer.clone());
+                            cid = c;
+                            let event = ProtocolEvent::Message { data: buffer, sid };
+                            sorted_send_protocols
+                                .get_mut(&cid)
+                                .unwrap()
+                                .send(event)
+                                .await?;
+                        },
+                        // send once the stream resumed
+                        (_, Some(unacked)) => unacked.messages.push_back(buffer),
+                        (Some(&c), None) => {
+                            cid = c;
+                            let event = ProtocolEvent::Message { data: buffer, sid };
+                            sorted_send_protocols
+                                .get_mut(&cid)
+                                .unwrap()
+                                .send(event)
+                                .await?;
+                        },
+                        (None, None) => held.push((sid, buffer)),
+                    }
+                }
+
+                if last_ack.elapsed() >= Self::ACK_INTERVAL {
+                    last_ack = Instant::now();
+                    for (sid, count) in acks_due.drain() {
+                        if let Some(&c) = sorted_stream_protocols.get(&sid) {
+                            cid = c;
+                            let event = ProtocolEvent::Ack { sid, count };
+                            sorted_send_protocols
+                                .get_mut(&cid)
+                                .unwrap()
+                                .send(event)
+                                .await?;
+                        }
+                    }
                 }
 
                 // process recv content afterwards
-                for (cid, sid) in b2b_notify_send_of_recv_close_r.try_iter() {
+                for (c, sid) in b2b_notify_send_of_recv_close_r.try_iter() {
+                    cid = c;
                     match sorted_send_protocols.get_mut(&cid) {
                         Some(p) => {
                             let _ = sorted_stream_protocols.delete(&sid);
+                            stream_infos.remove(&sid);
+                            received.remove(&sid);
+                            acks_due.remove(&sid);
                             p.notify_from_recv(ProtocolEvent::CloseStream { sid });
                         },
                         None => warn!(?cid, "couldn't notify close protocol, doesn't exist"),
@@ -347,6 +679,21 @@ impl BParticipant {
                 if let Some(sid) = close {
                     trace!(?stream_ids, "delete stream");
                     self.delete_stream(sid).await;
+                    closing.push(sid);
+                }
+                // a resuming stream is closed after its messages were send again
+                for sid in std::mem::take(&mut closing) {
+                    let resuming = stream_infos.get(&sid).map_or(false, |si| {
+                        sorted_stream_protocols.get(&sid).is_none()
+                            || si.unacked.as_ref().map_or(false, |u| u.resuming)
+                    });
+                    if resuming {
+                        closing.push(sid);
+                        continue;
+                    }
+                    stream_infos.remove(&sid);
+                    received.remove(&sid);
+                    acks_due.remove(&sid);
                     // Fire&Forget the protocol will take care to verify that this Frame is delayed
                     // till the last msg was received!
                     if let Some(c) = sorted_stream_protocols.delete(&sid) {
@@ -364,15 +711,29 @@ impl BParticipant {
                 let diff = send_time.duration_since(last_instant);
                 last_instant = send_time;
                 let mut cnt = 0;
+                let mut available_bandwidth = 0;
                 for (c, p) in sorted_send_protocols.data.iter_mut() {
                     cid = *c;
-                    cnt += p.flush(1_000_000_000, diff).await?; //this actually blocks, so we cant set streams while it.
+                    let cc = congestion
+                        .get_mut(c)
+                        .expect("every channel has a congestion control");
+                    let flush_start = Instant::now();
+                    let sent = p.flush(cc.bandwidth(), diff).await?; //this actually blocks, so we cant set streams while it.
+                    cc.on_flush(
+                        Instant::now(),
+                        diff,
+                        sent,
+                        flush_start.elapsed(),
+                        p.link_report(),
+                    );
+                    cnt += sent;
+                    available_bandwidth += cc.bandwidth();
                 }
                 let flush_time = send_time.elapsed().as_secs_f32();
                 part_bandwidth = 0.99 * part_bandwidth + 0.01 * (cnt as f32 / flush_time);
                 self.metrics
                     .participant_bandwidth(&self.remote_pid_string, part_bandwidth);
-                let _ = b2a_bandwidth_stats_s.send(part_bandwidth);
+                let _ = b2a_bandwidth_stats_s.send(available_bandwidth as f32);
                 let r: Result<(), network_protocol::ProtocolError> = Ok(());
                 r
             }
@@ -381,19 +742,37 @@ impl BParticipant {
                 info!(?cid, ?e, "protocol failed, shutting down channel");
                 // remote recv will now fail, which will trigger remote send which will trigger
                 // recv
-                trace!("TODO: for now decide to FAIL this participant and not wait for a failover");
-                sorted_send_protocols.delete(&cid).unwrap();
-                self.metrics.channels_disconnected(&self.remote_pid_string);
+                self.lose_protocol(
+                    cid,
+                    &mut sorted_send_protocols,
+                    &mut sorted_stream_protocols,
+                    &mut congestion,
+                    &stream_infos,
+                    &mut held,
+                );
                 if sorted_send_protocols.data.is_empty() {
-                    break;
+                    lost_since = Some(Instant::now());
                 }
             }
 
             if let Some(cid) = remp {
                 debug!(?cid, "remove protocol");
+                let _ = congestion.delete(&cid);
                 match sorted_send_protocols.delete(&cid) {
                     Some(mut prot) => {
                         self.metrics.channels_disconnected(&self.remote_pid_string);
+                        // the Shutdown is held back till all streams of the channel are closed
+                        let sids = sorted_stream_protocols
+                            .data
+                            .iter()
+                            .filter(|(_, c)| *c == cid)
+                            .map(|(sid, _)| *sid)
+                            .collect::<Vec<_>>();
+                        for sid in sids {
+                            let _ = sorted_stream_protocols.delete(&sid);
+                            stream_infos.remove(&sid);
+                            let _ = prot.send(ProtocolEvent::CloseStream { sid }).await;
+                        }
                         trace!("blocking flush");
                         let _ = prot.flush(u64::MAX, Duration::from_secs(1)).await;
                         trace!("shutdown prot");
@@ -406,6 +785,9 @@ impl BParticipant {
                 }
             }
         }
+        if let Some(reconnect) = reconnect {
+            reconnect.abort();
+        }
         trace!("stop sending in api!");
         self.open_stream_channels.lock().await.take();
         trace!("Stop send_mgr");
@@ -413,6 +795,58 @@ impl BParticipant {
             .fetch_sub(Self::BARR_SEND, Ordering::SeqCst);
     }
 
+    /// Remove a failed channel, its streams are opened again on another one and
+    /// its unflushed messages are held till then, see [`ResumeConfig`]
+    fn lose_protocol(
+        &self,
+        cid: Cid,
+        sorted_send_protocols: &mut SortedVec<Cid, SendProtocols>,
+        sorted_stream_protocols: &mut SortedVec<Sid, Cid>,
+        congestion: &mut SortedVec<Cid, CongestionControl>,
+        stream_infos: &HashMap<Sid, SendStreamInfo>,
+        held: &mut Vec<(Sid, Bytes)>,
+    ) {
+        if let Some(mut p) = sorted_send_protocols.delete(&cid) {
+            // unacknowledged messages are send again anyway
+            held.extend(p.take_unflushed().into_iter().filter(|(sid, _)| {
+                stream_infos
+                    .get(sid)
+                    .map_or(false, |si| si.unacked.is_none())
+            }));
+            self.metrics.channels_disconnected(&self.remote_pid_string);
+        }
+        let _ = congestion.delete(&cid);
+        sorted_stream_protocols.data.retain(|(_, c)| *c != cid);
+    }
+
+    /// Keep connecting to `addr` and present the `resume_token` of the remote,
+    /// the scheduler attaches a successful channel to this participant
+    async fn reconnect(
+        addr: ConnectAddr,
+        remote_pid: Pid,
+        resume_token: u128,
+        a2s_connect_s: mpsc::UnboundedSender<A2sConnect>,
+    ) {
+        loop {
+            let (pid_s, pid_r) = oneshot::channel();
+            let resume = Some((remote_pid, resume_token));
+            if a2s_connect_s.send((addr.clone(), resume, pid_s)).is_err() {
+                break;
+            }
+            match pid_r.await {
+                Ok(Err(NetworkConnectError::InvalidSecret)) => {
+                    warn!("remote restarted, it can't resume this participant");
+                    break;
+                },
+                Ok(Err(e)) => debug!(?e, "reconnect failed"),
+                // a resume never creates a new participant, dropping the sender
+                // means the channel was attached to this one
+                Ok(Ok(_)) | Err(_) => {},
+            }
+            tokio::time::sleep(ResumeConfig::RECONNECT_INTERVAL).await;
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn recv_mgr(
         &self,
@@ -420,6 +854,7 @@ impl BParticipant {
         mut b2b_add_protocol_r: mpsc::UnboundedReceiver<(Cid, RecvProtocols)>,
         b2b_force_close_recv_protocol_r: async_channel::Receiver<Cid>,
         b2b_close_send_protocol_s: async_channel::Sender<Cid>,
+        b2b_lost_protocol_s: async_channel::Sender<Cid>,
         b2b_notify_send_of_recv_open_r: crossbeam_channel::Sender<(
             Cid,
             Sid,
@@ -428,6 +863,8 @@ impl BParticipant {
             Bandwidth,
         )>,
         b2b_notify_send_of_recv_close_s: crossbeam_channel::Sender<(Cid, Sid)>,
+        b2b_notify_send_of_recv_msg_s: crossbeam_channel::Sender<(Sid, u64)>,
+        b2b_notify_send_of_recv_ack_s: crossbeam_channel::Sender<(Cid, Sid, u64)>,
     ) {
         let mut recv_protocols: HashMap<Cid, JoinHandle<()>> = HashMap::new();
         // we should be able to directly await futures imo
@@ -455,12 +892,22 @@ impl BParticipant {
         };
 
         let mut defered_orphan = DeferredTracer::new(tracing::Level::WARN);
+        // messages received per stream with guaranteed delivery, see `ResumeConfig`
+        let mut received = HashMap::<Sid, u64>::new();
+        // set while all channels are lost, see `ResumeConfig`
+        let mut lost_since: Option<Instant> = None;
 
         loop {
+            let grace_left =
+                lost_since.map(|since| self.resume.grace.saturating_sub(since.elapsed()));
             let (event, addp, remp) = select!(
                 Some(n) = hacky_recv_r.recv().fuse() => (Some(n), None, None),
                 Some(n) = b2b_add_protocol_r.recv().fuse() => (None, Some(n), None),
                 Ok(n) = b2b_force_close_recv_protocol_r.recv().fuse() => (None, None, Some(n)),
+                _ = tokio::time::sleep(grace_left.unwrap_or_default()), if grace_left.is_some() => {
+                    info!("no channel came back in time");
+                    break;
+                },
                 else => {
                     error!("recv_mgr -> something is seriously wrong!, end recv_mgr");
                     break;
@@ -469,6 +916,7 @@ impl BParticipant {
 
             if let Some((cid, p)) = addp {
                 debug!(?cid, "add protocol");
+                lost_since = None;
                 retrigger(cid, p, &mut recv_protocols);
             };
             if let Some(cid) = remp {
@@ -496,15 +944,19 @@ impl BParticipant {
                         ));
                         // waiting for receiving is not necessary, because the send_mgr will first
                         // process this before process messages!
-                        let stream = self
-                            .create_stream(sid, prio, promises, guaranteed_bandwidth)
-                            .await;
-                        b2a_stream_opened_s.send(stream).unwrap();
+                        // A known stream was opened again after its channel was lost
+                        if !self.streams.read().await.contains_key(&sid) {
+                            let stream = self
+                                .create_stream(sid, prio, promises, guaranteed_bandwidth)
+                                .await;
+                            b2a_stream_opened_s.send(stream).unwrap();
+                        }
                         retrigger(cid, p, &mut recv_protocols);
                     },
                     Ok(ProtocolEvent::CloseStream { sid }) => {
                         trace!(?sid, "close stream");
                         let _ = b2b_notify_send_of_recv_close_s.send((cid, sid));
+                        received.remove(&sid);
                         self.delete_stream(sid).await;
                         retrigger(cid, p, &mut recv_protocols);
                     },
@@ -512,12 +964,21 @@ impl BParticipant {
                         let lock = self.streams.read().await;
                         match lock.get(&sid) {
                             Some(stream) => {
+                                if stream.promises.contains(Promises::GUARANTEED_DELIVERY) {
+                                    let count = received.entry(sid).or_insert(0);
+                                    *count += 1;
+                                    let _ = b2b_notify_send_of_recv_msg_s.send((sid, *count));
+                                }
                                 let _ = stream.b2a_msg_recv_s.lock().await.send(data).await;
                             },
                             None => defered_orphan.log(sid),
                         };
                         retrigger(cid, p, &mut recv_protocols);
                     },
+                    Ok(ProtocolEvent::Ack { sid, count }) => {
+                        let _ = b2b_notify_send_of_recv_ack_s.send((cid, sid, count));
+                        retrigger(cid, p, &mut recv_protocols);
+                    },
                     Ok(ProtocolEvent::Shutdown) => {
                         info!(?cid, "shutdown protocol");
                         if let Err(e) = b2b_close_send_protocol_s.send(cid).await {
@@ -529,11 +990,14 @@ impl BParticipant {
                     },
                     Err(e) => {
                         info!(?e, ?cid, "protocol failed, shutting down channel");
-                        if let Err(e) = b2b_close_send_protocol_s.send(cid).await {
+                        if let Err(e) = b2b_lost_protocol_s.send(cid).await {
                             debug!(?e, ?cid, "send_mgr was already closed simultaneously");
                         }
                         if remove_c(&mut recv_protocols, &cid) {
-                            break;
+                            if self.resume.grace.is_zero() {
+                                break;
+                            }
+                            lost_since = Some(Instant::now());
                         }
                     },
                 }
@@ -792,6 +1256,22 @@ mod tests {
         mpsc::UnboundedReceiver<B2sPrioStatistic>,
         watch::Receiver<f32>,
         JoinHandle<()>,
+    ) {
+        mock_bparticipant_with_grace(Duration::ZERO)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn mock_bparticipant_with_grace(
+        grace: Duration,
+    ) -> (
+        Arc<Runtime>,
+        mpsc::UnboundedSender<A2bStreamOpen>,
+        mpsc::UnboundedReceiver<Stream>,
+        mpsc::UnboundedSender<S2bCreateChannel>,
+        oneshot::Sender<S2bShutdownBparticipant>,
+        mpsc::UnboundedReceiver<B2sPrioStatistic>,
+        watch::Receiver<f32>,
+        JoinHandle<()>,
     ) {
         let runtime = Arc::new(tokio::runtime::Runtime::new().unwrap());
         let runtime_clone = Arc::clone(&runtime);
@@ -812,7 +1292,11 @@ mod tests {
             let sid = Sid::new(1000);
             let metrics = Arc::new(NetworkMetrics::new(&local_pid).unwrap());
 
-            BParticipant::new(local_pid, remote_pid, sid, Arc::clone(&metrics))
+            let resume = ResumeConfig {
+                grace,
+                reconnect: None,
+            };
+            BParticipant::new(local_pid, remote_pid, sid, Arc::clone(&metrics), resume)
         });
 
         let handle = runtime_clone.spawn(bparticipant.run(b2s_prio_statistic_s));
@@ -837,14 +1321,14 @@ mod tests {
         let (s2, r2) = mpsc::channel(100);
         let met = Arc::new(ProtocolMetrics::new().unwrap());
         let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&met));
-        let p1 = Protocols::new_mpsc(s1, r2, metrics);
+        let p1 = Protocols::new_mpsc(s1, r2, metrics, None);
         let (complete_s, complete_r) = oneshot::channel();
         create_channel
             .send((cid, Sid::new(0), p1, complete_s))
             .unwrap();
         complete_r.await.unwrap();
         let metrics = ProtocolMetricCache::new(&cid.to_string(), met);
-        Protocols::new_mpsc(s2, r1, metrics)
+        Protocols::new_mpsc(s2, r1, metrics, None)
     }
 
     #[test]
@@ -1020,4 +1504,92 @@ mod tests {
         drop((a2b_open_stream_s, b2a_stream_opened_r, b2s_prio_statistic_r));
         drop(runtime);
     }
+
+    #[test]
+    fn resumed_stream_sends_unacked_messages_again() {
+        let (
+            runtime,
+            a2b_open_stream_s,
+            b2a_stream_opened_r,
+            mut s2b_create_channel_s,
+            s2b_shutdown_bparticipant_s,
+            b2s_prio_statistic_r,
+            _b2a_bandwidth_stats_r,
+            handle,
+        ) = mock_bparticipant_with_grace(Duration::from_secs(10));
+
+        let remote = runtime.block_on(mock_mpsc(0, &runtime, &mut s2b_create_channel_s));
+        std::thread::sleep(Duration::from_millis(50));
+
+        let (rs, mut rr) = remote.split();
+        let (stream_sender, stream_receiver) = oneshot::channel();
+        a2b_open_stream_s
+            .send((5u8, Promises::GUARANTEED_DELIVERY, 0, stream_sender))
+            .unwrap();
+        let mut stream = runtime.block_on(stream_receiver).unwrap().unwrap();
+        let sid = Sid::new(1000);
+        assert!(matches!(
+            runtime.block_on(rr.recv()).unwrap(),
+            ProtocolEvent::OpenStream { .. }
+        ));
+        stream.send("first").unwrap();
+        stream.send("second").unwrap();
+        let _first = runtime.block_on(rr.recv()).unwrap();
+        let second = match runtime.block_on(rr.recv()).unwrap() {
+            ProtocolEvent::Message { data, .. } => data,
+            _ => panic!("wrong event"),
+        };
+        let mut rs = rs;
+        runtime
+            .block_on(rs.send(ProtocolEvent::Ack { sid, count: 1 }))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        // lose the channel, the stream resumes on a new one
+        drop((rs, rr));
+        std::thread::sleep(Duration::from_millis(50));
+        let remote = runtime.block_on(mock_mpsc(1, &runtime, &mut s2b_create_channel_s));
+        let (mut rs, mut rr) = remote.split();
+        assert!(matches!(
+            runtime.block_on(rr.recv()).unwrap(),
+            ProtocolEvent::OpenStream { sid: s, .. } if s == sid
+        ));
+        assert!(matches!(
+            runtime.block_on(rr.recv()).unwrap(),
+            ProtocolEvent::Ack { sid: s, count: 0 } if s == sid
+        ));
+        runtime
+            .block_on(rs.send(ProtocolEvent::Ack { sid, count: 1 }))
+            .unwrap();
+        // only the message the remote didn't acknowledge is send again
+        assert!(matches!(
+            runtime.block_on(rr.recv()).unwrap(),
+            ProtocolEvent::Message { data, .. } if data == second
+        ));
+        stream.send("third").unwrap();
+        assert!(matches!(
+            runtime.block_on(rr.recv()).unwrap(),
+            ProtocolEvent::Message { .. }
+        ));
+
+        let (s, r) = oneshot::channel();
+        runtime.block_on(async {
+            drop(s2b_create_channel_s);
+            s2b_shutdown_bparticipant_s
+                .send((Duration::from_secs(1), s))
+                .unwrap();
+            drop((rs, rr));
+            r.await.unwrap().unwrap();
+        });
+
+        runtime.block_on(handle).unwrap();
+
+        drop((
+            a2b_open_stream_s,
+            b2a_stream_opened_r,
+            b2s_prio_statistic_r,
+            stream,
+        ));
+        drop(runtime);
+    }
 }
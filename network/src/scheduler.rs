@@ -1,12 +1,16 @@
 use crate::{
     api::{ConnectAddr, ListenAddr, NetworkConnectError, Participant},
-    channel::Protocols,
+    capture::CaptureSlot,
+    channel::{IdentitySlot, Protocols},
+    link::LinkSlot,
     metrics::{NetworkMetrics, ProtocolInfo},
-    participant::{B2sPrioStatistic, BParticipant, S2bCreateChannel, S2bShutdownBparticipant},
+    participant::{
+        B2sPrioStatistic, BParticipant, ResumeConfig, S2bCreateChannel, S2bShutdownBparticipant,
+    },
 };
 use futures_util::StreamExt;
 use hashbrown::HashMap;
-use network_protocol::{Cid, Pid, ProtocolMetricCache, ProtocolMetrics};
+use network_protocol::{Cid, FrameCapture, Pid, ProtocolMetricCache, ProtocolMetrics};
 #[cfg(feature = "metrics")]
 use prometheus::Registry;
 use rand::Rng;
@@ -36,14 +40,18 @@ use tracing::*;
 #[derive(Debug)]
 struct ParticipantInfo {
     secret: u128,
-    #[allow(dead_code)]
+    /// offered to a connecting remote, it presents it again to resume
+    resume_token: Option<u128>,
     s2b_create_channel_s: mpsc::UnboundedSender<S2bCreateChannel>,
     s2b_shutdown_bparticipant_s: Option<oneshot::Sender<S2bShutdownBparticipant>>,
 }
 
 type A2sListen = (ListenAddr, oneshot::Sender<io::Result<()>>);
+/// The optional pid and token identify a participant to resume, see
+/// [`ResumeConfig`]
 pub(crate) type A2sConnect = (
     ConnectAddr,
+    Option<(Pid, u128)>,
     oneshot::Sender<Result<Participant, NetworkConnectError>>,
 );
 type A2sDisconnect = (Pid, S2bShutdownBparticipant);
@@ -62,6 +70,7 @@ struct ParticipantChannels {
     s2a_connected_s: mpsc::UnboundedSender<Participant>,
     a2s_disconnect_s: mpsc::UnboundedSender<A2sDisconnect>,
     b2s_prio_statistic_s: mpsc::UnboundedSender<B2sPrioStatistic>,
+    a2s_connect_s: mpsc::UnboundedSender<A2sConnect>,
 }
 
 #[derive(Debug)]
@@ -76,11 +85,17 @@ pub struct Scheduler {
     channel_listener: Mutex<HashMap<ProtocolInfo, oneshot::Sender<()>>>,
     metrics: Arc<NetworkMetrics>,
     protocol_metrics: Arc<ProtocolMetrics>,
+    capture: CaptureSlot,
+    link: LinkSlot,
+    identities: IdentitySlot,
 }
 
 impl Scheduler {
     pub fn new(
         local_pid: Pid,
+        capture: CaptureSlot,
+        link: LinkSlot,
+        identities: IdentitySlot,
         #[cfg(feature = "metrics")] registry: Option<&Registry>,
     ) -> (
         Self,
@@ -109,6 +124,7 @@ impl Scheduler {
             s2a_connected_s,
             a2s_disconnect_s,
             b2s_prio_statistic_s,
+            a2s_connect_s: a2s_connect_s.clone(),
         };
 
         let metrics = Arc::new(NetworkMetrics::new(&local_pid).unwrap());
@@ -137,6 +153,9 @@ impl Scheduler {
                 channel_listener: Mutex::new(HashMap::new()),
                 metrics,
                 protocol_metrics,
+                capture,
+                link,
+                identities,
             },
             a2s_listen_s,
             a2s_connect_s,
@@ -176,6 +195,8 @@ impl Scheduler {
                 let (s2s_stop_listening_s, s2s_stop_listening_r) = oneshot::channel::<()>();
                 let (c2s_protocol_s, mut c2s_protocol_r) = mpsc::unbounded_channel();
                 let metrics = Arc::clone(&self.protocol_metrics);
+                let link = *self.link.lock().unwrap();
+                let identity = self.identities.lock().unwrap().own.clone();
 
                 async move {
                     self.channel_listener
@@ -192,6 +213,8 @@ impl Scheduler {
                                 addr,
                                 cids,
                                 metrics,
+                                link,
+                                identity,
                                 s2s_stop_listening_r,
                                 c2s_protocol_s,
                             )
@@ -214,17 +237,28 @@ impl Scheduler {
                                 addr,
                                 cids,
                                 metrics,
+                                link,
+                                s2s_stop_listening_r,
+                                c2s_protocol_s,
+                            )
+                            .await
+                        },
+                        ListenAddr::Udp(addr) => {
+                            Protocols::with_udp_listen(
+                                addr,
+                                cids,
+                                metrics,
+                                link,
                                 s2s_stop_listening_r,
                                 c2s_protocol_s,
                             )
                             .await
                         },
-                        _ => unimplemented!(),
                     };
                     let _ = s2a_listen_result_s.send(res);
 
                     while let Some((prot, cid)) = c2s_protocol_r.recv().await {
-                        self.init_protocol(prot, cid, None, true).await;
+                        self.init_protocol(prot, cid, None, None, None, true).await;
                     }
                 }
             })
@@ -234,29 +268,42 @@ impl Scheduler {
 
     async fn connect_mgr(&self, mut a2s_connect_r: mpsc::UnboundedReceiver<A2sConnect>) {
         trace!("Start connect_mgr");
-        while let Some((addr, pid_sender)) = a2s_connect_r.recv().await {
+        while let Some((addr, resume, pid_sender)) = a2s_connect_r.recv().await {
             let cid = self.channel_ids.fetch_add(1, Ordering::Relaxed);
             let metrics =
                 ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&self.protocol_metrics));
             self.metrics.connect_request(&addr);
+            let connect_addr = addr.clone();
+            let link = *self.link.lock().unwrap();
+            let pinned = self.identities.lock().unwrap().pinned;
             let protocol = match addr {
-                ConnectAddr::Tcp(addr) => Protocols::with_tcp_connect(addr, metrics).await,
+                ConnectAddr::Tcp(addr) => {
+                    Protocols::with_tcp_connect(addr, metrics, link, pinned).await
+                },
                 #[cfg(feature = "quic")]
                 ConnectAddr::Quic(addr, ref config, name) => {
                     Protocols::with_quic_connect(addr, config.clone(), name, metrics).await
                 },
-                ConnectAddr::Mpsc(addr) => Protocols::with_mpsc_connect(addr, metrics).await,
-                _ => unimplemented!(),
+                ConnectAddr::Mpsc(addr) => Protocols::with_mpsc_connect(addr, metrics, link).await,
+                ConnectAddr::Udp(addr) => Protocols::with_udp_connect(addr, metrics, link).await,
             };
             let protocol = match protocol {
                 Ok(p) => p,
                 Err(e) => {
-                    pid_sender.send(Err(e)).unwrap();
+                    // a reconnecting participant might have stopped waiting
+                    let _ = pid_sender.send(Err(e));
                     continue;
                 },
             };
-            self.init_protocol(protocol, cid, Some(pid_sender), false)
-                .await;
+            self.init_protocol(
+                protocol,
+                cid,
+                Some(connect_addr),
+                resume,
+                Some(pid_sender),
+                false,
+            )
+            .await;
         }
         trace!("Stop connect_mgr");
     }
@@ -374,8 +421,10 @@ impl Scheduler {
 
     async fn init_protocol(
         &self,
-        mut protocol: Protocols,
+        protocol: Protocols,
         cid: Cid,
+        connect_addr: Option<ConnectAddr>,
+        resume: Option<(Pid, u128)>,
         s2a_return_pid_s: Option<oneshot::Sender<Result<Participant, NetworkConnectError>>>,
         send_handshake: bool,
     ) {
@@ -386,6 +435,11 @@ impl Scheduler {
                   - Speed, because otherwise the message can be send with the creation
         */
         let participant_channels = self.participant_channels.lock().await.clone().unwrap();
+        let capture = self.capture.lock().unwrap().clone();
+        let mut protocol = match capture {
+            Some((start, sink)) => protocol.with_capture(FrameCapture::new(cid, start, sink)),
+            None => protocol,
+        };
         // spawn is needed here, e.g. for TCP connect it would mean that only 1
         // participant can be in handshake phase ever! Someone could deadlock
         // the whole server easily for new clients UDP doesnt work at all, as
@@ -394,24 +448,53 @@ impl Scheduler {
         let metrics = Arc::clone(&self.metrics);
         let local_pid = self.local_pid;
         let local_secret = self.local_secret;
+        // the listening side offers a new token for every participant, the
+        // connecting side presents it again to resume one
+        let local_resume_token = match resume {
+            Some((_, token)) => Some(token),
+            None => send_handshake.then(|| rand::thread_rng().gen::<u128>()),
+        };
         // this is necessary for UDP to work at all and to remove code duplication
         tokio::spawn(
             async move {
                 trace!(?cid, "Open channel and be ready for Handshake");
                 use network_protocol::InitProtocol;
                 let init_result = protocol
-                    .initialize(send_handshake, local_pid, local_secret)
+                    .initialize_with_token(
+                        send_handshake,
+                        local_pid,
+                        local_secret,
+                        local_resume_token,
+                    )
                     .instrument(tracing::info_span!("handshake", ?cid))
                     .await;
                 match init_result {
-                    Ok((pid, sid, secret)) => {
+                    Ok((pid, sid, secret, remote_resume_token)) => {
                         trace!(
                             ?cid,
                             ?pid,
                             "Detected that my channel is ready!, activating it :)"
                         );
                         let mut participants = participants.lock().await;
-                        if !participants.contains_key(&pid) {
+                        let unknown = !participants.contains_key(&pid);
+                        // a remote that wants to resume a participant that is already
+                        // gone, or that restarted in between
+                        let resume_gone = match resume {
+                            Some((resume_pid, _)) => resume_pid != pid || unknown,
+                            None => unknown && send_handshake && remote_resume_token.is_some(),
+                        };
+                        if resume_gone {
+                            info!(
+                                ?cid,
+                                ?pid,
+                                "participant to resume is gone, dropping channel"
+                            );
+                            if let Some(pid_oneshot) = s2a_return_pid_s {
+                                let _ = pid_oneshot.send(Err(NetworkConnectError::InvalidSecret));
+                            }
+                            return;
+                        }
+                        if unknown {
                             debug!(?cid, "New participant connected via a channel");
                             let (
                                 bparticipant,
@@ -420,7 +503,24 @@ impl Scheduler {
                                 s2b_create_channel_s,
                                 s2b_shutdown_bparticipant_s,
                                 b2a_bandwidth_stats_r,
-                            ) = BParticipant::new(local_pid, pid, sid, Arc::clone(&metrics));
+                            ) = BParticipant::new(
+                                local_pid,
+                                pid,
+                                sid,
+                                Arc::clone(&metrics),
+                                ResumeConfig {
+                                    grace: ResumeConfig::GRACE,
+                                    reconnect: connect_addr.zip(remote_resume_token).map(
+                                        |(addr, token)| {
+                                            (
+                                                addr,
+                                                token,
+                                                participant_channels.a2s_connect_s.clone(),
+                                            )
+                                        },
+                                    ),
+                                },
+                            );
 
                             let participant = Participant::new(
                                 local_pid,
@@ -435,6 +535,7 @@ impl Scheduler {
                             metrics.participants_connected_total.inc();
                             participants.insert(pid, ParticipantInfo {
                                 secret,
+                                resume_token: local_resume_token,
                                 s2b_create_channel_s: s2b_create_channel_s.clone(),
                                 s2b_shutdown_bparticipant_s: Some(s2b_shutdown_bparticipant_s),
                             });
@@ -456,7 +557,9 @@ impl Scheduler {
                             b2s_create_channel_done_r.await.unwrap();
                             if let Some(pid_oneshot) = s2a_return_pid_s {
                                 // someone is waiting with `connect`, so give them their PID
-                                pid_oneshot.send(Ok(participant)).unwrap();
+                                // a reconnecting participant might have stopped waiting, dropping
+                                // the participant disconnects it again
+                                let _ = pid_oneshot.send(Ok(participant));
                             } else {
                                 // no one is waiting on this Participant, return in to Network
                                 if participant_channels
@@ -473,28 +576,48 @@ impl Scheduler {
                                 ?cid,
                                 "2nd+ channel of participant, going to compare security ids"
                             );
-                            if pi.secret != secret {
+                            // only a channel presenting the token may resume it
+                            let resumes = match resume {
+                                Some(_) => true,
+                                None => {
+                                    remote_resume_token.is_some()
+                                        && remote_resume_token == pi.resume_token
+                                },
+                            };
+                            if pi.secret != secret || !resumes {
                                 warn!(
                                     ?cid,
                                     ?pid,
                                     ?secret,
-                                    "Detected incompatible Secret!, this is probably an attack!"
+                                    "Detected incompatible Secret or resume token!, this is \
+                                     probably an attack!"
                                 );
                                 error!(?cid, "Just dropping here, TODO handle this correctly!");
                                 //TODO
                                 if let Some(pid_oneshot) = s2a_return_pid_s {
                                     // someone is waiting with `connect`, so give them their Error
-                                    pid_oneshot
-                                        .send(Err(NetworkConnectError::InvalidSecret))
-                                        .unwrap();
+                                    let _ =
+                                        pid_oneshot.send(Err(NetworkConnectError::InvalidSecret));
                                 }
                                 return;
                             }
-                            error!(
-                                ?cid,
-                                "Ufff i cant answer the pid_oneshot. as i need to create the SAME \
-                                 participant. maybe switch to ARC"
-                            );
+                            // a participant that lost its channels and resumes within
+                            // `ResumeConfig::grace`
+                            let s2b_create_channel_s = pi.s2b_create_channel_s.clone();
+                            drop(participants);
+                            trace!("dropped participants lock");
+                            let (b2s_create_channel_done_s, b2s_create_channel_done_r) =
+                                oneshot::channel();
+                            if s2b_create_channel_s
+                                .send((cid, sid, protocol, b2s_create_channel_done_s))
+                                .is_err()
+                                || b2s_create_channel_done_r.await.is_err()
+                            {
+                                debug!(?cid, ?pid, "participant already closed, dropping channel");
+                            }
+                            // dropping `s2a_return_pid_s` tells a reconnecting
+                            // participant
+                            // that the channel was attached to it
                         }
                         //From now on this CHANNEL can receiver other frames!
                         // move directly to participant!
@@ -506,9 +629,7 @@ impl Scheduler {
                         if let Some(pid_oneshot) = s2a_return_pid_s {
                             // someone is waiting with `connect`, so give them their Error
                             trace!(?cid, "returning the Err to api who requested the connect");
-                            pid_oneshot
-                                .send(Err(NetworkConnectError::Handshake(e)))
-                                .unwrap();
+                            let _ = pid_oneshot.send(Err(NetworkConnectError::Handshake(e)));
                         }
                     },
                 }
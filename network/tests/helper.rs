@@ -11,7 +11,9 @@ use std::{
 use tokio::runtime::Runtime;
 use tracing::*;
 use tracing_subscriber::EnvFilter;
-use veloren_network::{ConnectAddr, ListenAddr, Network, Participant, Pid, Promises, Stream};
+use veloren_network::{
+    ConnectAddr, LinkConditions, ListenAddr, Network, Participant, Pid, Promises, Stream,
+};
 
 // sleep time when only internal rust calculations are done
 #[allow(dead_code)]
@@ -64,11 +66,31 @@ pub fn network_participant_stream(
     Network,
     Participant,
     Stream,
+) {
+    network_participant_stream_conditioned(addr, None)
+}
+
+/// Like `network_participant_stream`, but both sides send over a link with
+/// `conditions`
+#[allow(dead_code)]
+pub fn network_participant_stream_conditioned(
+    addr: (ListenAddr, ConnectAddr),
+    conditions: Option<LinkConditions>,
+) -> (
+    Arc<Runtime>,
+    Network,
+    Participant,
+    Stream,
+    Network,
+    Participant,
+    Stream,
 ) {
     let runtime = Arc::new(Runtime::new().unwrap());
     let (n_a, p1_a, s1_a, n_b, p1_b, s1_b) = runtime.block_on(async {
         let n_a = Network::new(Pid::fake(0), &runtime);
         let n_b = Network::new(Pid::fake(1), &runtime);
+        n_a.condition_links(conditions);
+        n_b.condition_links(conditions);
 
         n_a.listen(addr.0).await.unwrap();
         let p1_b = n_b.connect(addr.1).await.unwrap();
@@ -1,10 +1,16 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::runtime::Runtime;
-use veloren_network::{NetworkError, StreamError};
+use veloren_network::{NetworkError, ParticipantError, StreamError};
 mod helper;
-use helper::{mpsc, network_participant_stream, quic, tcp, udp, SLEEP_EXTERNAL, SLEEP_INTERNAL};
+use helper::{
+    mpsc, network_participant_stream, network_participant_stream_conditioned, quic, tcp, udp,
+    SLEEP_EXTERNAL, SLEEP_INTERNAL,
+};
 use std::io::ErrorKind;
-use veloren_network::{ConnectAddr, ListenAddr, Network, Pid, Promises};
+use veloren_network::{ConnectAddr, LinkConditions, ListenAddr, Network, Pid, Promises};
 
 #[test]
 fn stream_simple() {
@@ -90,7 +96,6 @@ fn stream_simple_quic_3msg() {
 }
 
 #[test]
-#[ignore]
 fn stream_simple_udp() {
     let (_, _) = helper::setup(false, 0);
     let (r, _n_a, _p_a, mut s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(udp());
@@ -101,7 +106,6 @@ fn stream_simple_udp() {
 }
 
 #[test]
-#[ignore]
 fn stream_simple_udp_3msg() {
     let (_, _) = helper::setup(false, 0);
     let (r, _n_a, _p_a, mut s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(udp());
@@ -115,6 +119,42 @@ fn stream_simple_udp_3msg() {
     drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
 }
 
+fn bad_link() -> LinkConditions {
+    LinkConditions {
+        latency: Duration::from_millis(50),
+        jitter: Duration::from_millis(20),
+        loss: 0.1,
+        reorder: 0.2,
+        bandwidth: Some(100_000),
+    }
+}
+
+/// Messages still arrive complete and in order, but not before the latency
+fn stream_over_bad_link(addr: (ListenAddr, ConnectAddr)) {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, _p_a, mut s1_a, _n_b, _p_b, mut s1_b) =
+        network_participant_stream_conditioned(addr, Some(bad_link()));
+
+    let start = Instant::now();
+    for i in 0..100u32 {
+        s1_a.send(i).unwrap();
+    }
+    for i in 0..100u32 {
+        assert_eq!(r.block_on(s1_b.recv()), Ok(i));
+    }
+    assert!(start.elapsed() >= bad_link().latency);
+    drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
+}
+
+#[test]
+fn stream_bad_link_tcp() { stream_over_bad_link(tcp()); }
+
+#[test]
+fn stream_bad_link_mpsc() { stream_over_bad_link(mpsc()); }
+
+#[test]
+fn stream_bad_link_udp() { stream_over_bad_link(udp()); }
+
 #[test]
 #[ignore]
 fn tcp_and_udp_2_connections() -> std::result::Result<(), Box<dyn std::error::Error>> {
@@ -143,7 +183,6 @@ fn tcp_and_udp_2_connections() -> std::result::Result<(), Box<dyn std::error::Er
 }
 
 #[test]
-#[ignore]
 fn failed_listen_on_used_ports() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let (_, _) = helper::setup(false, 0);
     let r = Arc::new(Runtime::new().unwrap());
@@ -262,3 +301,17 @@ fn multiple_try_recv() {
     assert_eq!(s1_b.try_recv::<String>(), Err(StreamError::StreamClosed));
     drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
 }
+
+#[test]
+fn open_encrypted_stream_on_unauthenticated_tcp() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, p_a, _s1_a, _n_b, _p_b, _s1_b) = network_participant_stream(tcp());
+
+    // Nobody pinned the identity of the listening side, so the channel is
+    // encrypted, but a man in the middle can't be ruled out
+    assert_eq!(
+        r.block_on(p_a.open(4, Promises::ENCRYPTED, 0)).unwrap_err(),
+        ParticipantError::UnsupportedPromises(Promises::ENCRYPTED)
+    );
+    drop((_n_a, _n_b, p_a, _p_b)); //clean teardown
+}
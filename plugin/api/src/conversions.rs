@@ -0,0 +1,59 @@
+use common::{comp, terrain::Block};
+
+use super::{Alignment, BlockInfo, KillSource, KillType};
+
+impl From<comp::Alignment> for Alignment {
+    fn from(alignment: comp::Alignment) -> Self {
+        match alignment {
+            comp::Alignment::Wild => Alignment::Wild,
+            comp::Alignment::Enemy => Alignment::Enemy,
+            comp::Alignment::Npc => Alignment::Npc,
+            comp::Alignment::Tame => Alignment::Tame,
+            comp::Alignment::Owned(uid) => Alignment::Owned(uid),
+            comp::Alignment::Passive => Alignment::Passive,
+        }
+    }
+}
+
+impl From<Block> for BlockInfo {
+    fn from(block: Block) -> Self {
+        BlockInfo {
+            kind: block.kind().to_string(),
+            color: block.get_color(),
+            sprite: block.get_sprite().map(|sprite| sprite.to_string()),
+        }
+    }
+}
+
+impl From<comp::chat::KillType> for KillType {
+    fn from(kill_type: comp::chat::KillType) -> Self {
+        match kill_type {
+            comp::chat::KillType::Buff(kind) => KillType::Buff(kind),
+            comp::chat::KillType::Melee => KillType::Melee,
+            comp::chat::KillType::Projectile => KillType::Projectile,
+            comp::chat::KillType::Explosion => KillType::Explosion,
+            comp::chat::KillType::Energy => KillType::Energy,
+            comp::chat::KillType::Other => KillType::Other,
+        }
+    }
+}
+
+impl From<comp::chat::KillSource> for KillSource {
+    fn from(kill_source: comp::chat::KillSource) -> Self {
+        match kill_source {
+            comp::chat::KillSource::Player(uid, kill_type) => {
+                KillSource::Player(uid, kill_type.into())
+            },
+            comp::chat::KillSource::NonPlayer(name, kill_type) => {
+                KillSource::NonPlayer(name, kill_type.into())
+            },
+            comp::chat::KillSource::NonExistent(kill_type) => {
+                KillSource::NonExistent(kill_type.into())
+            },
+            comp::chat::KillSource::Environment(name) => KillSource::Environment(name),
+            comp::chat::KillSource::FallDamage => KillSource::FallDamage,
+            comp::chat::KillSource::Suicide => KillSource::Suicide,
+            comp::chat::KillSource::Other => KillSource::Other,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use common::uid::Uid;
+use vek::Vec3;
 
 use serde::{Deserialize, Serialize};
 
@@ -45,6 +46,7 @@ pub enum EcsAccessError {
     EcsComponentNotFound(Uid, String),
     EcsResourceNotFound(String),
     EcsEntityNotFound(Uid),
+    EcsBlockNotLoaded(Vec3<i32>),
 }
 
 impl core::fmt::Display for EcsAccessError {
@@ -66,6 +68,9 @@ impl core::fmt::Display for EcsAccessError {
             EcsAccessError::EcsEntityNotFound(a) => {
                 write!(f, "EcsAccessError can't find entity from UID {}", a)
             },
+            EcsAccessError::EcsBlockNotLoaded(a) => {
+                write!(f, "EcsAccessError the block at {} isn't loaded", a)
+            },
         }
     }
 }
@@ -1,10 +1,21 @@
 pub extern crate common;
 
-pub use common::comp::Health;
+pub use common::{
+    comp::{BuffKind, Health},
+    DamageSource,
+};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
 
-pub use common::{resources::GameMode, uid::Uid};
+pub use common::{
+    calendar::{CalendarDate, Season},
+    resources::GameMode,
+    uid::Uid,
+};
+pub use vek::{Rgb, Vec3};
 
+#[cfg(not(target_arch = "wasm32"))]
+mod conversions;
 mod errors;
 
 pub use errors::*;
@@ -35,6 +46,56 @@ pub enum Action {
     Print(String),
     PlayerSendMessage(Uid, String),
     KillEntity(Uid),
+    /// Spawn an NPC from an entity config asset (for instance
+    /// `common.entity.wild.peaceful.deer`) at the given position
+    SpawnEntity {
+        entity_config: String,
+        pos: Vec3<f32>,
+    },
+    /// Move an entity to the given position
+    TeleportEntity(Uid, Vec3<f32>),
+    /// Give `amount` of the item asset (for instance
+    /// `common.items.food.apple`) to the entity's inventory
+    GiveItem {
+        entity: Uid,
+        item: String,
+        amount: u32,
+    },
+    /// Remove up to `amount` of the item asset from the entity's inventory
+    RemoveItem {
+        entity: Uid,
+        item: String,
+        amount: u32,
+    },
+    /// Apply a buff to the entity, `None` as duration means the buff lasts
+    /// until it is removed
+    ApplyBuff {
+        entity: Uid,
+        kind: BuffKind,
+        strength: f32,
+        duration: Option<Duration>,
+    },
+    /// Replace the block at the given position. `block` is the name of a
+    /// `BlockKind` (for instance `Rock`)
+    SetBlock {
+        pos: Vec3<i32>,
+        block: String,
+        color: Rgb<u8>,
+    },
+    /// Set the sprite at the given position. `sprite` is the name of a
+    /// `SpriteKind` (for instance `Apple`)
+    SetSprite {
+        pos: Vec3<i32>,
+        sprite: String,
+    },
+    /// Store a value under `key` in the storage of the plugin, the storage is
+    /// kept across server restarts. It is loaded after the `on_load` event.
+    SetStorageValue {
+        key: String,
+        value: Vec<u8>,
+    },
+    /// Remove the value stored under the key from the storage of the plugin
+    RemoveStorageValue(String),
 }
 
 /// The [`Retrieve`] enum represents read of the ECS is sync and blocking.
@@ -68,6 +129,22 @@ pub enum Action {
 pub enum Retrieve {
     GetPlayerName(Uid),
     GetEntityHealth(Uid),
+    GetEntityPosition(Uid),
+    GetEntityBody(Uid),
+    GetEntityInventory(Uid),
+    GetEntitySkillSet(Uid),
+    GetEntityGroup(Uid),
+    GetEntityAlignment(Uid),
+    /// Get all the entities within `radius` blocks of `pos`
+    GetNearbyEntities {
+        pos: Vec3<f32>,
+        radius: f32,
+    },
+    GetBlock(Vec3<i32>),
+    /// Get the value stored under the key in the storage of the plugin
+    GetStorageValue(String),
+    /// Get the current in-game date
+    GetDate,
 }
 
 /// The [`RetrieveResult`] struct is generated while using the `retrieve_action`
@@ -95,6 +172,102 @@ pub enum Retrieve {
 pub enum RetrieveResult {
     GetPlayerName(String),
     GetEntityHealth(Health),
+    GetEntityPosition(Vec3<f32>),
+    GetEntityBody(EntityBody),
+    GetEntityInventory(Vec<ItemStack>),
+    GetEntitySkillSet(SkillSetInfo),
+    /// The members of the entity's group, `None` if the entity isn't in a group
+    GetEntityGroup(Option<Vec<Uid>>),
+    GetEntityAlignment(Option<Alignment>),
+    GetNearbyEntities(Vec<Uid>),
+    GetBlock(BlockInfo),
+    /// The stored value, `None` if nothing is stored under the key
+    GetStorageValue(Option<Vec<u8>>),
+    GetDate(CalendarDate),
+}
+
+/// This struct represent the body of an entity as seen by plugins
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EntityBody {
+    /// The kind of body (for instance `Humanoid` or `QuadrupedSmall`)
+    pub kind: String,
+    pub height: f32,
+    pub mass: f32,
+}
+
+/// This struct represent a stack of items in an inventory
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ItemStack {
+    /// The asset identifier of the item (for instance
+    /// `common.items.food.apple`)
+    pub item: String,
+    pub amount: u32,
+    /// Whether this item is equipped in the loadout
+    pub equipped: bool,
+}
+
+/// This struct represent a skill group of a [`SkillSetInfo`]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SkillGroupInfo {
+    pub kind: String,
+    pub exp: u16,
+    pub available_sp: u16,
+    pub earned_sp: u16,
+}
+
+/// This struct represent the skill set of an entity
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SkillSetInfo {
+    pub skill_groups: Vec<SkillGroupInfo>,
+    /// The unlocked skills with their level
+    pub skills: Vec<(String, Option<u16>)>,
+}
+
+/// This enum represent the alignment of an entity, see
+/// `common::comp::Alignment`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Alignment {
+    Wild,
+    Enemy,
+    Npc,
+    Tame,
+    Owned(Uid),
+    Passive,
+}
+
+/// This struct represent a block of the terrain
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct BlockInfo {
+    /// The name of the `BlockKind` of this block (for instance `Rock`)
+    pub kind: String,
+    pub color: Option<Rgb<u8>>,
+    /// The name of the `SpriteKind` in this block if there is one
+    pub sprite: Option<String>,
+}
+
+/// This enum represent how an entity was killed, see
+/// `common::comp::chat::KillType`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum KillType {
+    Buff(BuffKind),
+    Melee,
+    Projectile,
+    Explosion,
+    Energy,
+    Other,
+}
+
+/// This enum represent what killed an entity, see
+/// `common::comp::chat::KillSource`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum KillSource {
+    Player(Uid, KillType),
+    NonPlayer(String, KillType),
+    NonExistent(KillType),
+    Environment(String),
+    FallDamage,
+    Suicide,
+    Other,
 }
 
 /// This trait is implement by all events and ensure type safety of FFI.
@@ -220,6 +393,170 @@ pub mod event {
         fn get_event_name(&self) -> String { "on_load".to_owned() }
     }
 
+    /// This event is called when a player leaves the server.
+    /// Your event should be named `on_player_leave`
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[event_handler]
+    /// pub fn on_player_leave(leave: PlayerLeaveEvent) {
+    ///     emit_action(Action::Print(format!("{} left", leave.player_name)));
+    /// }
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct PlayerLeaveEvent {
+        pub player_name: String,
+        pub player_id: [u8; 16],
+    }
+
+    impl Event for PlayerLeaveEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_player_leave".to_owned() }
+    }
+
+    /// This event is called when an entity dies.
+    /// Your event should be named `on_entity_death`
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[event_handler]
+    /// pub fn on_entity_death(death: EntityDeathEvent) {
+    ///     if let KillSource::Player(killer, _) = death.cause {
+    ///         emit_action(Action::GiveItem {
+    ///             entity: killer,
+    ///             item: "common.items.utility.coins".to_owned(),
+    ///             amount: 10,
+    ///         });
+    ///     }
+    /// }
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct EntityDeathEvent {
+        pub entity: Uid,
+        pub cause: KillSource,
+    }
+
+    impl Event for EntityDeathEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_entity_death".to_owned() }
+    }
+
+    /// This event is called when an entity takes damage.
+    /// Your event should be named `on_damage`
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct DamageEvent {
+        pub entity: Uid,
+        /// The entity that caused the damage, if any
+        pub attacker: Option<Uid>,
+        pub amount: f32,
+        pub source: Option<DamageSource>,
+    }
+
+    impl Event for DamageEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_damage".to_owned() }
+    }
+
+    /// This event is called when an entity picks up an item from the ground.
+    /// Your event should be named `on_item_pickup`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct ItemPickupEvent {
+        pub entity: Uid,
+        pub item: ItemStack,
+    }
+
+    impl Event for ItemPickupEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_item_pickup".to_owned() }
+    }
+
+    /// This event is called when an entity sends a chat message.
+    /// Your event should be named `on_chat_message`
+    ///
+    /// You can return `Cancel` to prevent the message from being sent or
+    /// `Rewrite` to change its content
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[event_handler]
+    /// pub fn on_chat_message(chat: ChatMessageEvent) -> ChatMessageResult {
+    ///     if chat.message.contains("cheese") {
+    ///         ChatMessageResult::Rewrite(chat.message.replace("cheese", "*****"))
+    ///     } else {
+    ///         ChatMessageResult::None
+    ///     }
+    /// }
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct ChatMessageEvent {
+        pub sender: Uid,
+        pub message: String,
+    }
+
+    impl Event for ChatMessageEvent {
+        type Response = ChatMessageResult;
+
+        fn get_event_name(&self) -> String { "on_chat_message".to_owned() }
+    }
+
+    /// This is the return type of an `on_chat_message` event. See
+    /// [`ChatMessageEvent`]
+    ///
+    /// Variants:
+    ///  - `Cancel` will prevent the message from being sent.
+    ///  - `Rewrite` will replace the content of the message.
+    ///  - `None` will send the message as is.
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub enum ChatMessageResult {
+        Cancel,
+        Rewrite(String),
+        None,
+    }
+
+    impl Default for ChatMessageResult {
+        fn default() -> Self { Self::None }
+    }
+
+    /// This event is called when a block of the terrain changes, whether a
+    /// player, a plugin or the world (like an explosion) changed it.
+    /// Your event should be named `on_block_change`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct BlockChangeEvent {
+        pub pos: Vec3<i32>,
+        pub old_block: BlockInfo,
+        pub new_block: BlockInfo,
+        /// The entity responsible for the change, if any
+        pub entity: Option<Uid>,
+    }
+
+    impl Event for BlockChangeEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_block_change".to_owned() }
+    }
+
+    /// This event is called on every server tick.
+    /// Your event should be named `on_tick`
+    ///
+    /// Keep this handler light, it runs on the main thread
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct TickEvent {
+        /// The time since the start of the server in seconds
+        pub time: f64,
+        /// The duration of the last tick in seconds
+        pub delta_time: f32,
+    }
+
+    impl Event for TickEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_tick".to_owned() }
+    }
+
     // impl Default for PlayerJoinResult {
     //     fn default() -> Self {
     //         Self::None
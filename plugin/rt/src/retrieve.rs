@@ -1,4 +1,7 @@
-use plugin_api::{Health, RetrieveError};
+use plugin_api::{
+    Alignment, BlockInfo, CalendarDate, EntityBody, Health, ItemStack, RetrieveError, SkillSetInfo,
+    Uid, Vec3,
+};
 
 use crate::api::{Retrieve, RetrieveResult};
 
@@ -10,6 +13,32 @@ pub trait GetEntityHealth {
     fn get_entity_health(&self) -> Result<Health, RetrieveError>;
 }
 
+pub trait GetEntityPosition {
+    fn get_entity_position(&self) -> Result<Vec3<f32>, RetrieveError>;
+}
+
+pub trait GetEntityBody {
+    fn get_entity_body(&self) -> Result<EntityBody, RetrieveError>;
+}
+
+pub trait GetEntityInventory {
+    fn get_entity_inventory(&self) -> Result<Vec<ItemStack>, RetrieveError>;
+}
+
+pub trait GetEntitySkillSet {
+    fn get_entity_skill_set(&self) -> Result<SkillSetInfo, RetrieveError>;
+}
+
+pub trait GetEntityGroup {
+    /// Returns the members of the entity's group or `None` if the entity isn't
+    /// in a group
+    fn get_entity_group(&self) -> Result<Option<Vec<Uid>>, RetrieveError>;
+}
+
+pub trait GetEntityAlignment {
+    fn get_entity_alignment(&self) -> Result<Option<Alignment>, RetrieveError>;
+}
+
 impl GetEntityHealth for crate::api::event::Player {
     fn get_entity_health(&self) -> Result<Health, RetrieveError> {
         if let RetrieveResult::GetEntityHealth(e) =
@@ -33,3 +62,100 @@ impl GetPlayerName for crate::api::event::Player {
         }
     }
 }
+
+/// Implement a getter trait for both `Uid` and `Player` from a `Retrieve`
+/// variant and the matching `RetrieveResult` variant
+macro_rules! impl_entity_getter {
+    ($trait:ident, $fn:ident, $variant:ident, $ret:ty) => {
+        impl $trait for Uid {
+            fn $fn(&self) -> Result<$ret, RetrieveError> {
+                if let RetrieveResult::$variant(e) =
+                    crate::retrieve_action(&Retrieve::$variant(*self))?
+                {
+                    Ok(e)
+                } else {
+                    Err(RetrieveError::InvalidType)
+                }
+            }
+        }
+
+        impl $trait for crate::api::event::Player {
+            fn $fn(&self) -> Result<$ret, RetrieveError> { self.id.$fn() }
+        }
+    };
+}
+
+impl_entity_getter!(
+    GetEntityPosition,
+    get_entity_position,
+    GetEntityPosition,
+    Vec3<f32>
+);
+impl_entity_getter!(GetEntityBody, get_entity_body, GetEntityBody, EntityBody);
+impl_entity_getter!(
+    GetEntityInventory,
+    get_entity_inventory,
+    GetEntityInventory,
+    Vec<ItemStack>
+);
+impl_entity_getter!(
+    GetEntitySkillSet,
+    get_entity_skill_set,
+    GetEntitySkillSet,
+    SkillSetInfo
+);
+impl_entity_getter!(
+    GetEntityGroup,
+    get_entity_group,
+    GetEntityGroup,
+    Option<Vec<Uid>>
+);
+impl_entity_getter!(
+    GetEntityAlignment,
+    get_entity_alignment,
+    GetEntityAlignment,
+    Option<Alignment>
+);
+
+/// Get all the entities within `radius` blocks of `pos`
+pub fn get_nearby_entities(pos: Vec3<f32>, radius: f32) -> Result<Vec<Uid>, RetrieveError> {
+    if let RetrieveResult::GetNearbyEntities(e) =
+        crate::retrieve_action(&Retrieve::GetNearbyEntities { pos, radius })?
+    {
+        Ok(e)
+    } else {
+        Err(RetrieveError::InvalidType)
+    }
+}
+
+/// Get the terrain block at `pos`, fails if the chunk containing it isn't
+/// loaded
+pub fn get_block(pos: Vec3<i32>) -> Result<BlockInfo, RetrieveError> {
+    if let RetrieveResult::GetBlock(e) = crate::retrieve_action(&Retrieve::GetBlock(pos))? {
+        Ok(e)
+    } else {
+        Err(RetrieveError::InvalidType)
+    }
+}
+
+/// Get the value stored under `key` in the storage of the plugin, `None` if
+/// nothing is stored under it. Values are set with
+/// [`Action::SetStorageValue`](crate::api::Action::SetStorageValue).
+pub fn get_storage_value(key: &str) -> Result<Option<Vec<u8>>, RetrieveError> {
+    if let RetrieveResult::GetStorageValue(e) =
+        crate::retrieve_action(&Retrieve::GetStorageValue(key.to_owned()))?
+    {
+        Ok(e)
+    } else {
+        Err(RetrieveError::InvalidType)
+    }
+}
+
+/// Get the current in-game date, the season can be used for seasonal content
+pub fn get_date() -> Result<CalendarDate, RetrieveError> {
+    if let RetrieveResult::GetDate(e) = crate::retrieve_action(&Retrieve::GetDate)? {
+        Ok(e)
+    } else {
+        Err(RetrieveError::InvalidType)
+    }
+}
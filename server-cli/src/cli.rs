@@ -1,6 +1,6 @@
-use common::comp;
+use common::{character::CharacterId, comp};
 use server::persistence::SqlLogMode;
-use std::sync::mpsc::Sender;
+use std::{path::PathBuf, sync::mpsc::Sender};
 use structopt::StructOpt;
 use tracing::error;
 
@@ -67,6 +67,36 @@ pub enum Message {
     },
     /// Disconnects all connected clients
     DisconnectAllClients,
+    /// Reads the settings files again, the settings that can't change while
+    /// the server runs are only applied after a restart
+    Reload,
+    /// Shows the last privileged commands executed by players
+    Audit {
+        /// Only show the commands executed by this player
+        username: Option<String>,
+        /// Number of entries to show
+        #[structopt(short, long, default_value = "20")]
+        count: usize,
+    },
+    /// Writes a character to a file that can be imported on another server
+    ExportCharacter {
+        /// ID of the character to export
+        character_id: CharacterId,
+        /// File to write the character to
+        path: PathBuf,
+    },
+    /// Creates a character for a player from an exported file
+    ImportCharacter {
+        /// Name of the player who will own the character
+        username: String,
+        /// File written by export-character
+        path: PathBuf,
+    },
+    /// Unloads a plugin and loads it again from disk
+    ReloadPlugin {
+        /// Name of the plugin to reload
+        name: String,
+    },
 }
 
 #[derive(StructOpt)]
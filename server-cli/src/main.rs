@@ -18,7 +18,7 @@ use crate::{
 use common::{clock::Clock, consts::MIN_RECOMMENDED_TOKIO_THREADS};
 use common_base::span;
 use core::sync::atomic::{AtomicUsize, Ordering};
-use server::{persistence::DatabaseSettings, Event, Input, Server};
+use server::{persistence::DatabaseSettings, settings::SettingsWatcher, Event, Input, Server};
 use std::{
     io,
     sync::{atomic::AtomicBool, mpsc, Arc},
@@ -172,6 +172,7 @@ fn main() -> io::Result<()> {
     );
 
     let mut shutdown_coordinator = ShutdownCoordinator::new(Arc::clone(&sigusr1_signal));
+    let mut settings_watcher = SettingsWatcher::new(&server_data_dir);
 
     // Set up an fps clock
     let mut clock = Clock::new(Duration::from_secs_f64(1.0 / TPS as f64));
@@ -201,6 +202,11 @@ fn main() -> io::Result<()> {
         // Clean up the server after a tick.
         server.cleanup();
 
+        if settings_watcher.poll(&server.settings(), &server.editable_settings()) {
+            info!("Settings files changed, reloading them");
+            server.reload_settings();
+        }
+
         if tick_no.rem_euclid(1000) == 0 {
             trace!(?tick_no, "keepalive")
         }
@@ -246,6 +252,32 @@ fn main() -> io::Result<()> {
                     Message::DisconnectAllClients => {
                         server.disconnect_all_clients();
                     },
+                    Message::Reload => {
+                        server.reload_settings();
+                    },
+                    Message::Audit { username, count } => {
+                        let entries = server.audit_log(username.as_deref(), count);
+                        if entries.is_empty() {
+                            info!("No matching entries in the audit log");
+                        }
+                        for entry in entries {
+                            info!("{}", entry);
+                        }
+                    },
+                    Message::ExportCharacter { character_id, path } => {
+                        server.export_character(character_id, &path);
+                    },
+                    Message::ImportCharacter { username, path } => {
+                        server.import_character(&username, &path);
+                    },
+                    #[cfg(feature = "plugins")]
+                    Message::ReloadPlugin { name } => {
+                        server.reload_plugin(&name);
+                    },
+                    #[cfg(not(feature = "plugins"))]
+                    Message::ReloadPlugin { .. } => {
+                        tracing::warn!("Can't reload plugins, plugins are disabled in this build");
+                    },
                 },
                 Err(mpsc::TryRecvError::Empty) | Err(mpsc::TryRecvError::Disconnected) => {},
             }
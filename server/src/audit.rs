@@ -0,0 +1,225 @@
+//! Append-only log of the privileged commands executed on the server.
+//!
+//! Every command requiring an admin role is recorded with who ran it, on whom
+//! and when. The log is stored in the data directory as one JSON object per
+//! line, entries are only ever appended while the server runs and the
+//! retention limits of [`AuditLogSettings`] are applied when it starts.
+
+use crate::settings::{admin::Role, AuditLogSettings};
+use atomicwrites::{AtomicFile, Error as AtomicError, OverwriteBehavior};
+use authc::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{error, info, warn};
+
+const AUDIT_LOG_FILENAME: &str = "audit.log";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub date: DateTime<Utc>,
+    pub actor: Uuid,
+    pub actor_username: String,
+    /// Role of the actor when the command was executed, commands are also
+    /// recorded when the actor lacked the role needed to run them
+    pub role: Option<Role>,
+    pub command: String,
+    pub args: Vec<String>,
+    /// Differs from the actor when the command was executed through `/sudo`
+    pub target: Option<Uuid>,
+    pub target_username: Option<String>,
+    pub succeeded: bool,
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} ({}, {:?}) /{}",
+            self.date.format("%Y-%m-%d %H:%M:%S"),
+            self.actor_username,
+            self.actor,
+            self.role,
+            self.command,
+        )?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        if let Some(target) = &self.target_username {
+            write!(f, " (as {})", target)?;
+        }
+        if !self.succeeded {
+            write!(f, " [failed]")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    settings: AuditLogSettings,
+    entries: VecDeque<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Load the log from the data directory, dropping the entries that exceed
+    /// the retention limits.
+    pub fn load(data_dir: &Path, settings: AuditLogSettings) -> Self {
+        let path = data_dir.join(AUDIT_LOG_FILENAME);
+        let mut log = Self {
+            path,
+            settings,
+            entries: VecDeque::new(),
+        };
+
+        match File::open(&log.path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    match line
+                        .map_err(|e| e.to_string())
+                        .and_then(|line| serde_json::from_str(&line).map_err(|e| e.to_string()))
+                    {
+                        Ok(entry) => log.entries.push_back(entry),
+                        Err(e) => warn!(?e, "Skipping invalid audit log entry"),
+                    }
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => error!(?e, "Failed to open the audit log at {:?}", log.path),
+        }
+
+        let loaded = log.entries.len();
+        log.apply_retention(Utc::now());
+        if log.entries.len() < loaded {
+            info!(
+                "Removed {} entries from the audit log",
+                loaded - log.entries.len()
+            );
+            if let Err(e) = log.rewrite() {
+                error!(?e, "Failed to rewrite the audit log");
+            }
+        }
+
+        log
+    }
+
+    /// Record an entry, it is kept in memory even if it couldn't be written
+    /// to disk.
+    pub fn push(&mut self, entry: AuditEntry) {
+        if let Err(e) = self.append(&entry) {
+            error!(?e, "Failed to write to the audit log: {}", entry);
+        }
+        let now = entry.date;
+        self.entries.push_back(entry);
+        self.apply_retention(now);
+    }
+
+    /// The last `count` entries, optionally only those of the actor with the
+    /// given username, from the oldest to the newest.
+    pub fn query(&self, actor_username: Option<&str>, count: usize) -> Vec<&AuditEntry> {
+        let mut entries = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| {
+                actor_username.map_or(true, |username| {
+                    entry.actor_username.eq_ignore_ascii_case(username)
+                })
+            })
+            .take(count)
+            .collect::<Vec<_>>();
+        entries.reverse();
+        entries
+    }
+
+    fn apply_retention(&mut self, now: DateTime<Utc>) {
+        if let Some(days) = self.settings.retention_days {
+            let oldest = now - Duration::days(i64::from(days));
+            while self
+                .entries
+                .front()
+                .map_or(false, |entry| entry.date < oldest)
+            {
+                self.entries.pop_front();
+            }
+        }
+        if let Some(max_entries) = self.settings.max_entries {
+            while self.entries.len() > max_entries {
+                self.entries.pop_front();
+            }
+        }
+    }
+
+    fn append(&self, entry: &AuditEntry) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+    }
+
+    fn rewrite(&self) -> io::Result<()> {
+        let mut contents = String::new();
+        for entry in &self.entries {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+        let atomic_file = AtomicFile::new(&self.path, OverwriteBehavior::AllowOverwrite);
+        match atomic_file.write(|file| file.write_all(contents.as_bytes())) {
+            Ok(()) => Ok(()),
+            Err(AtomicError::Internal(err)) | Err(AtomicError::User(err)) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(actor_username: &str, date: DateTime<Utc>) -> AuditEntry {
+        AuditEntry {
+            date,
+            actor: Uuid::default(),
+            actor_username: actor_username.to_owned(),
+            role: Some(Role::Admin),
+            command: "kill_npcs".to_owned(),
+            args: Vec::new(),
+            target: None,
+            target_username: None,
+            succeeded: true,
+        }
+    }
+
+    #[test]
+    fn retention_and_query() {
+        let now = Utc::now();
+        let mut log = AuditLog {
+            path: PathBuf::new(),
+            settings: AuditLogSettings {
+                retention_days: Some(7),
+                max_entries: Some(2),
+            },
+            entries: VecDeque::new(),
+        };
+        log.entries.push_back(entry("old", now - Duration::days(8)));
+        log.entries
+            .push_back(entry("alice", now - Duration::days(2)));
+        log.entries.push_back(entry("bob", now - Duration::days(1)));
+        log.entries.push_back(entry("alice", now));
+        log.apply_retention(now);
+
+        assert_eq!(log.entries.len(), 2);
+        assert_eq!(log.query(None, 10).len(), 2);
+        assert_eq!(log.query(Some("Alice"), 10).len(), 1);
+        assert_eq!(log.query(None, 1)[0].actor_username, "alice");
+    }
+}
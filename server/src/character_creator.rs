@@ -1,6 +1,6 @@
 use crate::persistence::character_updater::CharacterUpdater;
 use common::comp::{
-    inventory::loadout_builder::LoadoutBuilder, Body, Inventory, Item, SkillSet, Stats,
+    inventory::loadout_builder::LoadoutBuilder, Body, Inventory, Item, SkillSet, Stash, Stats,
 };
 use specs::{Entity, WriteExpect};
 
@@ -68,7 +68,15 @@ pub fn create_character(
         entity,
         player_uuid,
         character_alias,
-        (body, stats, skill_set, inventory, waypoint, Vec::new()),
+        (
+            body,
+            stats,
+            skill_set,
+            inventory,
+            waypoint,
+            Vec::new(),
+            Stash::default(),
+        ),
     );
     Ok(())
 }
@@ -2,9 +2,13 @@ use crate::metrics::ChunkGenMetrics;
 #[cfg(not(feature = "worldgen"))]
 use crate::test_world::{IndexOwned, World};
 use common::{
-    generation::ChunkSupplement, resources::TimeOfDay, slowjob::SlowJobPool, terrain::TerrainChunk,
+    calendar::{Calendar, Season},
+    generation::ChunkSupplement,
+    resources::TimeOfDay,
+    slowjob::SlowJobPool,
+    terrain::TerrainChunk,
 };
-use hashbrown::{hash_map::Entry, HashMap};
+use hashbrown::{hash_map::Entry, HashMap, HashSet};
 use specs::Entity as EcsEntity;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -23,6 +27,16 @@ pub struct ChunkGenerator {
     chunk_tx: crossbeam_channel::Sender<ChunkGenResult>,
     chunk_rx: crossbeam_channel::Receiver<ChunkGenResult>,
     pending_chunks: HashMap<Vec2<i32>, Arc<AtomicBool>>,
+    /// Season the loaded chunks were generated in
+    season: Option<Season>,
+    /// Loaded chunks that were changed after they were generated
+    modified_chunks: HashSet<Vec2<i32>>,
+    /// Loaded chunks that are generated again, see
+    /// [`ChunkGenerator::regenerate_chunk`]
+    regenerating_chunks: HashSet<Vec2<i32>>,
+    /// Unmodified loaded chunks that were generated in a past season and wait
+    /// to be generated again
+    outdated_chunks: HashSet<Vec2<i32>>,
     metrics: Arc<ChunkGenMetrics>,
 }
 impl ChunkGenerator {
@@ -33,6 +47,10 @@ impl ChunkGenerator {
             chunk_tx,
             chunk_rx,
             pending_chunks: HashMap::new(),
+            season: None,
+            modified_chunks: HashSet::new(),
+            regenerating_chunks: HashSet::new(),
+            outdated_chunks: HashSet::new(),
             metrics: Arc::new(metrics),
         }
     }
@@ -44,7 +62,7 @@ impl ChunkGenerator {
         slowjob_pool: &SlowJobPool,
         world: Arc<World>,
         index: IndexOwned,
-        time: TimeOfDay,
+        time: (TimeOfDay, Calendar),
     ) {
         let v = if let Entry::Vacant(v) = self.pending_chunks.entry(key) {
             v
@@ -64,6 +82,67 @@ impl ChunkGenerator {
         });
     }
 
+    /// Generates a loaded chunk again, e.g. to show a new season. Unlike a
+    /// newly loaded chunk its supplement must not be spawned again.
+    pub fn regenerate_chunk(
+        &mut self,
+        key: Vec2<i32>,
+        slowjob_pool: &SlowJobPool,
+        world: Arc<World>,
+        index: IndexOwned,
+        time: (TimeOfDay, Calendar),
+    ) {
+        if !self.pending_chunks.contains_key(&key) {
+            self.regenerating_chunks.insert(key);
+            self.generate_chunk(None, key, slowjob_pool, world, index, time);
+        }
+    }
+
+    /// Whether a received chunk was requested by
+    /// [`ChunkGenerator::regenerate_chunk`]
+    pub fn take_regenerated(&mut self, key: Vec2<i32>) -> bool {
+        self.regenerating_chunks.remove(&key)
+    }
+
+    /// Returns whether the loaded chunks were generated in another season
+    /// than `season`, which they are generated in from now on
+    pub fn season_changed(&mut self, season: Season) -> bool {
+        self.season
+            .replace(season)
+            .map_or(false, |generated| generated != season)
+    }
+
+    /// Marks a loaded chunk as changed since it was generated, it won't be
+    /// generated again
+    pub fn mark_modified(&mut self, key: Vec2<i32>) {
+        self.outdated_chunks.remove(&key);
+        self.modified_chunks.insert(key);
+    }
+
+    /// Marks a loaded chunk as generated in a past season, unless it was
+    /// changed since
+    pub fn mark_outdated(&mut self, key: Vec2<i32>) {
+        if !self.modified_chunks.contains(&key) {
+            self.outdated_chunks.insert(key);
+        }
+    }
+
+    /// Takes up to `max` outdated chunks to generate again
+    pub fn take_outdated(&mut self, max: usize) -> Vec<Vec2<i32>> {
+        let keys = self
+            .outdated_chunks
+            .iter()
+            .take(max)
+            .copied()
+            .collect::<Vec<_>>();
+        for key in &keys {
+            self.outdated_chunks.remove(key);
+        }
+        keys
+    }
+
+    pub fn is_modified(&self, key: Vec2<i32>) -> bool { self.modified_chunks.contains(&key) }
+
     pub fn recv_new_chunk(&mut self) -> Option<ChunkGenResult> {
         // Make sure chunk wasn't cancelled and if it was check to see if there are more
         // chunks to receive
@@ -83,6 +162,9 @@ impl ChunkGenerator {
     }
 
     pub fn cancel_if_pending(&mut self, key: Vec2<i32>) {
+        self.modified_chunks.remove(&key);
+        self.regenerating_chunks.remove(&key);
+        self.outdated_chunks.remove(&key);
         if let Some(cancel) = self.pending_chunks.remove(&key) {
             cancel.store(true, Ordering::Relaxed);
             self.metrics.chunks_canceled.inc();
@@ -90,6 +172,8 @@ impl ChunkGenerator {
     }
 
     pub fn cancel_all(&mut self) {
+        self.regenerating_chunks.clear();
+        self.outdated_chunks.clear();
         let metrics = Arc::clone(&self.metrics);
         self.pending_chunks.drain().for_each(|(_, cancel)| {
             cancel.store(true, Ordering::Relaxed);
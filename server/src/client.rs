@@ -106,6 +106,7 @@ impl Client {
                     | ServerGeneral::InventoryUpdate(_, _)
                     | ServerGeneral::SetViewDistance(_)
                     | ServerGeneral::SiteEconomy(_)
+                    | ServerGeneral::WeatherUpdate(_)
                     | ServerGeneral::Outcomes(_)
                     | ServerGeneral::Knockback(_)
                     | ServerGeneral::UpdatePendingTrade(_, _, _)
@@ -125,6 +126,7 @@ impl Client {
                     | ServerGeneral::TimeOfDay(_)
                     | ServerGeneral::EntitySync(_)
                     | ServerGeneral::CompSync(_)
+                    | ServerGeneral::DeltaSync(_)
                     | ServerGeneral::CreateEntity(_)
                     | ServerGeneral::DeleteEntity(_)
                     | ServerGeneral::Disconnect(_)
@@ -179,6 +181,7 @@ impl Client {
                     | ServerGeneral::Outcomes(_)
                     | ServerGeneral::Knockback(_)
                     | ServerGeneral::SiteEconomy(_)
+                    | ServerGeneral::WeatherUpdate(_)
                     | ServerGeneral::UpdatePendingTrade(_, _, _)
                     | ServerGeneral::FinishedTrade(_) => {
                         PreparedMsg::new(2, &g, &self.in_game_stream_params)
@@ -196,6 +199,7 @@ impl Client {
                     | ServerGeneral::TimeOfDay(_)
                     | ServerGeneral::EntitySync(_)
                     | ServerGeneral::CompSync(_)
+                    | ServerGeneral::DeltaSync(_)
                     | ServerGeneral::CreateEntity(_)
                     | ServerGeneral::DeleteEntity(_)
                     | ServerGeneral::Disconnect(_)
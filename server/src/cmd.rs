@@ -1,12 +1,14 @@
 //! # Implementing new commands.
 //! To implement a new command provide a handler function
-//! in [do_command].
+//! in [run_command].
 
 use crate::{
+    audit::{AuditEntry, AuditLog},
     client::Client,
     login_provider::LoginProvider,
     settings::{
-        Ban, BanAction, BanInfo, EditableSetting, SettingError, WhitelistInfo, WhitelistRecord,
+        admin::Role, Ban, BanAction, BanInfo, EditableSetting, Mute, MuteAction, SettingError,
+        WhitelistInfo, WhitelistRecord,
     },
     sys::terrain::NpcData,
     wiring,
@@ -101,6 +103,23 @@ fn do_command(
     target: EcsEntity,
     args: Vec<String>,
     cmd: &ChatCommand,
+) -> CmdResult<()> {
+    // Privileged commands and commands executed on someone else are recorded,
+    // whether they succeed or not.
+    let audit_args = (cmd.needs_role().is_some() || client != target).then(|| args.clone());
+    let result = run_command(server, client, target, args, cmd);
+    if let Some(args) = audit_args {
+        audit_command(server, client, target, args, cmd, result.is_ok());
+    }
+    result
+}
+
+fn run_command(
+    server: &mut Server,
+    client: EcsEntity,
+    target: EcsEntity,
+    args: Vec<String>,
+    cmd: &ChatCommand,
 ) -> CmdResult<()> {
     // Make sure your role is at least high enough to execute this command.
     if cmd.needs_role() > server.entity_admin_role(client) {
@@ -115,6 +134,7 @@ fn do_command(
         ChatCommand::Airship => handle_spawn_airship,
         ChatCommand::Alias => handle_alias,
         ChatCommand::ApplyBuff => handle_apply_buff,
+        ChatCommand::Audit => handle_audit,
         ChatCommand::Ban => handle_ban,
         ChatCommand::BattleMode => handle_battlemode,
         ChatCommand::BattleModeForce => handle_battlemode_force,
@@ -151,6 +171,7 @@ fn do_command(
         ChatCommand::MakeNpc => handle_make_npc,
         ChatCommand::MakeSprite => handle_make_sprite,
         ChatCommand::Motd => handle_motd,
+        ChatCommand::Mute => handle_mute,
         ChatCommand::Object => handle_object,
         ChatCommand::PermitBuild => handle_permit_build,
         ChatCommand::Players => handle_players,
@@ -166,11 +187,13 @@ fn do_command(
         ChatCommand::SkillPoint => handle_skill_point,
         ChatCommand::SkillPreset => handle_skill_preset,
         ChatCommand::Spawn => handle_spawn,
+        ChatCommand::Stash => handle_stash,
         ChatCommand::Sudo => handle_sudo,
         ChatCommand::Tell => handle_tell,
         ChatCommand::Time => handle_time,
         ChatCommand::Tp => handle_tp,
         ChatCommand::Unban => handle_unban,
+        ChatCommand::Unmute => handle_unmute,
         ChatCommand::Version => handle_version,
         ChatCommand::Waypoint => handle_waypoint,
         ChatCommand::Wiring => handle_spawn_wiring,
@@ -182,6 +205,41 @@ fn do_command(
     handler(server, client, target, args, cmd)
 }
 
+fn audit_command(
+    server: &Server,
+    client: EcsEntity,
+    target: EcsEntity,
+    args: Vec<String>,
+    cmd: &ChatCommand,
+    succeeded: bool,
+) {
+    let ecs = server.state.ecs();
+    let players = ecs.read_storage::<comp::Player>();
+    let actor = match players.get(client) {
+        Some(actor) => actor,
+        None => return,
+    };
+    let target = if target != client {
+        players.get(target)
+    } else {
+        None
+    };
+
+    let entry = AuditEntry {
+        date: Utc::now(),
+        actor: actor.uuid(),
+        actor_username: actor.alias.clone(),
+        role: server.entity_admin_role(client).map(Role::from),
+        command: cmd.keyword().to_owned(),
+        args,
+        target: target.map(|target| target.uuid()),
+        target_username: target.map(|target| target.alias.clone()),
+        succeeded,
+    };
+    drop(players);
+    ecs.write_resource::<AuditLog>().push(entry);
+}
+
 // Fallibly get position of entity with the given descriptor (used for error
 // message).
 fn position(server: &Server, entity: EcsEntity, descriptor: &str) -> CmdResult<comp::Pos> {
@@ -2413,7 +2471,7 @@ fn handle_tell(
         } else {
             message_opt.join(" ")
         };
-        server.state.send_chat(mode.new_message(target_uid, msg));
+        server.send_player_chat(mode.new_message(target_uid, msg));
         server.notify_client(target, ServerGeneral::ChatMode(mode));
         Ok(())
     } else {
@@ -2437,8 +2495,14 @@ fn handle_faction(
         insert_or_replace_component(server, target, mode.clone(), "target")?;
         let msg = args.join(" ");
         if !msg.is_empty() {
-            if let Some(uid) = server.state.ecs().read_storage().get(target) {
-                server.state.send_chat(mode.new_message(*uid, msg));
+            let uid = server
+                .state
+                .ecs()
+                .read_storage::<Uid>()
+                .get(target)
+                .copied();
+            if let Some(uid) = uid {
+                server.send_player_chat(mode.new_message(uid, msg));
             }
         }
         server.notify_client(target, ServerGeneral::ChatMode(mode));
@@ -2464,8 +2528,14 @@ fn handle_group(
         insert_or_replace_component(server, target, mode.clone(), "target")?;
         let msg = args.join(" ");
         if !msg.is_empty() {
-            if let Some(uid) = server.state.ecs().read_storage().get(target) {
-                server.state.send_chat(mode.new_message(*uid, msg));
+            let uid = server
+                .state
+                .ecs()
+                .read_storage::<Uid>()
+                .get(target)
+                .copied();
+            if let Some(uid) = uid {
+                server.send_player_chat(mode.new_message(uid, msg));
             }
         }
         server.notify_client(target, ServerGeneral::ChatMode(mode));
@@ -2588,8 +2658,14 @@ fn handle_region(
     insert_or_replace_component(server, target, mode.clone(), "target")?;
     let msg = args.join(" ");
     if !msg.is_empty() {
-        if let Some(uid) = server.state.ecs().read_storage().get(target) {
-            server.state.send_chat(mode.new_message(*uid, msg));
+        let uid = server
+            .state
+            .ecs()
+            .read_storage::<Uid>()
+            .get(target)
+            .copied();
+        if let Some(uid) = uid {
+            server.send_player_chat(mode.new_message(uid, msg));
         }
     }
     server.notify_client(target, ServerGeneral::ChatMode(mode));
@@ -2609,8 +2685,14 @@ fn handle_say(
     insert_or_replace_component(server, target, mode.clone(), "target")?;
     let msg = args.join(" ");
     if !msg.is_empty() {
-        if let Some(uid) = server.state.ecs().read_storage().get(target) {
-            server.state.send_chat(mode.new_message(*uid, msg));
+        let uid = server
+            .state
+            .ecs()
+            .read_storage::<Uid>()
+            .get(target)
+            .copied();
+        if let Some(uid) = uid {
+            server.send_player_chat(mode.new_message(uid, msg));
         }
     }
     server.notify_client(target, ServerGeneral::ChatMode(mode));
@@ -2630,8 +2712,14 @@ fn handle_world(
     insert_or_replace_component(server, target, mode.clone(), "target")?;
     let msg = args.join(" ");
     if !msg.is_empty() {
-        if let Some(uid) = server.state.ecs().read_storage().get(target) {
-            server.state.send_chat(mode.new_message(*uid, msg));
+        let uid = server
+            .state
+            .ecs()
+            .read_storage::<Uid>()
+            .get(target)
+            .copied();
+        if let Some(uid) = uid {
+            server.send_player_chat(mode.new_message(uid, msg));
         }
     }
     server.notify_client(target, ServerGeneral::ChatMode(mode));
@@ -2907,6 +2995,85 @@ fn handle_remove_lights(
     Ok(())
 }
 
+fn handle_stash(
+    server: &mut Server,
+    client: EcsEntity,
+    target: EcsEntity,
+    args: Vec<String>,
+    action: &ChatCommand,
+) -> CmdResult<()> {
+    let (action_name, slot) = parse_args!(args, String, usize);
+    let manip = match (action_name.as_deref(), slot) {
+        (Some("list"), _) => {
+            let ecs = server.state.ecs();
+            let stashes = ecs.read_storage::<comp::Stash>();
+            let stash = stashes.get(target).ok_or("Target has no stash")?;
+            let inventories = ecs.read_storage::<Inventory>();
+            let inventory = inventories.get(target).ok_or("Target has no inventory")?;
+
+            let describe = |(slot, item): (usize, Option<&Item>)| {
+                item.map(|item| format!("\n  {}: {} x{}", slot, item.name(), item.amount()))
+            };
+            let stash_list = stash
+                .slots()
+                .map(Option::as_ref)
+                .enumerate()
+                .filter_map(describe)
+                .collect::<String>();
+            let inventory_list = inventory
+                .slots_with_id()
+                .map(|(_, item)| item.as_ref())
+                .enumerate()
+                .filter_map(describe)
+                .collect::<String>();
+
+            server.notify_client(
+                client,
+                ServerGeneral::server_msg(
+                    ChatType::CommandInfo,
+                    format!(
+                        "Stash:{}\nInventory:{}",
+                        if stash_list.is_empty() {
+                            " empty"
+                        } else {
+                            &stash_list
+                        },
+                        if inventory_list.is_empty() {
+                            " empty"
+                        } else {
+                            &inventory_list
+                        },
+                    ),
+                ),
+            );
+            return Ok(());
+        },
+        (Some("deposit"), Some(slot)) => {
+            let inv_slot = server
+                .state
+                .ecs()
+                .read_storage::<Inventory>()
+                .get(target)
+                .ok_or("Target has no inventory")?
+                .slots_with_id()
+                .nth(slot)
+                .map(|(inv_slot, _)| inv_slot)
+                .ok_or_else(|| format!("There is no inventory slot {}", slot))?;
+            comp::InventoryManip::StashDeposit(inv_slot)
+        },
+        (Some("withdraw"), Some(slot)) => comp::InventoryManip::StashWithdraw(slot),
+        _ => return Err(action.help_string()),
+    };
+
+    // Goes through the usual inventory manipulation, which checks that the
+    // target can currently use their inventory
+    server
+        .state
+        .mut_resource::<EventBus<ServerEvent>>()
+        .emit_now(ServerEvent::InventoryManip(target, manip));
+    Ok(())
+}
+
 fn handle_sudo(
     server: &mut Server,
     client: EcsEntity,
@@ -3166,6 +3333,111 @@ fn handle_ban(
     }
 }
 
+fn handle_mute(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ChatCommand,
+) -> CmdResult<()> {
+    if let (Some(username), overwrite, parse_duration, reason_opt) =
+        parse_args!(args, String, bool, HumanDuration, String)
+    {
+        let reason = reason_opt.unwrap_or_default();
+        let overwrite = overwrite.unwrap_or(false);
+
+        let player_uuid = find_username(server, &username)?;
+
+        let client_uuid = uuid(server, client, "client")?;
+        let client_username = uuid_to_username(server, client, client_uuid)?;
+        let client_role = real_role(server, client_uuid, "client")?;
+
+        let now = Utc::now();
+        let end_date = parse_duration
+            .map(|duration| chrono::Duration::from_std(duration.into()))
+            .transpose()
+            .map_err(|err| format!("Error converting to duration: {}", err))?
+            // On overflow (someone adding some ridiculous timespan), just make the mute infinite.
+            .and_then(|duration| now.checked_add_signed(duration));
+
+        let mute = Mute {
+            reason: reason.clone(),
+            info: BanInfo {
+                performed_by: client_uuid,
+                performed_by_username: client_username,
+                performed_by_role: client_role.into(),
+            },
+            end_date,
+        };
+
+        let edit = server
+            .editable_settings_mut()
+            .banlist
+            .mute_action(
+                server.data_dir().as_ref(),
+                now,
+                player_uuid,
+                username.clone(),
+                MuteAction::Mute(mute),
+                overwrite,
+            )
+            .map(|result| {
+                (
+                    format!("Muted {} with reason: {}", username, reason),
+                    result,
+                )
+            });
+
+        edit_setting_feedback(server, client, edit, || {
+            format!("{} is already muted", username)
+        })?;
+        // Let the player know why their messages aren't sent anymore
+        let ecs = server.state.ecs();
+        if let Ok(target_player) = find_uuid(ecs, player_uuid) {
+            server.notify_client(
+                target_player,
+                ServerGeneral::server_msg(
+                    ChatType::CommandInfo,
+                    format!("You have been muted: {}", reason),
+                ),
+            );
+        }
+        Ok(())
+    } else {
+        Err(action.help_string())
+    }
+}
+
+fn handle_audit(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    _action: &ChatCommand,
+) -> CmdResult<()> {
+    // Both are optional, a leading argument that isn't a number is the username
+    let (count, username) = match args.first().map(|arg| arg.parse::<usize>()) {
+        Some(Ok(count)) => (Some(count), args.get(1).cloned()),
+        _ => (None, args.first().cloned()),
+    };
+    let entries = server.audit_log(username.as_deref(), count.unwrap_or(10));
+
+    let message = if entries.is_empty() {
+        "No matching entries in the audit log".to_owned()
+    } else {
+        entries
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    server.notify_client(
+        client,
+        ServerGeneral::server_msg(ChatType::CommandInfo, message),
+    );
+    Ok(())
+}
+
 fn handle_battlemode(
     server: &mut Server,
     client: EcsEntity,
@@ -3350,6 +3622,47 @@ fn handle_unban(
     }
 }
 
+fn handle_unmute(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ChatCommand,
+) -> CmdResult<()> {
+    if let Some(username) = parse_args!(args, String) {
+        let player_uuid = find_username(server, &username)?;
+
+        let client_uuid = uuid(server, client, "client")?;
+        let client_username = uuid_to_username(server, client, client_uuid)?;
+        let client_role = real_role(server, client_uuid, "client")?;
+
+        let now = Utc::now();
+
+        let unmute = MuteAction::Unmute(BanInfo {
+            performed_by: client_uuid,
+            performed_by_username: client_username,
+            performed_by_role: client_role.into(),
+        });
+
+        let edit = server
+            .editable_settings_mut()
+            .banlist
+            .mute_action(
+                server.data_dir().as_ref(),
+                now,
+                player_uuid,
+                username.clone(),
+                unmute,
+                false,
+            )
+            .map(|result| (format!("{} was successfully unmuted", username), result));
+
+        edit_setting_feedback(server, client, edit, || format!("{} isn't muted", username))
+    } else {
+        Err(action.help_string())
+    }
+}
+
 fn handle_server_physics(
     server: &mut Server,
     client: EcsEntity,
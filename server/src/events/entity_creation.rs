@@ -31,6 +31,10 @@ pub fn handle_initialize_character(
     server.state.initialize_character_data(entity, character_id);
 }
 
+pub fn handle_initialize_spectator(server: &mut Server, entity: EcsEntity) {
+    server.state.initialize_spectator_data(entity);
+}
+
 #[allow(clippy::type_complexity)]
 pub fn handle_loaded_character_data(
     server: &mut Server,
@@ -42,6 +46,7 @@ pub fn handle_loaded_character_data(
         comp::Inventory,
         Option<comp::Waypoint>,
         Vec<(comp::Pet, comp::Body, comp::Stats)>,
+        comp::Stash,
     ),
 ) {
     server
@@ -73,6 +73,18 @@ pub fn handle_health_change(server: &Server, entity: EcsEntity, change: HealthCh
             agent.inbox.push_front(AgentEvent::Hurt);
         }
     }
+
+    #[cfg(feature = "plugins")]
+    if damage > 0.0 {
+        if let Some(uid) = ecs.uid_from_entity(entity) {
+            super::plugin::dispatch_event(&server.state, &plugin_api::event::DamageEvent {
+                entity: uid,
+                attacker: change.by.map(|by| by.uid()),
+                amount: damage,
+                source: change.cause,
+            });
+        }
+    }
 }
 
 pub fn handle_knockback(server: &Server, entity: EcsEntity, impulse: Vec3<f32>) {
@@ -160,35 +172,39 @@ pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: Healt
         }
     }
 
+    let kill_source = match (last_change.cause, last_change.by.map(|x| x.uid())) {
+        (Some(DamageSource::Melee), Some(by)) => get_attacker_name(KillType::Melee, by),
+        (Some(DamageSource::Projectile), Some(by)) => get_attacker_name(KillType::Projectile, by),
+        (Some(DamageSource::Explosion), Some(by)) => get_attacker_name(KillType::Explosion, by),
+        (Some(DamageSource::Energy), Some(by)) => get_attacker_name(KillType::Energy, by),
+        (Some(DamageSource::Buff(buff_kind)), Some(by)) => {
+            get_attacker_name(KillType::Buff(buff_kind), by)
+        },
+        (Some(DamageSource::Other), Some(by)) => get_attacker_name(KillType::Other, by),
+        (Some(DamageSource::Falling), _) => KillSource::FallDamage,
+        // HealthSource::Suicide => KillSource::Suicide,
+        _ => KillSource::Other,
+    };
+
     // Chat message
     // If it was a player that died
     if let Some(_player) = state.ecs().read_storage::<Player>().get(entity) {
         if let Some(uid) = state.ecs().read_storage::<Uid>().get(entity) {
-            let kill_source = match (last_change.cause, last_change.by.map(|x| x.uid())) {
-                (Some(DamageSource::Melee), Some(by)) => get_attacker_name(KillType::Melee, by),
-                (Some(DamageSource::Projectile), Some(by)) => {
-                    get_attacker_name(KillType::Projectile, by)
-                },
-                (Some(DamageSource::Explosion), Some(by)) => {
-                    get_attacker_name(KillType::Explosion, by)
-                },
-                (Some(DamageSource::Energy), Some(by)) => get_attacker_name(KillType::Energy, by),
-                (Some(DamageSource::Buff(buff_kind)), Some(by)) => {
-                    get_attacker_name(KillType::Buff(buff_kind), by)
-                },
-                (Some(DamageSource::Other), Some(by)) => get_attacker_name(KillType::Other, by),
-                (Some(DamageSource::Falling), _) => KillSource::FallDamage,
-                // HealthSource::Suicide => KillSource::Suicide,
-                _ => KillSource::Other,
-            };
-
             state.send_chat(GenericChatMsg {
-                chat_type: comp::ChatType::Kill(kill_source, *uid),
+                chat_type: comp::ChatType::Kill(kill_source.clone(), *uid),
                 message: "".to_string(),
             });
         }
     }
 
+    #[cfg(feature = "plugins")]
+    if let Some(uid) = state.ecs().uid_from_entity(entity) {
+        super::plugin::dispatch_event(state, &plugin_api::event::EntityDeathEvent {
+            entity: uid,
+            cause: kill_source.into(),
+        });
+    }
+
     // Award EXP to damage contributors
     //
     // NOTE: Debug logging is disabled by default for this module - to enable it add
@@ -24,7 +24,7 @@ use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
 
 use crate::{
     client::Client,
-    presence::{Presence, RegionSubscription},
+    presence::{DeltaSync, Presence, RegionSubscription},
     state_ext::StateExt,
     Server,
 };
@@ -230,6 +230,7 @@ pub fn handle_possess(server: &mut Server, possessor_uid: Uid, possesse_uid: Uid
             let mut players = ecs.write_storage::<comp::Player>();
             let mut presence = ecs.write_storage::<Presence>();
             let mut subscriptions = ecs.write_storage::<RegionSubscription>();
+            let mut delta_syncs = ecs.write_storage::<DeltaSync>();
             let mut admins = ecs.write_storage::<comp::Admin>();
             let mut waypoints = ecs.write_storage::<comp::Waypoint>();
             players
@@ -241,6 +242,9 @@ pub fn handle_possess(server: &mut Server, possessor_uid: Uid, possesse_uid: Uid
             subscriptions
                 .remove(possessor)
                 .map(|s| subscriptions.insert(possesse, s).ok()?);
+            delta_syncs
+                .remove(possessor)
+                .map(|d| delta_syncs.insert(possesse, d).ok()?);
             admins
                 .remove(possessor)
                 .map(|a| admins.insert(possesse, a).ok()?);
@@ -385,7 +389,10 @@ pub fn handle_mine_block(
                     .build();
             }
 
-            state.set_block(pos, block.into_vacant());
+            match state.ecs().uid_from_entity(entity) {
+                Some(uid) => state.set_block_by(pos, block.into_vacant(), uid),
+                None => state.set_block(pos, block.into_vacant()),
+            }
             state
                 .ecs()
                 .write_resource::<Vec<Outcome>>()
@@ -151,11 +151,14 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
             );
 
             // Next, we try to equip the picked up item
-            let event = match inventory.try_equip(item).or_else(|returned_item| {
-                // If we couldn't equip it (no empty slot for it or unequippable) then attempt
-                // to add the item to the entity's inventory
-                inventory.pickup_item(returned_item)
-            }) {
+            let event = match inventory
+                .try_equip(item)
+                .map(|()| true)
+                .or_else(|returned_item| {
+                    // If we couldn't equip it (no empty slot for it or unequippable) then attempt
+                    // to add the item to the entity's inventory
+                    inventory.pickup_item(returned_item).map(|()| false)
+                }) {
                 Err(returned_item) => {
                     // Inventory was full, so we need to put back the item (note that we know there
                     // was no old item component for this entity).
@@ -167,7 +170,8 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                     drop(inventories);
                     comp::InventoryUpdate::new(comp::InventoryUpdateEvent::EntityCollectFailed(uid))
                 },
-                Ok(_) => {
+                #[cfg_attr(not(feature = "plugins"), allow(unused_variables))]
+                Ok(equipped) => {
                     // We succeeded in picking up the item, so we may now delete its old entity
                     // entirely.
                     drop(item_storage);
@@ -176,6 +180,17 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                         "We knew item_entity existed since we just successfully removed its Item \
                          component.",
                     );
+                    #[cfg(feature = "plugins")]
+                    if let Some(picker) = state.ecs().uid_from_entity(entity) {
+                        super::plugin::dispatch_event(state, &plugin_api::event::ItemPickupEvent {
+                            entity: picker,
+                            item: plugin_api::ItemStack {
+                                item: item_msg.item_definition_id().to_owned(),
+                                amount: item_msg.amount(),
+                                equipped,
+                            },
+                        });
+                    }
                     let ecs = state.ecs();
                     if let Some(group_id) = ecs.read_storage::<comp::Group>().get(entity) {
                         announce_loot_to_group(group_id, ecs, entity, &item_msg.name);
@@ -683,6 +698,62 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
             inventory.swap_equipped_weapons();
             drop(inventories);
         },
+        comp::InventoryManip::StashDeposit(slot) => {
+            let mut stashes = state.ecs().write_storage::<comp::Stash>();
+            let stash = if let Some(stash) = stashes.get_mut(entity) {
+                stash
+            } else {
+                debug!(?entity, "Can't deposit item, entity doesn't have a stash");
+                return;
+            };
+
+            if let Some(item) = inventory.remove(slot) {
+                if let Err(item) = stash.push(item) {
+                    // Put the item back where it was taken from, the slot is empty
+                    let _ = inventory.insert_at(slot, item);
+                    notify_stash_error(state, entity, "Your stash is full");
+                }
+            }
+            drop(stashes);
+            drop(inventories);
+
+            state
+                .ecs()
+                .write_storage()
+                .insert(
+                    entity,
+                    comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Swapped),
+                )
+                .expect("We know entity exists since we got its inventory.");
+        },
+        comp::InventoryManip::StashWithdraw(slot) => {
+            let mut stashes = state.ecs().write_storage::<comp::Stash>();
+            let stash = if let Some(stash) = stashes.get_mut(entity) {
+                stash
+            } else {
+                debug!(?entity, "Can't withdraw item, entity doesn't have a stash");
+                return;
+            };
+
+            if let Some(item) = stash.remove(slot) {
+                if let Err(item) = inventory.push(item) {
+                    // Put the item back where it was taken from, the slot is empty
+                    let _ = stash.insert_at(slot, item);
+                    notify_stash_error(state, entity, "Your inventory is full");
+                }
+            }
+            drop(stashes);
+            drop(inventories);
+
+            state
+                .ecs()
+                .write_storage()
+                .insert(
+                    entity,
+                    comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Swapped),
+                )
+                .expect("We know entity exists since we got its inventory.");
+        },
     }
 
     // Drop items, Debug items should simply disappear when dropped
@@ -782,6 +853,12 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
     }
 }
 
+fn notify_stash_error(state: &State, entity: EcsEntity, message: &str) {
+    if let Some(client) = state.ecs().read_storage::<Client>().get(entity) {
+        client.send_fallible(ServerGeneral::server_msg(ChatType::Meta, message));
+    }
+}
+
 fn within_pickup_range<S: FindDist<find_dist::Cylinder>>(
     entity_cylinder: Option<find_dist::Cylinder>,
     shape_fn: impl FnOnce() -> Option<S>,
@@ -193,6 +193,8 @@ pub fn handle_invite_response(
 
 pub fn handle_invite_accept(server: &mut Server, entity: specs::Entity) {
     let index = server.index.clone();
+    #[cfg(feature = "worldgen")]
+    let season = server.state.get_date().season;
     let state = server.state_mut();
     if let Some((inviter, kind)) = get_inviter_and_kind(entity, state) {
         handle_invite_answer(state, inviter, entity, InviteAnswer::Accepted, kind);
@@ -252,13 +254,13 @@ pub fn handle_invite_accept(server: &mut Server, entity: specs::Entity) {
                         .and_then(|a| {
                             a.behavior
                                 .trade_site
-                                .and_then(|id| index.get_site_prices(id))
+                                .and_then(|id| index.get_site_prices(id, season))
                         })
                         .or_else(|| {
                             agents.get(entity).and_then(|a| {
                                 a.behavior
                                     .trade_site
-                                    .and_then(|id| index.get_site_prices(id))
+                                    .and_then(|id| index.get_site_prices(id, season))
                             })
                         });
                     #[cfg(not(feature = "worldgen"))]
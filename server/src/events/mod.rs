@@ -1,9 +1,19 @@
-use crate::{events::interaction::handle_tame_pet, state_ext::StateExt, Server};
-use common::event::{EventBus, ServerEvent};
+use crate::{
+    events::interaction::handle_tame_pet, moderation::moderate_chat_msg, state_ext::StateExt,
+    Server,
+};
+#[cfg(feature = "plugins")]
+use common::resources::{DeltaTime, Time};
+use common::{
+    comp::{ChatType, UnresolvedChatMsg},
+    event::{EventBus, ServerEvent},
+};
 use common_base::span;
+use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
 use entity_creation::{
     handle_beam, handle_create_npc, handle_create_ship, handle_create_waypoint,
-    handle_initialize_character, handle_loaded_character_data, handle_shockwave, handle_shoot,
+    handle_initialize_character, handle_initialize_spectator, handle_loaded_character_data,
+    handle_shockwave, handle_shoot,
 };
 use entity_manipulation::{
     handle_aura, handle_bonk, handle_buff, handle_combo_change, handle_delete, handle_destroy,
@@ -23,6 +33,9 @@ use player::{handle_client_disconnect, handle_exit_ingame};
 use specs::{Builder, Entity as EcsEntity, WorldExt};
 use trade::{cancel_trade_for, handle_process_trade_action};
 
+#[cfg(feature = "plugins")]
+pub(crate) use plugin::dispatch_block_changes;
+
 mod entity_creation;
 mod entity_manipulation;
 mod group_manip;
@@ -31,6 +44,7 @@ mod interaction;
 mod inventory_manip;
 mod invite;
 mod player;
+#[cfg(feature = "plugins")] mod plugin;
 mod trade;
 
 pub enum Event {
@@ -54,6 +68,22 @@ impl Server {
         let mut commands = Vec::new();
         let mut chat_messages = Vec::new();
 
+        #[cfg(feature = "plugins")]
+        {
+            plugin::handle_plugin_actions(self);
+            let (time, delta_time) = {
+                let ecs = self.state.ecs();
+                (
+                    ecs.read_resource::<Time>().0,
+                    ecs.read_resource::<DeltaTime>().0,
+                )
+            };
+            plugin::dispatch_event(&self.state, &plugin_api::event::TickEvent {
+                time,
+                delta_time,
+            });
+        }
+
         let events = self
             .state
             .ecs()
@@ -132,6 +162,7 @@ impl Server {
                     entity,
                     character_id,
                 } => handle_initialize_character(self, entity, character_id),
+                ServerEvent::InitSpectator { entity } => handle_initialize_spectator(self, entity),
                 ServerEvent::UpdateCharacterData { entity, components } => {
                     handle_loaded_character_data(self, entity, components);
                 },
@@ -245,9 +276,35 @@ impl Server {
         }
 
         for msg in chat_messages {
-            self.state.send_chat(msg);
+            self.send_player_chat(msg);
         }
 
         frontend_events
     }
+
+    /// Send a chat message written by a player once it went through moderation
+    /// and plugins. If the message is rejected by moderation the player is
+    /// told why.
+    pub fn send_player_chat(&mut self, msg: UnresolvedChatMsg) {
+        if let Err(reason) = moderate_chat_msg(&self.state, &msg) {
+            if let Some(sender) = msg
+                .uid()
+                .and_then(|uid| self.state.ecs().entity_from_uid(uid.into()))
+            {
+                self.notify_client(
+                    sender,
+                    ServerGeneral::server_msg(ChatType::CommandError, reason),
+                );
+            }
+            return;
+        }
+
+        #[cfg(feature = "plugins")]
+        let msg = if let Some(msg) = plugin::filter_chat_message(&self.state, msg) {
+            msg
+        } else {
+            return;
+        };
+        self.state.send_chat(msg);
+    }
 }
@@ -1,8 +1,8 @@
 use super::Event;
 use crate::{
     client::Client, events::trade::cancel_trade_for, metrics::PlayerMetrics,
-    persistence::character_updater::CharacterUpdater, presence::Presence, state_ext::StateExt,
-    BattleModeBuffer, Server,
+    moderation::ChatModeration, persistence::character_updater::CharacterUpdater,
+    presence::Presence, state_ext::StateExt, BattleModeBuffer, Server,
 };
 use common::{
     comp,
@@ -179,6 +179,28 @@ pub fn handle_client_disconnect(
         )));
     }
 
+    #[cfg(feature = "plugins")]
+    {
+        let player_info = state
+            .read_storage::<comp::Player>()
+            .get(entity)
+            .map(|player| (player.alias.clone(), *player.uuid().as_bytes()));
+        if let Some((player_name, player_id)) = player_info {
+            super::plugin::dispatch_event(state, &plugin_api::event::PlayerLeaveEvent {
+                player_name,
+                player_id,
+            });
+        }
+    }
+
+    // Forget the moderation state of the player, like their recent messages
+    if let Some(player) = state.read_storage::<comp::Player>().get(entity) {
+        state
+            .ecs()
+            .write_resource::<ChatModeration>()
+            .forget_player(player.uuid());
+    }
+
     // Sync the player's character data to the database
     if !skip_persistence {
         entity = persist_entity(state, entity);
@@ -249,9 +271,16 @@ fn persist_entity(state: &mut State, entity: EcsEntity) -> EcsEntity {
                     })
                     .collect();
 
+                let stash = state
+                    .ecs()
+                    .read_storage::<comp::Stash>()
+                    .get(entity)
+                    .cloned();
+
                 character_updater.add_pending_logout_update(
                     char_id,
-                    (skill_set.clone(), inventory.clone(), pets, waypoint),
+                    player_info.uuid().to_string(),
+                    (skill_set.clone(), inventory.clone(), pets, waypoint, stash),
                 );
             },
             PresenceKind::Spectator => { /* Do nothing, spectators do not need persisting */ },
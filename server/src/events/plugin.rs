@@ -0,0 +1,336 @@
+use crate::{persistence::character_updater::CharacterUpdater, sys::terrain::NpcData, Server};
+use common::{
+    assets::AssetExt,
+    comp::{
+        self,
+        inventory::item::{tool::AbilityMap, MaterialStatManifest},
+        Buff, BuffChange, BuffData, BuffSource, Inventory, Item, UnresolvedChatMsg,
+    },
+    event::{EventBus, ServerEvent},
+    generation::{EntityConfig, EntityInfo},
+    terrain::{Block, BlockKind, SpriteKind},
+    uid::Uid,
+};
+use common_net::sync::WorldSyncExt;
+use common_state::{plugin::PluginMgr, State, TerrainChanges};
+use plugin_api::{
+    event::{BlockChangeEvent, ChatMessageEvent, ChatMessageResult},
+    Action, Event,
+};
+use specs::{Entity as EcsEntity, WorldExt};
+use std::{convert::TryFrom, str::FromStr};
+use tracing::{error, warn};
+use vek::*;
+
+/// Execute an event on every plugin, errors are logged and the responses of
+/// the plugins that failed are skipped.
+pub fn dispatch_event<T: Event>(state: &State, event: &T) -> Vec<T::Response> {
+    state.execute_plugin_event(event).unwrap_or_else(|e| {
+        error!(
+            ?e,
+            "Failed to execute plugin event {}",
+            event.get_event_name()
+        );
+        Vec::new()
+    })
+}
+
+/// Tell plugins about the blocks changed by the last terrain changes, whatever
+/// changed them.
+pub fn dispatch_block_changes(state: &State) {
+    let events = state
+        .ecs()
+        .read_resource::<TerrainChanges>()
+        .replaced_blocks
+        .iter()
+        .filter_map(|(pos, (old_block, cause))| {
+            let new_block = state.get_block(*pos)?;
+            (new_block != *old_block).then(|| BlockChangeEvent {
+                pos: *pos,
+                old_block: (*old_block).into(),
+                new_block: new_block.into(),
+                entity: *cause,
+            })
+        })
+        .collect::<Vec<_>>();
+    for event in &events {
+        dispatch_event(state, event);
+    }
+}
+
+/// Let plugins cancel or rewrite a chat message before it is sent, returns
+/// `None` if the message was cancelled.
+pub fn filter_chat_message(state: &State, mut msg: UnresolvedChatMsg) -> Option<UnresolvedChatMsg> {
+    let sender = match msg.uid() {
+        Some(sender) => sender,
+        None => return Some(msg),
+    };
+    // A plugin rewriting the message does not affect what the following plugins
+    // see, the last rewrite wins.
+    for result in dispatch_event(state, &ChatMessageEvent {
+        sender,
+        message: msg.message.clone(),
+    }) {
+        match result {
+            ChatMessageResult::Cancel => return None,
+            ChatMessageResult::Rewrite(message) => msg.message = message,
+            ChatMessageResult::None => {},
+        }
+    }
+    Some(msg)
+}
+
+/// Turn the world modifying actions emitted by plugins since the last tick
+/// into server events, or apply them directly when there is no event for
+/// them (the same way the matching chat commands do).
+pub fn handle_plugin_actions(server: &mut Server) {
+    let (actions, storage_writes) = {
+        let plugin_mgr = server.state.ecs().read_resource::<PluginMgr>();
+        (
+            plugin_mgr.take_pending_actions(),
+            plugin_mgr.take_storage_writes(),
+        )
+    };
+
+    server
+        .state
+        .ecs()
+        .write_resource::<CharacterUpdater>()
+        .add_pending_plugin_data_updates(
+            storage_writes
+                .into_iter()
+                .map(|(plugin_name, (key, value))| (plugin_name, key, value)),
+        );
+
+    for action in actions {
+        match action {
+            Action::SpawnEntity { entity_config, pos } => {
+                handle_spawn_entity(server, &entity_config, pos)
+            },
+            Action::TeleportEntity(uid, pos) => {
+                if let Some(entity) = entity_from_uid(server, uid) {
+                    handle_teleport_entity(server, entity, pos);
+                }
+            },
+            Action::GiveItem {
+                entity,
+                item,
+                amount,
+            } => {
+                if let Some(entity) = entity_from_uid(server, entity) {
+                    handle_give_item(server, entity, &item, amount);
+                }
+            },
+            Action::RemoveItem {
+                entity,
+                item,
+                amount,
+            } => {
+                if let Some(entity) = entity_from_uid(server, entity) {
+                    handle_remove_item(server, entity, &item, amount);
+                }
+            },
+            Action::ApplyBuff {
+                entity,
+                kind,
+                strength,
+                duration,
+            } => {
+                if let Some(entity) = entity_from_uid(server, entity) {
+                    server
+                        .state
+                        .ecs()
+                        .read_resource::<EventBus<ServerEvent>>()
+                        .emit_now(ServerEvent::Buff {
+                            entity,
+                            buff_change: BuffChange::Add(Buff::new(
+                                kind,
+                                BuffData::new(strength, duration),
+                                Vec::new(),
+                                BuffSource::Plugin,
+                            )),
+                        });
+                }
+            },
+            Action::SetBlock { pos, block, color } => match BlockKind::from_str(&block) {
+                Ok(kind) => set_block(server, pos, Block::new(kind, color)),
+                Err(_) => warn!("Plugin tried to set an invalid block kind: {}", block),
+            },
+            Action::SetSprite { pos, sprite } => match SpriteKind::try_from(sprite.as_str()) {
+                Ok(sprite) => server
+                    .state
+                    .ecs()
+                    .read_resource::<EventBus<ServerEvent>>()
+                    .emit_now(ServerEvent::CreateSprite { pos, sprite }),
+                Err(_) => warn!("Plugin tried to set an invalid sprite kind: {}", sprite),
+            },
+            // These actions are handled directly by the plugin runtime
+            Action::ServerClose
+            | Action::Print(_)
+            | Action::PlayerSendMessage(..)
+            | Action::KillEntity(_)
+            | Action::SetStorageValue { .. }
+            | Action::RemoveStorageValue(_) => {},
+        }
+    }
+}
+
+fn entity_from_uid(server: &Server, uid: Uid) -> Option<EcsEntity> {
+    let entity = server.state.ecs().entity_from_uid(uid.into());
+    if entity.is_none() {
+        warn!(
+            "Plugin action targets an entity that doesn't exist: {}",
+            uid
+        );
+    }
+    entity
+}
+
+fn handle_spawn_entity(server: &mut Server, entity_config: &str, pos: Vec3<f32>) {
+    let config = match EntityConfig::load(entity_config) {
+        Ok(config) => config.read().clone(),
+        Err(e) => {
+            warn!(
+                ?e,
+                "Plugin tried to spawn an invalid entity config: {}", entity_config
+            );
+            return;
+        },
+    };
+    let entity_info = EntityInfo::at(pos).with_entity_config(config, Some(entity_config));
+
+    let server_eventbus = server.state.ecs().read_resource::<EventBus<ServerEvent>>();
+    match NpcData::from_entity_info(entity_info, &mut rand::thread_rng()) {
+        NpcData::Waypoint(pos) => server_eventbus.emit_now(ServerEvent::CreateWaypoint(pos)),
+        NpcData::Data {
+            pos,
+            stats,
+            skill_set,
+            health,
+            poise,
+            loadout,
+            agent,
+            body,
+            alignment,
+            scale,
+            loot,
+        } => server_eventbus.emit_now(ServerEvent::CreateNpc {
+            pos,
+            stats,
+            skill_set,
+            health,
+            poise,
+            loadout,
+            agent,
+            body,
+            alignment,
+            scale,
+            anchor: None,
+            loot,
+            rtsim_entity: None,
+            projectile: None,
+        }),
+    }
+}
+
+fn handle_teleport_entity(server: &mut Server, entity: EcsEntity, pos: Vec3<f32>) {
+    let ecs = server.state.ecs();
+    if let Some(old_pos) = ecs.write_storage::<comp::Pos>().get_mut(entity) {
+        old_pos.0 = pos;
+        ecs.write_storage()
+            .insert(entity, comp::ForceUpdate)
+            .err()
+            .map(|e| {
+                error!(
+                    ?e,
+                    "Error inserting ForceUpdate component when teleporting entity"
+                )
+            });
+    }
+}
+
+fn handle_give_item(server: &mut Server, entity: EcsEntity, item_name: &str, amount: u32) {
+    let mut item = match Item::new_from_asset(item_name) {
+        Ok(item) => item,
+        Err(e) => {
+            warn!(?e, "Plugin tried to give an invalid item: {}", item_name);
+            return;
+        },
+    };
+
+    let ecs = server.state.ecs();
+    let mut inventories = ecs.write_storage::<Inventory>();
+    let inventory = match inventories.get_mut(entity) {
+        Some(inventory) => inventory,
+        None => return,
+    };
+
+    // NOTE: Deliberately ignores items that couldn't be pushed.
+    if item.set_amount(amount).is_ok() {
+        let _ = inventory.push(item);
+    } else {
+        let ability_map = ecs.read_resource::<AbilityMap>();
+        let msm = ecs.read_resource::<MaterialStatManifest>();
+        for _ in 0..amount {
+            if inventory.push(item.duplicate(&ability_map, &msm)).is_err() {
+                break;
+            }
+        }
+    }
+
+    ecs.write_storage()
+        .insert(
+            entity,
+            comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Given),
+        )
+        .err()
+        .map(|e| error!(?e, "Error inserting InventoryUpdate component"));
+}
+
+fn handle_remove_item(server: &mut Server, entity: EcsEntity, item_name: &str, amount: u32) {
+    let ecs = server.state.ecs();
+    let ability_map = ecs.read_resource::<AbilityMap>();
+    let msm = ecs.read_resource::<MaterialStatManifest>();
+    let mut inventories = ecs.write_storage::<Inventory>();
+    let inventory = match inventories.get_mut(entity) {
+        Some(inventory) => inventory,
+        None => return,
+    };
+
+    let slots = inventory
+        .slots_with_id()
+        .filter(|(_, slot)| {
+            slot.as_ref()
+                .map_or(false, |item| item.item_definition_id() == item_name)
+        })
+        .map(|(slot, _)| slot)
+        .collect::<Vec<_>>();
+
+    let mut remaining = amount;
+    for slot in slots {
+        while remaining > 0 && inventory.take(slot, &ability_map, &msm).is_some() {
+            remaining -= 1;
+        }
+    }
+
+    ecs.write_storage()
+        .insert(
+            entity,
+            comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Removed),
+        )
+        .err()
+        .map(|e| error!(?e, "Error inserting InventoryUpdate component"));
+}
+
+fn set_block(server: &mut Server, pos: Vec3<i32>, block: Block) {
+    server.state.set_block(pos, block);
+    #[cfg(feature = "persistent_world")]
+    if let Some(terrain_persistence) = server
+        .state
+        .ecs()
+        .try_fetch_mut::<crate::TerrainPersistence>()
+        .as_mut()
+    {
+        terrain_persistence.set_block(pos, block);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::Server;
 use common::{
+    calendar::Season,
     comp::{
         agent::{Agent, AgentEvent},
         inventory::{
@@ -32,12 +33,13 @@ fn notify_agent_simple(
 fn notify_agent_prices(
     mut agents: specs::WriteStorage<Agent>,
     index: &IndexOwned,
+    season: Season,
     entity: EcsEntity,
     event: AgentEvent,
 ) {
     if let Some((Some(site_id), agent)) = agents.get_mut(entity).map(|a| (a.behavior.trade_site, a))
     {
-        let prices = index.get_site_prices(site_id);
+        let prices = index.get_site_prices(site_id, season);
         if let AgentEvent::UpdatePendingTrade(boxval) = event {
             // Box<(tid, pend, _, inventories)>) = event {
             agent
@@ -108,6 +110,8 @@ pub fn handle_process_trade_action(
                     let mut entities: [Option<specs::Entity>; 2] = [None, None];
                     let mut inventories: [Option<ReducedInventory>; 2] = [None, None];
                     let mut prices = None;
+                    #[cfg(feature = "worldgen")]
+                    let season = server.state.get_date().season;
                     let agents = server.state.ecs().read_storage::<Agent>();
                     // sadly there is no map and collect on arrays
                     for i in 0..2 {
@@ -128,7 +132,7 @@ pub fn handle_process_trade_action(
                                     agents
                                         .get(e)
                                         .and_then(|a| a.behavior.trade_site)
-                                        .and_then(|id| server.index.get_site_prices(id))
+                                        .and_then(|id| server.index.get_site_prices(id, season))
                                 });
                             }
                         }
@@ -148,6 +152,7 @@ pub fn handle_process_trade_action(
                             notify_agent_prices(
                                 server.state.ecs().write_storage::<Agent>(),
                                 &server.index,
+                                season,
                                 e,
                                 AgentEvent::UpdatePendingTrade(Box::new((
                                     trade_id,
@@ -13,6 +13,7 @@
 #![cfg_attr(not(feature = "worldgen"), feature(const_panic))]
 
 pub mod alias_validator;
+pub mod audit;
 mod character_creator;
 pub mod chunk_generator;
 pub mod client;
@@ -24,9 +25,11 @@ pub mod events;
 pub mod input;
 pub mod login_provider;
 pub mod metrics;
+pub mod moderation;
 pub mod persistence;
 mod pet;
 pub mod presence;
+pub mod query_server;
 pub mod rtsim;
 pub mod settings;
 pub mod state_ext;
@@ -34,6 +37,7 @@ pub mod sys;
 #[cfg(feature = "persistent_world")]
 pub mod terrain_persistence;
 #[cfg(not(feature = "worldgen"))] mod test_world;
+pub mod weather;
 pub mod wiring;
 
 // Reexports
@@ -49,13 +53,16 @@ pub use crate::{
 use crate::terrain_persistence::TerrainPersistence;
 use crate::{
     alias_validator::AliasValidator,
+    audit::{AuditEntry, AuditLog},
     chunk_generator::ChunkGenerator,
     client::Client,
     cmd::ChatCommandExt,
     connection_handler::ConnectionHandler,
     data_dir::DataDir,
     login_provider::LoginProvider,
-    presence::{Presence, RegionSubscription, RepositionOnChunkLoad},
+    moderation::ChatModeration,
+    presence::{DeltaSync, Presence, RegionSubscription, RepositionOnChunkLoad, Spectating},
+    query_server::QueryServer,
     rtsim::RtSim,
     state_ext::StateExt,
     sys::sentinel::{DeletedEntities, TrackedComps},
@@ -64,6 +71,7 @@ use crate::{
 use common::grid::Grid;
 use common::{
     assets::AssetExt,
+    calendar::Calendar,
     character::CharacterId,
     cmd::ChatCommand,
     comp::{self, item::MaterialStatManifest},
@@ -78,14 +86,15 @@ use common::{
 use common_ecs::run_now;
 use common_net::{
     msg::{
-        ClientType, DisconnectReason, ServerGeneral, ServerInfo, ServerInit, ServerMsg, WorldMapMsg,
+        ClientType, DeltaSyncStats, DisconnectReason, ServerGeneral, ServerInfo, ServerInit,
+        ServerMsg, ServerQueryInfo, WorldMapMsg,
     },
     sync::WorldSyncExt,
 };
 use common_state::{BuildAreas, State};
 use common_systems::add_local_systems;
 use metrics::{EcsSystemMetrics, PhysicsMetrics, TickMetrics};
-use network::{ListenAddr, Network, Pid};
+use network::{Identity, ListenAddr, Network, Pid};
 use persistence::{
     character_loader::{CharacterLoader, CharacterLoaderResponseKind},
     character_updater::CharacterUpdater,
@@ -112,12 +121,6 @@ use crate::{
 use hashbrown::HashMap;
 use std::sync::RwLock;
 
-#[cfg(feature = "plugins")]
-use {
-    common::uid::UidAllocator,
-    common_state::plugin::{memory_manager::EcsWorld, PluginMgr},
-};
-
 use common::comp::Anchor;
 #[cfg(feature = "worldgen")]
 use world::{
@@ -138,6 +141,10 @@ impl Default for SpawnPoint {
 // various mechanics working fluidly (i.e: not unloading nearby entities).
 pub const MIN_VD: u32 = 6;
 
+/// File in the data dir holding the key pair that proves the identity of the
+/// server during the network handshake
+const NETWORK_IDENTITY_FILE: &str = "network_identity.pk8";
+
 // Tick count used for throttling network updates
 // Note this doesn't account for dt (so update rate changes with tick rate)
 #[derive(Copy, Clone, Default)]
@@ -191,6 +198,7 @@ pub struct Server {
     map: WorldMapMsg,
 
     connection_handler: ConnectionHandler,
+    query_server: Option<QueryServer>,
 
     runtime: Arc<Runtime>,
 
@@ -227,6 +235,7 @@ impl Server {
         let ecs_system_metrics = EcsSystemMetrics::new(&registry).unwrap();
         let tick_metrics = TickMetrics::new(&registry).unwrap();
         let physics_metrics = PhysicsMetrics::new(&registry).unwrap();
+        let delta_sync_metrics = metrics::DeltaSyncMetrics::new(&registry).unwrap();
         let battlemode_buffer = BattleModeBuffer::default();
 
         let mut state = State::server();
@@ -236,6 +245,9 @@ impl Server {
         state.ecs_mut().insert(DataDir {
             path: data_dir.to_owned(),
         });
+        state
+            .ecs_mut()
+            .insert(AuditLog::load(data_dir, settings.audit_log.clone()));
         state.ecs_mut().insert(EventBus::<ServerEvent>::default());
         state.ecs_mut().insert(Vec::<ChunkRequest>::new());
         state.ecs_mut().insert(LoginProvider::new(
@@ -254,6 +266,8 @@ impl Server {
         state.ecs_mut().insert(ecs_system_metrics);
         state.ecs_mut().insert(tick_metrics);
         state.ecs_mut().insert(physics_metrics);
+        state.ecs_mut().insert(delta_sync_metrics);
+        state.ecs_mut().insert(DeltaSyncStats::default());
         if settings.experimental_terrain_persistence {
             #[cfg(feature = "persistent_world")]
             {
@@ -297,6 +311,23 @@ impl Server {
             Arc::<RwLock<DatabaseSettings>>::clone(&database_settings),
         )?);
 
+        // Load the storage of the plugins
+        #[cfg(feature = "plugins")]
+        match persistence::load_plugin_data(&*database_settings.read().unwrap()) {
+            Ok(entries) => {
+                let plugin_mgr = state
+                    .ecs()
+                    .read_resource::<common_state::plugin::PluginMgr>();
+                for (plugin_name, key, value) in entries {
+                    plugin_mgr.load_storage_value(&plugin_name, key, value);
+                }
+            },
+            Err(e) => error!(?e, "Failed to load the storage of plugins"),
+        }
+        // Only now that their storage is loaded the plugins can be initialized
+        #[cfg(feature = "plugins")]
+        state.init_plugins();
+
         // System schedulers to control execution of systems
         state
             .ecs_mut()
@@ -304,6 +335,7 @@ impl Server {
 
         // Server-only components
         state.ecs_mut().register::<RegionSubscription>();
+        state.ecs_mut().register::<DeltaSync>();
         state.ecs_mut().register::<Client>();
         state.ecs_mut().register::<Presence>();
         state.ecs_mut().register::<wiring::WiringElement>();
@@ -312,6 +344,7 @@ impl Server {
         state.ecs_mut().register::<comp::Pet>();
         state.ecs_mut().register::<login_provider::PendingLogin>();
         state.ecs_mut().register::<RepositionOnChunkLoad>();
+        state.ecs_mut().register::<Spectating>();
 
         //Alias validator
         let banned_words_paths = &settings.banned_words_files;
@@ -343,6 +376,9 @@ impl Server {
         tracing::debug!(?banned_words_count);
         tracing::trace!(?banned_words);
         state.ecs_mut().insert(AliasValidator::new(banned_words));
+        state
+            .ecs_mut()
+            .insert(ChatModeration::new(&settings.moderation));
 
         #[cfg(feature = "worldgen")]
         let (world, index) = World::generate(
@@ -441,6 +477,7 @@ impl Server {
 
         // Set starting time for the server.
         state.ecs_mut().write_resource::<TimeOfDay>().0 = settings.start_time;
+        state.ecs_mut().insert(settings.calendar);
 
         // Register trackers
         sys::sentinel::register_trackers(state.ecs_mut());
@@ -448,6 +485,7 @@ impl Server {
         state.ecs_mut().insert(DeletedEntities::default());
 
         let network = Network::new_with_registry(Pid::new(), &runtime, &registry);
+        network.set_identity(load_network_identity(data_dir));
         let metrics_shutdown = Arc::new(Notify::new());
         let metrics_shutdown_clone = Arc::clone(&metrics_shutdown);
         let addr = settings.metrics_address;
@@ -512,13 +550,20 @@ impl Server {
         #[cfg(not(feature = "worldgen"))]
         rtsim::init(&mut state);
 
-        let this = Self {
+        // Start the weather simulation
+        #[cfg(feature = "worldgen")]
+        weather::init(&mut state, &world);
+        #[cfg(not(feature = "worldgen"))]
+        weather::init(&mut state);
+
+        let mut this = Self {
             state,
             world,
             index,
             map,
 
             connection_handler,
+            query_server: None,
             runtime,
 
             metrics_shutdown,
@@ -526,6 +571,17 @@ impl Server {
             disconnect_all_clients_requested: false,
         };
 
+        if let Some(addr) = settings.query_address {
+            match QueryServer::start(addr, this.get_query_info(), &this.runtime) {
+                Ok(query_server) => this.query_server = Some(query_server),
+                Err(e) => error!(
+                    ?e,
+                    ?addr,
+                    "Failed to start the query server, run without it"
+                ),
+            }
+        }
+
         debug!(?settings, "created veloren server with");
 
         let git_hash = *common::util::GIT_HASH;
@@ -550,6 +606,22 @@ impl Server {
         }
     }
 
+    /// What server browsers get to know about this server, see
+    /// [`QueryServer`]
+    pub fn get_query_info(&self) -> ServerQueryInfo {
+        let settings = self.state.ecs().fetch::<Settings>();
+        let editable_settings = self.state.ecs().fetch::<EditableSettings>();
+        ServerQueryInfo {
+            name: settings.server_name.clone(),
+            description: (&*editable_settings.server_description).clone(),
+            players: self.state.ecs().read_storage::<Client>().join().count() as u32,
+            max_players: settings.max_players as u32,
+            git_hash: common::util::GIT_HASH.to_string(),
+            battle_mode: settings.battle_mode.into(),
+            world_seed: settings.world_seed,
+        }
+    }
+
     /// Get a reference to the server's settings
     pub fn settings(&self) -> impl Deref<Target = Settings> + '_ {
         self.state.ecs().fetch::<Settings>()
@@ -625,6 +697,16 @@ impl Server {
 
         // 3) Handle inputs from clients
         self.handle_new_connections(&mut frontend_events);
+        if self
+            .query_server
+            .as_ref()
+            .map_or(false, QueryServer::needs_update)
+        {
+            let info = self.get_query_info();
+            if let Some(query_server) = &mut self.query_server {
+                query_server.update(info);
+            }
+        }
 
         let before_state_tick = Instant::now();
 
@@ -637,6 +719,7 @@ impl Server {
                 add_local_systems(dispatcher_builder);
                 sys::msg::add_server_systems(dispatcher_builder);
                 sys::add_server_systems(dispatcher_builder);
+                weather::add_server_systems(dispatcher_builder);
                 #[cfg(feature = "worldgen")]
                 rtsim::add_server_systems(dispatcher_builder);
             },
@@ -662,6 +745,8 @@ impl Server {
         // NOTE: apply_terrain_changes sends the *new* value since it is not being
         // synchronized during the tick.
         self.state.apply_terrain_changes();
+        #[cfg(feature = "plugins")]
+        events::dispatch_block_changes(&self.state);
 
         let before_sync = Instant::now();
 
@@ -875,7 +960,10 @@ impl Server {
                             &slow_jobs,
                             Arc::clone(world),
                             index.clone(),
-                            *ecs.read_resource::<TimeOfDay>(),
+                            (
+                                *ecs.read_resource::<TimeOfDay>(),
+                                *ecs.read_resource::<Calendar>(),
+                            ),
                         );
                     });
                 }
@@ -975,6 +1063,7 @@ impl Server {
                          it definitely has a uid",
                     ),
                 time_of_day: *self.state.ecs().read_resource(),
+                calendar: *self.state.ecs().read_resource(),
                 max_group_size: self.settings().max_player_group_size,
                 client_timeout: self.settings().client_timeout,
                 world_map: self.map.clone(),
@@ -1088,7 +1177,10 @@ impl Server {
             &slow_jobs,
             Arc::clone(&self.world),
             self.index.clone(),
-            *ecs.read_resource::<TimeOfDay>(),
+            (
+                *ecs.read_resource::<TimeOfDay>(),
+                *ecs.read_resource::<Calendar>(),
+            ),
         );
     }
 
@@ -1099,15 +1191,7 @@ impl Server {
         } else {
             #[cfg(feature = "plugins")]
             {
-                let plugin_manager = self.state.ecs().read_resource::<PluginMgr>();
-                let ecs_world = EcsWorld {
-                    entities: &self.state.ecs().entities(),
-                    health: self.state.ecs().read_component().into(),
-                    uid: self.state.ecs().read_component().into(),
-                    uid_allocator: &self.state.ecs().read_resource::<UidAllocator>().into(),
-                    player: self.state.ecs().read_component().into(),
-                };
-                let uid = if let Some(uid) = ecs_world.uid.get(entity).copied() {
+                let uid = if let Some(uid) = self.state.ecs().uid_from_entity(entity) {
                     uid
                 } else {
                     self.notify_client(
@@ -1119,14 +1203,13 @@ impl Server {
                     );
                     return;
                 };
-                let rs = plugin_manager.execute_event(
-                    &ecs_world,
-                    &plugin_api::event::ChatCommandEvent {
+                let rs = self
+                    .state
+                    .execute_plugin_event(&plugin_api::event::ChatCommandEvent {
                         command: name.clone(),
                         command_args: args.clone(),
                         player: plugin_api::event::Player { id: uid },
-                    },
-                );
+                    });
                 match rs {
                     Ok(e) => {
                         if e.is_empty() {
@@ -1295,10 +1378,150 @@ impl Server {
         info!("SQL log mode changed to {:?}", sql_log_mode);
     }
 
+    /// Kick the connected players that the banlist doesn't let on the server
+    /// anymore, e.g. after it was edited by hand and reloaded.
+    fn kick_banned_players(&mut self) {
+        let now = chrono::Utc::now();
+        let banned = {
+            let editable_settings = self.editable_settings();
+            let players = self.state.ecs().read_storage::<comp::Player>();
+            let entities = self.state.ecs().entities();
+            (&entities, &players)
+                .join()
+                .filter_map(|(entity, player)| {
+                    login_provider::active_ban(
+                        &editable_settings.admins,
+                        &editable_settings.banlist,
+                        &player.uuid(),
+                        now,
+                    )
+                    .map(|ban| (entity, player.alias.clone(), ban.reason.clone()))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for (entity, alias, reason) in banned {
+            info!(?alias, "Kicking player banned by the reloaded banlist");
+            self.notify_client(
+                entity,
+                ServerGeneral::Disconnect(DisconnectReason::Kicked(reason)),
+            );
+            self.state.mut_resource::<EventBus<ServerEvent>>().emit_now(
+                ServerEvent::ClientDisconnect(entity, comp::DisconnectReason::Kicked),
+            );
+        }
+    }
+
     pub fn disconnect_all_clients(&mut self) {
         info!("Disconnecting all clients due to local console command");
         self.disconnect_all_clients_requested = true;
     }
+
+    /// Read the settings files again. The editable settings are replaced and
+    /// the fields of the server settings that can change while the server runs
+    /// are applied, the other ones are only reported.
+    pub fn reload_settings(&mut self) {
+        let data_dir = self.data_dir().path.clone();
+
+        self.editable_settings_mut().reload(&data_dir);
+        self.kick_banned_players();
+
+        let new_settings = match Settings::try_load(&data_dir) {
+            Some(settings) => settings,
+            None => {
+                warn!("Keeping the current server settings");
+                return;
+            },
+        };
+        let changes = self.settings_mut().apply_reload(new_settings);
+        if changes.applied.contains(&"moderation") {
+            let moderation = ChatModeration::new(&self.settings().moderation);
+            self.state.ecs_mut().insert(moderation);
+        }
+
+        if !changes.applied.is_empty() {
+            info!(
+                "Applied the new server settings: {}",
+                changes.applied.join(", ")
+            );
+        }
+        if !changes.needs_restart.is_empty() {
+            warn!(
+                "These server settings changed but need a restart to take effect: {}",
+                changes.needs_restart.join(", ")
+            );
+        }
+    }
+
+    /// The last `count` entries of the audit log, optionally only those of the
+    /// actor with the given username
+    pub fn audit_log(&self, actor_username: Option<&str>, count: usize) -> Vec<AuditEntry> {
+        self.state
+            .ecs()
+            .read_resource::<AuditLog>()
+            .query(actor_username, count)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Write the last persisted state of a character to a file that can be
+    /// imported on another server
+    ///
+    /// NOTE: Do *not* allow this to be called from any command that doesn't go
+    /// through the CLI!
+    pub fn export_character(&self, character_id: CharacterId, path: &std::path::Path) {
+        match persistence::character_export::export_character(
+            &*self.database_settings.read().unwrap(),
+            character_id,
+            path,
+        ) {
+            Ok(()) => info!("Exported character {} to {}", character_id, path.display()),
+            Err(e) => error!(?e, "Failed to export character {}", character_id),
+        }
+    }
+
+    /// Create a character for a player from a file written by
+    /// [`Server::export_character`]
+    ///
+    /// NOTE: Do *not* allow this to be called from any command that doesn't go
+    /// through the CLI!
+    pub fn import_character(&self, username: &str, path: &std::path::Path) {
+        let uuid = match self
+            .state
+            .ecs()
+            .fetch::<LoginProvider>()
+            .username_to_uuid(username)
+        {
+            Ok(uuid) => uuid,
+            Err(err) => {
+                error!(?err, "Could not find uuid for {}", username);
+                return;
+            },
+        };
+        match persistence::character_export::import_character(
+            &*self.database_settings.read().unwrap(),
+            &uuid.to_string(),
+            path,
+        ) {
+            Ok(character_id) => info!(
+                "Imported {} as character {} of {}",
+                path.display(),
+                character_id,
+                username
+            ),
+            Err(e) => error!(?e, "Failed to import character from {}", path.display()),
+        }
+    }
+
+    /// Unload a plugin and load it again from disk
+    #[cfg(feature = "plugins")]
+    pub fn reload_plugin(&mut self, name: &str) {
+        match self.state.reload_plugin(name) {
+            Ok(()) => info!("Reloaded plugin '{}'", name),
+            Err(e) => error!(?e, "Failed to reload plugin '{}'", name),
+        }
+    }
 }
 
 impl Drop for Server {
@@ -1316,6 +1539,23 @@ impl Drop for Server {
                 info!("Unloading terrain persistence...");
                 terrain_persistence.unload_all()
             });
+
+        // Persist what plugins wrote to their storage since the last batch update
+        #[cfg(feature = "plugins")]
+        {
+            let storage_writes = self
+                .state
+                .ecs()
+                .read_resource::<common_state::plugin::PluginMgr>()
+                .take_storage_writes();
+            let mut character_updater = self.state.ecs().write_resource::<CharacterUpdater>();
+            character_updater.add_pending_plugin_data_updates(
+                storage_writes
+                    .into_iter()
+                    .map(|(plugin_name, (key, value))| (plugin_name, key, value)),
+            );
+            character_updater.batch_update(std::iter::empty());
+        }
     }
 }
 
@@ -1347,6 +1587,52 @@ pub fn handle_edit<T, S: settings::EditableSetting>(
     }
 }
 
+/// Loads the key which proves the identity of this server to clients that
+/// pinned it, a new one is created on the first start
+fn load_network_identity(data_dir: &std::path::Path) -> Option<Identity> {
+    let path = data_dir.join(NETWORK_IDENTITY_FILE);
+    let identity = match std::fs::read(&path) {
+        Ok(pkcs8) => match Identity::from_pkcs8(&pkcs8) {
+            Ok(identity) => identity,
+            Err(()) => {
+                error!(
+                    ?path,
+                    "Invalid network identity, clients can't authenticate the server"
+                );
+                return None;
+            },
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let identity = Identity::generate().ok()?;
+            if let Err(e) = write_private_key(&path, identity.to_pkcs8()) {
+                warn!(
+                    ?e,
+                    ?path,
+                    "Couldn't save the network identity, it changes on the next start"
+                );
+            }
+            identity
+        },
+        Err(e) => {
+            error!(?e, ?path, "Couldn't read the network identity");
+            return None;
+        },
+    };
+    info!("Network identity key is: {}", identity.public_key());
+    Some(identity)
+}
+
+/// Creates the file holding a private key, only readable by its owner
+fn write_private_key(path: &std::path::Path, key: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(key)
+}
+
 /// If successful returns the Some(uuid) of the added admin
 ///
 /// NOTE: Do *not* allow this to be called from any command that doesn't go
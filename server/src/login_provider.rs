@@ -1,6 +1,6 @@
-use crate::settings::{AdminRecord, BanEntry, WhitelistRecord};
+use crate::settings::{AdminRecord, Ban, BanEntry, WhitelistRecord};
 use authc::{AuthClient, AuthClientError, AuthToken, Uuid};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use common::comp::AdminRole;
 use common_net::msg::RegisterError;
 use hashbrown::HashMap;
@@ -30,6 +30,27 @@ fn derive_uuid(username: &str) -> Uuid {
 /// derive Uuid for "singleplayer" is a pub fn
 pub fn derive_singleplayer_uuid() -> Uuid { derive_uuid("singleplayer") }
 
+/// The ban that keeps the player with `uuid` off the server, if any.
+pub fn active_ban<'a>(
+    admins: &HashMap<Uuid, AdminRecord>,
+    banlist: &'a HashMap<Uuid, BanEntry>,
+    uuid: &Uuid,
+    now: DateTime<Utc>,
+) -> Option<&'a Ban> {
+    let ban = banlist
+        .get(uuid)
+        .and_then(|ban_record| ban_record.current.action.ban())?;
+    // Make sure the ban is active, and that we can't override it.
+    //
+    // If we are an admin and our role is at least as high as the role of the
+    // person who banned us, we can override the ban; we negate this to find
+    // people who cannot override it.
+    let exceeds_ban_role = |admin: &AdminRecord| {
+        Into::<AdminRole>::into(admin.role) >= Into::<AdminRole>::into(ban.performed_by_role())
+    };
+    (!ban.is_expired(now) && !admins.get(uuid).map_or(false, exceeds_ban_role)).then(|| ban)
+}
+
 pub struct PendingLogin {
     pending_r: oneshot::Receiver<Result<(String, Uuid), RegisterError>>,
 }
@@ -110,26 +131,11 @@ impl LoginProvider {
         match pending.pending_r.try_recv() {
             Ok(Err(e)) => Some(Err(e)),
             Ok(Ok((username, uuid))) => {
-                let now = Utc::now();
                 // Hardcoded admins can always log in.
                 let admin = admins.get(&uuid);
-                if let Some(ban) = banlist
-                    .get(&uuid)
-                    .and_then(|ban_record| ban_record.current.action.ban())
-                {
-                    // Make sure the ban is active, and that we can't override it.
-                    //
-                    // If we are an admin and our role is at least as high as the role of the
-                    // person who banned us, we can override the ban; we negate this to find
-                    // people who cannot override it.
-                    let exceeds_ban_role = |admin: &AdminRecord| {
-                        Into::<AdminRole>::into(admin.role)
-                            >= Into::<AdminRole>::into(ban.performed_by_role())
-                    };
-                    if !ban.is_expired(now) && !admin.map_or(false, exceeds_ban_role) {
-                        // Pull reason string out of ban record and send a copy of it
-                        return Some(Err(RegisterError::Banned(ban.reason.clone())));
-                    }
+                if let Some(ban) = active_ban(admins, banlist, &uuid, Utc::now()) {
+                    // Pull reason string out of ban record and send a copy of it
+                    return Some(Err(RegisterError::Banned(ban.reason.clone())));
                 }
 
                 // non-admins can only join if the whitelist is empty (everyone can join)
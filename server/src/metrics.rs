@@ -1,3 +1,4 @@
+use common_net::msg::TerrainEncoding;
 use prometheus::{
     Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
     IntGaugeVec, Opts, Registry,
@@ -36,6 +37,14 @@ pub struct NetworkRequestMetrics {
     pub chunks_generation_triggered: IntCounter,
     pub chunks_served_lossy: IntCounter,
     pub chunks_served_lossless: IntCounter,
+    pub chunks_served_encoding: IntCounterVec,
+}
+
+pub struct DeltaSyncMetrics {
+    pub full_updates: IntCounter,
+    pub delta_updates: IntCounter,
+    pub full_bytes: IntCounter,
+    pub sent_bytes: IntCounter,
 }
 
 pub struct ChunkGenMetrics {
@@ -196,18 +205,26 @@ impl NetworkRequestMetrics {
         ))?;
         let chunks_served_lossy = IntCounter::with_opts(Opts::new(
             "chunks_served_lossy",
-            "number of chunks that were sent with lossy compression requested",
+            "number of chunks that were sent with a lossy encoding",
         ))?;
         let chunks_served_lossless = IntCounter::with_opts(Opts::new(
             "chunks_served_lossless",
-            "number of chunks that were sent with lossless compression requested",
+            "number of chunks that were sent with a lossless encoding",
         ))?;
+        let chunks_served_encoding = IntCounterVec::new(
+            Opts::new(
+                "chunks_served_encoding",
+                "number of chunks that were sent per encoding",
+            ),
+            &["encoding"],
+        )?;
 
         registry.register(Box::new(chunks_request_dropped.clone()))?;
         registry.register(Box::new(chunks_served_from_memory.clone()))?;
         registry.register(Box::new(chunks_generation_triggered.clone()))?;
         registry.register(Box::new(chunks_served_lossy.clone()))?;
         registry.register(Box::new(chunks_served_lossless.clone()))?;
+        registry.register(Box::new(chunks_served_encoding.clone()))?;
 
         Ok(Self {
             chunks_request_dropped,
@@ -215,6 +232,52 @@ impl NetworkRequestMetrics {
             chunks_generation_triggered,
             chunks_served_lossy,
             chunks_served_lossless,
+            chunks_served_encoding,
+        })
+    }
+
+    pub fn chunk_served(&self, encoding: TerrainEncoding) {
+        if encoding.is_lossy() {
+            self.chunks_served_lossy.inc();
+        } else {
+            self.chunks_served_lossless.inc();
+        }
+        self.chunks_served_encoding
+            .with_label_values(&[encoding.name()])
+            .inc();
+    }
+}
+
+impl DeltaSyncMetrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let full_updates = IntCounter::with_opts(Opts::new(
+            "delta_sync_full_updates",
+            "number of delta synced components that were sent as a whole",
+        ))?;
+        let delta_updates = IntCounter::with_opts(Opts::new(
+            "delta_sync_delta_updates",
+            "number of delta synced components that were sent as a delta to an acknowledged \
+             baseline",
+        ))?;
+        let full_bytes = IntCounter::with_opts(Opts::new(
+            "delta_sync_full_bytes",
+            "bytes the delta synced components would have taken without delta encoding",
+        ))?;
+        let sent_bytes = IntCounter::with_opts(Opts::new(
+            "delta_sync_sent_bytes",
+            "bytes the delta synced components actually took",
+        ))?;
+
+        registry.register(Box::new(full_updates.clone()))?;
+        registry.register(Box::new(delta_updates.clone()))?;
+        registry.register(Box::new(full_bytes.clone()))?;
+        registry.register(Box::new(sent_bytes.clone()))?;
+
+        Ok(Self {
+            full_updates,
+            delta_updates,
+            full_bytes,
+            sent_bytes,
         })
     }
 }
@@ -0,0 +1,7 @@
+-- Creates the key/value storage of plugins
+CREATE TABLE "plugin_data" (
+      "plugin_name" TEXT NOT NULL,
+      "key" TEXT NOT NULL,
+      "value" BLOB NOT NULL,
+      PRIMARY KEY("plugin_name", "key")
+);
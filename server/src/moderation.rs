@@ -0,0 +1,245 @@
+//! Moderation of the chat messages sent by players.
+//!
+//! Before being sent, every message of a player is checked against their
+//! mute (see [`Banlist::mute`](crate::settings::Banlist::mute)) and then goes
+//! through the [`ChatFilter`]s of the [`ChatModeration`] pipeline. The first
+//! check rejecting the message stops it, and the reason is sent back to the
+//! player.
+
+use crate::settings::{EditableSettings, ModerationSettings};
+use authc::Uuid;
+use chrono::Utc;
+use common::{
+    comp::{self, AdminRole},
+    resources::Time,
+};
+use common_net::sync::WorldSyncExt;
+use common_state::State;
+use hashbrown::HashMap;
+use specs::WorldExt;
+use std::collections::VecDeque;
+
+/// A step of the moderation pipeline.
+pub trait ChatFilter: Send + Sync {
+    /// Check a message sent by `player` at `time` (in seconds, see [`Time`]),
+    /// returning the reason it was rejected for.
+    fn check(&mut self, player: Uuid, message: &str, time: f64) -> Result<(), String>;
+
+    /// Drop what the filter remembers about `player`, called when they
+    /// disconnect.
+    fn forget_player(&mut self, _player: Uuid) {}
+}
+
+/// Limits the number of messages a player can send within a period.
+pub struct RateLimiter {
+    max_messages: usize,
+    period: f64,
+    sent: HashMap<Uuid, VecDeque<f64>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_messages: usize, period: f64) -> Self {
+        Self {
+            max_messages,
+            period,
+            sent: HashMap::new(),
+        }
+    }
+}
+
+impl ChatFilter for RateLimiter {
+    fn check(&mut self, player: Uuid, _message: &str, time: f64) -> Result<(), String> {
+        let sent = self.sent.entry(player).or_default();
+        while sent
+            .front()
+            .map_or(false, |sent_time| time - sent_time >= self.period)
+        {
+            sent.pop_front();
+        }
+
+        if sent.len() >= self.max_messages {
+            Err("You are sending messages too fast, slow down.".to_owned())
+        } else {
+            sent.push_back(time);
+            Ok(())
+        }
+    }
+
+    fn forget_player(&mut self, player: Uuid) { self.sent.remove(&player); }
+}
+
+/// Rejects a message once a player sent it too many times in a row, the count
+/// is reset when the player sends another message or stops repeating it for a
+/// while.
+pub struct SpamFilter {
+    max_repeats: usize,
+    period: f64,
+    /// The last message of each player, how many times it was repeated and
+    /// when it was last sent
+    last_messages: HashMap<Uuid, (String, usize, f64)>,
+}
+
+impl SpamFilter {
+    pub fn new(max_repeats: usize, period: f64) -> Self {
+        Self {
+            max_repeats,
+            period,
+            last_messages: HashMap::new(),
+        }
+    }
+}
+
+impl ChatFilter for SpamFilter {
+    fn check(&mut self, player: Uuid, message: &str, time: f64) -> Result<(), String> {
+        // Ignore differences that don't change what the other players read
+        let message = message.trim().to_lowercase();
+        match self.last_messages.get_mut(&player) {
+            Some((last_message, repeats, last_time))
+                if *last_message == message && time - *last_time < self.period =>
+            {
+                *repeats += 1;
+                *last_time = time;
+                if *repeats > self.max_repeats {
+                    return Err("Please don't repeat the same message.".to_owned());
+                }
+            },
+            _ => {
+                self.last_messages.insert(player, (message, 1, time));
+            },
+        }
+        Ok(())
+    }
+
+    fn forget_player(&mut self, player: Uuid) { self.last_messages.remove(&player); }
+}
+
+/// The filters every chat message sent by a non-admin player goes through.
+#[derive(Default)]
+pub struct ChatModeration {
+    filters: Vec<Box<dyn ChatFilter>>,
+}
+
+impl ChatModeration {
+    pub fn new(settings: &ModerationSettings) -> Self {
+        let mut moderation = Self::default();
+        if settings.rate_limit_messages > 0 {
+            moderation.add_filter(RateLimiter::new(
+                settings.rate_limit_messages as usize,
+                settings.rate_limit_period.as_secs_f64(),
+            ));
+        }
+        if settings.max_repeated_messages > 0 {
+            moderation.add_filter(SpamFilter::new(
+                settings.max_repeated_messages as usize,
+                settings.repeated_message_period.as_secs_f64(),
+            ));
+        }
+        moderation
+    }
+
+    /// Add a filter at the end of the pipeline.
+    pub fn add_filter(&mut self, filter: impl ChatFilter + 'static) {
+        self.filters.push(Box::new(filter));
+    }
+
+    pub fn check(&mut self, player: Uuid, message: &str, time: f64) -> Result<(), String> {
+        self.filters
+            .iter_mut()
+            .try_for_each(|filter| filter.check(player, message, time))
+    }
+
+    pub fn forget_player(&mut self, player: Uuid) {
+        self.filters
+            .iter_mut()
+            .for_each(|filter| filter.forget_player(player));
+    }
+}
+
+/// Run a chat message through the moderation pipeline, returning the reason it
+/// was rejected for. Messages that weren't sent by a player are always
+/// accepted.
+pub fn moderate_chat_msg(state: &State, msg: &comp::UnresolvedChatMsg) -> Result<(), String> {
+    let ecs = state.ecs();
+    let sender = match msg.uid().and_then(|uid| ecs.entity_from_uid(uid.into())) {
+        Some(sender) => sender,
+        None => return Ok(()),
+    };
+    let player_uuid = match ecs.read_storage::<comp::Player>().get(sender) {
+        Some(player) => player.uuid(),
+        None => return Ok(()),
+    };
+    let admin_role = ecs
+        .read_storage::<comp::Admin>()
+        .get(sender)
+        .map(|admin| admin.0);
+
+    // Like bans, a mute can be overridden by admins whose role is at least the
+    // role of the person who muted them.
+    if let Some(record) = ecs
+        .read_resource::<EditableSettings>()
+        .banlist
+        .mute(&player_uuid, Utc::now())
+    {
+        if admin_role.map_or(true, |role| {
+            role < AdminRole::from(record.mute.info.performed_by_role)
+        }) {
+            return Err(match record.mute.end_date {
+                Some(end_date) => format!(
+                    "You are muted until {} UTC: {}",
+                    end_date.format("%Y-%m-%d %H:%M:%S"),
+                    record.mute.reason
+                ),
+                None => format!("You are muted: {}", record.mute.reason),
+            });
+        }
+    }
+
+    if admin_role.is_some() {
+        return Ok(());
+    }
+
+    let time = ecs.read_resource::<Time>().0;
+    ecs.write_resource::<ChatModeration>()
+        .check(player_uuid, &msg.message, time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_recovers_after_period() {
+        let player = Uuid::default();
+        let mut limiter = RateLimiter::new(2, 5.0);
+
+        assert!(limiter.check(player, "a", 0.0).is_ok());
+        assert!(limiter.check(player, "b", 1.0).is_ok());
+        assert!(limiter.check(player, "c", 2.0).is_err());
+        assert!(limiter.check(player, "d", 5.5).is_ok());
+    }
+
+    #[test]
+    fn spam_filter_counts_repeats() {
+        let player = Uuid::default();
+        let mut filter = SpamFilter::new(2, 30.0);
+
+        assert!(filter.check(player, "hello", 0.0).is_ok());
+        assert!(filter.check(player, " HELLO", 1.0).is_ok());
+        assert!(filter.check(player, "hello", 2.0).is_err());
+        assert!(filter.check(player, "bye", 3.0).is_ok());
+        assert!(filter.check(player, "bye", 40.0).is_ok());
+    }
+
+    #[test]
+    fn forget_disconnected_player() {
+        let player = Uuid::default();
+        let mut moderation = ChatModeration::default();
+        moderation.add_filter(RateLimiter::new(1, 5.0));
+        moderation.add_filter(SpamFilter::new(1, 30.0));
+
+        assert!(moderation.check(player, "a", 0.0).is_ok());
+        assert!(moderation.check(player, "a", 1.0).is_err());
+        moderation.forget_player(player);
+        assert!(moderation.check(player, "a", 2.0).is_ok());
+    }
+}
@@ -16,8 +16,13 @@ use crate::{
             convert_character_from_database, convert_inventory_from_database_items,
             convert_items_to_database_items, convert_loadout_from_database_items,
             convert_skill_groups_to_database, convert_skill_set_from_database,
-            convert_skills_to_database, convert_stats_from_database,
-            convert_waypoint_from_database_json, convert_waypoint_to_database_json,
+            convert_skills_to_database, convert_stash_from_database_items,
+            convert_stash_to_database_items, convert_stats_from_database,
+            convert_waypoint_from_database_json, convert_waypoint_to_database_json, ItemModelPair,
+        },
+        character_export::{
+            CharacterExport, ExportedItem, CHARACTER_EXPORT_VERSION, INVENTORY_CONTAINER_ID,
+            LOADOUT_CONTAINER_ID,
         },
         character_loader::{CharacterCreationResult, CharacterDataResult, CharacterListResult},
         character_updater::PetPersistenceData,
@@ -27,6 +32,7 @@ use crate::{
 };
 use common::character::{CharacterId, CharacterItem, MAX_CHARACTERS_PER_PLAYER};
 use core::ops::Range;
+use hashbrown::HashMap;
 use rusqlite::{types::Value, Connection, ToSql, Transaction, NO_PARAMS};
 use std::{num::NonZeroU64, rc::Rc};
 use tracing::{debug, error, trace, warn};
@@ -41,6 +47,7 @@ pub(crate) type EntityId = i64;
 const CHARACTER_PSEUDO_CONTAINER_DEF_ID: &str = "veloren.core.pseudo_containers.character";
 const INVENTORY_PSEUDO_CONTAINER_DEF_ID: &str = "veloren.core.pseudo_containers.inventory";
 const LOADOUT_PSEUDO_CONTAINER_DEF_ID: &str = "veloren.core.pseudo_containers.loadout";
+const STASH_PSEUDO_CONTAINER_DEF_ID: &str = "veloren.core.pseudo_containers.stash";
 const INVENTORY_PSEUDO_CONTAINER_POSITION: &str = "inventory";
 const LOADOUT_PSEUDO_CONTAINER_POSITION: &str = "loadout";
 const WORLD_PSEUDO_CONTAINER_ID: EntityId = 1;
@@ -115,6 +122,16 @@ pub fn load_character_data(
     let inventory_items = load_items(connection, character_containers.inventory_container_id)?;
     let loadout_items = load_items(connection, character_containers.loadout_container_id)?;
 
+    // The stash is shared by all the characters of the account, it only exists
+    // once something was stored in it
+    let stash = match get_stash_container_id(connection, &requesting_player_uuid)? {
+        Some(stash_container_id) => convert_stash_from_database_items(
+            stash_container_id,
+            &load_items(connection, stash_container_id)?,
+        )?,
+        None => comp::Stash::default(),
+    };
+
     let mut stmt = connection.prepare_cached(
         "
         SELECT  c.character_id,
@@ -263,6 +280,7 @@ pub fn load_character_data(
         )?,
         char_waypoint,
         pets,
+        stash,
     ))
 }
 
@@ -348,7 +366,7 @@ pub fn create_character(
 ) -> CharacterCreationResult {
     check_character_limit(uuid, transactionn)?;
 
-    let (body, _stats, skill_set, inventory, waypoint, _) = persisted_components;
+    let (body, _stats, skill_set, inventory, waypoint, _, _) = persisted_components;
 
     // Fetch new entity IDs for character, inventory and loadout
     let mut new_entity_ids = get_new_entity_ids(transactionn, |next_id| next_id + 3)?;
@@ -497,6 +515,212 @@ pub fn create_character(
     load_character_list(uuid, transactionn).map(|list| (character_id, list))
 }
 
+/// Converts the persisted data of a character to its portable form, see
+/// [`character_export`](super::character_export).
+pub fn export_character(
+    char_id: CharacterId,
+    connection: &Connection,
+) -> Result<CharacterExport, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  player_uuid
+        FROM    character
+        WHERE   character_id = ?1",
+    )?;
+
+    #[allow(clippy::needless_question_mark)]
+    let player_uuid: String = stmt.query_row(&[char_id], |row| Ok(row.get(0)?))?;
+    drop(stmt);
+
+    let (body, stats, skill_set, inventory, waypoint, _, _) =
+        load_character_data(player_uuid, char_id, connection)?;
+    let (body_variant, body_data) = convert_body_to_database_json(&body)?;
+
+    // Give the items IDs that are only meaningful within the file, starting
+    // after the IDs of the containers
+    let mut next_id = LOADOUT_CONTAINER_ID + 1;
+    let items = convert_items_to_database_items(
+        LOADOUT_CONTAINER_ID,
+        &inventory,
+        INVENTORY_CONTAINER_ID,
+        &mut next_id,
+    );
+    // Items are sorted by depth, so the container of each parent is known
+    // before its components are reached
+    let mut item_containers = HashMap::new();
+    let (mut inventory_items, mut loadout_items) = (Vec::new(), Vec::new());
+    for item in items {
+        let container = item_containers
+            .get(&item.model.parent_container_item_id)
+            .copied()
+            .unwrap_or(item.model.parent_container_item_id);
+        item_containers.insert(item.model.item_id, container);
+        if container == LOADOUT_CONTAINER_ID {
+            loadout_items.push(ExportedItem::from(item.model));
+        } else {
+            inventory_items.push(ExportedItem::from(item.model));
+        }
+    }
+
+    Ok(CharacterExport {
+        version: CHARACTER_EXPORT_VERSION,
+        alias: stats.name,
+        body_variant: body_variant.to_owned(),
+        body_data,
+        waypoint: convert_waypoint_to_database_json(waypoint),
+        skill_groups: convert_skill_groups_to_database(char_id, skill_set.skill_groups)
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        skills: convert_skills_to_database(char_id, skill_set.skills)
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        inventory: inventory_items,
+        loadout: loadout_items,
+    })
+}
+
+/// Creates a character for the player from its portable form, returning the
+/// ID of the new character.
+pub fn import_character(
+    uuid: &str,
+    export: &CharacterExport,
+    transaction: &mut Transaction,
+) -> Result<CharacterId, PersistenceError> {
+    // Reject the items that don't exist on this server before anything else,
+    // to report all of them at once
+    let mut unknown_items = export
+        .inventory
+        .iter()
+        .chain(export.loadout.iter())
+        .map(|item| item.item_definition_id.as_str())
+        .filter(|item_definition_id| comp::Item::new_from_asset(item_definition_id).is_err())
+        .collect::<Vec<_>>();
+    if !unknown_items.is_empty() {
+        unknown_items.sort_unstable();
+        unknown_items.dedup();
+        return Err(PersistenceError::AssetError(format!(
+            "Unknown item definitions: {}",
+            unknown_items.join(", ")
+        )));
+    }
+
+    let inventory_items = export
+        .inventory
+        .iter()
+        .map(ExportedItem::to_model)
+        .collect::<Vec<_>>();
+    let loadout_items = export
+        .loadout
+        .iter()
+        .map(ExportedItem::to_model)
+        .collect::<Vec<_>>();
+    // Only done to validate the layout of the items, they are stored as they
+    // are in the file
+    convert_inventory_from_database_items(
+        INVENTORY_CONTAINER_ID,
+        &inventory_items,
+        LOADOUT_CONTAINER_ID,
+        &loadout_items,
+    )?;
+
+    let body = convert_body_from_database(&export.body_variant, &export.body_data)?;
+    let skill_set = convert_skill_set_from_database(
+        &export
+            .skills
+            .iter()
+            .map(|skill| skill.to_model(0))
+            .collect::<Vec<_>>(),
+        &export
+            .skill_groups
+            .iter()
+            .map(|skill_group| skill_group.to_model(0))
+            .collect::<Vec<_>>(),
+    );
+    let waypoint = export
+        .waypoint
+        .as_deref()
+        .map(convert_waypoint_from_database_json)
+        .transpose()?;
+
+    let (character_id, _) = create_character(
+        uuid,
+        &export.alias,
+        (
+            body,
+            convert_stats_from_database(export.alias.clone()),
+            skill_set.clone(),
+            Inventory::new_empty(),
+            waypoint,
+            Vec::new(),
+            comp::Stash::default(),
+        ),
+        transaction,
+    )?;
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT INTO skill (entity_id,
+                           skill,
+                           level)
+        VALUES (?1, ?2, ?3)",
+    )?;
+
+    for skill in convert_skills_to_database(character_id, skill_set.skills) {
+        stmt.execute(&[&skill.entity_id as &dyn ToSql, &skill.skill, &skill.level])?;
+    }
+    drop(stmt);
+
+    // Give the items of the file new entity IDs and move them to the
+    // containers of the new character
+    let pseudo_containers = get_pseudo_containers(transaction, character_id)?;
+    let item_count = (inventory_items.len() + loadout_items.len()) as i64;
+    let mut new_ids = get_new_entity_ids(transaction, |next_id| next_id + item_count)?;
+    let mut item_ids = HashMap::new();
+    item_ids.insert(
+        INVENTORY_CONTAINER_ID,
+        pseudo_containers.inventory_container_id,
+    );
+    item_ids.insert(LOADOUT_CONTAINER_ID, pseudo_containers.loadout_container_id);
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT INTO item (item_id,
+                          parent_container_item_id,
+                          item_definition_id,
+                          stack_size,
+                          position)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+
+    for item in inventory_items.iter().chain(loadout_items.iter()) {
+        let parent_id = *item_ids
+            .get(&item.parent_container_item_id)
+            .ok_or_else(|| {
+                PersistenceError::ConversionError(format!(
+                    "Couldn't find parent item {} before item {}",
+                    item.parent_container_item_id, item.item_id
+                ))
+            })?;
+        let item_id = new_ids
+            .next()
+            .expect("We reserved an entity ID for every item.");
+        item_ids.insert(item.item_id, item_id);
+
+        stmt.execute(&[
+            &item_id as &dyn ToSql,
+            &parent_id,
+            &item.item_definition_id,
+            &item.stack_size,
+            &item.position,
+        ])?;
+    }
+    drop(stmt);
+
+    Ok(character_id)
+}
+
 /// Delete a character. Returns the updated character list.
 pub fn delete_character(
     requesting_player_uuid: &str,
@@ -736,6 +960,93 @@ fn get_pseudo_container_id(
     }
 }
 
+/// Fetches the ID of the pseudo-container holding the stash of an account, if
+/// it was created yet
+fn get_stash_container_id(
+    connection: &Connection,
+    player_uuid: &str,
+) -> Result<Option<EntityId>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  stash_container_id
+        FROM    stash
+        WHERE   player_uuid = ?1",
+    )?;
+
+    #[allow(clippy::needless_question_mark)]
+    match stmt.query_row(&[player_uuid], |row| Ok(row.get(0)?)) {
+        Ok(id) => Ok(Some(id)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(DatabaseError(e)),
+    }
+}
+
+/// Stores the stash of the account owning the character, creating its
+/// pseudo-container the first time it is stored.
+fn update_stash(
+    char_id: CharacterId,
+    stash: &comp::Stash,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        SELECT  player_uuid
+        FROM    character
+        WHERE   character_id = ?1",
+    )?;
+
+    #[allow(clippy::needless_question_mark)]
+    let player_uuid: String = stmt.query_row(&[char_id], |row| Ok(row.get(0)?))?;
+    drop(stmt);
+
+    let stash_container_id = match get_stash_container_id(transaction, &player_uuid)? {
+        Some(stash_container_id) => stash_container_id,
+        None => {
+            let stash_container_id = get_new_entity_ids(transaction, |next_id| next_id + 1)?.start;
+
+            let mut stmt = transaction.prepare_cached(
+                "
+                INSERT INTO item (item_id,
+                                  parent_container_item_id,
+                                  item_definition_id,
+                                  stack_size,
+                                  position)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+
+            stmt.execute(&[
+                &stash_container_id as &dyn ToSql,
+                &WORLD_PSEUDO_CONTAINER_ID,
+                &STASH_PSEUDO_CONTAINER_DEF_ID,
+                &1,
+                &player_uuid,
+            ])?;
+            drop(stmt);
+
+            let mut stmt = transaction.prepare_cached(
+                "
+                INSERT INTO stash (player_uuid,
+                                   stash_container_id)
+                VALUES (?1, ?2)",
+            )?;
+
+            stmt.execute(&[&player_uuid as &dyn ToSql, &stash_container_id])?;
+            drop(stmt);
+
+            stash_container_id
+        },
+    };
+
+    let mut upserts = Vec::new();
+    get_new_entity_ids(transaction, |mut next_id| {
+        upserts = convert_stash_to_database_items(stash_container_id, stash, &mut next_id);
+        next_id
+    })?;
+
+    trace!("Updating stash of player {}", player_uuid);
+    replace_container_items(&[stash_container_id], upserts, transaction)
+}
+
 /// Stores new pets in the database, and removes pets from the database that the
 /// player no longer has. Currently there are no actual updates to pet data
 /// since we don't store any updatable data about pets in the database.
@@ -859,43 +1170,20 @@ fn delete_pets(
 
     Ok(())
 }
-pub fn update(
-    char_id: CharacterId,
-    char_skill_set: comp::SkillSet,
-    inventory: comp::Inventory,
-    pets: Vec<PetPersistenceData>,
-    char_waypoint: Option<comp::Waypoint>,
+/// Replaces the items stored in the given containers (and in the items they
+/// contain) with `upserts`, which must hold every item that is kept.
+fn replace_container_items(
+    containers: &[EntityId],
+    upserts: Vec<ItemModelPair>,
     transaction: &mut Transaction,
 ) -> Result<(), PersistenceError> {
-    // Run pet persistence
-    update_pets(char_id, pets, transaction)?;
-
-    let pseudo_containers = get_pseudo_containers(transaction, char_id)?;
-    let mut upserts = Vec::new();
-    // First, get all the entity IDs for any new items, and identify which
-    // slots to upsert and which ones to delete.
-    get_new_entity_ids(transaction, |mut next_id| {
-        let upserts_ = convert_items_to_database_items(
-            pseudo_containers.loadout_container_id,
-            &inventory,
-            pseudo_containers.inventory_container_id,
-            &mut next_id,
-        );
-        upserts = upserts_;
-        next_id
-    })?;
-
-    // Next, delete any slots we aren't upserting.
-    trace!("Deleting items for character_id {}", char_id);
-    let mut existing_item_ids: Vec<_> = vec![
-        Value::from(pseudo_containers.inventory_container_id),
-        Value::from(pseudo_containers.loadout_container_id),
-    ];
-    for it in load_items(transaction, pseudo_containers.inventory_container_id)? {
-        existing_item_ids.push(Value::from(it.item_id));
-    }
-    for it in load_items(transaction, pseudo_containers.loadout_container_id)? {
-        existing_item_ids.push(Value::from(it.item_id));
+    // First, delete any slots we aren't upserting.
+    let mut existing_item_ids = Vec::new();
+    for container_id in containers {
+        existing_item_ids.push(Value::from(*container_id));
+        for it in load_items(transaction, *container_id)? {
+            existing_item_ids.push(Value::from(it.item_id));
+        }
     }
 
     let non_upserted_items = upserts
@@ -927,11 +1215,7 @@ pub fn update(
                 (model_pair.model, model_pair.comp)
             })
             .unzip();
-        trace!(
-            "Upserting items {:?} for character_id {}",
-            upserted_items,
-            char_id
-        );
+        trace!("Upserting items {:?}", upserted_items);
 
         // When moving inventory items around, foreign key constraints on
         // `parent_container_item_id` can be temporarily violated by one
@@ -964,6 +1248,46 @@ pub fn update(
         }
     }
 
+    Ok(())
+}
+
+pub fn update(
+    char_id: CharacterId,
+    char_skill_set: comp::SkillSet,
+    inventory: comp::Inventory,
+    pets: Vec<PetPersistenceData>,
+    char_waypoint: Option<comp::Waypoint>,
+    stash: Option<comp::Stash>,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    // Run pet persistence
+    update_pets(char_id, pets, transaction)?;
+
+    let pseudo_containers = get_pseudo_containers(transaction, char_id)?;
+    let mut upserts = Vec::new();
+    // First, get all the entity IDs for any new items, and identify which
+    // slots to upsert and which ones to delete.
+    get_new_entity_ids(transaction, |mut next_id| {
+        let upserts_ = convert_items_to_database_items(
+            pseudo_containers.loadout_container_id,
+            &inventory,
+            pseudo_containers.inventory_container_id,
+            &mut next_id,
+        );
+        upserts = upserts_;
+        next_id
+    })?;
+
+    trace!("Updating items for character_id {}", char_id);
+    replace_container_items(
+        &[
+            pseudo_containers.inventory_container_id,
+            pseudo_containers.loadout_container_id,
+        ],
+        upserts,
+        transaction,
+    )?;
+
     let db_skill_groups = convert_skill_groups_to_database(char_id, char_skill_set.skill_groups);
 
     let mut stmt = transaction.prepare_cached(
@@ -1039,5 +1363,9 @@ pub fn update(
         )));
     }
 
+    if let Some(stash) = stash {
+        update_stash(char_id, &stash, transaction)?;
+    }
+
     Ok(())
 }
@@ -67,13 +67,44 @@ pub fn convert_items_to_database_items(
         )
     });
 
+    convert_item_tree_to_database_items(
+        inventory.chain(loadout),
+        &[inventory_container_id, loadout_container_id],
+        next_id,
+    )
+}
+
+/// Returns the item rows to upsert for the stash of an account, the position of
+/// each item is the index of its stash slot.
+pub fn convert_stash_to_database_items(
+    stash_container_id: EntityId,
+    stash: &Stash,
+    next_id: &mut i64,
+) -> Vec<ItemModelPair> {
+    let slots = stash
+        .slots()
+        .enumerate()
+        .map(|(i, item)| (i.to_string(), item.as_ref(), stash_container_id));
+
+    convert_item_tree_to_database_items(slots, &[stash_container_id], next_id)
+}
+
+/// Assigns entity IDs to the given items and the items they contain, `roots`
+/// are the `(position, item, parent_container_item_id)` of the top-level items
+/// which must all be parented to one of `containers`.
+fn convert_item_tree_to_database_items<'a>(
+    roots: impl Iterator<Item = (String, Option<&'a common::comp::Item>, EntityId)>,
+    containers: &[EntityId],
+    next_id: &mut i64,
+) -> Vec<ItemModelPair> {
     // Use Breadth-first search to recurse into containers/modular weapons to store
     // their parts
-    let mut bfs_queue: VecDeque<_> = inventory.chain(loadout).collect();
+    let mut bfs_queue: VecDeque<_> = roots.collect();
     let mut upserts = Vec::new();
     let mut depth = HashMap::new();
-    depth.insert(inventory_container_id, 0);
-    depth.insert(loadout_container_id, 0);
+    for container in containers {
+        depth.insert(*container, 0);
+    }
     while let Some((position, item, parent_container_item_id)) = bfs_queue.pop_front() {
         // Construct new items.
         if let Some(item) = item {
@@ -273,32 +304,7 @@ pub fn convert_inventory_from_database_items(
     for (i, db_item) in inventory_items.iter().enumerate() {
         item_indices.insert(db_item.item_id, i);
 
-        let mut item = get_item_from_asset(db_item.item_definition_id.as_str())?;
-
-        // NOTE: Since this is freshly loaded, the atomic is *unique.*
-        let comp = item.get_item_id_for_database();
-
-        // Item ID
-        comp.store(Some(NonZeroU64::try_from(db_item.item_id as u64).map_err(
-            |_| PersistenceError::ConversionError("Item with zero item_id".to_owned()),
-        )?));
-
-        // Stack Size
-        if db_item.stack_size == 1 || item.is_stackable() {
-            // FIXME: On failure, collect the set of items that don't fit and return them
-            // (to be dropped next to the player) as this could be the result of
-            // a change in the max amount for that item.
-            item.set_amount(u32::try_from(db_item.stack_size).map_err(|_| {
-                PersistenceError::ConversionError(format!(
-                    "Invalid item stack size for stackable={}: {}",
-                    item.is_stackable(),
-                    &db_item.stack_size
-                ))
-            })?)
-            .map_err(|_| {
-                PersistenceError::ConversionError("Error setting amount for item".to_owned())
-            })?;
-        }
+        let item = convert_stackable_item_from_database(db_item)?;
 
         // Insert item into inventory
 
@@ -357,6 +363,93 @@ pub fn convert_inventory_from_database_items(
     Ok(inventory)
 }
 
+/// Like [`convert_inventory_from_database_items`], the caller is responsible
+/// for ensuring that `stash_items` are topologically sorted.
+pub fn convert_stash_from_database_items(
+    stash_container_id: i64,
+    stash_items: &[Item],
+) -> Result<Stash, PersistenceError> {
+    let mut stash = Stash::default();
+    let mut item_indices = HashMap::new();
+
+    for (i, db_item) in stash_items.iter().enumerate() {
+        item_indices.insert(db_item.item_id, i);
+
+        let item = convert_stackable_item_from_database(db_item)?;
+
+        let slot = |s: &str| {
+            s.parse::<usize>().map_err(|_| {
+                PersistenceError::ConversionError(format!(
+                    "Failed to parse stash item position: {:?}",
+                    &db_item.position
+                ))
+            })
+        };
+
+        if db_item.parent_container_item_id == stash_container_id {
+            let slot = slot(&db_item.position)?;
+            stash.insert_at(slot, item).map_err(|_| {
+                PersistenceError::ConversionError(format!(
+                    "Error inserting item into stash, position: {}",
+                    slot
+                ))
+            })?;
+        } else if let Some(&j) = item_indices.get(&db_item.parent_container_item_id) {
+            if let Some(parent) = stash.get_mut(slot(&stash_items[j].position)?) {
+                parent.add_component(item, &ABILITY_MAP, &MATERIAL_STATS_MANIFEST);
+            } else {
+                return Err(PersistenceError::ConversionError(format!(
+                    "Parent slot {} for component {} was empty even though it occurred earlier in \
+                     the loop?",
+                    db_item.parent_container_item_id, db_item.item_id
+                )));
+            }
+        } else {
+            return Err(PersistenceError::ConversionError(format!(
+                "Couldn't find parent item {} before item {} in stash",
+                db_item.parent_container_item_id, db_item.item_id
+            )));
+        }
+    }
+
+    Ok(stash)
+}
+
+/// Creates the item stored in a database row, restoring its ID and the size of
+/// its stack.
+fn convert_stackable_item_from_database(
+    db_item: &Item,
+) -> Result<common::comp::Item, PersistenceError> {
+    let mut item = get_item_from_asset(db_item.item_definition_id.as_str())?;
+
+    // NOTE: Since this is freshly loaded, the atomic is *unique.*
+    let comp = item.get_item_id_for_database();
+
+    // Item ID
+    comp.store(Some(NonZeroU64::try_from(db_item.item_id as u64).map_err(
+        |_| PersistenceError::ConversionError("Item with zero item_id".to_owned()),
+    )?));
+
+    // Stack Size
+    if db_item.stack_size == 1 || item.is_stackable() {
+        // FIXME: On failure, collect the set of items that don't fit and return them
+        // (to be dropped next to the player) as this could be the result of
+        // a change in the max amount for that item.
+        item.set_amount(u32::try_from(db_item.stack_size).map_err(|_| {
+            PersistenceError::ConversionError(format!(
+                "Invalid item stack size for stackable={}: {}",
+                item.is_stackable(),
+                &db_item.stack_size
+            ))
+        })?)
+        .map_err(|_| {
+            PersistenceError::ConversionError("Error setting amount for item".to_owned())
+        })?;
+    }
+
+    Ok(item)
+}
+
 pub fn convert_loadout_from_database_items(
     loadout_container_id: i64,
     database_items: &[Item],
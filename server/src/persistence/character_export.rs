@@ -0,0 +1,240 @@
+//! Portable files holding a character, used to move characters between servers
+//!
+//! A file holds the character as it is stored in the database, with item IDs
+//! that are only meaningful within the file. Inventory items are parented to
+//! [`INVENTORY_CONTAINER_ID`] and loadout items to [`LOADOUT_CONTAINER_ID`].
+
+use super::{
+    character,
+    error::PersistenceError,
+    establish_connection,
+    models::{Item, Skill, SkillGroup},
+    ConnectionMode, DatabaseSettings,
+};
+use common::character::CharacterId;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+/// Version of the file format, increased whenever a change prevents older
+/// files from being imported as they are
+pub const CHARACTER_EXPORT_VERSION: u32 = 1;
+
+pub const INVENTORY_CONTAINER_ID: i64 = 1;
+pub const LOADOUT_CONTAINER_ID: i64 = 2;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct CharacterExport {
+    pub version: u32,
+    pub alias: String,
+    pub body_variant: String,
+    /// The body as JSON, in the same format as the database
+    pub body_data: String,
+    pub waypoint: Option<String>,
+    pub skill_groups: Vec<ExportedSkillGroup>,
+    pub skills: Vec<ExportedSkill>,
+    /// Topologically sorted items of the inventory
+    pub inventory: Vec<ExportedItem>,
+    /// Topologically sorted items of the loadout
+    pub loadout: Vec<ExportedItem>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct ExportedSkillGroup {
+    pub skill_group_kind: String,
+    pub exp: i32,
+    pub available_sp: i32,
+    pub earned_sp: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct ExportedSkill {
+    pub skill: String,
+    pub level: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct ExportedItem {
+    pub item_id: i64,
+    pub parent_container_item_id: i64,
+    pub item_definition_id: String,
+    pub stack_size: i32,
+    pub position: String,
+}
+
+impl From<SkillGroup> for ExportedSkillGroup {
+    fn from(skill_group: SkillGroup) -> Self {
+        Self {
+            skill_group_kind: skill_group.skill_group_kind,
+            exp: skill_group.exp,
+            available_sp: skill_group.available_sp,
+            earned_sp: skill_group.earned_sp,
+        }
+    }
+}
+
+impl ExportedSkillGroup {
+    pub(super) fn to_model(&self, entity_id: CharacterId) -> SkillGroup {
+        SkillGroup {
+            entity_id,
+            skill_group_kind: self.skill_group_kind.clone(),
+            exp: self.exp,
+            available_sp: self.available_sp,
+            earned_sp: self.earned_sp,
+        }
+    }
+}
+
+impl From<Skill> for ExportedSkill {
+    fn from(skill: Skill) -> Self {
+        Self {
+            skill: skill.skill,
+            level: skill.level,
+        }
+    }
+}
+
+impl ExportedSkill {
+    pub(super) fn to_model(&self, entity_id: CharacterId) -> Skill {
+        Skill {
+            entity_id,
+            skill: self.skill.clone(),
+            level: self.level,
+        }
+    }
+}
+
+impl From<Item> for ExportedItem {
+    fn from(item: Item) -> Self {
+        Self {
+            item_id: item.item_id,
+            parent_container_item_id: item.parent_container_item_id,
+            item_definition_id: item.item_definition_id,
+            stack_size: item.stack_size,
+            position: item.position,
+        }
+    }
+}
+
+impl ExportedItem {
+    pub(super) fn to_model(&self) -> Item {
+        Item {
+            item_id: self.item_id,
+            parent_container_item_id: self.parent_container_item_id,
+            item_definition_id: self.item_definition_id.clone(),
+            stack_size: self.stack_size,
+            position: self.position.clone(),
+        }
+    }
+}
+
+/// Writes the last persisted state of a character to a file, changes made
+/// since the character was last saved aren't included.
+pub fn export_character(
+    settings: &DatabaseSettings,
+    character_id: CharacterId,
+    path: &Path,
+) -> Result<(), PersistenceError> {
+    let conn = establish_connection(settings, ConnectionMode::ReadOnly);
+    let export = character::export_character(character_id, &conn)?;
+    let contents = serde_json::to_string_pretty(&export)?;
+    fs::write(path, contents).map_err(|e| {
+        PersistenceError::OtherError(format!("Failed to write {}: {}", path.display(), e))
+    })
+}
+
+/// Creates a character for the player from an exported file, returning the ID
+/// of the new character. The file is rejected if any of its items doesn't
+/// exist on this server.
+pub fn import_character(
+    settings: &DatabaseSettings,
+    player_uuid: &str,
+    path: &Path,
+) -> Result<CharacterId, PersistenceError> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        PersistenceError::OtherError(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    let export = serde_json::from_str::<CharacterExport>(&contents)?;
+    if export.version != CHARACTER_EXPORT_VERSION {
+        return Err(PersistenceError::OtherError(format!(
+            "Unsupported character file version {}, expected {}",
+            export.version, CHARACTER_EXPORT_VERSION
+        )));
+    }
+
+    let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
+    let mut transaction = conn.connection.transaction()?;
+    let character_id = character::import_character(player_uuid, &export, &mut transaction)?;
+    transaction.commit()?;
+
+    Ok(character_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{run_migrations, SqlLogMode};
+    use common::comp::{
+        self, inventory::loadout_builder::LoadoutBuilder, Inventory, SkillSet, Stats,
+    };
+
+    #[test]
+    fn export_import_round_trip() {
+        let db_dir = std::env::temp_dir().join(format!(
+            "veloren-character-export-test-{}",
+            std::process::id()
+        ));
+        let settings = DatabaseSettings {
+            db_dir: db_dir.clone(),
+            sql_log_mode: SqlLogMode::Disabled,
+        };
+        run_migrations(&settings);
+
+        let loadout = LoadoutBuilder::empty()
+            .defaults()
+            .active_mainhand(Some(comp::Item::new_from_asset_expect(
+                "common.items.weapons.sword.starter",
+            )))
+            .build();
+        let mut inventory = Inventory::new_with_loadout(loadout);
+        inventory
+            .push(comp::Item::new_from_asset_expect(
+                "common.items.food.cheese",
+            ))
+            .expect("Inventory has free slots");
+
+        let mut conn = establish_connection(&settings, ConnectionMode::ReadWrite);
+        let mut transaction = conn.connection.transaction().unwrap();
+        let (character_id, _) = character::create_character(
+            "exporter",
+            "Exported",
+            (
+                comp::Body::Humanoid(comp::humanoid::Body::random()),
+                Stats::new("Exported".to_owned()),
+                SkillSet::default(),
+                inventory,
+                None,
+                Vec::new(),
+                comp::Stash::default(),
+            ),
+            &mut transaction,
+        )
+        .unwrap();
+        transaction.commit().unwrap();
+        drop(conn);
+
+        let path = db_dir.join("exported_character.json");
+        export_character(&settings, character_id, &path).unwrap();
+        let imported_id = import_character(&settings, "importer", &path).unwrap();
+        assert_ne!(character_id, imported_id);
+
+        let conn = establish_connection(&settings, ConnectionMode::ReadOnly);
+        let exported = character::export_character(character_id, &conn).unwrap();
+        let reexported = character::export_character(imported_id, &conn).unwrap();
+        assert!(!exported.inventory.is_empty());
+        assert!(!exported.loadout.is_empty());
+        assert_eq!(exported, reexported);
+        drop(conn);
+
+        let _ = fs::remove_dir_all(&db_dir);
+    }
+}
@@ -4,7 +4,9 @@ use common::character::CharacterId;
 use crate::persistence::{
     character_loader::{CharacterLoaderResponse, CharacterLoaderResponseKind},
     error::PersistenceError,
-    establish_connection, ConnectionMode, DatabaseSettings, PersistedComponents, VelorenConnection,
+    establish_connection,
+    plugin_data::PluginDataUpdate,
+    ConnectionMode, DatabaseSettings, PersistedComponents, VelorenConnection,
 };
 use crossbeam_channel::TryIter;
 use rusqlite::DropBehavior;
@@ -23,13 +25,17 @@ pub type CharacterUpdateData = (
     comp::Inventory,
     Vec<PetPersistenceData>,
     Option<comp::Waypoint>,
+    Option<comp::Stash>,
 );
 
 pub type PetPersistenceData = (comp::Pet, comp::Body, comp::Stats);
 
 #[allow(clippy::large_enum_variant)]
 pub enum CharacterUpdaterEvent {
-    BatchUpdate(Vec<(CharacterId, CharacterUpdateData)>),
+    BatchUpdate(
+        Vec<(CharacterId, CharacterUpdateData)>,
+        Vec<PluginDataUpdate>,
+    ),
     CreateCharacter {
         entity: Entity,
         player_uuid: String,
@@ -53,7 +59,10 @@ pub struct CharacterUpdater {
     update_tx: Option<crossbeam_channel::Sender<CharacterUpdaterEvent>>,
     response_rx: crossbeam_channel::Receiver<CharacterLoaderResponse>,
     handle: Option<std::thread::JoinHandle<()>>,
-    pending_logout_updates: HashMap<CharacterId, CharacterUpdateData>,
+    /// Characters that recently logged out with the UUID of their player
+    pending_logout_updates: HashMap<CharacterId, (String, CharacterUpdateData)>,
+    /// Writes to the storage of plugins, persisted with the next batch update
+    pending_plugin_data_updates: Vec<PluginDataUpdate>,
     /// Will disconnect all characters (without persistence) on the next tick if
     /// set to true
     disconnect_all_clients_requested: Arc<AtomicBool>,
@@ -76,7 +85,7 @@ impl CharacterUpdater {
                     establish_connection(&*settings.read().unwrap(), ConnectionMode::ReadWrite);
                 while let Ok(updates) = update_rx.recv() {
                     match updates {
-                        CharacterUpdaterEvent::BatchUpdate(updates) => {
+                        CharacterUpdaterEvent::BatchUpdate(updates, plugin_data_updates) => {
                             if disconnect_all_clients_requested_clone.load(Ordering::Relaxed) {
                                 debug!(
                                     "Skipping persistence due to pending disconnection of all \
@@ -85,7 +94,9 @@ impl CharacterUpdater {
                                 continue;
                             }
                             conn.update_log_mode(&settings);
-                            if let Err(e) = execute_batch_update(updates, &mut conn) {
+                            if let Err(e) =
+                                execute_batch_update(updates, plugin_data_updates, &mut conn)
+                            {
                                 error!(
                                     "Error during character batch update, disconnecting all \
                                      clients to avoid loss of data integrity. Error: {:?}",
@@ -170,6 +181,7 @@ impl CharacterUpdater {
             response_rx,
             handle: Some(handle),
             pending_logout_updates: HashMap::new(),
+            pending_plugin_data_updates: Vec::new(),
             disconnect_all_clients_requested,
         })
     }
@@ -179,6 +191,7 @@ impl CharacterUpdater {
     pub fn add_pending_logout_update(
         &mut self,
         character_id: CharacterId,
+        player_uuid: String,
         update_data: CharacterUpdateData,
     ) {
         if !self
@@ -186,7 +199,7 @@ impl CharacterUpdater {
             .load(Ordering::Relaxed)
         {
             self.pending_logout_updates
-                .insert(character_id, update_data);
+                .insert(character_id, (player_uuid, update_data));
         } else {
             warn!(
                 "Ignoring request to add pending logout update for character ID {} as there is a \
@@ -196,12 +209,31 @@ impl CharacterUpdater {
         }
     }
 
+    /// Adds writes to the storage of plugins that will be persisted in the
+    /// next batch update, in the order they are given.
+    pub fn add_pending_plugin_data_updates(
+        &mut self,
+        updates: impl IntoIterator<Item = PluginDataUpdate>,
+    ) {
+        self.pending_plugin_data_updates.extend(updates);
+    }
+
     /// Returns the character IDs of characters that have recently logged out
     /// and are awaiting persistence in the next batch update.
     pub fn characters_pending_logout(&self) -> impl Iterator<Item = CharacterId> + '_ {
         self.pending_logout_updates.keys().copied()
     }
 
+    /// Returns whether a character of the player recently logged out and is
+    /// awaiting persistence in the next batch update. Since the stash is
+    /// shared by all the characters of a player, none of them can be loaded
+    /// until then.
+    pub fn player_pending_logout(&self, player_uuid: &str) -> bool {
+        self.pending_logout_updates
+            .values()
+            .any(|(uuid, _)| uuid == player_uuid)
+    }
+
     /// Returns a value indicating whether there is a pending request to
     /// disconnect all clients due to a batch update transaction failure
     pub fn disconnect_all_clients_requested(&self) -> bool {
@@ -267,29 +299,41 @@ impl CharacterUpdater {
                 &'a comp::Inventory,
                 Vec<PetPersistenceData>,
                 Option<&'a comp::Waypoint>,
+                Option<&'a comp::Stash>,
             ),
         >,
     ) {
         let updates = updates
-            .map(|(character_id, skill_set, inventory, pets, waypoint)| {
-                (
-                    character_id,
+            .map(
+                |(character_id, skill_set, inventory, pets, waypoint, stash)| {
                     (
-                        skill_set.clone(),
-                        inventory.clone(),
-                        pets,
-                        waypoint.cloned(),
-                    ),
-                )
-            })
-            .chain(self.pending_logout_updates.drain())
+                        character_id,
+                        (
+                            skill_set.clone(),
+                            inventory.clone(),
+                            pets,
+                            waypoint.cloned(),
+                            stash.cloned(),
+                        ),
+                    )
+                },
+            )
+            .chain(
+                self.pending_logout_updates
+                    .drain()
+                    .map(|(character_id, (_, update_data))| (character_id, update_data)),
+            )
             .collect::<Vec<_>>();
+        let plugin_data_updates = std::mem::take(&mut self.pending_plugin_data_updates);
 
         if let Err(e) = self
             .update_tx
             .as_ref()
             .unwrap()
-            .send(CharacterUpdaterEvent::BatchUpdate(updates))
+            .send(CharacterUpdaterEvent::BatchUpdate(
+                updates,
+                plugin_data_updates,
+            ))
         {
             error!(?e, "Could not send stats updates");
         }
@@ -314,23 +358,26 @@ impl CharacterUpdater {
 
 fn execute_batch_update(
     updates: Vec<(CharacterId, CharacterUpdateData)>,
+    plugin_data_updates: Vec<PluginDataUpdate>,
     connection: &mut VelorenConnection,
 ) -> Result<(), PersistenceError> {
     let mut transaction = connection.connection.transaction()?;
     transaction.set_drop_behavior(DropBehavior::Rollback);
     trace!("Transaction started for character batch update");
-    updates
-        .into_iter()
-        .try_for_each(|(character_id, (stats, inventory, pets, waypoint))| {
+    updates.into_iter().try_for_each(
+        |(character_id, (stats, inventory, pets, waypoint, stash))| {
             super::character::update(
                 character_id,
                 stats,
                 inventory,
                 pets,
                 waypoint,
+                stash,
                 &mut transaction,
             )
-        })?;
+        },
+    )?;
+    super::plugin_data::update(plugin_data_updates, &mut transaction)?;
     transaction.commit()?;
 
     trace!("Commit for character batch update completed");
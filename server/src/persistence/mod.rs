@@ -1,14 +1,18 @@
 //! DB operations and schema migrations
 
 pub(in crate::persistence) mod character;
+pub mod character_export;
 pub mod character_loader;
 pub mod character_updater;
 mod diesel_to_rusqlite;
 pub mod error;
 mod json_models;
 mod models;
+pub mod plugin_data;
 
-use crate::persistence::character_updater::PetPersistenceData;
+use crate::persistence::{
+    character_updater::PetPersistenceData, error::PersistenceError, plugin_data::PluginDataEntry,
+};
 use common::comp;
 use refinery::Report;
 use rusqlite::{Connection, OpenFlags};
@@ -21,7 +25,8 @@ use std::{
 };
 use tracing::info;
 
-/// A tuple of the components that are persisted to the DB for each character
+/// A tuple of the components that are persisted to the DB for each character,
+/// the stash being shared with the other characters of the account
 pub type PersistedComponents = (
     comp::Body,
     comp::Stats,
@@ -29,6 +34,7 @@ pub type PersistedComponents = (
     comp::Inventory,
     Option<comp::Waypoint>,
     Vec<PetPersistenceData>,
+    comp::Stash,
 );
 
 // See: https://docs.rs/refinery/0.5.0/refinery/macro.embed_migrations.html
@@ -168,6 +174,14 @@ pub fn run_migrations(settings: &DatabaseSettings) {
     info!("Applied {} database migrations", applied_migrations);
 }
 
+/// Loads the values stored by plugins. This is executed during server startup
+pub fn load_plugin_data(
+    settings: &DatabaseSettings,
+) -> Result<Vec<PluginDataEntry>, PersistenceError> {
+    let conn = establish_connection(settings, ConnectionMode::ReadOnly);
+    plugin_data::load_all(&conn)
+}
+
 // These callbacks use info logging because they are never enabled by default,
 // only when explicitly turned on via CLI arguments or interactive CLI commands.
 // Setting them to anything other than info would remove the ability to get SQL
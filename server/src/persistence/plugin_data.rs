@@ -0,0 +1,69 @@
+//! Database operations related to the key/value storage of plugins
+//!
+//! Writes are batched with the character updates by the [`CharacterUpdater`],
+//! the data is only read once during server startup.
+//!
+//! [`CharacterUpdater`]: super::character_updater::CharacterUpdater
+
+use super::error::PersistenceError;
+use rusqlite::{Connection, ToSql, Transaction, NO_PARAMS};
+use tracing::trace;
+
+/// A change to the storage of a plugin: the plugin name, the key and the new
+/// value, `None` if the value was removed
+pub type PluginDataUpdate = (String, String, Option<Vec<u8>>);
+
+/// A value stored by a plugin: the plugin name, the key and the value
+pub type PluginDataEntry = (String, String, Vec<u8>);
+
+pub(super) fn load_all(connection: &Connection) -> Result<Vec<PluginDataEntry>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  plugin_name,
+                key,
+                value
+        FROM    plugin_data",
+    )?;
+
+    let entries = stmt
+        .query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+pub(super) fn update(
+    updates: Vec<PluginDataUpdate>,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    // The updates are applied in order, so only the last write to a key is kept
+    for (plugin_name, key, value) in updates {
+        match value {
+            Some(value) => {
+                trace!("Setting plugin data {} for plugin {}", key, plugin_name);
+                let mut stmt = transaction.prepare_cached(
+                    "
+                    REPLACE
+                    INTO    plugin_data (plugin_name,
+                                         key,
+                                         value)
+                    VALUES  (?1, ?2, ?3)",
+                )?;
+                stmt.execute(&[&plugin_name as &dyn ToSql, &key, &value])?;
+            },
+            None => {
+                trace!("Deleting plugin data {} for plugin {}", key, plugin_name);
+                let mut stmt = transaction.prepare_cached(
+                    "
+                    DELETE
+                    FROM    plugin_data
+                    WHERE   plugin_name = ?1
+                    AND     key = ?2",
+                )?;
+                stmt.execute(&[&plugin_name, &key])?;
+            },
+        }
+    }
+
+    Ok(())
+}
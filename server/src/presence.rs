@@ -1,4 +1,4 @@
-use common_net::msg::PresenceKind;
+use common_net::msg::{DeltaEncoder, PresenceKind, SpectateTarget, TerrainEncodingPreferences};
 use hashbrown::HashSet;
 use serde::{Deserialize, Serialize};
 use specs::{Component, DerefFlaggedStorage, NullStorage};
@@ -9,7 +9,7 @@ use vek::*;
 pub struct Presence {
     pub view_distance: u32,
     pub kind: PresenceKind,
-    pub lossy_terrain_compression: bool,
+    pub terrain_encoding: TerrainEncodingPreferences,
 }
 
 impl Presence {
@@ -17,7 +17,7 @@ impl Presence {
         Self {
             view_distance,
             kind,
-            lossy_terrain_compression: false,
+            terrain_encoding: TerrainEncodingPreferences::default(),
         }
     }
 }
@@ -41,6 +41,22 @@ impl Component for RegionSubscription {
     type Storage = DerefFlaggedStorage<Self, IdvStorage<Self>>;
 }
 
+/// Remembers what a client acknowledged of the delta synced components
+#[derive(Debug, Default)]
+pub struct DeltaSync(pub DeltaEncoder);
+
+impl Component for DeltaSync {
+    type Storage = IdvStorage<Self>;
+}
+
+/// Where a spectator looks, the entity is moved there by `sys::spectator`
+#[derive(Clone, Copy, Debug)]
+pub struct Spectating(pub SpectateTarget);
+
+impl Component for Spectating {
+    type Storage = IdvStorage<Self>;
+}
+
 #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct RepositionOnChunkLoad;
 
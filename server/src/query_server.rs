@@ -0,0 +1,177 @@
+//! Answers the queries of server browsers on a separate UDP port, see
+//! [`common_net::msg::query`].
+
+use common_net::msg::{
+    query::QUERY_PROTOCOL_VERSION, QueryRequest, QueryResponse, ServerQueryInfo,
+};
+use hashbrown::HashMap;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::{net::UdpSocket, runtime::Runtime, sync::oneshot};
+use tracing::{debug, trace, warn};
+
+/// Number of queries answered per address within [`RATE_LIMIT_PERIOD`]
+const MAX_QUERIES_PER_IP: u32 = 5;
+/// Number of queries answered in total within [`RATE_LIMIT_PERIOD`]
+const MAX_QUERIES: u32 = 2000;
+const RATE_LIMIT_PERIOD: Duration = Duration::from_secs(10);
+
+/// Limits the queries answered per address and in total, so the endpoint
+/// can't be used to flood someone else with responses. Only answered
+/// addresses are tracked, so at most `max_total` of them are kept per period.
+pub struct QueryRateLimiter {
+    max_per_ip: u32,
+    max_total: u32,
+    period: Duration,
+    period_start: Instant,
+    total: u32,
+    per_ip: HashMap<IpAddr, u32>,
+}
+
+impl QueryRateLimiter {
+    pub fn new(max_per_ip: u32, max_total: u32, period: Duration, now: Instant) -> Self {
+        Self {
+            max_per_ip,
+            max_total,
+            period,
+            period_start: now,
+            total: 0,
+            per_ip: HashMap::new(),
+        }
+    }
+
+    /// Whether a query of `ip` received at `now` should be answered
+    pub fn allow(&mut self, ip: IpAddr, now: Instant) -> bool {
+        if now.duration_since(self.period_start) >= self.period {
+            self.period_start = now;
+            self.total = 0;
+            self.per_ip.clear();
+        }
+        if self.total >= self.max_total {
+            return false;
+        }
+        let count = self.per_ip.entry(ip).or_insert(0);
+        if *count >= self.max_per_ip {
+            return false;
+        }
+        *count += 1;
+        self.total += 1;
+        true
+    }
+}
+
+/// Owns the task answering queries, it's stopped when this is dropped
+pub struct QueryServer {
+    info: Arc<RwLock<ServerQueryInfo>>,
+    last_update: Instant,
+    _stop: oneshot::Sender<()>,
+}
+
+impl QueryServer {
+    /// How often the server updates the info that is sent
+    pub const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn start(
+        addr: SocketAddr,
+        info: ServerQueryInfo,
+        runtime: &Runtime,
+    ) -> std::io::Result<Self> {
+        let socket = runtime.block_on(UdpSocket::bind(addr))?;
+        debug!(?addr, "Query server listening");
+        let info = Arc::new(RwLock::new(info.truncated()));
+        let (stop_s, stop_r) = oneshot::channel();
+        runtime.spawn(Self::run(socket, Arc::clone(&info), stop_r));
+        Ok(Self {
+            info,
+            last_update: Instant::now(),
+            _stop: stop_s,
+        })
+    }
+
+    /// Whether the info is older than [`Self::UPDATE_INTERVAL`]
+    pub fn needs_update(&self) -> bool { self.last_update.elapsed() >= Self::UPDATE_INTERVAL }
+
+    pub fn update(&mut self, info: ServerQueryInfo) {
+        *self.info.write().unwrap() = info.truncated();
+        self.last_update = Instant::now();
+    }
+
+    async fn run(
+        socket: UdpSocket,
+        info: Arc<RwLock<ServerQueryInfo>>,
+        mut stop_r: oneshot::Receiver<()>,
+    ) {
+        let mut limiter = QueryRateLimiter::new(
+            MAX_QUERIES_PER_IP,
+            MAX_QUERIES,
+            RATE_LIMIT_PERIOD,
+            Instant::now(),
+        );
+        let mut buffer = [0u8; 1024];
+        loop {
+            let (n, remote) = tokio::select! {
+                next = socket.recv_from(&mut buffer) => match next {
+                    Ok(next) => next,
+                    Err(e) => {
+                        trace!(?e, "Query socket error, ignoring datagram");
+                        continue;
+                    },
+                },
+                _ = &mut stop_r => break,
+            };
+            let request = match QueryRequest::from_bytes(&buffer[..n]) {
+                Some(request) => request,
+                None => continue,
+            };
+            if !limiter.allow(remote.ip(), Instant::now()) {
+                trace!(?remote, "Query rate limited");
+                continue;
+            }
+            let response = if request.version == QUERY_PROTOCOL_VERSION {
+                QueryResponse::Info {
+                    nonce: request.nonce,
+                    info: info.read().unwrap().clone(),
+                }
+            } else {
+                QueryResponse::UnsupportedVersion {
+                    nonce: request.nonce,
+                    version: QUERY_PROTOCOL_VERSION,
+                }
+            };
+            if let Err(e) = socket.send_to(&response.to_bytes(), remote).await {
+                warn!(?e, ?remote, "Failed to answer query");
+            }
+        }
+        debug!("Query server stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn rate_limit_per_ip_and_total() {
+        let start = Instant::now();
+        let mut limiter = QueryRateLimiter::new(2, 3, Duration::from_secs(10), start);
+        let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let c = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+        assert!(limiter.allow(a, start));
+        assert!(limiter.allow(a, start));
+        assert!(!limiter.allow(a, start));
+        assert!(limiter.allow(b, start));
+        // the total is used up
+        assert!(!limiter.allow(c, start));
+        // rejected addresses aren't tracked
+        assert_eq!(limiter.per_ip.len(), 2);
+        // everything is allowed again in the next period
+        let later = start + Duration::from_secs(10);
+        assert!(limiter.allow(a, later));
+        assert!(limiter.allow(c, later));
+    }
+}
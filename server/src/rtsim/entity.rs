@@ -1,21 +1,32 @@
 use super::*;
 use common::{
+    calendar::Season,
     comp::inventory::{loadout_builder::make_potion_bag, slot::ArmorSlot},
     resources::Time,
     rtsim::{Memory, MemoryItem},
     store::Id,
-    terrain::TerrainGrid,
+    terrain::{TerrainChunkSize, TerrainGrid},
     trade, LoadoutBuilder,
 };
+use hashbrown::HashMap;
 use rand_distr::{Distribution, Normal};
 use std::f32::consts::PI;
 use tracing::warn;
 use world::{
     civ::{Site, Track},
+    pathfinding::{SearchCfg, Searcher},
     util::RandomPerm,
     IndexRef, World,
 };
 
+/// Travellers search their own way between sites without a track in the rtsim
+/// tick, a longer search would stall it
+const MAX_ROUTE_SEARCH_ITERS: usize = 20_000;
+
+/// Ways travellers found between sites without a track, `None` if the search
+/// gave up. Each route is only searched once.
+pub type Routes = HashMap<(Id<Site>, Id<Site>), Option<Vec<Vec2<i32>>>>;
+
 pub struct Entity {
     pub is_loaded: bool,
     pub pos: Vec3<f32>,
@@ -90,10 +101,13 @@ impl Entity {
         }
     }
 
+    /// The goods a merchant can sell, goods that are dearer in `season` are
+    /// scarcer too
     pub fn get_trade_info(
         &self,
         world: &World,
         index: &world::IndexOwned,
+        season: Season,
     ) -> Option<trade::SiteInformation> {
         let site = match self.kind {
             /*
@@ -110,7 +124,11 @@ impl Entity {
         }?;
 
         let site = world.civs().sites[site].site_tmp?;
-        index.sites[site].trade_information(site.id())
+        let mut info = index.sites[site].trade_information(site.id())?;
+        for (good, amount) in info.unconsumed_stock.iter_mut() {
+            *amount /= good.seasonal_price_factor(season);
+        }
+        Some(info)
     }
 
     pub fn get_entity_config(&self) -> &str {
@@ -144,7 +162,14 @@ impl Entity {
         }
     }
 
-    pub fn tick(&mut self, time: &Time, terrain: &TerrainGrid, world: &World, index: &IndexRef) {
+    pub fn tick(
+        &mut self,
+        time: &Time,
+        terrain: &TerrainGrid,
+        world: &World,
+        index: &IndexRef,
+        routes: &mut Routes,
+    ) {
         self.brain.route = match self.brain.route.clone() {
             Travel::Lost => {
                 match self.get_body() {
@@ -309,9 +334,32 @@ impl Entity {
                     .map(|(id, _)| id)
                 {
                     // This code should only trigger when no paths out of the current town exist.
-                    // The traveller will attempt to directly travel to another town
+                    // The traveller will attempt to find its own way to another town
                     self.brain.last_visited = Some(site_id);
-                    Travel::Direct { target_id }
+                    let route = routes.entry((site_id, target_id)).or_insert_with(|| {
+                        let from = world.civs().sites[site_id].center;
+                        let to = world.civs().sites[target_id].center;
+                        let cfg = SearchCfg {
+                            max_iters: MAX_ROUTE_SEARCH_ITERS,
+                            ..SearchCfg::default()
+                        };
+                        Searcher::new(world.sim(), cfg)
+                            .search(from, to)
+                            .map(|path| {
+                                path.iter()
+                                    .copied()
+                                    .map(TerrainChunkSize::center_wpos)
+                                    .collect()
+                            })
+                    });
+                    match route {
+                        Some(path) => Travel::CustomPath {
+                            target_id,
+                            path: path.clone(),
+                            progress: 0,
+                        },
+                        None => Travel::Direct { target_id },
+                    }
                 } else {
                     // No paths we're picked, so stay in town. This will cause direct travel on the
                     // next tick.
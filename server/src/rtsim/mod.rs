@@ -8,6 +8,7 @@ mod unload_chunks;
 
 use self::chunks::Chunks;
 use common::{
+    calendar::CalendarDate,
     comp,
     rtsim::{Memory, RtSimController, RtSimEntity, RtSimId},
     terrain::TerrainChunk,
@@ -20,23 +21,30 @@ use slab::Slab;
 use specs::{DispatcherBuilder, WorldExt};
 use vek::*;
 
-pub use self::entity::{Brain, Entity, RtSimEntityKind};
+pub use self::entity::{Brain, Entity, Routes, RtSimEntityKind};
 
 pub struct RtSim {
     tick: u64,
     chunks: Chunks,
     entities: Slab<Entity>,
+    routes: Routes,
+    /// The in-game date, updated every tick
+    date: CalendarDate,
 }
 
 impl RtSim {
-    pub fn new(world_chunk_size: Vec2<u32>) -> Self {
+    pub fn new(world_chunk_size: Vec2<u32>, date: CalendarDate) -> Self {
         Self {
             tick: 0,
             chunks: Chunks::new(world_chunk_size),
             entities: Slab::new(),
+            routes: Routes::default(),
+            date,
         }
     }
 
+    pub fn date(&self) -> CalendarDate { self.date }
+
     pub fn hook_load_chunk(&mut self, key: Vec2<i32>) {
         if let Some(chunk) = self.chunks.chunk_mut(key) {
             if !chunk.is_loaded {
@@ -111,9 +119,9 @@ pub fn init(
     #[cfg(feature = "worldgen")] spawn_point: crate::SpawnPoint,
 ) {
     #[cfg(feature = "worldgen")]
-    let mut rtsim = RtSim::new(world.sim().get_size());
+    let mut rtsim = RtSim::new(world.sim().get_size(), state.get_date());
     #[cfg(not(feature = "worldgen"))]
-    let mut rtsim = RtSim::new(Vec2::new(40, 40));
+    let mut rtsim = RtSim::new(Vec2::new(40, 40), state.get_date());
 
     // TODO: Determine number of rtsim entities based on things like initial site
     // populations rather than world size
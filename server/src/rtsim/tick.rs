@@ -3,10 +3,11 @@
 use super::*;
 use crate::sys::terrain::NpcData;
 use common::{
+    calendar::Calendar,
     comp,
     event::{EventBus, ServerEvent},
     generation::{BodyBuilder, EntityConfig, EntityInfo},
-    resources::{DeltaTime, Time},
+    resources::{DeltaTime, Time, TimeOfDay},
     terrain::TerrainGrid,
 };
 use common_ecs::{Job, Origin, Phase, System};
@@ -20,6 +21,8 @@ impl<'a> System<'a> for Sys {
     type SystemData = (
         Read<'a, Time>,
         Read<'a, DeltaTime>,
+        Read<'a, TimeOfDay>,
+        Read<'a, Calendar>,
         Read<'a, EventBus<ServerEvent>>,
         WriteExpect<'a, RtSim>,
         ReadExpect<'a, TerrainGrid>,
@@ -39,6 +42,8 @@ impl<'a> System<'a> for Sys {
         (
             time,
             _dt,
+            time_of_day,
+            calendar,
             server_event_bus,
             mut rtsim,
             terrain,
@@ -51,6 +56,7 @@ impl<'a> System<'a> for Sys {
     ) {
         let rtsim = &mut *rtsim;
         rtsim.tick += 1;
+        rtsim.date = calendar.date(*time_of_day);
 
         // Update unloaded rtsim entities, in groups at a time
         const TICK_STAGGER: usize = 30;
@@ -95,13 +101,25 @@ impl<'a> System<'a> for Sys {
                     entity.pos.z = alt;
                 }
             }
-            entity.tick(&time, &terrain, &world, &index.as_index_ref());
+            entity.tick(
+                &time,
+                &terrain,
+                &world,
+                &index.as_index_ref(),
+                &mut rtsim.routes,
+            );
         }
 
         // Tick entity AI each time if it's loaded
         for (_, entity) in rtsim.entities.iter_mut().filter(|(_, e)| e.is_loaded) {
             entity.last_time_ticked = time.0;
-            entity.tick(&time, &terrain, &world, &index.as_index_ref());
+            entity.tick(
+                &time,
+                &terrain,
+                &world,
+                &index.as_index_ref(),
+                &mut rtsim.routes,
+            );
         }
 
         let mut server_emitter = server_event_bus.emitter();
@@ -140,7 +158,7 @@ impl<'a> System<'a> for Sys {
                     .with_lazy_loadout(ad_hoc_loadout)
                     .with_health_scaling(10);
                 // Merchants can be traded with
-                if let Some(economy) = entity.get_trade_info(&world, &index) {
+                if let Some(economy) = entity.get_trade_info(&world, &index, rtsim.date().season) {
                     entity_info = entity_info
                         .with_agent_mark(comp::agent::Mark::Merchant)
                         .with_economy(&economy);
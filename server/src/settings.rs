@@ -8,13 +8,15 @@ pub use editable::{EditableSetting, Error as SettingError};
 
 pub use admin::{AdminRecord, Admins};
 pub use banlist::{
-    Ban, BanAction, BanEntry, BanError, BanErrorKind, BanInfo, BanKind, BanRecord, Banlist,
+    Ban, BanAction, BanEntry, BanError, BanErrorKind, BanInfo, BanKind, BanRecord, Banlist, Mute,
+    MuteAction, MuteRecord,
 };
 pub use server_description::ServerDescription;
 pub use whitelist::{Whitelist, WhitelistInfo, WhitelistRecord};
 
 use chrono::Utc;
-use common::resources::BattleMode;
+use common::{calendar::Calendar, resources::BattleMode};
+use common_net::msg::QueryBattleMode;
 use core::time::Duration;
 use portpicker::pick_unused_port;
 use serde::{Deserialize, Serialize};
@@ -22,6 +24,7 @@ use std::{
     fs,
     net::SocketAddr,
     path::{Path, PathBuf},
+    time::{Instant, SystemTime},
 };
 use tracing::{error, warn};
 use world::sim::FileOpts;
@@ -62,11 +65,68 @@ impl ServerBattleMode {
     }
 }
 
+impl From<ServerBattleMode> for QueryBattleMode {
+    fn from(mode: ServerBattleMode) -> Self {
+        match mode {
+            ServerBattleMode::Global(mode) => QueryBattleMode::Global(mode),
+            ServerBattleMode::PerPlayer { default } => QueryBattleMode::PerPlayer { default },
+        }
+    }
+}
+
+/// Limits on the chat messages sent by players, admins are exempt from them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModerationSettings {
+    /// Number of messages a player can send within `rate_limit_period`, 0
+    /// disables rate limiting.
+    pub rate_limit_messages: u32,
+    pub rate_limit_period: Duration,
+    /// Number of times a player can send the same message in a row within
+    /// `repeated_message_period`, 0 disables spam detection.
+    pub max_repeated_messages: u32,
+    pub repeated_message_period: Duration,
+}
+
+impl Default for ModerationSettings {
+    fn default() -> Self {
+        Self {
+            rate_limit_messages: 5,
+            rate_limit_period: Duration::from_secs(5),
+            max_repeated_messages: 3,
+            repeated_message_period: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Retention limits of the audit log of privileged commands, they are applied
+/// when the server starts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditLogSettings {
+    /// Entries older than this are removed, `None` keeps them forever.
+    pub retention_days: Option<u32>,
+    /// Only the most recent entries are kept, `None` keeps all of them.
+    pub max_entries: Option<usize>,
+}
+
+impl Default for AuditLogSettings {
+    fn default() -> Self {
+        Self {
+            retention_days: Some(90),
+            max_entries: Some(50_000),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Settings {
     pub gameserver_address: SocketAddr,
     pub metrics_address: SocketAddr,
+    /// Where server browsers can query the server, `None` disables queries.
+    /// The default port is [`common_net::msg::query::DEFAULT_QUERY_PORT`]
+    pub query_address: Option<SocketAddr>,
     pub auth_server_address: Option<String>,
     pub quic_files: Option<X509FilePair>,
     pub max_players: usize,
@@ -74,6 +134,8 @@ pub struct Settings {
     pub battle_mode: ServerBattleMode,
     pub server_name: String,
     pub start_time: f64,
+    /// Days, months and seasons of the in-game year
+    pub calendar: Calendar,
     /// When set to None, loads the default map file (if available); otherwise,
     /// uses the value of the file options to decide how to proceed.
     pub map_file: Option<FileOpts>,
@@ -84,6 +146,10 @@ pub struct Settings {
     pub spawn_town: Option<String>,
     pub safe_spawn: bool,
     pub max_player_for_kill_broadcast: Option<usize>,
+    /// Whether players may watch without a character, admins always can
+    pub allow_spectators: bool,
+    pub moderation: ModerationSettings,
+    pub audit_log: AuditLogSettings,
 
     /// Experimental feature. No guaranteed forwards-compatibility, may be
     /// removed at *any time* with no migration.
@@ -96,6 +162,7 @@ impl Default for Settings {
         Self {
             gameserver_address: SocketAddr::from(([0; 4], 14004)),
             metrics_address: SocketAddr::from(([0; 4], 14005)),
+            query_address: None,
             auth_server_address: Some("https://auth.veloren.net".into()),
             quic_files: None,
             world_seed: DEFAULT_WORLD_SEED,
@@ -103,6 +170,7 @@ impl Default for Settings {
             max_players: 100,
             battle_mode: ServerBattleMode::Global(BattleMode::PvP),
             start_time: 9.0 * 3600.0,
+            calendar: Calendar::default(),
             map_file: None,
             max_view_distance: Some(65),
             banned_words_files: Vec::new(),
@@ -111,6 +179,9 @@ impl Default for Settings {
             spawn_town: None,
             safe_spawn: true,
             max_player_for_kill_broadcast: None,
+            allow_spectators: false,
+            moderation: ModerationSettings::default(),
+            audit_log: AuditLogSettings::default(),
             experimental_terrain_persistence: false,
         }
     }
@@ -150,19 +221,95 @@ impl Settings {
         }
     }
 
+    /// Read the settings file again, unlike [`Settings::load`] a missing or
+    /// invalid file is never replaced and `None` is returned.
+    ///
+    /// path: Directory that contains the server config directory
+    pub fn try_load(path: &Path) -> Option<Self> {
+        let path = Self::get_settings_path(path);
+
+        match fs::File::open(&path) {
+            Ok(file) => ron::de::from_reader(file)
+                .map_err(|e| warn!(?e, "Failed to parse setting file!"))
+                .ok(),
+            Err(e) => {
+                warn!(?e, ?path, "Failed to open setting file");
+                None
+            },
+        }
+    }
+
+    /// Apply the fields of `new` that can be changed while the server runs,
+    /// the other fields are left untouched.
+    pub fn apply_reload(&mut self, new: Self) -> SettingsChanges {
+        fn differs<T: Serialize>(a: &T, b: &T) -> bool {
+            ron::ser::to_string(a).ok() != ron::ser::to_string(b).ok()
+        }
+
+        let mut changes = SettingsChanges::default();
+        macro_rules! live {
+            ($($field:ident),* $(,)?) => {
+                $(if differs(&self.$field, &new.$field) {
+                    changes.applied.push(stringify!($field));
+                    self.$field = new.$field;
+                })*
+            };
+        }
+        macro_rules! needs_restart {
+            ($($field:ident),* $(,)?) => {
+                $(if differs(&self.$field, &new.$field) {
+                    changes.needs_restart.push(stringify!($field));
+                })*
+            };
+        }
+
+        needs_restart!(
+            gameserver_address,
+            metrics_address,
+            query_address,
+            auth_server_address,
+            quic_files,
+            world_seed,
+            start_time,
+            calendar,
+            map_file,
+            banned_words_files,
+            spawn_town,
+            audit_log,
+            experimental_terrain_persistence,
+        );
+        live!(
+            max_players,
+            max_view_distance,
+            client_timeout,
+            safe_spawn,
+            battle_mode,
+            server_name,
+            max_player_group_size,
+            max_player_for_kill_broadcast,
+            allow_spectators,
+            moderation,
+        );
+
+        changes
+    }
+
     fn save_to_file(&self, path: &Path) -> std::io::Result<()> {
         // Create dir if it doesn't exist
         if let Some(dir) = path.parent() {
             fs::create_dir_all(dir)?;
         }
-        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
-            .expect("Failed serialize settings.");
-
-        fs::write(path, ron.as_bytes())?;
+        fs::write(path, self.to_ron().as_bytes())?;
 
         Ok(())
     }
 
+    /// The content of the settings file for these settings
+    fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("Failed serialize settings.")
+    }
+
     /// path: Directory that contains the server config directory
     pub fn singleplayer(path: &Path) -> Self {
         let load = Self::load(path);
@@ -177,6 +324,7 @@ impl Settings {
                 [127, 0, 0, 1],
                 pick_unused_port().expect("Failed to find unused port!"),
             )),
+            query_address: None,
             auth_server_address: None,
             quic_files: None,
             // If loading the default map file, make sure the seed is also default.
@@ -202,6 +350,102 @@ impl Settings {
     }
 }
 
+/// The fields that changed when reloading the settings file
+#[derive(Debug, Default)]
+pub struct SettingsChanges {
+    /// Fields whose new value is already in use
+    pub applied: Vec<&'static str>,
+    /// Fields that only take effect once the server is restarted
+    pub needs_restart: Vec<&'static str>,
+}
+
+#[derive(Clone, Copy)]
+enum SettingsFile {
+    Settings,
+    Whitelist,
+    Banlist,
+    ServerDescription,
+    Admins,
+}
+
+impl SettingsFile {
+    /// What the server writes to the file when saving the settings in use
+    fn saved_content(self, settings: &Settings, editable_settings: &EditableSettings) -> String {
+        match self {
+            Self::Settings => settings.to_ron(),
+            Self::Whitelist => editable_settings.whitelist.to_ron(),
+            Self::Banlist => editable_settings.banlist.to_ron(),
+            Self::ServerDescription => editable_settings.server_description.to_ron(),
+            Self::Admins => editable_settings.admins.to_ron(),
+        }
+    }
+}
+
+/// Detects edits of the settings files by polling their modification time.
+pub struct SettingsWatcher {
+    files: Vec<(SettingsFile, PathBuf, Option<SystemTime>)>,
+    last_poll: Instant,
+}
+
+impl SettingsWatcher {
+    const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+    /// data_dir: Directory that contains the server config directory
+    pub fn new(data_dir: &Path) -> Self {
+        let files = vec![
+            (
+                SettingsFile::Settings,
+                Settings::get_settings_path(data_dir),
+            ),
+            (SettingsFile::Whitelist, Whitelist::get_path(data_dir)),
+            (SettingsFile::Banlist, Banlist::get_path(data_dir)),
+            (
+                SettingsFile::ServerDescription,
+                ServerDescription::get_path(data_dir),
+            ),
+            (SettingsFile::Admins, Admins::get_path(data_dir)),
+        ]
+        .into_iter()
+        .map(|(file, path)| {
+            let modified = modified_time(&path);
+            (file, path, modified)
+        })
+        .collect();
+
+        Self {
+            files,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Whether a settings file was modified since the last poll, the files are
+    /// only checked once per [`SettingsWatcher::POLL_INTERVAL`]. Files written
+    /// by the server itself match the settings in use and are ignored.
+    pub fn poll(&mut self, settings: &Settings, editable_settings: &EditableSettings) -> bool {
+        if self.last_poll.elapsed() < Self::POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = false;
+        for (file, path, last_modified) in &mut self.files {
+            let modified = modified_time(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                let saved_by_server = fs::read_to_string(path.as_path()).map_or(false, |content| {
+                    content == file.saved_content(settings, editable_settings)
+                });
+                changed |= !saved_by_server;
+            }
+        }
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
 fn with_config_dir(path: &Path) -> PathBuf {
     let mut path = PathBuf::from(path);
     path.push(CONFIG_DIR);
@@ -238,6 +482,15 @@ impl EditableSettings {
         }
     }
 
+    /// Read the settings files again, the files that are missing or invalid
+    /// are skipped and keep their current settings.
+    pub fn reload(&mut self, data_dir: &Path) {
+        self.whitelist.reload(data_dir);
+        self.banlist.reload(data_dir);
+        self.server_description.reload(data_dir);
+        self.admins.reload(data_dir);
+    }
+
     pub fn singleplayer(data_dir: &Path) -> Self {
         let load = Self::load(data_dir);
 
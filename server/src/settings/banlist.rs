@@ -13,7 +13,7 @@ use serde::{Deserialize, Serialize};
 /// BanlistRaw, the TryFrom<BanlistRaw> for Banlist, the previously most recent
 /// module, and add a new module for the latest version!  Please respect the
 /// migration upgrade guarantee found in the parent module with any upgrade.
-pub use self::v1::*;
+pub use self::v2::*;
 
 /// Versioned settings files, one per version (v0 is only here as an example; we
 /// do not expect to see any actual v0 settings files).
@@ -21,12 +21,13 @@ pub use self::v1::*;
 pub enum BanlistRaw {
     V0(v0::Banlist),
     V1(v1::Banlist),
+    V2(v2::Banlist),
 }
 
 impl From<Banlist> for BanlistRaw {
     fn from(value: Banlist) -> Self {
         // Replace variant with that of current latest version.
-        Self::V1(value)
+        Self::V2(value)
     }
 }
 
@@ -38,9 +39,10 @@ impl TryFrom<BanlistRaw> for (Version, Banlist) {
         Ok(match value {
             // Old versions
             V0(value) => (Version::Old, value.try_into()?),
+            V1(value) => (Version::Old, value.try_into()?),
             // Latest version (move to old section using the pattern of other old version when it
             // is no longer latest).
-            V1(mut value) => (value.validate()?, value),
+            V2(mut value) => (value.validate()?, value),
         })
     }
 }
@@ -59,6 +61,8 @@ impl EditableSetting for Banlist {
 pub enum BanKind {
     Ban,
     Unban,
+    Mute,
+    Unmute,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -190,16 +194,20 @@ mod v0 {
 }
 
 mod v1 {
-    use super::{v0 as prev, BanError, BanErrorKind, BanKind, Final};
-    use crate::settings::editable::{EditableSetting, Error, Version};
+    use super::{
+        v0 as prev, v2 as next, BanError, BanErrorKind, BanKind, Final, MIGRATION_UPGRADE_GUARANTEE,
+    };
+    use crate::settings::editable::{EditableSetting, Version};
     use authc::Uuid;
     use chrono::{prelude::*, Utc};
     use common::comp::AdminRole;
-    use core::{mem, ops::Deref};
-    use hashbrown::{hash_map, HashMap};
+    use core::{
+        convert::{TryFrom, TryInto},
+        ops::Deref,
+    };
+    use hashbrown::HashMap;
     use serde::{Deserialize, Serialize};
     use tracing::warn;
-    /* use super::v2 as next; */
 
     /// Important: even if the role we are storing here appears to be identical
     /// to one used in another versioned store (like admin::Role), we *must*
@@ -304,7 +312,7 @@ mod v1 {
     impl BanRecord {
         /// Returns true if this record represents an expired ban, false
         /// otherwise.
-        fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        pub(super) fn is_expired(&self, now: DateTime<Utc>) -> bool {
             match &self.action {
                 BanAction::Ban(ban) => ban.is_expired(now),
                 BanAction::Unban(_) => true,
@@ -425,7 +433,7 @@ mod v1 {
         /// If we were invalid, returns an error.  Otherwise, returns Ok(v),
         /// where v is Latest if the hint bit was modified, Old
         /// otherwise.
-        fn validate(
+        pub(super) fn validate(
             &mut self,
             now: DateTime<Utc>,
             uuid: Uuid,
@@ -475,6 +483,147 @@ mod v1 {
         fn deref(&self) -> &Self::Target { &self.0 }
     }
 
+    impl Banlist {
+        /// One-off migration from the previous version.  This must be
+        /// guaranteed to produce a valid settings file as long as it is
+        /// called with a valid settings file from the previous version.
+        pub(super) fn migrate(prev: prev::Banlist) -> Self {
+            // The ban start date for migrations from legacy is the current one; we could
+            // record that they actually have an unknown start date, but this
+            // would just complicate the format.
+            let date = Utc::now();
+            Banlist(
+                prev.0
+                    .into_iter()
+                    .map(
+                        |(
+                            uid,
+                            prev::BanRecord {
+                                username_when_banned,
+                                reason,
+                            },
+                        )| {
+                            (uid, BanEntry {
+                                current: BanRecord {
+                                    username_when_performed: username_when_banned,
+                                    // We only recorded unbans pre-migration.
+                                    action: BanAction::Ban(Ban {
+                                        reason,
+                                        // We don't know who banned this user pre-migration.
+                                        info: None,
+                                        // All bans pre-migration are of unlimited duration.
+                                        end_date: None,
+                                    }),
+                                    date,
+                                },
+                                // Old bans never expire, so set the expiration hint to false.
+                                expired: false,
+                                // There is no known ban history yet.
+                                history: Vec::new(),
+                            })
+                        },
+                    )
+                    .collect(),
+            )
+        }
+
+        /// Perform any needed validation on this banlist that can't be done
+        /// using parsing.
+        ///
+        /// The returned version being "Old" indicates the loaded setting has
+        /// been modified during validation (this is why validate takes
+        /// `&mut self`).
+        pub(super) fn validate(&mut self) -> Result<Version, <Final as EditableSetting>::Error> {
+            let mut version = Version::Latest;
+            let now = Utc::now();
+            for (&uuid, value) in self.0.iter_mut() {
+                if matches!(value.validate(now, uuid)?, Version::Old) {
+                    // Update detected.
+                    version = Version::Old;
+                }
+            }
+            Ok(version)
+        }
+    }
+
+    /// Pretty much every TryFrom implementation except that of the very last
+    /// version should look exactly like this.
+    impl TryFrom<Banlist> for Final {
+        type Error = <Final as EditableSetting>::Error;
+
+        fn try_from(mut value: Banlist) -> Result<Final, Self::Error> {
+            value.validate()?;
+            Ok(next::Banlist::migrate(value)
+                .try_into()
+                .expect(MIGRATION_UPGRADE_GUARANTEE))
+        }
+    }
+}
+
+mod v2 {
+    use super::{v1 as prev, BanError, BanErrorKind, BanKind, Final};
+    use crate::settings::editable::{EditableSetting, Error, Version};
+    use authc::Uuid;
+    use chrono::{prelude::*, Utc};
+    use core::{mem, ops::Deref};
+    use hashbrown::{hash_map, HashMap};
+    use serde::{Deserialize, Serialize};
+    /* use super::v3 as next; */
+
+    /// The ban records didn't change since the previous version, only mutes
+    /// were added.
+    ///
+    /// NOTE: When the ban records change, copy them into the new version
+    /// instead of modifying them in the previous one.
+    pub use prev::{Ban, BanAction, BanEntry, BanInfo, BanRecord, Role};
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub struct Mute {
+        pub reason: String,
+        pub info: BanInfo,
+        /// NOTE: Should always be higher than the date of the record, if
+        /// present!
+        pub end_date: Option<DateTime<Utc>>,
+    }
+
+    impl Mute {
+        /// Returns true if the mute is expired, false otherwise.
+        pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+            self.end_date.map_or(false, |end_date| end_date <= now)
+        }
+    }
+
+    type Unmute = BanInfo;
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub enum MuteAction {
+        Unmute(Unmute),
+        Mute(Mute),
+    }
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub struct MuteRecord {
+        /// Username of the user upon whom the mute was performed, when it was
+        /// performed.
+        pub username_when_performed: String,
+        pub mute: Mute,
+        pub date: DateTime<Utc>,
+    }
+
+    #[derive(Clone, Deserialize, Serialize, Default)]
+    pub struct Banlist {
+        pub(super) bans: HashMap<Uuid, BanEntry>,
+        /// Only the mutes currently in effect are kept, unmuting a user or
+        /// the mute expiring removes the record.
+        pub(super) mutes: HashMap<Uuid, MuteRecord>,
+    }
+
+    impl Deref for Banlist {
+        type Target = HashMap<Uuid, BanEntry>;
+
+        fn deref(&self) -> &Self::Target { &self.bans }
+    }
+
     impl Banlist {
         /// Attempt to perform the ban action `action` for the user with UUID
         /// `uuid` and username `username`, starting from itme `now`
@@ -539,7 +688,7 @@ mod v1 {
             // Perform an atomic edit.
             Some(
                 self.edit(data_dir.as_ref(), |banlist| {
-                    match banlist.0.entry(uuid) {
+                    match banlist.bans.entry(uuid) {
                         hash_map::Entry::Vacant(v) => {
                             // If this is an unban, it will have no effect, so return early.
                             if matches!(ban_record.action, BanAction::Unban(_)) {
@@ -584,50 +733,90 @@ mod v1 {
                 .1,
             )
         }
-    }
 
-    impl Banlist {
+        /// Returns the mute of the user with UUID `uuid`, if they are muted at
+        /// time `now`.
+        pub fn mute(&self, uuid: &Uuid, now: DateTime<Utc>) -> Option<&MuteRecord> {
+            self.mutes
+                .get(uuid)
+                .filter(|record| !record.mute.is_expired(now))
+        }
+
+        /// Attempt to perform the mute action `action` for the user with UUID
+        /// `uuid` and username `username_when_performed`, starting from time
+        /// `now`, with a settings file maintained at path root `data_dir`.
+        ///
+        /// This works the same way as [`Banlist::ban_action`]: None is returned
+        /// if the action would have no effect (muting an already muted user
+        /// without `overwrite`, or unmuting a user that isn't muted).
+        ///
+        /// A mute can only be lifted or shortened by a user whose role is at
+        /// least the role of the user who performed it.
+        #[must_use]
+        pub fn mute_action(
+            &mut self,
+            data_dir: &std::path::Path,
+            now: DateTime<Utc>,
+            uuid: Uuid,
+            username_when_performed: String,
+            action: MuteAction,
+            overwrite: bool,
+        ) -> Option<Result<(), Error<Final>>> {
+            let current = self.mute(&uuid, now);
+            let permission_denied = match (&action, current) {
+                (MuteAction::Mute(_), Some(_)) if !overwrite => return None,
+                (MuteAction::Unmute(_), None) => return None,
+                (MuteAction::Mute(_), None) => None,
+                (MuteAction::Mute(new_mute), Some(old)) => {
+                    let shortened = match (new_mute.end_date, old.mute.end_date) {
+                        (Some(_), None) => true,
+                        (Some(new_date), Some(old_date)) => new_date < old_date,
+                        (None, _) => false,
+                    };
+                    (new_mute.info.performed_by_role < old.mute.info.performed_by_role && shortened)
+                        .then(|| BanKind::Mute)
+                },
+                (MuteAction::Unmute(unmute), Some(old)) => (unmute.performed_by_role
+                    < old.mute.info.performed_by_role)
+                    .then(|| BanKind::Unmute),
+            };
+            if let Some(kind) = permission_denied {
+                return Some(Err(Error::Integrity(BanError {
+                    kind: BanErrorKind::PermissionDenied(kind),
+                    uuid,
+                    username: username_when_performed,
+                })));
+            }
+
+            // Perform an atomic edit.
+            Some(
+                self.edit(data_dir.as_ref(), |banlist| {
+                    match action {
+                        MuteAction::Mute(mute) => {
+                            banlist.mutes.insert(uuid, MuteRecord {
+                                username_when_performed,
+                                mute,
+                                date: now,
+                            });
+                        },
+                        MuteAction::Unmute(_) => {
+                            banlist.mutes.remove(&uuid);
+                        },
+                    }
+                    Some(())
+                })?
+                .1,
+            )
+        }
+
         /// One-off migration from the previous version.  This must be
         /// guaranteed to produce a valid settings file as long as it is
         /// called with a valid settings file from the previous version.
         pub(super) fn migrate(prev: prev::Banlist) -> Self {
-            // The ban start date for migrations from legacy is the current one; we could
-            // record that they actually have an unknown start date, but this
-            // would just complicate the format.
-            let date = Utc::now();
-            Banlist(
-                prev.0
-                    .into_iter()
-                    .map(
-                        |(
-                            uid,
-                            prev::BanRecord {
-                                username_when_banned,
-                                reason,
-                            },
-                        )| {
-                            (uid, BanEntry {
-                                current: BanRecord {
-                                    username_when_performed: username_when_banned,
-                                    // We only recorded unbans pre-migration.
-                                    action: BanAction::Ban(Ban {
-                                        reason,
-                                        // We don't know who banned this user pre-migration.
-                                        info: None,
-                                        // All bans pre-migration are of unlimited duration.
-                                        end_date: None,
-                                    }),
-                                    date,
-                                },
-                                // Old bans never expire, so set the expiration hint to false.
-                                expired: false,
-                                // There is no known ban history yet.
-                                history: Vec::new(),
-                            })
-                        },
-                    )
-                    .collect(),
-            )
+            Banlist {
+                bans: prev.0,
+                mutes: HashMap::new(),
+            }
         }
 
         /// Perform any needed validation on this banlist that can't be done
@@ -639,12 +828,35 @@ mod v1 {
         pub(super) fn validate(&mut self) -> Result<Version, <Final as EditableSetting>::Error> {
             let mut version = Version::Latest;
             let now = Utc::now();
-            for (&uuid, value) in self.0.iter_mut() {
+            for (&uuid, value) in self.bans.iter_mut() {
                 if matches!(value.validate(now, uuid)?, Version::Old) {
                     // Update detected.
                     version = Version::Old;
                 }
             }
+
+            for (&uuid, record) in self.mutes.iter() {
+                // End date of a mute must be at least as big as the start date.
+                if let Some(end_date) = record.mute.end_date {
+                    if record.date > end_date {
+                        return Err(BanError {
+                            kind: BanErrorKind::InvalidDateRange {
+                                start_date: record.date,
+                                end_date,
+                            },
+                            uuid,
+                            username: record.username_when_performed.clone(),
+                        });
+                    }
+                }
+            }
+            // Expired mutes are dropped, resave the file if there were any.
+            let mute_count = self.mutes.len();
+            self.mutes.retain(|_, record| !record.mute.is_expired(now));
+            if self.mutes.len() != mute_count {
+                version = Version::Old;
+            }
+
             Ok(version)
         }
     }
@@ -77,34 +77,7 @@ pub trait EditableSetting: Clone + Default {
         let path = Self::get_path(data_dir);
 
         if let Ok(mut file) = fs::File::open(&path) {
-            match ron::de::from_reader(&mut file)
-                .map(|setting: Self::Setting| setting.try_into())
-                .or_else(|orig_err| {
-                    file.seek(SeekFrom::Start(0))?;
-                    ron::de::from_reader(file)
-                         .map(|legacy| Ok((Version::Old, Self::Legacy::into(legacy))))
-                         // When both legacy and nonlegacy have parse errors, prioritize the
-                         // nonlegacy one, since we can't tell which one is "right" and legacy
-                         // formats are simple, early, and uncommon enough that we expect
-                         // few parse errors in those.
-                         .or(Err(orig_err))
-                })
-                .map_err(|e| {
-                    warn!(
-                        ?e,
-                        "Failed to parse setting file! Falling back to default and moving \
-                         existing file to a .invalid"
-                    );
-                })
-                .and_then(|inner| {
-                    inner.map_err(|e| {
-                        warn!(
-                            ?e,
-                            "Failed to parse setting file! Falling back to default and moving \
-                             existing file to a .invalid"
-                        );
-                    })
-                }) {
+            match parse_file(&mut file) {
                 Ok((version, mut settings)) => {
                     if matches!(version, Version::Old) {
                         // Old version, which means we either performed a migration or there was
@@ -131,6 +104,7 @@ pub trait EditableSetting: Clone + Default {
                     settings
                 },
                 Err(()) => {
+                    warn!("Falling back to default and moving existing file to a .invalid");
                     // Rename existing file to .invalid.ron
                     let mut new_path = path.with_extension("invalid.ron");
 
@@ -160,6 +134,39 @@ pub trait EditableSetting: Clone + Default {
         }
     }
 
+    /// The content of the settings file for these settings, as written when
+    /// they are saved
+    fn to_ron(&self) -> String {
+        let raw: Self::Setting = self.clone().into();
+        ron::ser::to_string_pretty(&raw, ron::ser::PrettyConfig::default())
+            .expect("RON does not throw any parse errors during serialization to string.")
+    }
+
+    /// Read the settings file again and replace the settings in memory with
+    /// it. Unlike [`EditableSetting::load`], a missing or invalid file is left
+    /// untouched and the current settings are kept, in which case `false` is
+    /// returned.
+    fn reload(&mut self, data_dir: &Path) -> bool {
+        let path = Self::get_path(data_dir);
+
+        match fs::File::open(&path) {
+            Ok(mut file) => match parse_file(&mut file) {
+                Ok((_, settings)) => {
+                    *self = settings;
+                    true
+                },
+                Err(()) => {
+                    warn!(?path, "Keeping the current settings");
+                    false
+                },
+            },
+            Err(e) => {
+                warn!(?e, ?path, "Failed to open setting file");
+                false
+            },
+        }
+    }
+
     /// If the result of calling f is None,we return None (this constitutes an
     /// early return and lets us abandon the in-progress edit).  For
     /// example, this can be used to avoid adding a new ban entry if someone
@@ -221,6 +228,30 @@ pub trait EditableSetting: Clone + Default {
     }
 }
 
+/// Parse a settings file, migrating it to the latest version if needed
+fn parse_file<S: EditableSetting>(file: &mut fs::File) -> Result<(Version, S), ()> {
+    ron::de::from_reader(&mut *file)
+        .map(|setting: S::Setting| setting.try_into())
+        .or_else(|orig_err| {
+            file.seek(SeekFrom::Start(0))?;
+            ron::de::from_reader(&mut *file)
+                 .map(|legacy| Ok((Version::Old, S::Legacy::into(legacy))))
+                 // When both legacy and nonlegacy have parse errors, prioritize the
+                 // nonlegacy one, since we can't tell which one is "right" and legacy
+                 // formats are simple, early, and uncommon enough that we expect
+                 // few parse errors in those.
+                 .or(Err(orig_err))
+        })
+        .map_err(|e| {
+            warn!(?e, "Failed to parse setting file!");
+        })
+        .and_then(|inner| {
+            inner.map_err(|e| {
+                warn!(?e, "Failed to validate setting file!");
+            })
+        })
+}
+
 fn save_to_file<S: EditableSetting>(setting: S, path: &Path) -> Result<S, ErrorInternal<S>> {
     let raw: <S as EditableSetting>::Setting = setting.into();
     let ron = ron::ser::to_string_pretty(&raw, ron::ser::PrettyConfig::default())
@@ -2,12 +2,13 @@ use crate::{
     client::Client,
     persistence::PersistedComponents,
     pet::restore_pet,
-    presence::{Presence, RepositionOnChunkLoad},
+    presence::{Presence, RepositionOnChunkLoad, Spectating},
     settings::Settings,
-    sys::sentinel::DeletedEntities,
+    sys::{self, sentinel::DeletedEntities},
     wiring, BattleModeBuffer, SpawnPoint,
 };
 use common::{
+    calendar::Calendar,
     character::CharacterId,
     combat,
     combat::DamageContributor,
@@ -20,9 +21,10 @@ use common::{
     resources::{Time, TimeOfDay},
     slowjob::SlowJobPool,
     uid::{Uid, UidAllocator},
+    weather::{self, WeatherGrid},
 };
 use common_net::{
-    msg::{CharacterInfo, PlayerListUpdate, PresenceKind, ServerGeneral},
+    msg::{CharacterInfo, PlayerListUpdate, PresenceKind, ServerGeneral, SpectateTarget},
     sync::WorldSyncExt,
 };
 use common_state::State;
@@ -102,6 +104,9 @@ pub trait StateExt {
     ) -> EcsEntityBuilder;
     /// Insert common/default components for a new character joining the server
     fn initialize_character_data(&mut self, entity: EcsEntity, character_id: CharacterId);
+    /// Insert the components for a client that watches without a character,
+    /// it has a position to sync the world around but no body
+    fn initialize_spectator_data(&mut self, entity: EcsEntity);
     /// Update the components associated with the entity's current character.
     /// Performed after loading component data from the database
     fn update_character_data(&mut self, entity: EcsEntity, components: PersistedComponents);
@@ -434,7 +439,7 @@ impl StateExt for State {
             .for_each(|chunk_key| {
                 #[cfg(feature = "worldgen")]
                 {
-                    chunk_generator.generate_chunk(None, chunk_key, &slow_jobs, Arc::clone(world), index.clone(), *ecs.read_resource::<TimeOfDay>());
+                    chunk_generator.generate_chunk(None, chunk_key, &slow_jobs, Arc::clone(world), index.clone(), (*ecs.read_resource::<TimeOfDay>(), *ecs.read_resource::<Calendar>()));
                 }
             });
         }
@@ -482,12 +487,54 @@ impl StateExt for State {
             // Tell the client its request was successful.
             if let Some(client) = self.ecs().read_storage::<Client>().get(entity) {
                 client.send_fallible(ServerGeneral::CharacterSuccess);
+                // Don't wait for the next periodic sync of the weather
+                if let Some(pos) = self.read_component_copied::<comp::Pos>(entity) {
+                    client.send_fallible(ServerGeneral::WeatherUpdate(
+                        self.ecs()
+                            .read_resource::<WeatherGrid>()
+                            .patch_around(pos.0.xy(), weather::SYNC_RADIUS),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn initialize_spectator_data(&mut self, entity: EcsEntity) {
+        let spawn_point = self.ecs().read_resource::<SpawnPoint>().0;
+
+        if self.read_component_copied::<Uid>(entity).is_some() {
+            // NOTE: By fetching the player_uid, we validated that the entity exists, so we
+            // can ignore the result of insertion.
+            self.write_component_ignore_entity_dead(entity, comp::Pos(spawn_point));
+            self.write_component_ignore_entity_dead(entity, comp::ForceUpdate);
+            self.write_component_ignore_entity_dead(
+                entity,
+                Spectating(SpectateTarget::Position(spawn_point)),
+            );
+
+            const INITIAL_VD: u32 = 5; //will be changed after login
+            self.write_component_ignore_entity_dead(
+                entity,
+                Presence::new(INITIAL_VD, PresenceKind::Spectator),
+            );
+            // Spectators are synced the entities around them like players
+            sys::subscription::initialize_region_subscription(self.ecs(), entity);
+
+            // Tell the client its request was successful.
+            if let Some(client) = self.ecs().read_storage::<Client>().get(entity) {
+                client.send_fallible(ServerGeneral::CharacterSuccess);
+                // Don't wait for the next periodic sync of the weather
+                client.send_fallible(ServerGeneral::WeatherUpdate(
+                    self.ecs()
+                        .read_resource::<WeatherGrid>()
+                        .patch_around(spawn_point.xy(), weather::SYNC_RADIUS),
+                ));
             }
         }
     }
 
     fn update_character_data(&mut self, entity: EcsEntity, components: PersistedComponents) {
-        let (body, stats, skill_set, inventory, waypoint, pets) = components;
+        let (body, stats, skill_set, inventory, waypoint, pets, stash) = components;
 
         if let Some(player_uid) = self.read_component_copied::<Uid>(entity) {
             // Notify clients of a player list update
@@ -529,6 +576,7 @@ impl StateExt for State {
                 entity,
                 comp::InventoryUpdate::new(comp::InventoryUpdateEvent::default()),
             );
+            self.write_component_ignore_entity_dead(entity, stash);
 
             if let Some(waypoint) = waypoint {
                 self.write_component_ignore_entity_dead(entity, RepositionOnChunkLoad);
@@ -900,3 +948,70 @@ fn capsule(body: &comp::Body) -> comp::Collider {
         z_max: body.height(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presence::{DeltaSync, RegionSubscription};
+    use common_net::msg::ClientType;
+    use network::{ConnectAddr, ListenAddr, Network, Pid, Promises};
+    use tokio::runtime::Runtime;
+
+    /// A client connected through an in-process channel. The networks have
+    /// to be kept alive as long as the client is used.
+    fn connect_client(runtime: &Runtime) -> (Network, Network, Client) {
+        let server_network = Network::new(Pid::new(), runtime);
+        let client_network = Network::new(Pid::new(), runtime);
+        let addr = rand::random();
+        runtime
+            .block_on(server_network.listen(ListenAddr::Mpsc(addr)))
+            .unwrap();
+        let participant = runtime
+            .block_on(client_network.connect(ConnectAddr::Mpsc(addr)))
+            .unwrap();
+        let mut streams = (0..6)
+            .map(|_| runtime.block_on(participant.open(3, Promises::ORDERED, 0)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+            .into_iter();
+        let mut stream = || streams.next().unwrap();
+        let client = Client::new(
+            ClientType::Game,
+            participant,
+            0.0,
+            stream(),
+            stream(),
+            stream(),
+            stream(),
+            stream(),
+            stream(),
+        );
+        (server_network, client_network, client)
+    }
+
+    #[test]
+    fn spectator_gets_region_subscription() {
+        let runtime = Runtime::new().unwrap();
+        let (_server_network, _client_network, client) = connect_client(&runtime);
+
+        let mut state = State::server();
+        state.ecs_mut().register::<RegionSubscription>();
+        state.ecs_mut().register::<DeltaSync>();
+        state.ecs_mut().register::<Client>();
+        state.ecs_mut().register::<Presence>();
+        state.ecs_mut().register::<Spectating>();
+        state.ecs_mut().insert(SpawnPoint::default());
+        state.ecs_mut().insert(WeatherGrid::new(Vec2::one()));
+        let entity = state.ecs_mut().create_entity_synced().with(client).build();
+
+        state.initialize_spectator_data(entity);
+
+        assert!(
+            state
+                .ecs()
+                .read_storage::<RegionSubscription>()
+                .contains(entity)
+        );
+        assert!(state.ecs().read_storage::<DeltaSync>().contains(entity));
+    }
+}
@@ -37,6 +37,7 @@ use common::{
     uid::{Uid, UidAllocator},
     util::Dir,
     vol::ReadVol,
+    weather::{WeatherGrid, WeatherKind},
 };
 use common_base::prof_span;
 use common_ecs::{Job, Origin, ParMode, Phase, System};
@@ -155,6 +156,7 @@ pub struct ReadData<'a> {
     bodies: ReadStorage<'a, Body>,
     mount_states: ReadStorage<'a, MountState>,
     time_of_day: Read<'a, TimeOfDay>,
+    weather: Read<'a, WeatherGrid>,
     light_emitter: ReadStorage<'a, LightEmitter>,
     #[cfg(feature = "worldgen")]
     world: ReadExpect<'a, Arc<world::World>>,
@@ -1142,39 +1144,36 @@ impl<'a> AgentData<'a> {
                                             // implement more mood contexts
                                             // This require that town NPCs becomes rtsim_entities to
                                             // work fully.
-                                            match rand::random::<u32>() % 3 {
-                                                0 => agent.rtsim_controller.events.push(
-                                                    RtSimEvent::SetMood(Memory {
-                                                        item: MemoryItem::Mood {
-                                                            state: MoodState::Good(
-                                                                MoodContext::GoodWeather,
-                                                            ),
-                                                        },
-                                                        time_to_forget: read_data.time.0 + 21200.0,
-                                                    }),
-                                                ),
-                                                1 => agent.rtsim_controller.events.push(
-                                                    RtSimEvent::SetMood(Memory {
-                                                        item: MemoryItem::Mood {
-                                                            state: MoodState::Neutral(
-                                                                MoodContext::EverydayLife,
-                                                            ),
-                                                        },
-                                                        time_to_forget: read_data.time.0 + 21200.0,
-                                                    }),
-                                                ),
-                                                2 => agent.rtsim_controller.events.push(
-                                                    RtSimEvent::SetMood(Memory {
-                                                        item: MemoryItem::Mood {
-                                                            state: MoodState::Bad(
-                                                                MoodContext::GoodWeather,
-                                                            ),
-                                                        },
-                                                        time_to_forget: read_data.time.0 + 86400.0,
-                                                    }),
-                                                ),
-                                                _ => {}, // will never happen
-                                            }
+                                            let weather =
+                                                read_data.weather.get_interpolated(self.pos.0.xy());
+                                            let (state, forget_after) =
+                                                match (rand::random::<bool>(), weather.kind()) {
+                                                    (true, WeatherKind::Clear) => (
+                                                        MoodState::Good(MoodContext::GoodWeather),
+                                                        21200.0,
+                                                    ),
+                                                    (
+                                                        true,
+                                                        WeatherKind::Rain
+                                                        | WeatherKind::Snow
+                                                        | WeatherKind::Storm,
+                                                    ) => (
+                                                        MoodState::Bad(MoodContext::BadWeather),
+                                                        86400.0,
+                                                    ),
+                                                    _ => (
+                                                        MoodState::Neutral(
+                                                            MoodContext::EverydayLife,
+                                                        ),
+                                                        21200.0,
+                                                    ),
+                                                };
+                                            agent.rtsim_controller.events.push(
+                                                RtSimEvent::SetMood(Memory {
+                                                    item: MemoryItem::Mood { state },
+                                                    time_to_forget: read_data.time.0 + forget_after,
+                                                }),
+                                            );
                                         }
                                         if let Some(memory) = rtsim_entity.brain.get_mood() {
                                             let msg = match &memory.item {
@@ -1,7 +1,7 @@
 use super::sentinel::{DeletedEntities, ReadTrackers, TrackedComps};
 use crate::{
     client::Client,
-    presence::{Presence, RegionSubscription},
+    presence::{DeltaSync, Presence, RegionSubscription},
     Tick,
 };
 use common::{
@@ -14,7 +14,10 @@ use common::{
     vol::RectVolSize,
 };
 use common_ecs::{Job, Origin, Phase, System};
-use common_net::{msg::ServerGeneral, sync::CompSyncPackage};
+use common_net::{
+    msg::{delta::DeltaComp, DeltaSyncStats, EcsCompPacket, ServerGeneral},
+    sync::CompSyncPackage,
+};
 use itertools::Either;
 use specs::{Entities, Join, Read, ReadExpect, ReadStorage, Write, WriteStorage};
 use vek::*;
@@ -41,6 +44,8 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, Last<Vel>>,
         WriteStorage<'a, Last<Ori>>,
         ReadStorage<'a, Client>,
+        WriteStorage<'a, DeltaSync>,
+        Write<'a, DeltaSyncStats>,
         WriteStorage<'a, ForceUpdate>,
         WriteStorage<'a, InventoryUpdate>,
         Write<'a, DeletedEntities>,
@@ -74,6 +79,8 @@ impl<'a> System<'a> for Sys {
             mut last_vel,
             mut last_ori,
             clients,
+            mut delta_syncs,
+            mut delta_sync_stats,
             mut force_updates,
             mut inventory_updates,
             mut deleted_entities,
@@ -113,15 +120,18 @@ impl<'a> System<'a> for Sys {
                 presences.maybe(),
                 &subscriptions,
                 &positions,
+                (&mut delta_syncs).maybe(),
             )
                 .join()
-                .filter_map(|(client, entity, presence, subscription, pos)| {
-                    if presence.is_some() && subscription.regions.contains(&key) {
-                        Some((client, &subscription.regions, entity, *pos))
-                    } else {
-                        None
-                    }
-                })
+                .filter_map(
+                    |(client, entity, presence, subscription, pos, delta_sync)| {
+                        if presence.is_some() && subscription.regions.contains(&key) {
+                            Some((client, &subscription.regions, entity, *pos, delta_sync))
+                        } else {
+                            None
+                        }
+                    },
+                )
                 .collect::<Vec<_>>();
 
             for event in region.events() {
@@ -145,7 +155,7 @@ impl<'a> System<'a> for Sys {
                             })
                         {
                             let create_msg = ServerGeneral::CreateEntity(pkg);
-                            for (client, regions, client_entity, _) in &mut subscribers {
+                            for (client, regions, client_entity, _, _) in &mut subscribers {
                                 if maybe_key
                                     .as_ref()
                                     .map(|key| !regions.contains(key))
@@ -161,7 +171,7 @@ impl<'a> System<'a> for Sys {
                     RegionEvent::Left(id, maybe_key) => {
                         // Lookup UID for entity
                         if let Some(&uid) = uids.get(entities.entity(*id)) {
-                            for (client, regions, _, _) in &mut subscribers {
+                            for (client, regions, _, _, _) in &mut subscribers {
                                 if maybe_key
                                     .as_ref()
                                     .map(|key| !regions.contains(key))
@@ -187,7 +197,7 @@ impl<'a> System<'a> for Sys {
             // We lazily initializethe the synchronization messages in case there are no
             // clients.
             let mut entity_comp_sync = Either::Left((entity_sync_package, comp_sync_package));
-            for (client, _, _, _) in &mut subscribers {
+            for (client, _, _, _, _) in &mut subscribers {
                 let msg =
                     entity_comp_sync.right_or_else(|(entity_sync_package, comp_sync_package)| {
                         (
@@ -202,7 +212,7 @@ impl<'a> System<'a> for Sys {
                 entity_comp_sync = Either::Right(msg);
             }
 
-            for (client, _, client_entity, client_pos) in &mut subscribers {
+            for (client, _, client_entity, client_pos, delta_sync) in &mut subscribers {
                 let mut comp_sync_package = CompSyncPackage::new();
 
                 for (_, entity, &uid, (&pos, last_pos), vel, ori, force_update, collider) in (
@@ -257,14 +267,19 @@ impl<'a> System<'a> for Sys {
                     if last_pos.is_none() {
                         comp_sync_package.comp_inserted(uid, pos);
                     } else if send_now {
-                        comp_sync_package.comp_modified(uid, pos);
+                        sync_modified(&mut comp_sync_package, delta_sync.as_deref_mut(), uid, pos);
                     }
 
                     if let Some((v, last_vel)) = vel {
                         if last_vel.is_none() {
                             comp_sync_package.comp_inserted(uid, *v);
                         } else if send_now {
-                            comp_sync_package.comp_modified(uid, *v);
+                            sync_modified(
+                                &mut comp_sync_package,
+                                delta_sync.as_deref_mut(),
+                                uid,
+                                *v,
+                            );
                         }
                     }
 
@@ -272,11 +287,49 @@ impl<'a> System<'a> for Sys {
                         if last_ori.is_none() {
                             comp_sync_package.comp_inserted(uid, *o);
                         } else if send_now {
-                            comp_sync_package.comp_modified(uid, *o);
+                            sync_modified(
+                                &mut comp_sync_package,
+                                delta_sync.as_deref_mut(),
+                                uid,
+                                *o,
+                            );
                         }
                     }
                 }
 
+                // Energy and health are left out of the tracked components, so they can be
+                // delta encoded for each client
+                for (_, &uid, energy, _) in (
+                    region.entities(),
+                    &uids,
+                    &tracked_comps.energy,
+                    trackers.energy.modified(),
+                )
+                    .join()
+                {
+                    sync_modified(
+                        &mut comp_sync_package,
+                        delta_sync.as_deref_mut(),
+                        uid,
+                        *energy,
+                    );
+                }
+                for (_, &uid, health, _) in (
+                    region.entities(),
+                    &uids,
+                    &tracked_comps.health,
+                    trackers.health.modified(),
+                )
+                    .join()
+                {
+                    sync_modified(
+                        &mut comp_sync_package,
+                        delta_sync.as_deref_mut(),
+                        uid,
+                        health.clone(),
+                    );
+                }
+
                 client.send_fallible(ServerGeneral::CompSync(comp_sync_package));
             }
 
@@ -299,6 +352,15 @@ impl<'a> System<'a> for Sys {
             }
         }
 
+        // Send the delta synced components of all regions in one package, so the
+        // client can acknowledge them at once
+        for (client, delta_sync) in (&clients, &mut delta_syncs).join() {
+            if let Some(package) = delta_sync.0.take_package() {
+                client.send_fallible(ServerGeneral::DeltaSync(package));
+            }
+            *delta_sync_stats += delta_sync.0.take_stats();
+        }
+
         // Handle entity deletion in regions that don't exist in RegionMap
         // (theoretically none)
         for (region_key, deleted) in deleted_entities.take_remaining_deleted() {
@@ -370,3 +432,17 @@ impl<'a> System<'a> for Sys {
         }
     }
 }
+
+/// Delta encodes the modification of `comp` if the client keeps track of what
+/// it received
+fn sync_modified(
+    comp_sync_package: &mut CompSyncPackage<EcsCompPacket>,
+    delta_sync: Option<&mut DeltaSync>,
+    uid: Uid,
+    comp: impl Into<DeltaComp>,
+) {
+    match delta_sync {
+        Some(delta_sync) => delta_sync.0.modified(uid, comp),
+        None => comp_sync_package.comp_modified(uid, EcsCompPacket::from(comp.into())),
+    }
+}
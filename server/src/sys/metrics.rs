@@ -1,10 +1,11 @@
 use crate::{
-    metrics::{EcsSystemMetrics, JobMetrics, PhysicsMetrics, TickMetrics},
+    metrics::{DeltaSyncMetrics, EcsSystemMetrics, JobMetrics, PhysicsMetrics, TickMetrics},
     HwStats, Tick, TickStart,
 };
 use common::{resources::TimeOfDay, slowjob::SlowJobPool, terrain::TerrainGrid};
 use common_ecs::{Job, Origin, Phase, SysMetrics, System};
-use specs::{Entities, Join, Read, ReadExpect};
+use common_net::msg::DeltaSyncStats;
+use specs::{Entities, Join, Read, ReadExpect, Write};
 use std::time::Instant;
 
 /// This system exports metrics
@@ -21,11 +22,13 @@ impl<'a> System<'a> for Sys {
         Option<Read<'a, TerrainGrid>>,
         Read<'a, SysMetrics>,
         Read<'a, common_ecs::PhysicsMetrics>,
+        Write<'a, DeltaSyncStats>,
         ReadExpect<'a, SlowJobPool>,
         ReadExpect<'a, EcsSystemMetrics>,
         ReadExpect<'a, TickMetrics>,
         ReadExpect<'a, PhysicsMetrics>,
         ReadExpect<'a, JobMetrics>,
+        ReadExpect<'a, DeltaSyncMetrics>,
     );
 
     const NAME: &'static str = "metrics";
@@ -43,11 +46,13 @@ impl<'a> System<'a> for Sys {
             terrain,
             sys_metrics,
             phys_metrics,
+            mut delta_sync_stats,
             slowjobpool,
             export_ecs,
             export_tick,
             export_physics,
             export_jobs,
+            export_delta_sync,
         ): Self::SystemData,
     ) {
         const NANOSEC_PER_SEC: f64 = std::time::Duration::from_secs(1).as_nanos() as f64;
@@ -118,6 +123,17 @@ impl<'a> System<'a> for Sys {
             .entity_entity_collisions_count
             .inc_by(phys_metrics.entity_entity_collisions);
 
+        //bandwidth saved by delta sync
+        let delta_sync = std::mem::take(&mut *delta_sync_stats);
+        export_delta_sync
+            .full_updates
+            .inc_by(delta_sync.full_updates);
+        export_delta_sync
+            .delta_updates
+            .inc_by(delta_sync.delta_updates);
+        export_delta_sync.full_bytes.inc_by(delta_sync.full_bytes);
+        export_delta_sync.sent_bytes.inc_by(delta_sync.sent_bytes);
+
         //detailed job metrics
         for (name, jobs) in slowjobpool.take_metrics() {
             let queried = export_jobs.job_queried_hst.with_label_values(&[&name]);
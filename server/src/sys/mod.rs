@@ -7,6 +7,7 @@ pub mod object;
 pub mod persistence;
 pub mod pets;
 pub mod sentinel;
+pub mod spectator;
 pub mod subscription;
 pub mod terrain;
 pub mod terrain_sync;
@@ -29,6 +30,7 @@ pub fn add_server_systems(dispatch_builder: &mut DispatcherBuilder) {
     dispatch::<agent::Sys>(dispatch_builder, &[]);
     dispatch::<terrain::Sys>(dispatch_builder, &[&msg::terrain::Sys::sys_name()]);
     dispatch::<waypoint::Sys>(dispatch_builder, &[]);
+    dispatch::<spectator::Sys>(dispatch_builder, &[]);
     dispatch::<invite_timeout::Sys>(dispatch_builder, &[]);
     dispatch::<persistence::Sys>(dispatch_builder, &[]);
     dispatch::<object::Sys>(dispatch_builder, &[]);
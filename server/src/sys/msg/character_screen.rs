@@ -4,10 +4,10 @@ use crate::{
     client::Client,
     persistence::{character_loader::CharacterLoader, character_updater::CharacterUpdater},
     presence::Presence,
-    EditableSettings,
+    EditableSettings, Settings,
 };
 use common::{
-    comp::{ChatType, Player, UnresolvedChatMsg},
+    comp::{Admin, ChatType, Player, UnresolvedChatMsg},
     event::{EventBus, ServerEvent},
     uid::Uid,
 };
@@ -15,7 +15,7 @@ use common_ecs::{Job, Origin, Phase, System};
 use common_net::msg::{ClientGeneral, ServerGeneral};
 use specs::{Entities, Join, Read, ReadExpect, ReadStorage, WriteExpect};
 use std::sync::atomic::Ordering;
-use tracing::{debug, warn};
+use tracing::debug;
 
 impl Sys {
     #[allow(clippy::too_many_arguments)]
@@ -28,6 +28,8 @@ impl Sys {
         uids: &ReadStorage<'_, Uid>,
         players: &ReadStorage<'_, Player>,
         presences: &ReadStorage<'_, Presence>,
+        admins: &ReadStorage<'_, Admin>,
+        settings: &ReadExpect<'_, Settings>,
         editable_settings: &ReadExpect<'_, EditableSettings>,
         alias_validator: &ReadExpect<'_, AliasValidator>,
         msg: ClientGeneral,
@@ -36,7 +38,15 @@ impl Sys {
             // Request spectator state
             ClientGeneral::Spectate => {
                 if players.contains(entity) {
-                    warn!("Spectator mode not yet implemented on server");
+                    if presences.contains(entity) {
+                        debug!("player already ingame, aborting");
+                    } else if settings.allow_spectators || admins.contains(entity) {
+                        server_emitter.emit(ServerEvent::InitSpectator { entity });
+                    } else {
+                        client.send(ServerGeneral::CharacterDataLoadError(
+                            "This server doesn't allow spectators".to_string(),
+                        ))?;
+                    }
                 } else {
                     debug!("dropped Spectate msg from unregistered client")
                 }
@@ -48,6 +58,7 @@ impl Sys {
                     } else if character_updater
                         .characters_pending_logout()
                         .any(|x| x == character_id)
+                        || character_updater.player_pending_logout(&player.uuid().to_string())
                     {
                         debug!("player recently logged out pending persistence, aborting");
                         client.send(ServerGeneral::CharacterDataLoadError(
@@ -174,6 +185,8 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Client>,
         ReadStorage<'a, Player>,
         ReadStorage<'a, Presence>,
+        ReadStorage<'a, Admin>,
+        ReadExpect<'a, Settings>,
         ReadExpect<'a, EditableSettings>,
         ReadExpect<'a, AliasValidator>,
     );
@@ -193,6 +206,8 @@ impl<'a> System<'a> for Sys {
             clients,
             players,
             presences,
+            admins,
+            settings,
             editable_settings,
             alias_validator,
         ): Self::SystemData,
@@ -210,6 +225,8 @@ impl<'a> System<'a> for Sys {
                     &uids,
                     &players,
                     &presences,
+                    &admins,
+                    &settings,
                     &editable_settings,
                     &alias_validator,
                     msg,
@@ -1,6 +1,10 @@
 #[cfg(feature = "persistent_world")]
 use crate::TerrainPersistence;
-use crate::{client::Client, presence::Presence, Settings};
+use crate::{
+    client::Client,
+    presence::{DeltaSync, Presence, Spectating},
+    Settings,
+};
 use common::{
     comp::{
         Admin, CanBuild, ControlEvent, Controller, ForceUpdate, Health, Ori, Player, Pos, SkillSet,
@@ -8,11 +12,12 @@ use common::{
     },
     event::{EventBus, ServerEvent},
     resources::PlayerPhysicsSettings,
-    terrain::TerrainGrid,
+    terrain::{Block, TerrainGrid},
+    uid::Uid,
     vol::ReadVol,
 };
 use common_ecs::{Job, Origin, Phase, System};
-use common_net::msg::{ClientGeneral, PresenceKind, ServerGeneral};
+use common_net::msg::{ClientGeneral, PresenceKind, ServerGeneral, SpectateTarget};
 use common_state::{BlockChange, BuildAreas};
 use specs::{Entities, Join, Read, ReadExpect, ReadStorage, Write, WriteStorage};
 use tracing::{debug, trace, warn};
@@ -23,11 +28,26 @@ pub type TerrainPersistenceData<'a> = Option<Write<'a, TerrainPersistence>>;
 #[cfg(not(feature = "persistent_world"))]
 pub type TerrainPersistenceData<'a> = ();
 
+/// Change a block on behalf of a client, returns whether it was changed
+fn try_set_block(
+    block_changes: &mut BlockChange,
+    pos: Vec3<i32>,
+    block: Block,
+    maybe_uid: Option<Uid>,
+) -> bool {
+    match maybe_uid {
+        Some(uid) => block_changes.try_set_by(pos, block, uid),
+        None => block_changes.try_set(pos, block),
+    }
+    .is_some()
+}
+
 impl Sys {
     #[allow(clippy::too_many_arguments)]
     fn handle_client_in_game_msg(
         server_emitter: &mut common::event::Emitter<'_, ServerEvent>,
         entity: specs::Entity,
+        maybe_uid: Option<Uid>,
         client: &Client,
         maybe_presence: &mut Option<&mut Presence>,
         terrain: &ReadExpect<'_, TerrainGrid>,
@@ -40,6 +60,8 @@ impl Sys {
         velocities: &mut WriteStorage<'_, Vel>,
         orientations: &mut WriteStorage<'_, Ori>,
         controllers: &mut WriteStorage<'_, Controller>,
+        delta_syncs: &mut WriteStorage<'_, DeltaSync>,
+        spectatings: &mut WriteStorage<'_, Spectating>,
         settings: &Read<'_, Settings>,
         build_areas: &Read<'_, BuildAreas>,
         player_physics_settings: &mut Write<'_, PlayerPhysicsSettings>,
@@ -194,6 +216,9 @@ impl Sys {
                     }
                 }
             },
+            // Spectators can't interact with the world
+            ClientGeneral::BreakBlock(_) | ClientGeneral::PlaceBlock(_, _)
+                if !matches!(presence.kind, PresenceKind::Character(_)) => {},
             ClientGeneral::BreakBlock(pos) => {
                 if let Some(comp_can_build) = can_build.get(entity) {
                     if comp_can_build.enabled {
@@ -207,7 +232,8 @@ impl Sys {
                                 .and_then(|_| terrain.get(pos).ok())
                             {
                                 let new_block = old_block.into_vacant();
-                                let _was_set = block_changes.try_set(pos, new_block).is_some();
+                                let _was_set =
+                                    try_set_block(block_changes, pos, new_block, maybe_uid);
                                 #[cfg(feature = "persistent_world")]
                                 if _was_set {
                                     if let Some(terrain_persistence) = _terrain_persistence.as_mut()
@@ -232,7 +258,8 @@ impl Sys {
                                 .filter(|aabb| aabb.contains_point(pos))
                                 .is_some()
                             {
-                                let _was_set = block_changes.try_set(pos, new_block).is_some();
+                                let _was_set =
+                                    try_set_block(block_changes, pos, new_block, maybe_uid);
                                 #[cfg(feature = "persistent_world")]
                                 if _was_set {
                                     if let Some(terrain_persistence) = _terrain_persistence.as_mut()
@@ -276,10 +303,24 @@ impl Sys {
                     setting.client_optin = server_authoritative;
                 }
             },
-            ClientGeneral::RequestLossyTerrainCompression {
-                lossy_terrain_compression,
-            } => {
-                presence.lossy_terrain_compression = lossy_terrain_compression;
+            ClientGeneral::RequestTerrainEncoding(preferences) => {
+                presence.terrain_encoding = preferences;
+            },
+            ClientGeneral::AckDeltaSync(seq) => {
+                if let Some(delta_sync) = delta_syncs.get_mut(entity) {
+                    delta_sync.0.ack(seq);
+                }
+            },
+            ClientGeneral::SetSpectateTarget(target) => {
+                if matches!(presence.kind, PresenceKind::Spectator) {
+                    if let SpectateTarget::Position(pos) = target {
+                        if !pos.map(|e| e.is_finite()).reduce_and() {
+                            return Ok(());
+                        }
+                        let _ = positions.get_mut(entity).map(|p| p.0 = pos);
+                    }
+                    let _ = spectatings.insert(entity, Spectating(target));
+                }
             },
             ClientGeneral::RequestCharacterList
             | ClientGeneral::CreateCharacter { .. }
@@ -315,12 +356,15 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, Presence>,
         WriteStorage<'a, Client>,
         WriteStorage<'a, Controller>,
+        WriteStorage<'a, DeltaSync>,
+        WriteStorage<'a, Spectating>,
         Read<'a, Settings>,
         Read<'a, BuildAreas>,
         Write<'a, PlayerPhysicsSettings>,
         TerrainPersistenceData<'a>,
         ReadStorage<'a, Player>,
         ReadStorage<'a, Admin>,
+        ReadStorage<'a, Uid>,
     );
 
     const NAME: &'static str = "msg::in_game";
@@ -344,12 +388,15 @@ impl<'a> System<'a> for Sys {
             mut presences,
             mut clients,
             mut controllers,
+            mut delta_syncs,
+            mut spectatings,
             settings,
             build_areas,
             mut player_physics_settings,
             mut terrain_persistence,
             players,
             admins,
+            uids,
         ): Self::SystemData,
     ) {
         let mut server_emitter = server_event_bus.emitter();
@@ -367,6 +414,7 @@ impl<'a> System<'a> for Sys {
                 Self::handle_client_in_game_msg(
                     &mut server_emitter,
                     entity,
+                    uids.get(entity).copied(),
                     client,
                     &mut maybe_presence.as_deref_mut(),
                     &terrain,
@@ -379,6 +427,8 @@ impl<'a> System<'a> for Sys {
                     &mut velocities,
                     &mut orientations,
                     &mut controllers,
+                    &mut delta_syncs,
+                    &mut spectatings,
                     &settings,
                     &build_areas,
                     &mut player_physics_settings,
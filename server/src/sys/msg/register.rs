@@ -5,8 +5,11 @@ use crate::{
     EditableSettings, Settings,
 };
 use common::{
-    comp::{Admin, Player, Stats},
+    calendar::Calendar,
+    comp::{Admin, Alignment, Body, Group, Inventory, Player, Pos, SkillSet, Stats},
     event::{EventBus, ServerEvent},
+    resources::TimeOfDay,
+    terrain::TerrainGrid,
     uid::{Uid, UidAllocator},
 };
 use common_ecs::{Job, Origin, Phase, System};
@@ -43,6 +46,15 @@ pub struct ReadData<'a> {
     _healths: ReadStorage<'a, Health>, // used by plugin feature
     _plugin_mgr: ReadPlugin<'a>,       // used by plugin feature
     _uid_allocator: Read<'a, UidAllocator>, // used by plugin feature
+    _positions: ReadStorage<'a, Pos>,  // used by plugin feature
+    _bodies: ReadStorage<'a, Body>,    // used by plugin feature
+    _inventories: ReadStorage<'a, Inventory>, // used by plugin feature
+    _skill_sets: ReadStorage<'a, SkillSet>, // used by plugin feature
+    _groups: ReadStorage<'a, Group>,   // used by plugin feature
+    _alignments: ReadStorage<'a, Alignment>, // used by plugin feature
+    _terrain: ReadExpect<'a, TerrainGrid>, // used by plugin feature
+    _time_of_day: Read<'a, TimeOfDay>, // used by plugin feature
+    _calendar: Read<'a, Calendar>,     // used by plugin feature
 }
 
 /// This system will handle new messages from clients
@@ -118,6 +130,14 @@ impl<'a> System<'a> for Sys {
                     uid: (&read_data.uids).into(),
                     player: (&players).into(),
                     uid_allocator: &read_data._uid_allocator,
+                    pos: (&read_data._positions).into(),
+                    body: (&read_data._bodies).into(),
+                    inventory: (&read_data._inventories).into(),
+                    skill_set: (&read_data._skill_sets).into(),
+                    group: (&read_data._groups).into(),
+                    alignment: (&read_data._alignments).into(),
+                    terrain: &read_data._terrain,
+                    date: read_data._calendar.date(*read_data._time_of_day),
                 };
 
                 let (username, uuid) = match login_provider.login(
@@ -1,4 +1,7 @@
-use crate::{client::Client, metrics::NetworkRequestMetrics, presence::Presence, ChunkRequest};
+use crate::{
+    client::Client, metrics::NetworkRequestMetrics, presence::Presence,
+    sys::terrain::LazyTerrainMessage, ChunkRequest,
+};
 use common::{
     comp::Pos,
     spiral::Spiral2d,
@@ -6,7 +9,7 @@ use common::{
     vol::RectVolSize,
 };
 use common_ecs::{Job, Origin, ParMode, Phase, System};
-use common_net::msg::{ClientGeneral, SerializedTerrainChunk, ServerGeneral};
+use common_net::msg::ClientGeneral;
 use rayon::iter::ParallelIterator;
 use specs::{Entities, Join, ParJoin, ReadExpect, ReadStorage, Write};
 use tracing::{debug, trace};
@@ -75,18 +78,15 @@ impl<'a> System<'a> for Sys {
                                 match terrain.get_key_arc(key) {
                                     Some(chunk) => {
                                         network_metrics.chunks_served_from_memory.inc();
-                                        client.send(ServerGeneral::TerrainChunkUpdate {
-                                            key,
-                                            chunk: Ok(SerializedTerrainChunk::via_heuristic(
-                                                chunk,
-                                                presence.lossy_terrain_compression,
-                                            )),
-                                        })?;
-                                        if presence.lossy_terrain_compression {
-                                            network_metrics.chunks_served_lossy.inc();
-                                        } else {
-                                            network_metrics.chunks_served_lossless.inc();
-                                        }
+                                        LazyTerrainMessage::new()
+                                            .prepare_and_send::<!, _>(
+                                                &network_metrics,
+                                                client,
+                                                presence,
+                                                &key,
+                                                || Ok(&**chunk),
+                                            )
+                                            .into_ok()?;
                                     },
                                     None => {
                                         network_metrics.chunks_generation_triggered.inc();
@@ -2,7 +2,7 @@ use crate::{persistence::character_updater, presence::Presence, sys::SysSchedule
 use common::{
     comp::{
         pet::{is_tameable, Pet},
-        Alignment, Body, Inventory, SkillSet, Stats, Waypoint,
+        Alignment, Body, Inventory, SkillSet, Stash, Stats, Waypoint,
     },
     uid::Uid,
 };
@@ -25,6 +25,7 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Waypoint>,
         ReadStorage<'a, Pet>,
         ReadStorage<'a, Stats>,
+        ReadStorage<'a, Stash>,
         WriteExpect<'a, character_updater::CharacterUpdater>,
         Write<'a, SysScheduler<Self>>,
     );
@@ -45,6 +46,7 @@ impl<'a> System<'a> for Sys {
             player_waypoints,
             pets,
             stats,
+            stashes,
             mut updater,
             mut scheduler,
         ): Self::SystemData,
@@ -57,30 +59,33 @@ impl<'a> System<'a> for Sys {
                     &player_inventories,
                     &uids,
                     player_waypoints.maybe(),
+                    stashes.maybe(),
                 )
                     .join()
                     .filter_map(
-                        |(presence, skill_set, inventory, player_uid, waypoint)| match presence.kind
-                        {
-                            PresenceKind::Character(id) => {
-                                let pets = (&alignments, &bodies, &stats, &pets)
-                                    .join()
-                                    .filter_map(|(alignment, body, stats, pet)| match alignment {
-                                        // Don't try to persist non-tameable pets (likely spawned
-                                        // using /spawn) since there isn't any code to handle
-                                        // persisting them
-                                        Alignment::Owned(ref pet_owner)
-                                            if pet_owner == player_uid && is_tameable(body) =>
+                        |(presence, skill_set, inventory, player_uid, waypoint, stash)| {
+                            match presence.kind {
+                                PresenceKind::Character(id) => {
+                                    let pets = (&alignments, &bodies, &stats, &pets)
+                                        .join()
+                                        .filter_map(|(alignment, body, stats, pet)| match alignment
                                         {
-                                            Some(((*pet).clone(), *body, stats.clone()))
-                                        },
-                                        _ => None,
-                                    })
-                                    .collect();
+                                            // Don't try to persist non-tameable pets (likely
+                                            // spawned using /spawn) since there isn't any code
+                                            // to handle persisting them
+                                            Alignment::Owned(ref pet_owner)
+                                                if pet_owner == player_uid && is_tameable(body) =>
+                                            {
+                                                Some(((*pet).clone(), *body, stats.clone()))
+                                            },
+                                            _ => None,
+                                        })
+                                        .collect();
 
-                                Some((id, skill_set, inventory, pets, waypoint))
-                            },
-                            PresenceKind::Spectator => None,
+                                    Some((id, skill_set, inventory, pets, waypoint, stash))
+                                },
+                                PresenceKind::Spectator => None,
+                            }
                         },
                     ),
             );
@@ -216,6 +216,8 @@ pub struct ReadTrackers<'a> {
     pub beam_segment: ReadExpect<'a, UpdateTracker<BeamSegment>>,
 }
 impl<'a> ReadTrackers<'a> {
+    /// Modifications of `Energy` and `Health` are left out, they are delta
+    /// synced to every client on its own
     pub fn create_sync_packages(
         &self,
         comps: &TrackedComps,
@@ -237,9 +239,9 @@ impl<'a> ReadTrackers<'a> {
             )
             .with_component(&comps.uid, &*self.buffs, &comps.buffs, filter)
             .with_component(&comps.uid, &*self.auras, &comps.auras, filter)
-            .with_component(&comps.uid, &*self.energy, &comps.energy, filter)
+            .with_component_unmodified(&comps.uid, &*self.energy, &comps.energy, filter)
             .with_component(&comps.uid, &*self.combo, &comps.combo, filter)
-            .with_component(&comps.uid, &*self.health, &comps.health, filter)
+            .with_component_unmodified(&comps.uid, &*self.health, &comps.health, filter)
             .with_component(&comps.uid, &*self.poise, &comps.poise, filter)
             .with_component(&comps.uid, &*self.can_build, &comps.can_build, filter)
             .with_component(
@@ -0,0 +1,44 @@
+use crate::presence::Spectating;
+use common::{
+    comp::{ForceUpdate, Pos},
+    uid::UidAllocator,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::SpectateTarget;
+use specs::{saveload::MarkerAllocator, Entities, Join, Read, ReadStorage, WriteStorage};
+
+/// This system moves spectators along with the entity they follow, so the
+/// world around it is synced to them
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, UidAllocator>,
+        ReadStorage<'a, Spectating>,
+        WriteStorage<'a, Pos>,
+        WriteStorage<'a, ForceUpdate>,
+    );
+
+    const NAME: &'static str = "spectator";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (entities, uid_allocator, spectatings, mut positions, mut force_updates): Self::SystemData,
+    ) {
+        for (entity, spectating) in (&entities, &spectatings).join() {
+            if let SpectateTarget::Entity(uid) = spectating.0 {
+                // Stay where the target was last seen if it's gone
+                let target_pos = uid_allocator
+                    .retrieve_entity_internal(uid.0)
+                    .and_then(|target| positions.get(target).copied());
+                if let Some(target_pos) = target_pos {
+                    let _ = positions.insert(entity, target_pos);
+                    let _ = force_updates.insert(entity, ForceUpdate);
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use super::sentinel::{DeletedEntities, TrackedComps};
 use crate::{
     client::Client,
-    presence::{self, Presence, RegionSubscription},
+    presence::{self, DeltaSync, Presence, RegionSubscription},
 };
 use common::{
     comp::{Ori, Pos, Vel},
@@ -270,6 +270,9 @@ pub fn initialize_region_subscription(world: &World, entity: specs::Entity) {
         }) {
             error!(?e, "Failed to insert region subscription component");
         }
+        if let Err(e) = world.write_storage().insert(entity, DeltaSync::default()) {
+            error!(?e, "Failed to insert delta sync component");
+        }
     } else {
         debug!(
             ?entity,
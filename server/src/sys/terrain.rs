@@ -15,19 +15,25 @@ use crate::{
     ChunkRequest, SpawnPoint, Tick,
 };
 use common::{
+    assets::AssetHandle,
+    calendar::Calendar,
     comp::{self, agent, bird_medium, BehaviorCapability, ForceUpdate, Pos, Waypoint},
     event::{EventBus, ServerEvent},
-    generation::EntityInfo,
+    generation::{ChunkSupplement, EntityInfo},
     lottery::LootSpec,
     resources::{Time, TimeOfDay},
     slowjob::SlowJobPool,
-    terrain::TerrainGrid,
+    terrain::{TerrainChunk, TerrainGrid},
     LoadoutBuilder, SkillSetBuilder,
 };
 use common_ecs::{Job, Origin, Phase, System};
-use common_net::msg::{SerializedTerrainChunk, ServerGeneral};
+use common_net::msg::{
+    terrain_encoding::terrain_dictionary, SerializedTerrainChunk, ServerGeneral, TerrainEncoding,
+    ZstdDictionary,
+};
 use common_state::TerrainChanges;
 use comp::Behavior;
+use network::StreamError;
 use rand::Rng;
 use specs::{Entities, Join, Read, ReadExpect, ReadStorage, Write, WriteExpect, WriteStorage};
 use std::sync::Arc;
@@ -38,9 +44,12 @@ pub type TerrainPersistenceData<'a> = Option<Write<'a, TerrainPersistence>>;
 #[cfg(not(feature = "persistent_world"))]
 pub type TerrainPersistenceData<'a> = ();
 
+/// Encodes a chunk once per encoding that is needed for the clients it's sent
+/// to
 pub(crate) struct LazyTerrainMessage {
-    lazy_msg_lo: Option<crate::client::PreparedMsg>,
-    lazy_msg_hi: Option<crate::client::PreparedMsg>,
+    /// By encoding and whether the zstd dictionary was used
+    lazy_msgs: Vec<((TerrainEncoding, bool), crate::client::PreparedMsg)>,
+    dictionary: AssetHandle<ZstdDictionary>,
 }
 
 pub const SAFE_ZONE_RADIUS: f32 = 200.0;
@@ -49,49 +58,56 @@ impl LazyTerrainMessage {
     #[allow(clippy::new_without_default)]
     pub(crate) fn new() -> Self {
         Self {
-            lazy_msg_lo: None,
-            lazy_msg_hi: None,
+            lazy_msgs: Vec::new(),
+            dictionary: terrain_dictionary(),
         }
     }
 
-    pub(crate) fn prepare_and_send<
-        'a,
-        A,
-        F: FnOnce() -> Result<&'a common::terrain::TerrainChunk, A>,
-    >(
+    /// Picks the encoding from the preferences of the client and the
+    /// bandwidth towards it. The outer error is the one of `generate_chunk`,
+    /// the inner one tells whether the chunk could be sent.
+    pub(crate) fn prepare_and_send<'a, A, F: FnOnce() -> Result<&'a TerrainChunk, A>>(
         &mut self,
         network_metrics: &NetworkRequestMetrics,
         client: &Client,
         presence: &Presence,
         chunk_key: &vek::Vec2<i32>,
         generate_chunk: F,
-    ) -> Result<(), A> {
-        let lazy_msg = if presence.lossy_terrain_compression {
-            &mut self.lazy_msg_lo
-        } else {
-            &mut self.lazy_msg_hi
+    ) -> Result<Result<(), StreamError>, A> {
+        let chunk = generate_chunk()?;
+        let bandwidth = client
+            .participant
+            .as_ref()
+            .map_or(0.0, |participant| participant.bandwidth());
+        let encoding = presence
+            .terrain_encoding
+            .select(bandwidth, chunk.get_max_z() - chunk.get_min_z());
+        let dictionary = self.dictionary.read();
+        let use_dictionary = encoding == TerrainEncoding::Zstd
+            && presence.terrain_encoding.shares_dictionary(&dictionary);
+
+        let key = (encoding, use_dictionary);
+        let index = match self.lazy_msgs.iter().position(|(k, _)| *k == key) {
+            Some(index) => index,
+            None => {
+                let chunk = SerializedTerrainChunk::encode(
+                    chunk,
+                    encoding,
+                    use_dictionary.then(|| &*dictionary),
+                );
+                self.lazy_msgs.push((
+                    key,
+                    client.prepare(ServerGeneral::TerrainChunkUpdate {
+                        key: *chunk_key,
+                        chunk: Ok(chunk),
+                    }),
+                ));
+                self.lazy_msgs.len() - 1
+            },
         };
-        if lazy_msg.is_none() {
-            *lazy_msg = Some(client.prepare(ServerGeneral::TerrainChunkUpdate {
-                key: *chunk_key,
-                chunk: Ok(match generate_chunk() {
-                    Ok(chunk) => SerializedTerrainChunk::via_heuristic(
-                        chunk,
-                        presence.lossy_terrain_compression,
-                    ),
-                    Err(e) => return Err(e),
-                }),
-            }));
-        }
-        lazy_msg.as_ref().map(|msg| {
-            let _ = client.send_prepared(msg);
-            if presence.lossy_terrain_compression {
-                network_metrics.chunks_served_lossy.inc();
-            } else {
-                network_metrics.chunks_served_lossless.inc();
-            }
-        });
-        Ok(())
+        Ok(client
+            .send_prepared(&self.lazy_msgs[index].1)
+            .map(|()| network_metrics.chunk_served(encoding)))
     }
 }
 
@@ -111,6 +127,7 @@ impl<'a> System<'a> for Sys {
         Read<'a, SpawnPoint>,
         Read<'a, Settings>,
         Read<'a, TimeOfDay>,
+        Read<'a, Calendar>,
         ReadExpect<'a, SlowJobPool>,
         ReadExpect<'a, IndexOwned>,
         ReadExpect<'a, Arc<World>>,
@@ -143,6 +160,7 @@ impl<'a> System<'a> for Sys {
             spawn_point,
             server_settings,
             time_of_day,
+            calendar,
             slow_jobs,
             index,
             world,
@@ -165,6 +183,29 @@ impl<'a> System<'a> for Sys {
     ) {
         let mut server_emitter = server_event_bus.emitter();
 
+        // Chunks that were changed keep the season they were generated in
+        for pos in terrain_changes.modified_blocks.keys() {
+            chunk_generator.mark_modified(terrain.pos_key(*pos));
+        }
+        // The season changes the look of the terrain, so generate the unmodified
+        // chunks again
+        if chunk_generator.season_changed(calendar.season(*time_of_day)) {
+            for (key, _) in terrain.iter() {
+                chunk_generator.mark_outdated(key);
+            }
+        }
+        // A few at a time, so that they don't hold up the chunks players request
+        const MAX_SEASON_REGENERATIONS_PER_TICK: usize = 4;
+        for key in chunk_generator.take_outdated(MAX_SEASON_REGENERATIONS_PER_TICK) {
+            chunk_generator.regenerate_chunk(
+                key,
+                &slow_jobs,
+                Arc::clone(&world),
+                index.clone(),
+                (*time_of_day, *calendar),
+            );
+        }
+
         // Generate requested chunks
         //
         // Submit requests for chunks right before receiving finished chunks so that we
@@ -177,7 +218,7 @@ impl<'a> System<'a> for Sys {
                 &slow_jobs,
                 Arc::clone(&world),
                 index.clone(),
-                *time_of_day,
+                (*time_of_day, *calendar),
             )
         });
 
@@ -185,6 +226,8 @@ impl<'a> System<'a> for Sys {
         // Also, send the chunk data to anybody that is close by.
         let mut new_chunks = Vec::new();
         'insert_terrain_chunks: while let Some((key, res)) = chunk_generator.recv_new_chunk() {
+            // A regenerated chunk was already loaded, its npcs are spawned
+            let regenerated = chunk_generator.take_regenerated(key);
             #[allow(unused_mut)]
             let (mut chunk, supplement) = match res {
                 Ok((chunk, supplement)) => (chunk, supplement),
@@ -201,6 +244,15 @@ impl<'a> System<'a> for Sys {
                     continue 'insert_terrain_chunks;
                 },
             };
+            if regenerated && chunk_generator.is_modified(key) {
+                // It was changed in the meantime, keep the changes
+                continue 'insert_terrain_chunks;
+            }
+            let supplement = if regenerated {
+                ChunkSupplement::default()
+            } else {
+                supplement
+            };
 
             // Apply changes from terrain persistence to this chunk
             #[cfg(feature = "persistent_world")]
@@ -274,7 +326,10 @@ impl<'a> System<'a> for Sys {
             }
 
             // Insert a safezone if chunk contains the spawn position
-            if server_settings.safe_spawn && is_spawn_chunk(key, *spawn_point, &terrain) {
+            if !regenerated
+                && server_settings.safe_spawn
+                && is_spawn_chunk(key, *spawn_point, &terrain)
+            {
                 server_emitter.emit(ServerEvent::CreateSafezone {
                     range: Some(SAFE_ZONE_RADIUS),
                     pos: Pos(spawn_point.0),
@@ -319,7 +374,8 @@ impl<'a> System<'a> for Sys {
                         .magnitude_squared();
 
                     if adjusted_dist_sqr <= presence.view_distance.pow(2) {
-                        lazy_msg
+                        // A client whose stream broke is removed by the message systems
+                        let _ = lazy_msg
                             .prepare_and_send::<!, _>(
                                 &network_metrics,
                                 client,
@@ -0,0 +1,40 @@
+//! Server side weather simulation, its result is synced to clients as a
+//! [`WeatherGrid`]
+mod sim;
+mod tick;
+
+use common::weather::WeatherGrid;
+use common_ecs::dispatch;
+use common_state::State;
+use specs::{DispatcherBuilder, WorldExt};
+#[cfg(not(feature = "worldgen"))] use vek::*;
+
+pub use self::sim::WeatherSim;
+
+/// Seconds between two steps of the simulation
+const WEATHER_DT: f32 = 1.0;
+/// Seconds between two syncs of the weather to clients
+const WEATHER_SYNC_INTERVAL: f64 = 10.0;
+
+/// When the weather was last simulated and synced
+#[derive(Default)]
+pub struct WeatherJob {
+    last_tick: f64,
+    last_sync: f64,
+}
+
+pub fn add_server_systems(dispatch_builder: &mut DispatcherBuilder) {
+    dispatch::<tick::Sys>(dispatch_builder, &[]);
+}
+
+pub fn init(state: &mut State, #[cfg(feature = "worldgen")] world: &world::World) {
+    #[cfg(feature = "worldgen")]
+    let mut sim = WeatherSim::new(world.sim().get_size(), world);
+    #[cfg(not(feature = "worldgen"))]
+    let mut sim = WeatherSim::new(Vec2::new(40, 40));
+    let mut grid = WeatherGrid::new(sim.size());
+    sim.tick(0.0, &mut grid);
+    state.ecs_mut().insert(grid);
+    state.ecs_mut().insert(sim);
+    state.ecs_mut().insert(WeatherJob::default());
+}
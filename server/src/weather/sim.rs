@@ -0,0 +1,194 @@
+use common::{
+    grid::Grid,
+    weather::{Weather, WeatherGrid, CELL_SIZE, CHUNKS_PER_CELL},
+};
+use rand::prelude::*;
+use std::f32::consts::PI;
+use vek::*;
+#[cfg(feature = "worldgen")]
+use world::{World, CONFIG};
+
+/// One front is spawned for roughly this many cells
+const CELLS_PER_FRONT: u32 = 24;
+/// Seconds a front lives, from forming to raining out
+const FRONT_LIFETIME: (f32, f32) = (600.0, 1800.0);
+/// Radius of a front in cells
+const FRONT_RADIUS: (f32, f32) = (1.5, 4.0);
+/// Range of the prevailing wind speed in blocks/s
+const PREVAILING_WIND: (f32, f32) = (2.0, 10.0);
+/// Cloud cover above which clouds start to rain or snow
+const PRECIPITATION_COVER: f32 = 0.4;
+/// Temperatures up to this much above `CONFIG.snow_temp` get snow instead of
+/// rain
+#[cfg(feature = "worldgen")]
+const SNOW_TEMP_MARGIN: f32 = 0.3;
+
+/// Climate of a weather cell, averaged over its chunks
+#[derive(Copy, Clone, Debug)]
+struct Climate {
+    /// See `SimChunk::temp`
+    temp: f32,
+    /// See `SimChunk::humidity`
+    humidity: f32,
+    /// Whether precipitation falls as snow
+    freezing: bool,
+}
+
+impl Default for Climate {
+    fn default() -> Self {
+        Self {
+            temp: 0.0,
+            humidity: 0.5,
+            freezing: false,
+        }
+    }
+}
+
+/// A patch of clouds drifting with the wind, it builds up and rains out over
+/// its lifetime. Fronts that meet make for storms.
+#[derive(Clone, Debug)]
+struct Front {
+    /// Centre in cells
+    pos: Vec2<f32>,
+    /// Radius in cells
+    radius: f32,
+    /// Cloud cover at its peak, up to 1.0
+    intensity: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+impl Front {
+    /// Cloud cover the front brings to the centre of `cell`
+    fn cover(&self, cell: Vec2<i32>) -> f32 {
+        let dist = (cell.map(|e| e as f32 + 0.5) - self.pos).magnitude() / self.radius;
+        let falloff = (1.0 - dist * dist).max(0.0);
+        let envelope = (self.age / self.lifetime * PI).sin().max(0.0);
+        self.intensity * falloff * envelope
+    }
+}
+
+/// Simulates the weather of the world, see [`WeatherGrid`] for its result
+pub struct WeatherSim {
+    climate: Grid<Climate>,
+    fronts: Vec<Front>,
+    /// Direction of the prevailing wind, in radians
+    wind_dir: f32,
+    /// Speed of the prevailing wind in blocks/s
+    wind_speed: f32,
+    rng: SmallRng,
+}
+
+impl WeatherSim {
+    pub fn new(world_size: Vec2<u32>, #[cfg(feature = "worldgen")] world: &World) -> Self {
+        let size = WeatherGrid::size_for_world(world_size).map(|e| e as i32);
+        #[cfg(feature = "worldgen")]
+        let climate = Grid::populate_from(size, |cell| {
+            let chunks = (0..CHUNKS_PER_CELL as i32)
+                .flat_map(|y| (0..CHUNKS_PER_CELL as i32).map(move |x| Vec2::new(x, y)))
+                .filter_map(|offs| world.sim().get(cell * CHUNKS_PER_CELL as i32 + offs))
+                .collect::<Vec<_>>();
+            if chunks.is_empty() {
+                return Climate::default();
+            }
+            let n = chunks.len() as f32;
+            let temp = chunks.iter().map(|chunk| chunk.temp).sum::<f32>() / n;
+            Climate {
+                temp,
+                humidity: chunks.iter().map(|chunk| chunk.humidity).sum::<f32>() / n,
+                freezing: temp < CONFIG.snow_temp + SNOW_TEMP_MARGIN,
+            }
+        });
+        #[cfg(not(feature = "worldgen"))]
+        let climate = Grid::new(size, Climate::default());
+
+        let mut rng = SmallRng::from_entropy();
+        let mut this = Self {
+            climate,
+            fronts: Vec::new(),
+            wind_dir: rng.gen_range(0.0..PI * 2.0),
+            wind_speed: rng.gen_range(PREVAILING_WIND.0..PREVAILING_WIND.1),
+            rng,
+        };
+        // Start with weather already going on, not a clear sky everywhere
+        for _ in 0..this.max_fronts() {
+            this.spawn_front();
+            if let Some(front) = this.fronts.last_mut() {
+                front.age = this.rng.gen_range(0.0..front.lifetime);
+            }
+        }
+        this
+    }
+
+    pub fn size(&self) -> Vec2<u32> { self.climate.size().map(|e| e as u32) }
+
+    fn max_fronts(&self) -> usize {
+        (self.climate.size().product() as u32 / CELLS_PER_FRONT).max(1) as usize
+    }
+
+    fn prevailing_wind(&self) -> Vec2<f32> {
+        Vec2::new(self.wind_dir.cos(), self.wind_dir.sin()) * self.wind_speed
+    }
+
+    /// Fronts form more often over humid cells
+    fn spawn_front(&mut self) {
+        let size = self.climate.size();
+        let cell = Vec2::new(
+            self.rng.gen_range(0..size.x.max(1)),
+            self.rng.gen_range(0..size.y.max(1)),
+        );
+        let humidity = self.climate.get(cell).map_or(0.0, |c| c.humidity);
+        if self.rng.gen::<f32>() < humidity.clamped(0.1, 1.0) {
+            self.fronts.push(Front {
+                pos: cell.map(|e| e as f32 + 0.5),
+                radius: self.rng.gen_range(FRONT_RADIUS.0..FRONT_RADIUS.1),
+                intensity: self.rng.gen_range(0.3..1.0),
+                age: 0.0,
+                lifetime: self.rng.gen_range(FRONT_LIFETIME.0..FRONT_LIFETIME.1),
+            });
+        }
+    }
+
+    /// Advance the weather by `dt` seconds and write it to `grid`
+    pub fn tick(&mut self, dt: f32, grid: &mut WeatherGrid) {
+        // The prevailing wind slowly turns and changes its strength
+        self.wind_dir += self.rng.gen_range(-0.01..0.01) * dt;
+        self.wind_speed = (self.wind_speed + self.rng.gen_range(-0.1..0.1) * dt)
+            .clamped(PREVAILING_WIND.0, PREVAILING_WIND.1);
+
+        let drift = self.prevailing_wind() / CELL_SIZE as f32 * dt;
+        for front in &mut self.fronts {
+            front.pos += drift;
+            front.age += dt;
+        }
+        self.fronts.retain(|front| front.age < front.lifetime);
+        if self.fronts.len() < self.max_fronts() {
+            self.spawn_front();
+        }
+
+        let prevailing_wind = self.prevailing_wind();
+        let fronts = &self.fronts;
+        let climate = &self.climate;
+        if grid.size() != self.size() {
+            *grid = WeatherGrid::new(self.size());
+        }
+        for (cell, weather) in grid.iter_mut() {
+            let climate = climate.get(cell).copied().unwrap_or_default();
+            let cover = fronts.iter().map(|front| front.cover(cell)).sum::<f32>();
+            let cloud = (cover + climate.humidity * 0.2).min(1.0);
+            // Deserts barely get any rain from the clouds passing over them
+            let precipitation = ((cover - PRECIPITATION_COVER) / (1.0 - PRECIPITATION_COVER))
+                .clamped(0.0, 1.0)
+                * (0.5 + climate.humidity).min(1.0);
+            let (rain, snow) = if climate.freezing {
+                (0.0, precipitation)
+            } else {
+                (precipitation, 0.0)
+            };
+            // Overlapping fronts stir up the wind into storms, hot cells have
+            // stronger gusts
+            let gusts = 1.0 + cover.min(2.0) + climate.temp.max(0.0) * 0.5;
+            *weather = Weather::new(cloud, rain, snow, prevailing_wind * gusts);
+        }
+    }
+}
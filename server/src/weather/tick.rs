@@ -0,0 +1,51 @@
+use super::{WeatherJob, WeatherSim, WEATHER_DT, WEATHER_SYNC_INTERVAL};
+use crate::{client::Client, presence::Presence};
+use common::{
+    comp::Pos,
+    resources::Time,
+    weather::{WeatherGrid, SYNC_RADIUS},
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use specs::{Join, Read, ReadStorage, Write, WriteExpect};
+
+/// This system steps the weather simulation and periodically sends clients in
+/// game the weather around them
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Read<'a, Time>,
+        WriteExpect<'a, WeatherSim>,
+        WriteExpect<'a, WeatherJob>,
+        Write<'a, WeatherGrid>,
+        ReadStorage<'a, Client>,
+        ReadStorage<'a, Presence>,
+        ReadStorage<'a, Pos>,
+    );
+
+    const NAME: &'static str = "weather::tick";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (time, mut sim, mut weather_job, mut grid, clients, presences, positions): Self::SystemData,
+    ) {
+        let dt = (time.0 - weather_job.last_tick) as f32;
+        if dt >= WEATHER_DT {
+            sim.tick(dt, &mut grid);
+            weather_job.last_tick = time.0;
+        }
+
+        if time.0 - weather_job.last_sync >= WEATHER_SYNC_INTERVAL {
+            weather_job.last_sync = time.0;
+            for (client, _, pos) in (&clients, &presences, &positions).join() {
+                let patch = grid.patch_around(pos.0.xy(), SYNC_RADIUS);
+                // We don't care much about stream errors here since they could just represent
+                // network disconnection, which is handled elsewhere.
+                let _ = client.send(ServerGeneral::WeatherUpdate(patch));
+            }
+        }
+    }
+}
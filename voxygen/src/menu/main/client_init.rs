@@ -1,7 +1,7 @@
 use client::{
     addr::ConnectionArgs,
     error::{Error as ClientError, NetworkConnectError, NetworkError},
-    Client, ServerInfo,
+    Client, LinkConditions, ServerInfo,
 };
 use crossbeam_channel::{unbounded, Receiver, Sender, TryRecvError};
 use std::{
@@ -47,6 +47,7 @@ impl ClientInit {
         username: String,
         password: String,
         runtime: Arc<runtime::Runtime>,
+        link_conditions: Option<LinkConditions>,
     ) -> Self {
         let (tx, rx) = unbounded();
         let (trust_tx, trust_rx) = unbounded();
@@ -75,6 +76,7 @@ impl ClientInit {
                 match Client::new(
                     connection_args.clone(),
                     Arc::clone(&runtime2),
+                    link_conditions,
                     &mut mismatched_server_info,
                 )
                 .await
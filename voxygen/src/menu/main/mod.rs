@@ -14,7 +14,7 @@ use crate::{
 use client::{
     addr::ConnectionArgs,
     error::{InitProtocolError, NetworkConnectError, NetworkError},
-    Client, ServerInfo,
+    Client, IdentityKey, LinkConditions, ServerInfo,
 };
 use client_init::{ClientInit, Error as InitError, Msg as InitMsg};
 use common::comp;
@@ -23,7 +23,7 @@ use i18n::LocalizationHandle;
 use scene::Scene;
 use std::sync::Arc;
 use tokio::runtime;
-use tracing::error;
+use tracing::{error, warn};
 use ui::{Event as MainMenuEvent, MainMenuUi};
 
 // TODO: show status messages for waiting on server creation, client init, and
@@ -103,6 +103,7 @@ impl PlayState for MainMenuState {
                             "singleplayer".to_owned(),
                             "".to_owned(),
                             ConnectionArgs::Mpsc(14004),
+                            global_state.settings.networking.link_conditions,
                             &mut self.init,
                             &global_state.tokio_runtime,
                             &global_state.i18n,
@@ -273,6 +274,19 @@ impl PlayState for MainMenuState {
                 } => {
                     let mut net_settings = &mut global_state.settings.networking;
                     let use_quic = net_settings.use_quic;
+                    let link_conditions = net_settings.link_conditions;
+                    let identity = match net_settings
+                        .server_identities
+                        .get(&server_address)
+                        .map(|key| key.parse::<IdentityKey>())
+                    {
+                        Some(Ok(key)) => Some(key),
+                        Some(Err(e)) => {
+                            warn!(?e, ?server_address, "Ignoring invalid server identity key");
+                            None
+                        },
+                        None => None,
+                    };
                     net_settings.username = username.clone();
                     net_settings.default_server = server_address.clone();
                     if !net_settings.servers.contains(&server_address) {
@@ -291,6 +305,7 @@ impl PlayState for MainMenuState {
                         ConnectionArgs::Tcp {
                             hostname: server_address,
                             prefer_ipv6: false,
+                            identity,
                         }
                     };
                     attempt_login(
@@ -298,6 +313,7 @@ impl PlayState for MainMenuState {
                         username,
                         password,
                         connection_args,
+                        link_conditions,
                         &mut self.init,
                         &global_state.tokio_runtime,
                         &global_state.i18n,
@@ -488,6 +504,7 @@ fn attempt_login(
     username: String,
     password: String,
     connection_args: ConnectionArgs,
+    link_conditions: Option<LinkConditions>,
     init: &mut InitState,
     runtime: &Arc<runtime::Runtime>,
     localized_strings: &LocalizationHandle,
@@ -521,6 +538,7 @@ fn attempt_login(
             username,
             password,
             Arc::clone(runtime),
+            link_conditions,
         ));
     }
 }
@@ -1,4 +1,5 @@
-use hashbrown::HashSet;
+use client::LinkConditions;
+use hashbrown::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 
 /// `NetworkingSettings` stores server and networking settings.
@@ -9,7 +10,12 @@ pub struct NetworkingSettings {
     pub servers: Vec<String>,
     pub default_server: String,
     pub trusted_auth_servers: HashSet<String>,
+    /// Identity keys servers have to prove when connecting over TCP, by server
+    /// address. Servers log their key on start.
+    pub server_identities: HashMap<String, String>,
     pub use_quic: bool,
+    /// Simulates a bad connection, for testing
+    pub link_conditions: Option<LinkConditions>,
 }
 
 impl Default for NetworkingSettings {
@@ -22,7 +28,9 @@ impl Default for NetworkingSettings {
                 .iter()
                 .map(|s| s.to_string())
                 .collect(),
+            server_identities: HashMap::new(),
             use_quic: false,
+            link_conditions: None,
         }
     }
 }
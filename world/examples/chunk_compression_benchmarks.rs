@@ -8,10 +8,13 @@ use common::{
         vol_grid_2d::VolGrid2d,
     },
 };
-use common_net::msg::compression::{
-    image_from_bytes, image_terrain_chonk, image_terrain_volgrid, CompressedData, GridLtrPacking,
-    PackingFormula, QuadPngEncoding, TriPngEncoding, VoxelImageDecoding, VoxelImageEncoding,
-    WidePacking,
+use common_net::msg::{
+    compression::{
+        image_from_bytes, image_terrain_chonk, image_terrain_volgrid, CompressedData,
+        GridLtrPacking, PackingFormula, QuadPngEncoding, TriPngEncoding, VoxelImageDecoding,
+        VoxelImageEncoding, WidePacking, ZstdCompressedData, ZstdDictionary,
+    },
+    terrain_encoding::{terrain_dictionary, TERRAIN_ZSTD_LEVEL},
 };
 use hashbrown::HashMap;
 use image::ImageBuffer;
@@ -691,6 +694,13 @@ fn main() {
     let mut histogram2: HashMap<Vec<u8>, usize> = HashMap::new();
     let mut dictionary = vec![0xffu8; 1 << 16];
     let mut dictionary2 = vec![0xffu8; 1 << 16];
+    // Trains the dictionary used for zstd encoded chunks and writes it to the
+    // assets, the current one is used for the measurements. Set this to train
+    // it again from the generated chunks.
+    const TRAIN_ZSTD_DICTIONARY: bool = false;
+    const ZSTD_DICTIONARY_SIZE: usize = 1 << 14;
+    let zstd_dictionary = terrain_dictionary().cloned();
+    let mut zstd_samples = Vec::new();
     let k = 32;
     let sz = world.sim().get_size();
 
@@ -760,6 +770,9 @@ fn main() {
             if let Ok((chunk, _)) = chunk {
                 let uncompressed = bincode::serialize(&chunk).unwrap();
                 let n = uncompressed.len();
+                if TRAIN_ZSTD_DICTIONARY {
+                    zstd_samples.push(uncompressed.clone());
+                }
                 if HISTOGRAMS {
                     for w in uncompressed.windows(k) {
                         *histogram.entry(w.to_vec()).or_default() += 1;
@@ -790,11 +803,28 @@ fn main() {
                 let deflate1chonk_pre = Instant::now();
                 let deflate1_chonk = do_deflate_flate2::<1>(&bincode::serialize(&chunk).unwrap());
                 let deflate1chonk_post = Instant::now();
+
+                let zstdchonk_pre = Instant::now();
+                let zstd_chonk = ZstdCompressedData::compress(&chunk, TERRAIN_ZSTD_LEVEL, None);
+                let zstdchonk_post = Instant::now();
+
+                let zstddictchonk_pre = Instant::now();
+                let zstddict_chonk = ZstdCompressedData::compress(
+                    &chunk,
+                    TERRAIN_ZSTD_LEVEL,
+                    Some(&zstd_dictionary),
+                );
+                let zstddictchonk_post = Instant::now();
                 let mut sizes = vec![
                     ("lz4_chonk", lz4_chonk.len() as f32 / n as f32),
                     ("rle_chonk", rle_chonk.len() as f32 / n as f32),
                     ("deflate0_chonk", deflate0_chonk.len() as f32 / n as f32),
                     ("deflate1_chonk", deflate1_chonk.len() as f32 / n as f32),
+                    ("zstd_chonk", zstd_chonk.data.len() as f32 / n as f32),
+                    (
+                        "zstddict_chonk",
+                        zstddict_chonk.data.len() as f32 / n as f32,
+                    ),
                 ];
                 #[rustfmt::skip]
                 let mut timings = vec![
@@ -802,6 +832,8 @@ fn main() {
                     ("rlechonk", (rlechonk_post - rlechonk_pre).subsec_nanos()),
                     ("deflate0chonk", (deflate0chonk_post - deflate0chonk_pre).subsec_nanos()),
                     ("deflate1chonk", (deflate1chonk_post - deflate1chonk_pre).subsec_nanos()),
+                    ("zstdchonk", (zstdchonk_post - zstdchonk_pre).subsec_nanos()),
+                    ("zstddictchonk", (zstddictchonk_post - zstddictchonk_pre).subsec_nanos()),
                 ];
                 {
                     let bucket = z_buckets
@@ -1280,4 +1312,14 @@ fn main() {
             }
         }
     }
+    if TRAIN_ZSTD_DICTIONARY {
+        println!("Training zstd dictionary on {} chunks", zstd_samples.len());
+        let dictionary = ZstdDictionary::train(&zstd_samples, ZSTD_DICTIONARY_SIZE).unwrap();
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../assets/common/terrain_zstd_dictionary.dict"
+        );
+        std::fs::write(path, dictionary.bytes()).unwrap();
+        println!("Wrote zstd dictionary {:?} to {}", dictionary.id(), path);
+    }
 }
@@ -0,0 +1,151 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use common::terrain::structure::StructureBlock;
+use hashbrown::HashMap;
+use rayon::ThreadPoolBuilder;
+use vek::{Vec2, Vec3};
+use veloren_world::{
+    sim::{FileOpts, WorldOpts, DEFAULT_WORLD_MAP},
+    site2::{
+        vox::{self, VoxExport},
+        Painter, Site, Structure,
+    },
+    CanvasInfo, Land, World,
+};
+
+/// This exports site2 plots to a MagicaVoxel .vox file, along with a .ron file
+/// holding the block each palette index stands for. Usage:
+///
+/// - `site2_voxel_export [dungeon|city] [<plot index>]` exports the plots of a
+///   generated site, or only one of them
+/// - `site2_voxel_export import <file.vox> <palette.ron>` imports a .vox file
+///   as a prefab and exports it again, to check what it looks like in game
+const USAGE: &str =
+    "usage: site2_voxel_export [dungeon|city] [<plot index>] | import <file.vox> <palette.ron>";
+
+struct Prefab(common::terrain::Structure);
+
+impl Structure for Prefab {
+    fn render(&self, _site: &Site, _land: &Land, painter: &Painter) {
+        painter.prefab(self.0.clone(), Vec3::zero(), 0);
+    }
+}
+
+/// Render the plots of a new site, or only the one with the given index
+fn render_site(
+    export: &mut VoxExport,
+    canvas: &CanvasInfo,
+    kind: &str,
+    plot: Option<usize>,
+) -> &'static str {
+    let mut rng = rand::thread_rng();
+    let (site, export_path) = if kind == "dungeon" {
+        (
+            Site::generate_dungeon(&Land::empty(), &mut rng, Vec2::zero()),
+            "dungeon.vox",
+        )
+    } else {
+        (
+            Site::generate_city(&Land::empty(), &mut rng, Vec2::zero()),
+            "city.vox",
+        )
+    };
+
+    match plot.and_then(|plot| site.plots().nth(plot)) {
+        Some(plot) => {
+            export.render_plot(&site, plot, canvas);
+        },
+        None => export.render_site(&site, canvas),
+    }
+    export_path
+}
+
+/// Render a .vox file imported as a prefab
+fn render_import(
+    export: &mut VoxExport,
+    canvas: &CanvasInfo,
+    vox_path: &str,
+    palette_path: &str,
+) -> io::Result<&'static str> {
+    let invalid_data = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+    let palette =
+        ron::de::from_str::<HashMap<u8, StructureBlock>>(&fs::read_to_string(palette_path)?)
+            .map_err(|err| invalid_data(err.to_string()))?;
+    let prefab = vox::import(&fs::read(vox_path)?, &palette, Vec3::zero())
+        .map_err(|err| invalid_data(err.to_string()))?;
+
+    let site = Site::generate_dungeon(&Land::empty(), &mut rand::thread_rng(), Vec2::zero());
+    let (prim_tree, fills) = Prefab(prefab).render_collect(&site, &canvas.land());
+    export.render(&prim_tree, &fills, canvas);
+    Ok("imported.vox")
+}
+
+/// Render what the arguments ask for, returning where to save it or `None` if
+/// the arguments are invalid
+fn render(
+    export: &mut VoxExport,
+    canvas: &CanvasInfo,
+    args: &[&str],
+) -> io::Result<Option<&'static str>> {
+    match args {
+        ["import", vox_path, palette_path] => {
+            render_import(export, canvas, vox_path, palette_path).map(Some)
+        },
+        [kind @ ("dungeon" | "city")] => Ok(Some(render_site(export, canvas, kind, None))),
+        [kind @ ("dungeon" | "city"), plot] => Ok(plot
+            .parse()
+            .ok()
+            .map(|plot| render_site(export, canvas, kind, Some(plot)))),
+        _ => Ok(None),
+    }
+}
+
+fn main() -> io::Result<()> {
+    common_frontend::init_stdout(None);
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let pool = ThreadPoolBuilder::new().build().unwrap();
+    println!("Loading world");
+    let (world, index) = World::generate(
+        59686,
+        WorldOpts {
+            seed_elements: true,
+            world_file: FileOpts::LoadAsset(DEFAULT_WORLD_MAP.into()),
+        },
+        &pool,
+    );
+    println!("Loaded world");
+
+    let mut export = VoxExport::default();
+    let export_path =
+        CanvasInfo::with_mock_canvas_info(index.as_index_ref(), world.sim(), |canvas| {
+            render(&mut export, canvas, &args)
+        })?;
+    let export_path = match export_path {
+        Some(export_path) => export_path,
+        None => {
+            println!("{}", USAGE);
+            return Ok(());
+        },
+    };
+
+    println!("Saving into {}", export_path);
+    let palette = export.write(&mut File::create(export_path)?)?;
+    let palette_path = Path::new(export_path).with_extension("ron");
+    let palette = ron::ser::to_string_pretty(
+        &palette
+            .custom_indices()
+            .into_iter()
+            .collect::<BTreeMap<_, _>>(),
+        ron::ser::PrettyConfig::default(),
+    )
+    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    println!("Saving palette into {}", palette_path.display());
+    fs::write(palette_path, palette)
+}
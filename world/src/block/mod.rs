@@ -253,6 +253,14 @@ pub fn block_from_structure(
         StructureBlock::Normal(color) => Some(Block::new(BlockKind::Misc, color)),
         StructureBlock::Filled(kind, color) => Some(Block::new(kind, color)),
         StructureBlock::Sprite(kind) => Some(with_sprite(kind).into_vacant().with_sprite(kind)),
+        StructureBlock::RotatedSprite(kind, ori) => {
+            let block = with_sprite(kind).into_vacant().with_sprite(kind);
+            Some(block.with_ori(ori).unwrap_or(block))
+        },
+        StructureBlock::WaterSprite(kind, ori) => {
+            let block = Block::water(kind);
+            Some(block.with_ori(ori).unwrap_or(block))
+        },
         StructureBlock::Water => Some(Block::water(SpriteKind::Empty)),
         // TODO: If/when liquid supports other colors again, revisit this.
         StructureBlock::GreenSludge => Some(Block::water(SpriteKind::Empty)),
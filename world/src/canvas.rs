@@ -8,6 +8,7 @@ use crate::{
     util::{Grid, Sampler},
 };
 use common::{
+    calendar::Season,
     generation::EntityInfo,
     terrain::{Block, BlockKind, Structure, TerrainChunk, TerrainChunkSize},
     vol::{ReadVol, RectVolSize, WriteVol},
@@ -24,11 +25,15 @@ pub struct CanvasInfo<'a> {
     pub(crate) chunks: &'a WorldSim,
     pub(crate) index: IndexRef<'a>,
     pub(crate) chunk: &'a SimChunk,
+    pub(crate) season: Option<Season>,
 }
 
 impl<'a> CanvasInfo<'a> {
     pub fn wpos(&self) -> Vec2<i32> { self.wpos }
 
+    /// Season the chunk is generated for, see [`ColumnGen::season`]
+    pub fn season(&self) -> Option<Season> { self.season }
+
     pub fn area(&self) -> Aabr<i32> {
         Rect::from((
             self.wpos(),
@@ -52,7 +57,9 @@ impl<'a> CanvasInfo<'a> {
     pub fn col_or_gen(&self, wpos: Vec2<i32>) -> Option<Cow<'a, ColumnSample>> {
         self.col(wpos).map(Cow::Borrowed).or_else(|| {
             Some(Cow::Owned(
-                ColumnGen::new(self.chunks()).get((wpos, self.index()))?,
+                ColumnGen::new(self.chunks())
+                    .with_season(self.season)
+                    .get((wpos, self.index()))?,
             ))
         })
     }
@@ -122,6 +129,7 @@ impl<'a> CanvasInfo<'a> {
             chunks: sim,
             index,
             chunk: &sim_chunk,
+            season: None,
         })
     }
 }
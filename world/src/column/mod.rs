@@ -5,6 +5,7 @@ use crate::{
     IndexRef, CONFIG,
 };
 use common::{
+    calendar::Season,
     terrain::{
         quadratic_nearest_point, river_spline_coeffs, uniform_idx_as_vec2, vec2_as_uniform_idx,
         TerrainChunkSize,
@@ -22,6 +23,9 @@ use vek::*;
 
 pub struct ColumnGen<'a> {
     pub sim: &'a WorldSim,
+    /// Season to colour the vegetation and spread the snow for, `None` for the
+    /// world as it is all year round
+    pub season: Option<Season>,
 }
 
 #[derive(Deserialize)]
@@ -30,6 +34,8 @@ pub struct Colors {
     pub warm_grass: (f32, f32, f32),
     pub dark_grass: (f32, f32, f32),
     pub wet_grass: (f32, f32, f32),
+    pub autumn_grass: (f32, f32, f32),
+    pub winter_grass: (f32, f32, f32),
     pub cold_stone: (f32, f32, f32),
     pub hot_stone: (f32, f32, f32),
     pub warm_stone: (f32, f32, f32),
@@ -60,7 +66,12 @@ fn power(x: f64, t: f64) -> f64 {
 }
 
 impl<'a> ColumnGen<'a> {
-    pub fn new(sim: &'a WorldSim) -> Self { Self { sim } }
+    pub fn new(sim: &'a WorldSim) -> Self { Self { sim, season: None } }
+
+    pub fn with_season(mut self, season: Option<Season>) -> Self {
+        self.season = season;
+        self
+    }
 }
 
 impl<'a> Sampler<'a> for ColumnGen<'a> {
@@ -891,6 +902,8 @@ impl<'a> Sampler<'a> for ColumnGen<'a> {
             warm_grass,
             dark_grass,
             wet_grass,
+            autumn_grass,
+            winter_grass,
             cold_stone,
             hot_stone,
             warm_stone,
@@ -930,20 +943,29 @@ impl<'a> Sampler<'a> for ColumnGen<'a> {
         let dead_tundra = Lerp::lerp(warm_stone, warm_stone_high, marble_mixed);
         let cliff = Rgb::lerp(cold_stone, hot_stone, marble_mixed);
 
-        let grass = Rgb::lerp(
+        // Leaves turn in autumn and grass withers in winter, rainforests stay
+        // green all year round
+        let seasonal = |col: Rgb<f32>| match self.season {
+            Some(Season::Autumn) => {
+                Rgb::lerp(col, autumn_grass.into(), 0.2 + marble_mixed.mul(0.4))
+            },
+            Some(Season::Winter) => Rgb::lerp(col, winter_grass.into(), 0.5),
+            _ => col,
+        };
+        let grass = seasonal(Rgb::lerp(
             cold_grass,
             warm_grass,
             marble_mixed
                 .sub(0.5)
                 .add(1.0.sub(humidity).mul(0.5))
                 .powf(1.5),
-        );
+        ));
         let snow_moss = Rgb::lerp(
             snow_moss.into(),
             cold_grass,
             0.4 + marble_mixed.powf(1.5) * 0.6,
         );
-        let moss = Rgb::lerp(dark_grass, cold_grass, marble_mixed.powf(1.5));
+        let moss = seasonal(Rgb::lerp(dark_grass, cold_grass, marble_mixed.powf(1.5)));
         let rainforest = Rgb::lerp(wet_grass, warm_grass, marble_mixed.powf(1.5));
         let sand = Rgb::lerp(beach_sand, desert_sand, marble_mixed);
 
@@ -1080,8 +1102,15 @@ impl<'a> Sampler<'a> for ColumnGen<'a> {
             humidity.sub(CONFIG.jungle_hum).mul(1.0),
         );
 
-        // Snow covering
+        // Snow covering, it reaches further from the cold regions in winter
+        let season_temp = match self.season {
+            Some(Season::Winter) => -0.2,
+            Some(Season::Autumn | Season::Spring) => -0.05,
+            Some(Season::Summer) => 0.1,
+            None => 0.0,
+        };
         let snow_cover = temp
+            .add(season_temp)
             .sub(CONFIG.snow_temp)
             .max(-humidity.sub(CONFIG.desert_hum))
             .mul(4.0)
@@ -1,10 +1,14 @@
 use crate::{
-    layer::wildlife::{self, DensityFn, SpawnEntry},
+    layer::{
+        spot::{Spot, SpotConfig},
+        wildlife::{self, DensityFn, SpawnEntry},
+    },
     site::{economy::TradeInformation, Site},
     Colors, Features,
 };
 use common::{
     assets::{AssetExt, AssetHandle},
+    calendar::Season,
     store::Store,
     trade::{SiteId, SitePrices},
 };
@@ -22,6 +26,8 @@ pub struct Index {
     pub sites: Store<Site>,
     pub trade: TradeInformation,
     pub wildlife_spawns: Vec<(AssetHandle<SpawnEntry>, DensityFn)>,
+    /// The spots that can be generated, see [`Spot`]
+    pub spots: Vec<AssetHandle<SpotConfig>>,
     colors: AssetHandle<Arc<Colors>>,
     features: AssetHandle<Arc<Features>>,
 }
@@ -61,7 +67,7 @@ impl<'a> Deref for IndexRef<'a> {
 }
 
 impl Index {
-    /// NOTE: Panics if the color manifest cannot be loaded.
+    /// NOTE: Panics if the color manifest or the spots cannot be loaded.
     pub fn new(seed: u32) -> Self {
         let colors = Arc::<Colors>::load_expect(WORLD_COLORS_MANIFEST);
         let features = Arc::<Features>::load_expect(WORLD_FEATURES_MANIFEST);
@@ -69,6 +75,7 @@ impl Index {
             .into_iter()
             .map(|(e, f)| (SpawnEntry::load_expect(e), f))
             .collect();
+        let spots = Spot::load_configs();
 
         Self {
             seed,
@@ -77,6 +84,7 @@ impl Index {
             sites: Store::default(),
             trade: Default::default(),
             wildlife_spawns,
+            spots,
             colors,
             features,
         }
@@ -86,11 +94,12 @@ impl Index {
 
     pub fn features(&self) -> impl Deref<Target = Arc<Features>> + '_ { self.features.read() }
 
-    pub fn get_site_prices(&self, site_id: SiteId) -> Option<SitePrices> {
+    /// Prices of the site for the given season of the year
+    pub fn get_site_prices(&self, site_id: SiteId, season: Season) -> Option<SitePrices> {
         self.sites
             .recreate_id(site_id)
             .map(|i| self.sites.get(i))
-            .map(|s| s.economy.get_site_prices())
+            .map(|s| s.economy.get_site_prices().with_season(season))
     }
 }
 
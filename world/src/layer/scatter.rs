@@ -1,5 +1,8 @@
 use crate::{column::ColumnSample, sim::SimChunk, Canvas, CONFIG};
-use common::terrain::{Block, SpriteKind};
+use common::{
+    calendar::Season,
+    terrain::{Block, SpriteKind},
+};
 use noise::NoiseFn;
 use rand::prelude::*;
 use std::f32;
@@ -12,6 +15,33 @@ fn close(x: f32, tgt: f32, falloff: f32) -> f32 {
 const MUSH_FACT: f32 = 1.0e-4; // To balance things around the mushroom spawning rate
 const GRASS_FACT: f32 = 1.0e-3; // To balance things around the grass spawning rate
 const DEPTH_WATER_NORM: f32 = 15.0; // Water depth at which regular underwater sprites start spawning
+
+/// How much more or less of a sprite grows in `season`, flowers bloom in
+/// spring and crops and berries ripen in autumn
+fn seasonal_density(kind: SpriteKind, season: Option<Season>) -> f32 {
+    use SpriteKind::*;
+    match (kind, season) {
+        (_, None) => 1.0,
+        (
+            BlueFlower | PinkFlower | PurpleFlower | RedFlower | WhiteFlower | YellowFlower
+            | Sunflower | WildFlax,
+            Some(season),
+        ) => match season {
+            Season::Spring => 1.5,
+            Season::Summer => 1.0,
+            Season::Autumn => 0.4,
+            Season::Winter => 0.0,
+        },
+        (Pumpkin | Blueberry | LingonBerry | Cotton, Some(season)) => match season {
+            Season::Spring => 0.3,
+            Season::Summer => 1.0,
+            Season::Autumn => 1.5,
+            Season::Winter => 0.1,
+        },
+        _ => 1.0,
+    }
+}
+
 pub fn apply_scatter_to(canvas: &mut Canvas, rng: &mut impl Rng) {
     enum WaterMode {
         Underwater,
@@ -661,6 +691,7 @@ pub fn apply_scatter_to(canvas: &mut Canvas, rng: &mut impl Rng) {
         }),
     ];
 
+    let season = canvas.season();
     canvas.foreach_col(|canvas, wpos2d, col| {
         let underwater = col.water_level.floor() > col.alt;
 
@@ -669,6 +700,7 @@ pub fn apply_scatter_to(canvas: &mut Canvas, rng: &mut impl Rng) {
             .enumerate()
             .find_map(|(i, (kind, water_mode, f))| {
                 let (density, patch) = f(canvas.chunk(), col);
+                let density = density * seasonal_density(*kind, season);
                 let density = patch
                     .map(|(base_density_prop, wavelen, threshold)| {
                         if canvas
@@ -1,388 +1,225 @@
 use crate::{
     sim::{SimChunk, WorldSim},
-    util::{seed_expan, Sampler, UnitChooser},
-    Canvas,
+    site::settlement::merchant_loadout,
+    util::{seed_expan, Sampler, UnitChooser, NEIGHBORS},
+    Canvas, IndexRef, CONFIG,
 };
 use common::{
+    assets::{self, AssetExt, AssetHandle},
+    comp::agent,
     generation::EntityInfo,
     terrain::{BiomeKind, Structure, TerrainChunkSize},
+    trade::SiteInformation,
     vol::RectVolSize,
 };
 use rand::prelude::*;
 use rand_chacha::ChaChaRng;
-use std::ops::Range;
+use serde::Deserialize;
 use vek::*;
 
+/// Directory the spots are loaded from
+const SPOTS_DIR: &str = "world.spot";
+
+/// Lists the spots in [`SPOTS_DIR`] in the order they are generated
+const SPOT_ORDER: &str = "world.spots";
+
 /// Spots are localised structures that spawn in the world. Conceptually, they
 /// fit somewhere between the tree generator and the site generator: an attempt
 /// to marry the simplicity of the former with the capability of the latter.
 /// They are not globally visible to the game: this means that they do not
 /// appear on the map, and cannot interact with rtsim (much).
 ///
-/// To add a new spot, one must:
-///
-/// 1. Add a new variant to the [`Spot`] enum.
-///
-/// 2. Add a new entry to [`Spot::generate`] that tells the system where to
-/// generate your new spot.
+/// Spots are defined by the RON files in `assets/world/spot`, see
+/// [`SpotConfig`]. To add a new spot, add a new file there: it tells the
+/// system where to generate the spot, the base structure that composes it and
+/// the entities that should be spawned there. Then append its name to
+/// `assets/world/spots.ron`: the spots are generated in that order, so adding
+/// it anywhere else would move the spots that come after it.
 ///
-/// 3. Add a new arm to the `match` expression in [`Spot::apply_spots_to`] that
-/// tells the generator how to generate a spot, including the base structure
-/// that composes the spot and the entities that should be spawned there.
-#[derive(Copy, Clone, Debug)]
-pub enum Spot {
-    DwarvenGrave,
-    SaurokAltar,
-    RockCircle,
-    MyrmidonTemple,
-    GnarlingTotem,
-    WitchHouse,
-    PirateHideout,
-    GnomeSpring,
-    WolfBurrow,
-    Igloo,
-    //BanditCamp,
-    //EnchantedRock,
-    //TowerRuin,
-    //WellOfLight,
-    //MerchantOutpost,
-    //RuinedHuntingCabin, <-- Bears!
-    //LionRock,
-    //WolfBurrow,
-    // *Random world objects*
-    LionRock,
-    TreeStumpForest,
-    DesertBones,
-    Arch,
-    AirshipCrash,
-    FruitTree,
-    Shipwreck,
-    Shipwreck2,
-    GnarlingTree,
-    TrollCave,
+/// A `Spot` is the index of its config in [`crate::Index::spots`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Spot(u16);
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpotConfig {
+    /// The manifest containing a list of possible base structures for the spot
+    /// (one will be chosen)
+    pub base_structures: Option<String>,
+    /// How often should this spot appear (per square km, on average)?
+    pub freq: f32,
+    /// Where the spot may be generated
+    #[serde(default)]
+    pub condition: SpotCondition,
+    /// Should we allow trees and other trivial structures to spawn close to the
+    /// spot?
+    #[serde(default)]
+    pub spawn: bool,
+    /// The maximum distance from the centre of the spot that entities will
+    /// spawn
+    pub entity_radius: f32,
+    /// The entities that should be spawned in the spot, from closest to
+    /// furthest: ((min, max), spec)
+    /// (min, max) = number of entities, chosen randomly within this range
+    /// spec = Manifest spec for the entity kind
+    #[serde(default)]
+    pub entities: Vec<((u32, u32), String)>,
+    /// Whether the entities are merchants that trade the goods of the closest
+    /// town
+    #[serde(default)]
+    pub merchants: bool,
+}
+
+impl assets::Asset for SpotConfig {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+/// The names of the spots in [`SPOTS_DIR`], in the order they are generated
+#[derive(Clone, Debug, Deserialize)]
+#[serde(transparent)]
+struct SpotOrder(Vec<String>);
+
+impl assets::Asset for SpotOrder {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+/// The tests we perform to see whether we can spawn a spot in a chunk, all of
+/// them must pass
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpotCondition {
+    /// Biomes the spot appears in, any biome if empty
+    pub biomes: Vec<BiomeKind>,
+    /// Biomes the spot never appears in
+    pub excluded_biomes: Vec<BiomeKind>,
+    /// Steepest gradient of the terrain the spot appears on
+    pub max_gradient: f32,
+    /// (min, max) altitude above sea level
+    pub altitude: Option<(f32, f32)>,
+    pub water: WaterCondition,
+    pub path: PathCondition,
+    /// Whether the spot may appear close to cliffs
+    pub near_cliffs: bool,
+}
+
+impl Default for SpotCondition {
+    fn default() -> Self {
+        Self {
+            biomes: Vec::new(),
+            excluded_biomes: Vec::new(),
+            max_gradient: 0.25,
+            altitude: None,
+            water: WaterCondition::Dry,
+            path: PathCondition::Avoid,
+            near_cliffs: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+pub enum WaterCondition {
+    /// Away from rivers, lakes and the ocean
+    Dry,
+    /// Close to water, but not in it
+    Shore,
+    /// At the bottom of at least `min_depth` blocks of water
+    Underwater {
+        min_depth: f32,
+    },
+    Any,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+pub enum PathCondition {
+    /// Away from paths and roads
+    Avoid,
+    /// Beside a path or road, for spots that travellers should come across
+    Near,
+    Any,
+}
+
+impl SpotCondition {
+    fn is_valid(&self, world: &WorldSim, pos: Vec2<i32>, gradient: f32, c: &SimChunk) -> bool {
+        let biome = c.get_biome();
+        let water = match self.water {
+            WaterCondition::Dry => !c.river.near_water(),
+            WaterCondition::Shore => c.river.near_water() && !c.is_underwater(),
+            WaterCondition::Underwater { min_depth } => {
+                c.is_underwater() && c.water_alt > c.alt + min_depth
+            },
+            WaterCondition::Any => true,
+        };
+        let path = match self.path {
+            PathCondition::Avoid => !c.path.0.is_way(),
+            PathCondition::Near => {
+                !c.path.0.is_way()
+                    && NEIGHBORS.iter().any(|dir| {
+                        world
+                            .get(pos + *dir)
+                            .map_or(false, |neighbor| neighbor.path.0.is_way())
+                    })
+            },
+            PathCondition::Any => true,
+        };
+        gradient < self.max_gradient
+            && c.sites.is_empty()
+            && (self.near_cliffs || !c.near_cliffs())
+            && (self.biomes.is_empty() || self.biomes.contains(&biome))
+            && !self.excluded_biomes.contains(&biome)
+            && self.altitude.map_or(true, |(min, max)| {
+                (min..=max).contains(&(c.alt - CONFIG.sea_level))
+            })
+            && water
+            && path
+    }
 }
 
 impl Spot {
-    pub fn generate(world: &mut WorldSim) {
-        use BiomeKind::*;
-        // Trees/spawn: false => *No* trees around the spot
-        // Themed Spots -> Act as an introduction to themes of sites
-        Self::generate_spots(
-            Spot::WitchHouse,
-            world,
-            1.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(
-                        c.get_biome(),
-                        Grassland | Forest | Taiga | Snowland | Jungle
-                    )
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::Igloo,
-            world,
-            2.0,
-            |g, c| {
-                g < 0.5
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Snowland)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::PirateHideout,
-            world,
-            1.3,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Forest | Jungle)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::DwarvenGrave,
-            world,
-            1.3,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Jungle | Forest)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::SaurokAltar,
-            world,
-            1.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Jungle | Forest)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::RockCircle,
-            world,
-            0.5,
-            |g, c| {
-                g < 0.1
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::MyrmidonTemple,
-            world,
-            1.0,
-            |g, c| {
-                g < 0.1
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Desert | Jungle)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::GnarlingTotem,
-            world,
-            1.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Forest | Grassland)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::GnarlingTree,
-            world,
-            1.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Forest | Grassland)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::TrollCave,
-            world,
-            1.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Forest | Grassland | Snowland | Taiga)
-            },
-            false,
-        );
-        // Random World Objects -> Themed to their Biome and the NPCs that regularly
-        // spawn there
-        Self::generate_spots(
-            Spot::LionRock,
-            world,
-            1.5,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Savannah)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::WolfBurrow,
-            world,
-            1.5,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Forest | Grassland)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::TreeStumpForest,
-            world,
-            20.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Jungle | Forest)
-            },
-            true,
-        );
-        Self::generate_spots(
-            Spot::DesertBones,
-            world,
-            6.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Desert)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::Arch,
-            world,
-            2.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Desert)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::AirshipCrash,
-            world,
-            0.7,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && !matches!(c.get_biome(), Mountain | Void | Ocean)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::FruitTree,
-            world,
-            20.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Forest)
-            },
-            true,
-        );
-        Self::generate_spots(
-            Spot::GnomeSpring,
-            world,
-            1.0,
-            |g, c| {
-                g < 0.25
-                    && !c.near_cliffs()
-                    && !c.river.near_water()
-                    && !c.path.0.is_way()
-                    && c.sites.is_empty()
-                    && matches!(c.get_biome(), Forest)
-            },
-            false,
-        );
-        Self::generate_spots(
-            Spot::Shipwreck,
-            world,
-            1.0,
-            |g, c| {
-                g < 0.25 && c.is_underwater() && c.sites.is_empty() && c.water_alt > c.alt + 30.0
-            },
-            true,
-        );
-        Self::generate_spots(
-            Spot::Shipwreck2,
-            world,
-            1.0,
-            |g, c| {
-                g < 0.25 && c.is_underwater() && c.sites.is_empty() && c.water_alt > c.alt + 30.0
-            },
-            true,
-        );
+    /// Load the configs of all spots, in the order given by [`SPOT_ORDER`].
+    ///
+    /// NOTE: Panics if a spot cannot be loaded.
+    pub fn load_configs() -> Vec<AssetHandle<SpotConfig>> {
+        SpotOrder::load_expect(SPOT_ORDER)
+            .read()
+            .0
+            .iter()
+            .map(|name| SpotConfig::load_expect(&[SPOTS_DIR, ".", name].concat()))
+            .collect()
+    }
 
-        // Missing:
-        /*
-        Bandit Camp
-        Hunter Camp
-        TowerRuinForest
-        TowerRuinDesert
-        WellOfLight
-        Merchant Outpost -> Near a road!
-        *Quirky:*
-        TreeHouse (Forest)
-        EnchantedRock (Forest, Jungle)
-        */
+    pub fn generate(world: &mut WorldSim, configs: &[AssetHandle<SpotConfig>]) {
+        for (i, config) in configs.iter().enumerate() {
+            Self::generate_spots(Spot(i as u16), world, &config.read());
+        }
     }
 
     fn generate_spots(
         // What kind of spot are we generating?
         spot: Spot,
         world: &mut WorldSim,
-        // How often should this spot appear (per square km, on average)?
-        freq: f32,
-        // What tests should we perform to see whether we can spawn the spot here? The two
-        // parameters are the gradient of the terrain and the [`SimChunk`] of the candidate
-        // location.
-        mut valid: impl FnMut(f32, &SimChunk) -> bool,
-        // Should we allow trees and other trivial structures to spawn close to the spot?
-        spawn: bool,
+        config: &SpotConfig,
     ) {
         let world_size = world.get_size();
-        for _ in
-            0..(world_size.product() as f32 * TerrainChunkSize::RECT_SIZE.product() as f32 * freq
-                / 1000.0f32.powi(2))
-            .ceil() as u64
+        for _ in 0..(world_size.product() as f32
+            * TerrainChunkSize::RECT_SIZE.product() as f32
+            * config.freq
+            / 1000.0f32.powi(2))
+        .ceil() as u64
         {
             let pos = world_size.map(|e| (world.rng.gen_range(0..e) & !0b11) as i32);
-            if let Some((_, chunk)) = world
+            let valid = world
                 .get_gradient_approx(pos)
-                .zip(world.get_mut(pos))
-                .filter(|(grad, chunk)| valid(*grad, chunk))
-            {
+                .zip(world.get(pos))
+                .map_or(false, |(grad, chunk)| {
+                    config.condition.is_valid(world, pos, grad, chunk)
+                });
+            if let Some(chunk) = world.get_mut(pos).filter(|_| valid) {
                 chunk.spot = Some(spot);
-                if !spawn {
+                if !config.spawn {
                     chunk.tree_density = 0.0;
                     chunk.spawn_rate = 0.0;
                 }
@@ -392,6 +229,7 @@ impl Spot {
 }
 
 pub fn apply_spots_to(canvas: &mut Canvas, _dynamic_rng: &mut impl Rng) {
+    let index = canvas.index();
     let nearby_spots = canvas.nearby_spots().collect::<Vec<_>>();
 
     for (spot_wpos2d, spot, seed) in nearby_spots.iter().copied() {
@@ -399,155 +237,9 @@ pub fn apply_spots_to(canvas: &mut Canvas, _dynamic_rng: &mut impl Rng) {
 
         let units = UnitChooser::new(seed).get(seed).into();
 
-        #[derive(Default)]
-        struct SpotConfig<'a> {
-            // The manifest containing a list of possible base structures for the spot (one will be
-            // chosen)
-            base_structures: Option<&'a str>,
-            // The maximum distance from the centre of the spot that entities will spawn
-            entity_radius: f32,
-            // The entities that should be spawned in the spot, from closest to furthest
-            // (count_range, spec)
-            // count_range = number of entities, chosen randomly within this range
-            // spec = Manifest spec for the entity kind
-            entities: &'a [(Range<u32>, &'a str)],
-        }
-
-        let spot_config = match spot {
-            // Themed Spots
-            Spot::DwarvenGrave => SpotConfig {
-                base_structures: Some("spots_grasslands.dwarven_grave"),
-                entity_radius: 60.0,
-                entities: &[(6..12, "common.entity.spot.dwarf_grave_robber")],
-            },
-            Spot::SaurokAltar => SpotConfig {
-                base_structures: Some("spots.jungle.saurok-altar"),
-                entity_radius: 6.0,
-                entities: &[
-                    (0..3, "common.entity.wild.aggressive.occult_saurok"),
-                    (0..3, "common.entity.wild.aggressive.sly_saurok"),
-                    (0..3, "common.entity.wild.aggressive.mighty_saurok"),
-                ],
-            },
-            Spot::RockCircle => SpotConfig {
-                base_structures: Some("spots.rock-circle"),
-                entity_radius: 20.0,
-                entities: &[
-                    (0..2, "common.entity.wild.aggressive.archaeos"),
-                    (0..2, "common.entity.wild.aggressive.ntouka"),
-                    (0..2, "common.entity.wild.aggressive.dreadhorn"),
-                ],
-            },
-            Spot::MyrmidonTemple => SpotConfig {
-                base_structures: Some("spots.myrmidon-temple"),
-                entity_radius: 10.0,
-                entities: &[(8..10, "common.entity.spot.myrmidon.spear")],
-            },
-            Spot::WitchHouse => SpotConfig {
-                base_structures: Some("spots_general.witch_hut"),
-                entity_radius: 1.0,
-                entities: &[
-                    (1..2, "common.entity.spot.witch_dark"),
-                    (0..4, "common.entity.wild.peaceful.cat"),
-                    (0..3, "common.entity.wild.peaceful.frog"),
-                ],
-            },
-            Spot::Igloo => SpotConfig {
-                base_structures: Some("spots_general.igloo"),
-                entity_radius: 2.0,
-                entities: &[
-                    (3..5, "common.entity.dungeon.tier-1.spear"),
-                    (3..5, "common.entity.dungeon.tier-1.bow"),
-                    (2..3, "common.entity.dungeon.tier-1.staff"),
-                ],
-            },
-            Spot::PirateHideout => SpotConfig {
-                base_structures: Some("spots_general.pirate_hideout"),
-                entity_radius: 70.0,
-                entities: &[
-                    (12..16, "common.entity.spot.pirate"),
-                    (2..4, "common.entity.wild.peaceful.parrot"),
-                    (4..6, "common.entity.wild.peaceful.rat"),
-                ],
-            },
-            Spot::GnarlingTotem => SpotConfig {
-                base_structures: Some("spots_grasslands.gnarling_totem"),
-                entity_radius: 30.0,
-                entities: &[
-                    (1..4, "common.entity.dungeon.tier-0.spear"),
-                    (2..5, "common.entity.dungeon.tier-0.bow"),
-                    (1..3, "common.entity.dungeon.tier-0.staff"),
-                ],
-            },
-            Spot::GnarlingTree => SpotConfig {
-                base_structures: Some("spots_grasslands.gnarling_tree"),
-                entity_radius: 64.0,
-                entities: &[
-                    (1..5, "common.entity.dungeon.tier-0.spear"),
-                    (2..4, "common.entity.dungeon.tier-0.bow"),
-                    (1..2, "common.entity.dungeon.tier-0.staff"),
-                    (1..4, "common.entity.wild.aggressive.deadwood"),
-                ],
-            },
-            Spot::TrollCave => SpotConfig {
-                base_structures: Some("spots_general.troll_cave"),
-                entity_radius: 40.0,
-                entities: &[(1..2, "common.entity.wild.aggressive.cave_troll")],
-            },
-            // Random World Objects
-            Spot::LionRock => SpotConfig {
-                base_structures: Some("spots_savannah.lion_rock"),
-                entity_radius: 20.0,
-                entities: &[(5..8, "common.entity.wild.aggressive.lion")],
-            },
-            Spot::WolfBurrow => SpotConfig {
-                base_structures: Some("spots_savannah.wolf_burrow"),
-                entity_radius: 10.0,
-                entities: &[(5..8, "common.entity.wild.aggressive.wolf")],
-            },
-            Spot::TreeStumpForest => SpotConfig {
-                base_structures: Some("trees.oak_stumps"),
-                entity_radius: 30.0,
-                entities: &[(0..2, "common.entity.wild.aggressive.deadwood")],
-            },
-            Spot::DesertBones => SpotConfig {
-                base_structures: Some("spots.bones"),
-                entity_radius: 40.0,
-                entities: &[(4..9, "common.entity.wild.aggressive.hyena")],
-            },
-            Spot::Arch => SpotConfig {
-                base_structures: Some("spots.arch"),
-                entity_radius: 50.0,
-                entities: &[(2..3, "common.entity.wild.aggressive.ngoubou")],
-            },
-            Spot::AirshipCrash => SpotConfig {
-                base_structures: Some("trees.airship_crash"),
-                entity_radius: 20.0,
-                entities: &[(4..9, "common.entity.spot.grim_salvager")],
-            },
-            Spot::FruitTree => SpotConfig {
-                base_structures: Some("trees.fruit_trees"),
-                entity_radius: 2.0,
-                entities: &[(0..2, "common.entity.wild.peaceful.bear")],
-            },
-            Spot::GnomeSpring => SpotConfig {
-                base_structures: Some("spots.gnome_spring"),
-                entity_radius: 40.0,
-                entities: &[(7..10, "common.entity.spot.gnome.spear")],
-            },
-            Spot::Shipwreck => SpotConfig {
-                base_structures: Some("spots.water.shipwreck"),
-                entity_radius: 2.0,
-                entities: &[(0..2, "common.entity.wild.peaceful.clownfish")],
-            },
-            Spot::Shipwreck2 => SpotConfig {
-                base_structures: Some("spots.water.shipwreck2"),
-                entity_radius: 20.0,
-                entities: &[(2..6, "common.entity.wild.peaceful.clownfish")],
-            },
-        };
+        let spot_config = index.spots[spot.0 as usize].read();
         // Blit base structure
-        if let Some(base_structures) = spot_config.base_structures {
+        if let Some(base_structures) = &spot_config.base_structures {
             let structures = Structure::load_group(base_structures).read();
             let structure = structures.choose(&mut rng).unwrap();
             let origin = spot_wpos2d.with_z(
@@ -559,10 +251,17 @@ pub fn apply_spots_to(canvas: &mut Canvas, _dynamic_rng: &mut impl Rng) {
             canvas.blit_structure(origin, structure, seed, units, true);
         }
 
+        // Merchants sell what the closest town has in stock
+        let economy = if spot_config.merchants {
+            closest_trade_information(index, spot_wpos2d)
+        } else {
+            None
+        };
+
         // Spawn entities
         const PHI: f32 = 1.618;
-        for (spawn_count, spec) in spot_config.entities {
-            let spawn_count = rng.gen_range(spawn_count.clone());
+        for ((min, max), spec) in &spot_config.entities {
+            let spawn_count = rng.gen_range(*min..=*max);
 
             let dir_offset = rng.gen::<f32>();
             for i in 0..spawn_count {
@@ -581,12 +280,70 @@ pub fn apply_spots_to(canvas: &mut Canvas, _dynamic_rng: &mut impl Rng) {
                     .then(|| canvas.find_spawn_pos(wpos2d.with_z(alt)))
                     .flatten()
                 {
-                    canvas.spawn(
-                        EntityInfo::at(wpos.map(|e| e as f32) + Vec3::new(0.5, 0.5, 0.0))
-                            .with_asset_expect(spec),
-                    );
+                    let mut entity =
+                        EntityInfo::at(wpos.map(|e| e as f32) + Vec3::new(0.5, 0.5, 0.0));
+                    if let Some(economy) = &economy {
+                        entity = entity
+                            .with_agent_mark(agent::Mark::Merchant)
+                            .with_economy(economy)
+                            .with_lazy_loadout(merchant_loadout);
+                    }
+                    canvas.spawn(entity.with_asset_expect(spec));
                 }
             }
         }
     }
 }
+
+/// The trade information of the town closest to `wpos`
+fn closest_trade_information(index: IndexRef, wpos: Vec2<i32>) -> Option<SiteInformation> {
+    index
+        .sites
+        .iter()
+        .filter_map(|(id, site)| Some((site.get_origin(), site.trade_information(id.id())?)))
+        .min_by_key(|(origin, _)| origin.distance_squared(wpos))
+        .map(|(_, information)| information)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::generation::EntityConfig;
+
+    // Checks that each spot is loadable, along with its structures and entities
+    #[test]
+    fn test_load_spots() {
+        for config in Spot::load_configs() {
+            let config = config.read();
+            if let Some(base_structures) = &config.base_structures {
+                assert!(!Structure::load_group(base_structures).read().is_empty());
+            }
+            for ((min, max), spec) in &config.entities {
+                assert!(min <= max, "{}: empty spawn count range", spec);
+                std::mem::drop(EntityConfig::load_expect_cloned(spec));
+            }
+        }
+    }
+
+    #[test]
+    fn test_spot_order() {
+        let mut listed = SpotOrder::load_expect(SPOT_ORDER)
+            .read()
+            .0
+            .iter()
+            .map(|name| [SPOTS_DIR, ".", name].concat())
+            .collect::<Vec<_>>();
+        let mut found = assets::load_dir::<SpotConfig>(SPOTS_DIR, true)
+            .expect("Failed loading the spot directory")
+            .ids()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        listed.sort();
+        found.sort();
+        assert_eq!(
+            listed, found,
+            "every spot must be listed once in {}",
+            SPOT_ORDER
+        );
+    }
+}
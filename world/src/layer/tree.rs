@@ -63,7 +63,9 @@ pub fn apply_trees_to(canvas: &mut Canvas, dynamic_rng: &mut impl Rng) {
         } in trees
         {
             let tree = if let Some(tree) = tree_cache.entry(pos).or_insert_with(|| {
-                let col = ColumnGen::new(info.chunks()).get((pos, info.index()))?;
+                let col = ColumnGen::new(info.chunks())
+                    .with_season(info.season())
+                    .get((pos, info.index()))?;
 
                 // Ensure that it's valid to place a *thing* here
                 if col.alt < col.water_level
@@ -49,6 +49,7 @@ use crate::{
 };
 use common::{
     assets,
+    calendar::Calendar,
     generation::{ChunkSupplement, EntityInfo},
     resources::TimeOfDay,
     terrain::{
@@ -105,7 +106,7 @@ impl World {
 
             sim2::simulate(&mut index, &mut sim);
 
-            Spot::generate(&mut sim);
+            Spot::generate(&mut sim, &index.spots);
 
             (Self { sim, civs }, IndexOwned::new(index))
         })
@@ -215,9 +216,10 @@ impl World {
         chunk_pos: Vec2<i32>,
         // TODO: misleading name
         mut should_continue: impl FnMut() -> bool,
-        time: Option<TimeOfDay>,
+        time: Option<(TimeOfDay, Calendar)>,
     ) -> Result<(TerrainChunk, ChunkSupplement), ()> {
-        let mut sampler = self.sample_blocks();
+        let season = time.map(|(time_of_day, calendar)| calendar.season(time_of_day));
+        let mut sampler = BlockGen::new(ColumnGen::new(&self.sim).with_season(season));
 
         let chunk_wpos2d = chunk_pos * TerrainChunkSize::RECT_SIZE.map(|e| e as i32);
         let chunk_center_wpos2d = chunk_wpos2d + TerrainChunkSize::RECT_SIZE.map(|e| e as i32 / 2);
@@ -347,6 +349,7 @@ impl World {
                 chunks: &self.sim,
                 index,
                 chunk: sim_chunk,
+                season,
             },
             chunk: &mut chunk,
             entities: Vec::new(),
@@ -424,7 +427,7 @@ impl World {
             index,
             sim_chunk,
             &mut supplement,
-            time,
+            time.map(|(time_of_day, _)| time_of_day),
         );
 
         // Apply site supplementary information
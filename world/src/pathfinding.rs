@@ -1,37 +1,242 @@
-use crate::sim::WorldSim;
-use common::path::Path;
-//use hashbrown::hash_map::DefaultHashBuilder;
+use crate::{sim::WorldSim, util::NEIGHBORS};
+use common::{astar::Astar, path::Path, terrain::TerrainChunkSize, vol::RectVolSize};
+use core::hash::BuildHasherDefault;
+use fxhash::FxHasher64;
 use vek::*;
 
-#[allow(dead_code)]
+/// Long journeys cross a few hundred chunks, this leaves room for detours
+const MAX_SEARCH_ITERS: usize = 250_000;
+/// Cost per metre of wading through a river, where no way bridges it
+const RIVER_CROSSING_COST: f32 = 8.0;
+
+#[derive(Copy, Clone, Debug)]
 pub struct SearchCfg {
     // 0.0 = no discount, 1.0 = free travel
-    path_discount: f32,
+    pub path_discount: f32,
     // Cost per metre altitude change per metre horizontal
     // 0.0 = no cost, 1.0 = same cost vertical as horizontal
-    gradient_aversion: f32,
+    pub gradient_aversion: f32,
+    // Chunks visited before the search gives up
+    pub max_iters: usize,
+}
+
+impl Default for SearchCfg {
+    fn default() -> Self {
+        Self {
+            path_discount: 0.5,
+            gradient_aversion: 1.0,
+            max_iters: MAX_SEARCH_ITERS,
+        }
+    }
 }
 
-#[allow(dead_code)]
 pub struct Searcher<'a> {
     land: &'a WorldSim,
     pub cfg: SearchCfg,
 }
 
-#[allow(dead_code)]
 impl<'a> Searcher<'a> {
+    pub fn new(land: &'a WorldSim, cfg: SearchCfg) -> Self { Self { land, cfg } }
+
     /// Attempt to find a path between two chunks on the map.
-    pub fn search(self, _a: Vec2<i32>, _b: Vec2<i32>) -> Option<Path<i32>> {
-        // TODO: implement this function
-
-        //let heuristic = |pos: &Vec2<i32>| (pos - b).map(|e| e as f32).magnitude();
-        // Astar::new(
-        //     100_000,
-        //     a,
-        //     heuristc,
-        //     DefaultHashBuilder::default(),
-        // );
-
-        None
+    pub fn search(self, a: Vec2<i32>, b: Vec2<i32>) -> Option<Path<Vec2<i32>>> {
+        self.search_with_cost(a, b).map(|(path, _)| path)
+    }
+
+    /// Like [`Searcher::search`], but also returns the cost of the path,
+    /// roughly the metres travelled
+    pub fn search_with_cost(self, a: Vec2<i32>, b: Vec2<i32>) -> Option<(Path<Vec2<i32>>, f32)> {
+        self.land.get(a)?;
+        // Travelling along ways is cheaper than the distance, the heuristic
+        // must not overestimate
+        let metres_per_chunk = TerrainChunkSize::RECT_SIZE.x as f32;
+        let discount = 1.0 - self.cfg.path_discount.clamped(0.0, 1.0);
+        let this = &self;
+        let heuristic = move |l: &Vec2<i32>| {
+            (l.distance_squared(b) as f32).sqrt() * metres_per_chunk * discount
+        };
+        let neighbors = |l: &Vec2<i32>| {
+            let l = *l;
+            NEIGHBORS
+                .iter()
+                .enumerate()
+                .filter(move |(i, _)| this.transition_cost(l, *i).is_some())
+                .map(move |(_, dir)| l + *dir)
+        };
+        let transition = |a: &Vec2<i32>, b: &Vec2<i32>| {
+            NEIGHBORS
+                .iter()
+                .position(|dir| *a + *dir == *b)
+                .and_then(|i| this.transition_cost(*a, i))
+                .unwrap_or(f32::MAX)
+        };
+        let satisfied = |l: &Vec2<i32>| *l == b;
+        let max_iters = self.cfg.max_iters;
+        // We use this hasher (FxHasher64) because
+        // (1) we don't care about DDOS attacks (ruling out SipHash);
+        // (2) we care about determinism across computers (ruling out AAHash);
+        // (3) we have 8-byte keys (for which FxHash is fastest).
+        let mut astar = Astar::new(
+            max_iters,
+            a,
+            heuristic,
+            BuildHasherDefault::<FxHasher64>::default(),
+        );
+        astar
+            .poll(max_iters, heuristic, neighbors, transition, satisfied)
+            .into_path()
+            .map(|path| {
+                let cost = path
+                    .iter()
+                    .zip(path.iter().skip(1))
+                    .map(|(a, b)| transition(a, b))
+                    .sum();
+                (path, cost)
+            })
+    }
+
+    /// Cost of travelling from chunk `a` in the direction `NEIGHBORS[dir]`,
+    /// `None` if it's impassable
+    fn transition_cost(&self, a: Vec2<i32>, dir: usize) -> Option<f32> {
+        let a_chunk = self.land.get(a)?;
+        let b_chunk = self.land.get(a + NEIGHBORS[dir])?;
+        // Ways are connected to their neighbours, they cross rivers on bridges.
+        // Sites have their own streets and bridges between their chunks.
+        let on_way = a_chunk.path.0.neighbors & (1 << dir) != 0
+            || a_chunk
+                .sites
+                .iter()
+                .any(|site| b_chunk.sites.contains(site));
+        if !on_way && (b_chunk.river.is_ocean() || b_chunk.river.is_lake() || b_chunk.near_cliffs())
+        {
+            return None;
+        }
+
+        let horizontal =
+            NEIGHBORS[dir].map(|e| e as f32).magnitude() * TerrainChunkSize::RECT_SIZE.x as f32;
+        let gradient_cost = (b_chunk.alt - a_chunk.alt).abs() * self.cfg.gradient_aversion;
+        let river_cost = if !on_way && b_chunk.river.is_river() {
+            horizontal * RIVER_CROSSING_COST
+        } else {
+            0.0
+        };
+        let cost = horizontal + gradient_cost + river_cost;
+        Some(if on_way {
+            cost * (1.0 - self.cfg.path_discount.clamped(0.0, 1.0))
+        } else {
+            cost
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{FileOpts, RiverKind, WorldOpts};
+
+    const RIVER_X: i32 = 8;
+    const BRIDGE_Y: i32 = 4;
+
+    /// A flat world with a river along `x == RIVER_X`, which a way crosses
+    /// at `y == BRIDGE_Y` from `x == 6` to `x == 10`
+    fn world(with_way: bool) -> WorldSim {
+        let threadpool = rayon::ThreadPoolBuilder::new().build().unwrap();
+        let opts = WorldOpts {
+            seed_elements: false,
+            world_file: FileOpts::Generate(
+                ron::de::from_str("(x_lg: 5, y_lg: 5, scale: 2.0)").unwrap(),
+            ),
+        };
+        let mut sim = WorldSim::generate(0, opts, &threadpool);
+        for chunk in sim.chunks.iter_mut() {
+            chunk.alt = 100.0;
+            chunk.cliff_height = 0.0;
+            chunk.river.river_kind = None;
+            chunk.path.0.clear();
+            chunk.sites.clear();
+        }
+        for y in 0..sim.get_size().y as i32 {
+            sim.get_mut(Vec2::new(RIVER_X, y)).unwrap().river.river_kind = Some(RiverKind::River {
+                cross_section: Vec2::new(20.0, 2.0),
+            });
+        }
+        if with_way {
+            for x in 6..10 {
+                // east and west
+                sim.get_mut(Vec2::new(x, BRIDGE_Y))
+                    .unwrap()
+                    .path
+                    .0
+                    .neighbors |= 1 << 0;
+                sim.get_mut(Vec2::new(x + 1, BRIDGE_Y))
+                    .unwrap()
+                    .path
+                    .0
+                    .neighbors |= 1 << 4;
+            }
+        }
+        sim
+    }
+
+    #[test]
+    fn same_chunk() {
+        let sim = world(false);
+        let (path, cost) = Searcher::new(&sim, SearchCfg::default())
+            .search_with_cost(Vec2::new(3, 3), Vec2::new(3, 3))
+            .unwrap();
+        assert_eq!(path.len(), 1);
+        assert_eq!(cost, 0.0);
+    }
+
+    #[test]
+    fn road_discount_and_bridge() {
+        let sim = world(true);
+        let metres_per_chunk = TerrainChunkSize::RECT_SIZE.x as f32;
+        let cfg = SearchCfg::default();
+
+        // Along the way, across the bridge
+        let (path, cost) = Searcher::new(&sim, cfg)
+            .search_with_cost(Vec2::new(6, BRIDGE_Y), Vec2::new(10, BRIDGE_Y))
+            .unwrap();
+        assert!(path.iter().all(|chunk| chunk.y == BRIDGE_Y));
+        let expected = 4.0 * metres_per_chunk * (1.0 - cfg.path_discount);
+        assert!((cost - expected).abs() < 0.01, "{} != {}", cost, expected);
+
+        // Next to the way the detour over the bridge is cheaper than wading
+        let (path, cost) = Searcher::new(&sim, cfg)
+            .search_with_cost(Vec2::new(6, BRIDGE_Y + 1), Vec2::new(10, BRIDGE_Y + 1))
+            .unwrap();
+        let crossings = path
+            .iter()
+            .filter(|chunk| chunk.x == RIVER_X)
+            .collect::<Vec<_>>();
+        assert_eq!(crossings, vec![&Vec2::new(RIVER_X, BRIDGE_Y)]);
+        assert!(cost < 4.0 * metres_per_chunk);
+    }
+
+    #[test]
+    fn river_crossing() {
+        let sim = world(false);
+        let metres_per_chunk = TerrainChunkSize::RECT_SIZE.x as f32;
+        let (path, cost) = Searcher::new(&sim, SearchCfg::default())
+            .search_with_cost(Vec2::new(6, 10), Vec2::new(10, 10))
+            .unwrap();
+        assert_eq!(path.iter().filter(|chunk| chunk.x == RIVER_X).count(), 1);
+        let expected = 4.0 * metres_per_chunk + metres_per_chunk * RIVER_CROSSING_COST;
+        assert!((cost - expected).abs() < 0.01, "{} != {}", cost, expected);
+    }
+
+    #[test]
+    fn gives_up_after_max_iters() {
+        let sim = world(false);
+        let cfg = SearchCfg {
+            max_iters: 2,
+            ..SearchCfg::default()
+        };
+        assert!(
+            Searcher::new(&sim, cfg)
+                .search(Vec2::new(6, 10), Vec2::new(10, 10))
+                .is_none()
+        );
     }
 }
@@ -397,6 +397,16 @@ impl Painter {
         .fill(Fill::Sprite(sprite))
     }
 
+    /// Place a prefab, like a plot designed in an external editor and
+    /// imported with [`vox::import`](super::vox::import), with its center at
+    /// `origin`
+    pub fn prefab(&self, prefab: PrefabStructure, origin: Vec3<i32>, seed: u32) -> PrimitiveRef {
+        let prim = self.prim(Primitive::Prefab(Box::new(prefab.clone())));
+        let prim = self.prim(Primitive::translate(prim, origin));
+        self.fill(prim, Fill::Prefab(Box::new(prefab), origin, seed));
+        prim
+    }
+
     pub fn pyramid(&self, aabb: Aabb<i32>) -> PrimitiveRef {
         let inset = 0;
         self.prim(Primitive::Ramp {
@@ -1,6 +1,7 @@
 mod gen;
 pub mod plot;
 mod tile;
+pub mod vox;
 
 use self::tile::{HazardKind, KeepKind, Ori, RoofKind, Tile, TileGrid, TileKind, TILE_SIZE};
 pub use self::{
@@ -858,12 +859,9 @@ impl Site {
         let info = canvas.info();
 
         for plot in plots_to_render {
-            let (prim_tree, fills) = match &self.plots[plot].kind {
-                PlotKind::House(house) => house.render_collect(self, &canvas.land()),
-                PlotKind::Workshop(workshop) => workshop.render_collect(self, &canvas.land()),
-                PlotKind::Castle(castle) => castle.render_collect(self, &canvas.land()),
-                PlotKind::Dungeon(dungeon) => dungeon.render_collect(self, &canvas.land()),
-                _ => continue,
+            let (prim_tree, fills) = match self.plots[plot].render_collect(self, &canvas.land()) {
+                Some(collected) => collected,
+                None => continue,
             };
 
             for (prim, fill) in fills {
@@ -35,6 +35,23 @@ impl Plot {
 
     pub fn kind(&self) -> &PlotKind { &self.kind }
 
+    /// The primitives and fills making up the plot, `None` for plots that
+    /// aren't built from primitives, like roads and plazas
+    #[allow(clippy::type_complexity)]
+    pub fn render_collect(
+        &self,
+        site: &Site,
+        land: &Land,
+    ) -> Option<(Store<Primitive>, Vec<(Id<Primitive>, Fill)>)> {
+        match &self.kind {
+            PlotKind::House(house) => Some(house.render_collect(site, land)),
+            PlotKind::Workshop(workshop) => Some(workshop.render_collect(site, land)),
+            PlotKind::Castle(castle) => Some(castle.render_collect(site, land)),
+            PlotKind::Dungeon(dungeon) => Some(dungeon.render_collect(site, land)),
+            PlotKind::Plaza | PlotKind::Road(_) => None,
+        }
+    }
+
     pub fn root_tile(&self) -> Vec2<i32> { self.root_tile }
 }
 
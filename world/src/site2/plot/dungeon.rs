@@ -1435,12 +1435,7 @@ impl SiteStructure for Dungeon {
         let entrances = entrances.read();
         let entrance = entrances[self.seed as usize % entrances.len()].clone();
 
-        let entrance_prim = painter.prim(Primitive::Prefab(Box::new(entrance.clone())));
-        let entrance_prim = painter.prim(Primitive::translate(entrance_prim, origin));
-        painter.fill(
-            entrance_prim,
-            Fill::Prefab(Box::new(entrance), origin, self.seed),
-        );
+        painter.prefab(entrance, origin, self.seed);
 
         let mut z = self.alt + ALT_OFFSET;
         for floor in &self.floors {
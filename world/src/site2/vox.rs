@@ -0,0 +1,597 @@
+//! Exporting site2 structures to MagicaVoxel `.vox` files and importing them
+//! back as prefabs, so that plots can be designed in an external editor.
+//!
+//! A `.vox` palette only stores colours, so the block each palette index
+//! stands for is kept separately by [`VoxPalette`]. Its
+//! [`custom_indices`](VoxPalette::custom_indices) can be used as the
+//! `custom_indices` of a structure manifest entry pointing at the file, or
+//! passed to [`import`].
+
+use super::{Fill, Plot, Primitive, Site};
+use crate::CanvasInfo;
+use common::{
+    store::{Id, Store},
+    terrain::{
+        structure::{Structure as PrefabStructure, StructureBlock},
+        Block, BlockKind, SpriteKind,
+    },
+};
+use dot_vox::DotVoxData;
+use hashbrown::HashMap;
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    io::{self, Seek, SeekFrom, Write},
+};
+use vek::*;
+
+/// Models in a `.vox` file can't be larger than this along any axis
+const MODEL_SIZE: i32 = 256;
+/// Colour of palette entries for sprites, which don't have a colour of their
+/// own
+const SPRITE_COLOR: Rgb<u8> = Rgb::new(255, 0, 0);
+const WATER_COLOR: Rgb<u8> = Rgb::new(0, 100, 255);
+
+/// The blocks standing behind the palette indices of an exported `.vox` file
+#[derive(Clone, Debug, Default)]
+pub struct VoxPalette {
+    blocks: Vec<StructureBlock>,
+}
+
+impl VoxPalette {
+    /// The palette index of `block`, adding it to the palette if there's
+    /// still room. Once all 255 indices are used up, the index of the closest
+    /// colour is returned instead, preferring blocks of the same kind.
+    pub fn index_of(&mut self, block: StructureBlock) -> u8 {
+        if let Some(i) = self.blocks.iter().position(|b| *b == block) {
+            i as u8 + 1
+        } else if self.blocks.len() < 255 {
+            self.blocks.push(block);
+            self.blocks.len() as u8
+        } else {
+            let color = Self::color(block).map(i32::from);
+            self.blocks
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, b)| {
+                    (
+                        Self::kind(**b) != Self::kind(block),
+                        (Self::color(**b).map(i32::from) - color)
+                            .map(|e| e * e)
+                            .sum(),
+                    )
+                })
+                .map_or(1, |(i, _)| i as u8 + 1)
+        }
+    }
+
+    /// The colours of the palette, starting at index 1
+    pub fn colors(&self) -> impl Iterator<Item = Rgb<u8>> + '_ {
+        self.blocks.iter().map(|b| Self::color(*b))
+    }
+
+    /// The block of every used palette index. Unused indices are left out, so
+    /// use these to override all the default indices of a structure manifest.
+    pub fn custom_indices(&self) -> HashMap<u8, StructureBlock> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (i as u8 + 1, *b))
+            .collect()
+    }
+
+    fn kind(block: StructureBlock) -> Option<BlockKind> {
+        match block {
+            StructureBlock::Filled(kind, _) => Some(kind),
+            StructureBlock::Water => Some(BlockKind::Water),
+            _ => None,
+        }
+    }
+
+    fn color(block: StructureBlock) -> Rgb<u8> {
+        match block {
+            StructureBlock::Filled(_, color) | StructureBlock::Normal(color) => color,
+            StructureBlock::Water => WATER_COLOR,
+            _ => SPRITE_COLOR,
+        }
+    }
+}
+
+/// The structure block that places `block` again, `None` for empty air
+fn structure_block(block: Block) -> Option<StructureBlock> {
+    match block.get_sprite() {
+        None | Some(SpriteKind::Empty) => match block.kind() {
+            BlockKind::Air => None,
+            BlockKind::Water => Some(StructureBlock::Water),
+            kind => Some(StructureBlock::Filled(
+                kind,
+                block.get_color().unwrap_or_default(),
+            )),
+        },
+        Some(sprite) => Some(match (block.kind(), block.get_ori().unwrap_or(0)) {
+            (BlockKind::Water, ori) => StructureBlock::WaterSprite(sprite, ori),
+            (_, 0) => StructureBlock::Sprite(sprite),
+            (_, ori) => StructureBlock::RotatedSprite(sprite, ori),
+        }),
+    }
+}
+
+/// Blocks rendered from site2 primitives, to be written to a `.vox` file
+#[derive(Default)]
+pub struct VoxExport {
+    blocks: HashMap<Vec3<i32>, Block>,
+}
+
+impl VoxExport {
+    /// Render the fills of a primitive tree, in the same way
+    /// [`Site::render_to`] does for chunks
+    pub fn render(
+        &mut self,
+        prim_tree: &Store<Primitive>,
+        fills: &[(Id<Primitive>, Fill)],
+        canvas_info: &CanvasInfo,
+    ) {
+        for (prim, fill) in fills {
+            let aabb = fill.get_bounds(prim_tree, *prim);
+
+            for x in aabb.min.x..aabb.max.x {
+                for y in aabb.min.y..aabb.max.y {
+                    for z in aabb.min.z..aabb.max.z {
+                        let pos = Vec3::new(x, y, z);
+                        let old_block = self.get(pos);
+
+                        if let Some(block) =
+                            fill.sample_at(prim_tree, *prim, pos, canvas_info, old_block)
+                        {
+                            self.blocks.insert(pos, block);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Render a single plot. Returns `false` for plots that aren't built from
+    /// primitives, like roads and plazas.
+    pub fn render_plot(&mut self, site: &Site, plot: &Plot, canvas_info: &CanvasInfo) -> bool {
+        match plot.render_collect(site, &canvas_info.land()) {
+            Some((prim_tree, fills)) => {
+                self.render(&prim_tree, &fills, canvas_info);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Render every plot of a site
+    pub fn render_site(&mut self, site: &Site, canvas_info: &CanvasInfo) {
+        for plot in site.plots() {
+            self.render_plot(site, plot, canvas_info);
+        }
+    }
+
+    pub fn get(&self, pos: Vec3<i32>) -> Block {
+        self.blocks.get(&pos).copied().unwrap_or_else(Block::empty)
+    }
+
+    /// The bounds of all the non-empty blocks, `None` if there are none
+    pub fn bounds(&self) -> Option<Aabb<i32>> {
+        self.blocks
+            .iter()
+            .filter(|(_, block)| structure_block(**block).is_some())
+            .map(|(pos, _)| Aabb {
+                min: *pos,
+                max: *pos + 1,
+            })
+            .reduce(|a, b| a.union(b))
+    }
+
+    /// Write the blocks to a `.vox` file with their minimum corner at the
+    /// origin. Structures too large for a single model are split into several
+    /// ones, placed next to each other by the scene graph.
+    ///
+    /// Returns the palette telling which block each palette index stands for.
+    pub fn write<W: Write + Seek>(&self, writer: &mut W) -> io::Result<VoxPalette> {
+        // File format defined at https://github.com/ephtracy/voxel-model
+
+        fn write_i32<W: Write>(writer: &mut W, value: i32) -> io::Result<()> {
+            // The spec doesn't specify endianess?!?
+            writer.write_all(&value.to_le_bytes())
+        }
+
+        fn write_chunk<W: Write + Seek>(
+            writer: &mut W,
+            name: &str,
+            write_body: &dyn Fn(&mut W) -> io::Result<()>,
+        ) -> io::Result<()> {
+            writer.write_all(name.as_bytes())?;
+            write_i32(writer, 0)?; // Chunk size (unknown at this point)
+            write_i32(writer, 0)?; // Size of child chunks
+            let chunk_start = writer.stream_position()?;
+            write_body(writer)?;
+            let chunk_end = writer.stream_position()?;
+            writer.seek(SeekFrom::Start(chunk_start - 8))?;
+            write_i32(writer, (chunk_end - chunk_start) as i32)?;
+            writer.seek(SeekFrom::Start(chunk_end))?;
+            Ok(())
+        }
+
+        fn write_translation_node<W: Write + Seek>(
+            writer: &mut W,
+            id: i32,
+            child_id: i32,
+            pos: Vec3<i32>,
+        ) -> io::Result<()> {
+            write_chunk(writer, "nTRN", &|writer| {
+                write_i32(writer, id)?; // Node index
+                write_i32(writer, 0)?; // Number of attributes
+                write_i32(writer, child_id)?; // Child node index
+                write_i32(writer, -1)?; // Reserved
+                write_i32(writer, 0)?; // Layer
+                write_i32(writer, 1)?; // Frames
+                write_i32(writer, 1)?; // Number of frame attributes
+                write_i32(writer, "_t".len() as i32)?; // Attribute name len
+                writer.write_all("_t".as_bytes())?; // Attribute name
+                let translation_string = format!("{} {} {}", pos.x, pos.y, pos.z);
+                write_i32(writer, translation_string.len() as i32)?; // Value len
+                writer.write_all(translation_string.as_bytes()) // Value
+            })
+        }
+
+        let mut voxels = self
+            .blocks
+            .iter()
+            .filter_map(|(pos, block)| Some((*pos, structure_block(*block)?)))
+            .collect::<Vec<_>>();
+        // Keep the palette independent of the order of the hash map
+        voxels.sort_unstable_by_key(|(pos, _)| (pos.z, pos.y, pos.x));
+        let origin = self.bounds().map_or(Vec3::zero(), |bounds| bounds.min);
+
+        let mut palette = VoxPalette::default();
+        // Voxel data and size of each model, by their position in the grid of
+        // models
+        let mut models = BTreeMap::<(i32, i32, i32), (Vec<u8>, Vec3<i32>)>::new();
+        for (pos, block) in voxels {
+            let pos = pos - origin;
+            let model_pos = pos.map(|e| e.div_euclid(MODEL_SIZE));
+            let rel_pos = pos.map(|e| e.rem_euclid(MODEL_SIZE));
+            let (model, size) = models.entry(model_pos.into_tuple()).or_default();
+            model.extend_from_slice(&[
+                rel_pos.x as u8,
+                rel_pos.y as u8,
+                rel_pos.z as u8,
+                palette.index_of(block),
+            ]);
+            *size = Vec3::max(*size, rel_pos + 1);
+        }
+
+        write!(writer, "VOX ")?; // Magic number
+        write_i32(writer, 150)?; // Version
+
+        write!(writer, "MAIN")?;
+        write_i32(writer, 0)?; // Chunk size
+        write_i32(writer, 0)?; // Size of child chunks (set later)
+        let chunks_start = writer.stream_position()?;
+
+        // Model data
+        for (model, size) in models.values() {
+            write_chunk(writer, "SIZE", &|writer| {
+                write_i32(writer, size.x)?; // Size X
+                write_i32(writer, size.y)?; // Size Y
+                write_i32(writer, size.z) // Size Z
+            })?;
+            write_chunk(writer, "XYZI", &|writer| {
+                write_i32(writer, model.len() as i32 / 4)?; // Number of voxels
+                writer.write_all(model)
+            })?;
+        }
+
+        // Scene graph
+        // Root Transform node
+        write_translation_node(writer, 0, 1, Vec3::zero())?;
+
+        // Group node
+        write_chunk(writer, "nGRP", &|writer| {
+            write_i32(writer, 1)?; // Node index
+            write_i32(writer, 0)?; // Number of attributes
+            write_i32(writer, models.len() as i32)?; // Number of child nodes
+            for index in 0..models.len() {
+                write_i32(writer, index as i32 * 2 + 2)?;
+            }
+            Ok(())
+        })?;
+
+        for (index, (model_pos, (_, size))) in models.iter().enumerate() {
+            // Transform node, MagicaVoxel places models by their center
+            let pos = Vec3::from(*model_pos) * MODEL_SIZE + *size / 2;
+            let transform_node_id = index as i32 * 2 + 2;
+            let shape_node_id = index as i32 * 2 + 3;
+            write_translation_node(writer, transform_node_id, shape_node_id, pos)?;
+
+            // Shape node
+            write_chunk(writer, "nSHP", &|writer| {
+                write_i32(writer, shape_node_id)?;
+                write_i32(writer, 0)?; // Number of attributes
+                write_i32(writer, 1)?; // Number of models
+                write_i32(writer, index as i32)?; // Model index (independent of scene graph index)
+                write_i32(writer, 0) // Number model of attributes
+            })?;
+        }
+
+        // Palette, the first entry is the colour of index 1
+        write_chunk(writer, "RGBA", &|writer| {
+            for color in palette.colors() {
+                writer.write_all(&[color.r, color.g, color.b, 255])?;
+            }
+            writer.write_all(&vec![0; 4 * (256 - palette.blocks.len())])
+        })?;
+
+        let chunks_end = writer.stream_position()?;
+        writer.seek(SeekFrom::Start(chunks_start - 4))?;
+        write_i32(writer, (chunks_end - chunks_start) as i32)?;
+        writer.seek(SeekFrom::Start(chunks_end))?;
+
+        Ok(palette)
+    }
+}
+
+/// A node of the scene graph of a `.vox` file
+enum SceneNode {
+    Transform { child: i32, translation: Vec3<i32> },
+    Group { children: Vec<i32> },
+    Shape { models: Vec<i32> },
+}
+
+/// Reads the chunks of a `.vox` file, which `dot_vox` doesn't parse the scene
+/// graph of
+struct VoxReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> VoxReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        if self.bytes.len() < len {
+            return Err("Unexpected end of the .vox file");
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, &'static str> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(i32::from_le_bytes(bytes))
+    }
+
+    fn len(&mut self) -> Result<usize, &'static str> {
+        usize::try_from(self.i32()?).map_err(|_| "Negative length in the .vox file")
+    }
+
+    fn string(&mut self) -> Result<&'a str, &'static str> {
+        let len = self.len()?;
+        std::str::from_utf8(self.bytes(len)?).map_err(|_| "Invalid string in the .vox file")
+    }
+
+    fn dict(&mut self) -> Result<HashMap<&'a str, &'a str>, &'static str> {
+        (0..self.len()?)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+
+    /// The nodes of the scene graph by their index
+    fn scene_nodes(mut self) -> Result<HashMap<i32, SceneNode>, &'static str> {
+        if self.bytes(4)? != b"VOX " {
+            return Err("Not a .vox file");
+        }
+        self.i32()?; // Version
+        if self.bytes(4)? != b"MAIN" {
+            return Err("Missing main chunk in the .vox file");
+        }
+        self.len()?; // Chunk size
+        let children_len = self.len()?;
+        let mut chunks = VoxReader {
+            bytes: self.bytes(children_len)?,
+        };
+
+        let mut nodes = HashMap::new();
+        while !chunks.bytes.is_empty() {
+            let name = chunks.bytes(4)?;
+            let content_len = chunks.len()?;
+            let children_len = chunks.len()?;
+            let mut chunk = VoxReader {
+                bytes: chunks.bytes(content_len)?,
+            };
+            chunks.bytes(children_len)?;
+
+            let (id, node) = match name {
+                b"nTRN" => {
+                    let id = chunk.i32()?;
+                    chunk.dict()?; // Attributes
+                    let child = chunk.i32()?;
+                    chunk.i32()?; // Reserved
+                    chunk.i32()?; // Layer
+                    let frame = if chunk.len()? > 0 {
+                        chunk.dict()?
+                    } else {
+                        HashMap::new()
+                    };
+                    // `4` is the identity rotation
+                    if frame.get("_r").map_or(false, |r| *r != "4") {
+                        return Err("Rotated models in .vox files aren't supported");
+                    }
+                    let translation = match frame.get("_t") {
+                        Some(t) => match t
+                            .split_whitespace()
+                            .map(str::parse)
+                            .collect::<Result<Vec<i32>, _>>()
+                            .as_deref()
+                        {
+                            Ok([x, y, z]) => Vec3::new(*x, *y, *z),
+                            _ => return Err("Invalid translation in the .vox file"),
+                        },
+                        None => Vec3::zero(),
+                    };
+                    (id, SceneNode::Transform { child, translation })
+                },
+                b"nGRP" => {
+                    let id = chunk.i32()?;
+                    chunk.dict()?; // Attributes
+                    let children = (0..chunk.len()?)
+                        .map(|_| chunk.i32())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    (id, SceneNode::Group { children })
+                },
+                b"nSHP" => {
+                    let id = chunk.i32()?;
+                    chunk.dict()?; // Attributes
+                    let models = (0..chunk.len()?)
+                        .map(|_| {
+                            let model = chunk.i32()?;
+                            chunk.dict()?; // Model attributes
+                            Ok(model)
+                        })
+                        .collect::<Result<Vec<_>, &'static str>>()?;
+                    (id, SceneNode::Shape { models })
+                },
+                _ => continue,
+            };
+            nodes.insert(id, node);
+        }
+
+        Ok(nodes)
+    }
+}
+
+/// The minimum corner of each model of a `.vox` file, following the
+/// translations of its scene graph
+fn model_offsets(bytes: &[u8], dot_vox_data: &DotVoxData) -> Result<Vec<Vec3<i32>>, &'static str> {
+    let nodes = VoxReader { bytes }.scene_nodes()?;
+    if nodes.is_empty() {
+        // Files written before scene graphs were added hold a single model
+        return if dot_vox_data.models.len() > 1 {
+            Err("The .vox file has several models but no scene graph")
+        } else {
+            Ok(vec![Vec3::zero(); dot_vox_data.models.len()])
+        };
+    }
+
+    let mut offsets = vec![None; dot_vox_data.models.len()];
+    let mut stack = vec![(0, Vec3::zero())];
+    let mut visited = 0;
+    while let Some((id, translation)) = stack.pop() {
+        visited += 1;
+        if visited > nodes.len() {
+            return Err("The scene graph of the .vox file has a cycle");
+        }
+        match nodes
+            .get(&id)
+            .ok_or("Missing node in the scene graph of the .vox file")?
+        {
+            SceneNode::Transform {
+                child,
+                translation: t,
+            } => stack.push((*child, translation + *t)),
+            SceneNode::Group { children } => {
+                stack.extend(children.iter().map(|child| (*child, translation)))
+            },
+            SceneNode::Shape { models } => {
+                for model in models {
+                    let (offset, model) = usize::try_from(*model)
+                        .ok()
+                        .and_then(|i| Some((offsets.get_mut(i)?, dot_vox_data.models.get(i)?)))
+                        .ok_or("Missing model in the .vox file")?;
+                    if offset.is_some() {
+                        return Err("A model is placed several times in the .vox file");
+                    }
+                    // MagicaVoxel places models by their center
+                    let size =
+                        Vec3::new(model.size.x, model.size.y, model.size.z).map(|e| e as i32);
+                    *offset = Some(translation - size / 2);
+                }
+            },
+        }
+    }
+
+    offsets
+        .into_iter()
+        .collect::<Option<_>>()
+        .ok_or("A model isn't placed in the scene of the .vox file")
+}
+
+/// Import a `.vox` file as a prefab to be placed with
+/// [`Painter::prefab`](super::Painter::prefab), merging its models as they
+/// are placed by the scene graph. `center` is relative to the minimum corner
+/// of the merged models. `blocks` tells which block each palette index stands
+/// for, like the [`VoxPalette`] returned when exporting it. Indices missing
+/// from it are placed as blocks of their palette colour.
+pub fn import(
+    bytes: &[u8],
+    blocks: &HashMap<u8, StructureBlock>,
+    center: Vec3<i32>,
+) -> Result<PrefabStructure, &'static str> {
+    let dot_vox_data = dot_vox::load_bytes(bytes)?;
+    let model_offsets = model_offsets(bytes, &dot_vox_data)?;
+    Ok(PrefabStructure::from_dot_vox(
+        &dot_vox_data,
+        &model_offsets,
+        center,
+        blocks,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::vol::ReadVol;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip() {
+        let mut export = VoxExport::default();
+        let blocks = [
+            (
+                Vec3::new(-3, 5, 10),
+                Block::new(BlockKind::Rock, Rgb::new(1, 2, 3)),
+            ),
+            (
+                Vec3::new(0, 7, 11),
+                Block::new(BlockKind::Wood, Rgb::new(4, 5, 6)),
+            ),
+            (Vec3::new(-3, 5, 12), Block::water(SpriteKind::Empty)),
+            (Vec3::new(-1, 6, 10), Block::air(SpriteKind::Lantern)),
+            (
+                Vec3::new(0, 6, 12),
+                Block::air(SpriteKind::Door).with_ori(2).unwrap(),
+            ),
+            (Vec3::new(-2, 7, 11), Block::water(SpriteKind::Seagrass)),
+            // Too far away to fit in the first model
+            (
+                Vec3::new(290, 6, 11),
+                Block::new(BlockKind::Wood, Rgb::new(7, 8, 9)),
+            ),
+        ];
+        export.blocks.extend(blocks.iter().copied());
+        export.blocks.insert(Vec3::new(2, 2, 2), Block::empty());
+
+        let mut bytes = Cursor::new(Vec::new());
+        let palette = export.write(&mut bytes).unwrap();
+        assert_eq!(palette.blocks.len(), 7);
+        assert_eq!(
+            structure_block(blocks[4].1),
+            Some(StructureBlock::RotatedSprite(SpriteKind::Door, 2))
+        );
+
+        let prefab = import(bytes.get_ref(), &palette.custom_indices(), Vec3::zero()).unwrap();
+        assert_eq!(prefab.get_bounds(), Aabb {
+            min: Vec3::zero(),
+            max: Vec3::new(294, 3, 3),
+        });
+        for (pos, block) in &blocks {
+            assert_eq!(
+                prefab.get(*pos - Vec3::new(-3, 5, 10)).ok().copied(),
+                structure_block(*block)
+            );
+        }
+    }
+}